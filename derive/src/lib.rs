@@ -0,0 +1,176 @@
+//! `#[derive(Alphabet)]` for fieldless enums. See the `arithmetic-coding`
+//! crate, which re-exports this macro with the `derive` feature.
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Error, Fields, Ident, LitInt, parse_macro_input};
+
+/// Implement `Symbol` for a fieldless enum, and generate a unit struct named
+/// after the enum with an `Alphabet` suffix, implementing `Alphabet` over its
+/// variants.
+///
+/// Each variant's interval width is given by `#[weight(n)]`, defaulting to 1,
+/// and exactly one variant must be marked `#[eof]`. Symbols are ordered as the
+/// variants are declared, and every bound is computed at compile time.
+///
+/// ```ignore
+/// #[derive(Alphabet, Copy, Clone, Debug, PartialEq)]
+/// enum Weather {
+///     #[weight(6)]
+///     Sunny,
+///     #[weight(3)]
+///     Rainy,
+///     #[eof]
+///     End,
+/// }
+///
+/// let bits = WeatherAlphabet.encode::<_, 32>([Weather::Sunny, Weather::End]);
+/// ```
+#[proc_macro_derive(Alphabet, attributes(weight, eof))]
+pub fn derive_alphabet(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "Alphabet can't be derived for a generic enum",
+        ));
+    }
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new_spanned(
+            name,
+            "Alphabet can only be derived for an enum",
+        ));
+    };
+
+    let mut variants: Vec<&Ident> = Vec::new();
+    let mut weights: Vec<u64> = Vec::new();
+    let mut eof = None;
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new_spanned(
+                variant,
+                "Alphabet can only be derived for an enum without fields",
+            ));
+        }
+
+        let mut weight = None;
+        for attr in &variant.attrs {
+            if attr.path().is_ident("weight") {
+                if weight.is_some() {
+                    return Err(Error::new_spanned(attr, "Duplicate weight"));
+                }
+                weight = Some(attr.parse_args::<LitInt>()?.base10_parse::<u64>()?);
+            } else if attr.path().is_ident("eof") {
+                attr.meta.require_path_only()?;
+                if eof.is_some() {
+                    return Err(Error::new_spanned(attr, "Only one variant can be EOF"));
+                }
+                eof = Some(&variant.ident);
+            }
+        }
+
+        variants.push(&variant.ident);
+        weights.push(weight.unwrap_or(1));
+    }
+    let Some(eof) = eof else {
+        return Err(Error::new_spanned(
+            name,
+            "One variant must be marked #[eof]",
+        ));
+    };
+
+    let mut lower_bounds = Vec::with_capacity(weights.len());
+    let mut total: u64 = 0;
+    for &weight in &weights {
+        lower_bounds.push(total);
+        total = total
+            .checked_add(weight)
+            .ok_or_else(|| Error::new_spanned(name, "Total weight overflows"))?;
+    }
+    let upper_bounds = lower_bounds.iter().zip(&weights).map(|(c, r)| c + r);
+
+    // Emit the numbers as unsuffixed literals, so they're typed as usize
+    let literal = |n: u64| proc_macro2::Literal::u64_unsuffixed(n);
+    let weights = weights.iter().copied().map(literal);
+    let lower_bounds = lower_bounds.iter().copied().map(literal);
+    let upper_bounds = upper_bounds.map(literal);
+    let total = literal(total);
+    let indices = (0..variants.len() as u64).map(literal);
+
+    let vis = &input.vis;
+    let alphabet = format_ident!("{}Alphabet", name);
+    let doc = format!("The alphabet of [`{name}`] symbols, derived from its variants.");
+
+    Ok(quote! {
+        impl ::arithmetic_coding::alphabet::Symbol for #name {}
+
+        #[doc = #doc]
+        #[derive(Copy, Clone, Debug, Default)]
+        #vis struct #alphabet;
+
+        impl ::arithmetic_coding::alphabet::Alphabet for #alphabet {
+            type S = #name;
+
+            fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+                const SYMBOLS: &[#name] = &[#(#name::#variants),*];
+                SYMBOLS.iter()
+            }
+
+            fn eof(&self) -> Self::S {
+                #name::#eof
+            }
+
+            fn interval_width(&self, symbol: &Self::S) -> usize {
+                match symbol {
+                    #(#name::#variants => #weights,)*
+                }
+            }
+
+            fn total_interval_width(&self) -> usize {
+                #total
+            }
+
+            fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+                match symbol {
+                    #(#name::#variants => #lower_bounds,)*
+                }
+            }
+
+            fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+                match symbol {
+                    #(#name::#variants => #upper_bounds,)*
+                }
+            }
+
+            fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+                Some(self.cdf(symbol))
+            }
+
+            fn contains(&self, _symbol: &Self::S) -> bool {
+                true
+            }
+
+            fn index(&self, symbol: &Self::S) -> usize {
+                match symbol {
+                    #(#name::#variants => #indices,)*
+                }
+            }
+
+            fn symbol_at(&self, index: usize) -> &Self::S {
+                const SYMBOLS: &[#name] = &[#(#name::#variants),*];
+                &SYMBOLS[index]
+            }
+
+            fn is_static(&self) -> bool {
+                true
+            }
+        }
+    })
+}
@@ -0,0 +1,42 @@
+//! Compress a nucleotide sequence read from standard input (the sequence lines
+//! of a FASTA record, say), and report how well it compressed.
+//!
+//! ```bash
+//! echo GATTACA | cargo run --example dna --features presets
+//! ```
+
+use arithmetic_coding::presets::AcgtAlphabet;
+use std::io::Read;
+
+fn main() {
+    let mut sequence = Vec::new();
+    std::io::stdin()
+        .read_to_end(&mut sequence)
+        .expect("Failed to read standard input");
+    // Skip FASTA header lines
+    let sequence: Vec<u8> = sequence
+        .split(|&byte| byte == b'\n')
+        .filter(|line| !line.starts_with(b">"))
+        .flatten()
+        .copied()
+        .collect();
+
+    let alphabet = AcgtAlphabet::with_ambiguity();
+    let bits = match alphabet.encode_sequence::<32>(&sequence) {
+        Ok(bits) => bits,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    let decoded = alphabet
+        .decode_sequence::<32>(&bits)
+        .expect("Failed to decode");
+
+    println!(
+        "{} bases in {} bits ({:.3} bits per base)",
+        decoded.len(),
+        bits.len(),
+        bits.len() as f64 / decoded.len().max(1) as f64
+    );
+}
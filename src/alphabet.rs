@@ -1,4 +1,14 @@
-pub trait Symbol: PartialEq + Copy + std::fmt::Debug {}
+#[cfg(feature = "derive")]
+pub use arithmetic_coding_derive::Alphabet;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A value which can be coded. Symbols are cloned as they are decoded, so
+/// cheaply cloned types are preferable, but owned types such as [`String`]
+/// can be symbols too.
+pub trait Symbol: PartialEq + Clone + std::fmt::Debug {}
+
+impl Symbol for String {}
 
 pub trait Alphabet {
     type S: Symbol;
@@ -43,4 +53,825 @@ pub trait Alphabet {
     fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
         self.interval_lower_bound(symbol) + self.interval_width(symbol)
     }
+
+    /// The cumulative distribution at the bounds of a symbol's interval,
+    /// (c_j, d_j).
+    fn cdf(&self, symbol: &Self::S) -> (usize, usize) {
+        (
+            self.interval_lower_bound(symbol),
+            self.interval_upper_bound(symbol),
+        )
+    }
+
+    /// The inverse of [`Alphabet::cdf`]: the symbol whose interval [c_j, d_j)
+    /// contains the given value, which must be less than R.
+    ///
+    /// The decoder uses this to find the symbol identified by its input. It
+    /// scans the symbols by default; alphabets with cumulative tables should
+    /// override it with a search.
+    fn quantile(&self, value: usize) -> &Self::S {
+        let mut upper_bound = 0;
+        for symbol in self.symbols() {
+            upper_bound += self.interval_width(symbol);
+            if value < upper_bound {
+                return symbol;
+            }
+        }
+        panic!("Value {value} is beyond the total interval width {upper_bound}")
+    }
+
+    /// [`Alphabet::cdf`], or None if the symbol isn't in the alphabet.
+    ///
+    /// The encoder codes each symbol of its input with this, so that a symbol
+    /// missing from the alphabet is reported rather than coded wrongly. By
+    /// default it finds the symbol and its bounds in one scan of the symbols;
+    /// alphabets which override [`Alphabet::cdf`] or the bounds with a lookup
+    /// should override this too, so that the encoder doesn't scan.
+    fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        let mut lower_bound = 0;
+        for s in self.symbols() {
+            let upper_bound = lower_bound + self.interval_width(s);
+            if s == symbol {
+                return Some((lower_bound, upper_bound));
+            }
+            lower_bound = upper_bound;
+        }
+        None
+    }
+
+    /// Whether the symbol is in the alphabet.
+    ///
+    /// Like [`Alphabet::index`], this searches the symbols by default.
+    fn contains(&self, symbol: &Self::S) -> bool {
+        self.symbols().any(|s| s == symbol)
+    }
+
+    /// The position of a symbol in [`Alphabet::symbols`]. Panics if the symbol
+    /// isn't in the alphabet.
+    ///
+    /// This searches the symbols by default. Alphabets which can locate a
+    /// symbol directly should override it, along with
+    /// [`Alphabet::symbol_at`], so that wrappers can look up widths by
+    /// position.
+    fn index(&self, symbol: &Self::S) -> usize {
+        self.symbols()
+            .position(|s| s == symbol)
+            .unwrap_or_else(|| panic!("Symbol {symbol:?} not in alphabet"))
+    }
+
+    /// The symbol at the given position in [`Alphabet::symbols`]. Panics if
+    /// the position is out of range.
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        self.symbols()
+            .nth(index)
+            .unwrap_or_else(|| panic!("No symbol at position {index}"))
+    }
+
+    /// Called by the encoder and decoder before the first symbol of each
+    /// message, so that alphabets whose distribution changes over the course
+    /// of a message (see [`Alphabet::update`]) can return to their initial
+    /// state.
+    fn begin_message(&self) {}
+
+    /// The largest total interval width the alphabet can reach over the course
+    /// of a message, which [`Alphabet::validate`] checks against the
+    /// precision of the coder.
+    ///
+    /// This is the current total by default, which is right for alphabets
+    /// which don't change. Alphabets whose total grows as symbols are coded
+    /// (see [`Alphabet::update`]) should return the limit they keep it
+    /// within.
+    fn max_total_interval_width(&self) -> usize {
+        self.total_interval_width()
+    }
+
+    /// Called by the encoder and decoder after each symbol (including EOF) is
+    /// coded.
+    ///
+    /// Most alphabets are static, so this does nothing by default. Alphabets
+    /// whose distribution depends on the symbols coded so far can update
+    /// their state here, using interior mutability. As both sides make the
+    /// same calls in the same order, the decoder always sees the distribution
+    /// the encoder used.
+    fn update(&self, _symbol: &Self::S) {}
+
+    /// Whether the alphabet's widths never change, so that a table of its
+    /// bounds (see
+    /// [`DecoderOutput::with_bound_table`](crate::decoder::DecoderOutput::with_bound_table))
+    /// can be built once and kept.
+    ///
+    /// This is false by default, which is always safe. Alphabets which don't
+    /// change in [`Alphabet::begin_message`] or [`Alphabet::update`] (or by
+    /// any other interior mutability) can return true.
+    fn is_static(&self) -> bool {
+        false
+    }
+
+    /// The Shannon entropy of the alphabet's distribution, in bits: the
+    /// expected number of bits per symbol an ideal coder spends on symbols
+    /// drawn from this distribution.
+    fn entropy(&self) -> f64 {
+        let total = self.total_interval_width() as f64;
+        self.symbols()
+            .map(|symbol| self.interval_width(symbol) as f64 / total)
+            .filter(|&p| p > 0.0)
+            .map(|p| -p * p.log2())
+            .sum()
+    }
+
+    /// The information content of a symbol, -log2(r_i / R), in bits: the
+    /// number of bits an ideal coder spends on it.
+    fn symbol_cost(&self, symbol: &Self::S) -> f64 {
+        let p = self.interval_width(symbol) as f64 / self.total_interval_width() as f64;
+        -p.log2()
+    }
+
+    /// The number of bits an ideal coder spends on the given message (which
+    /// should be terminated by EOF), following any changes in the
+    /// distribution as a coder would.
+    ///
+    /// The encoder's output is at most two bits longer than this, plus a small
+    /// loss from rounding subintervals to finite precision.
+    fn message_cost(&self, message: &[Self::S]) -> f64 {
+        self.begin_message();
+        message
+            .iter()
+            .map(|symbol| {
+                let cost = self.symbol_cost(symbol);
+                self.update(symbol);
+                cost
+            })
+            .sum()
+    }
+
+    /// Check that the alphabet can be used by a coder with the given precision:
+    /// its symbols are distinct and include EOF, every symbol has a nonzero
+    /// width, and R is at most a quarter of 2^precision (so that every symbol
+    /// keeps a nonzero part of the coder's interval, which is always wider
+    /// than a quarter) and R * 2^precision can be represented as a usize.
+    /// R is taken to be the largest total the alphabet can reach (see
+    /// [`Alphabet::max_total_interval_width`]), not just the current one.
+    ///
+    /// The encoder and decoder validate their alphabet (in its state after
+    /// [`Alphabet::begin_message`]) before coding their first message.
+    /// Checking for duplicates takes time quadratic in the number of symbols.
+    fn validate(&self, bits_of_precision: u32) -> Result<(), AlphabetError> {
+        let symbols: Vec<&Self::S> = self.symbols().collect();
+        let eof = self.eof();
+        if !symbols.contains(&&eof) {
+            return Err(AlphabetError::MissingEof(format!("{eof:?}")));
+        }
+
+        let mut total: usize = 0;
+        for (index, symbol) in symbols.iter().enumerate() {
+            if let Some(first) = symbols[..index].iter().position(|s| s == symbol) {
+                return Err(AlphabetError::DuplicateSymbol {
+                    symbol: format!("{symbol:?}"),
+                    first,
+                    second: index,
+                });
+            }
+
+            let width = self.interval_width(symbol);
+            if width == 0 {
+                return Err(AlphabetError::ZeroWidth {
+                    symbol: format!("{symbol:?}"),
+                    index,
+                });
+            }
+            total = total.saturating_add(width);
+        }
+        let total = total.max(self.max_total_interval_width());
+
+        let quarter = bits_of_precision
+            .checked_sub(2)
+            .map_or(0, |shift| 1_usize.checked_shl(shift).unwrap_or(usize::MAX));
+        let max = usize::MAX
+            .checked_shr(bits_of_precision)
+            .unwrap_or(0)
+            .min(quarter);
+        if total > max {
+            return Err(AlphabetError::TotalTooLarge {
+                total,
+                bits_of_precision,
+                max,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Ways in which an alphabet can be unusable by the encoder and decoder
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum AlphabetError {
+    #[error("EOF symbol {0} is not in the alphabet")]
+    MissingEof(String),
+    #[error("Symbol {symbol} appears at both index {first} and index {second}")]
+    DuplicateSymbol {
+        symbol: String,
+        first: usize,
+        second: usize,
+    },
+    #[error("Symbol {symbol} at index {index} has an interval width of zero")]
+    ZeroWidth { symbol: String, index: usize },
+    #[error(
+        "Total interval width {total} exceeds {max}, the most that {bits_of_precision} bits of precision allows"
+    )]
+    TotalTooLarge {
+        total: usize,
+        bits_of_precision: u32,
+        max: usize,
+    },
+}
+
+/// The alphabet a coder codes with, which it borrows, or shares ownership of
+/// when it codes with a [`Model`](crate::models::Model) (which it adapts to
+/// an alphabet of its own).
+pub(crate) enum AlphabetRef<'a, A> {
+    Borrowed(&'a A),
+    Shared(Arc<A>),
+}
+
+impl<A> Clone for AlphabetRef<'_, A> {
+    fn clone(&self) -> Self {
+        match self {
+            AlphabetRef::Borrowed(alphabet) => AlphabetRef::Borrowed(alphabet),
+            AlphabetRef::Shared(alphabet) => AlphabetRef::Shared(Arc::clone(alphabet)),
+        }
+    }
+}
+
+impl<A> Deref for AlphabetRef<'_, A> {
+    type Target = A;
+
+    fn deref(&self) -> &A {
+        match self {
+            AlphabetRef::Borrowed(alphabet) => alphabet,
+            AlphabetRef::Shared(alphabet) => alphabet,
+        }
+    }
+}
+
+impl<'a, A> From<&'a A> for AlphabetRef<'a, A> {
+    fn from(alphabet: &'a A) -> Self {
+        AlphabetRef::Borrowed(alphabet)
+    }
+}
+
+/// A view of an alphabet with the interval of its EOF symbol removed, for
+/// coding messages whose length is known instead of terminated by EOF.
+///
+/// EOF has a width of zero, and the bounds of the symbols after it are
+/// shifted down to close the gap.
+pub(crate) struct WithoutEof<'a, A: Alphabet>(pub(crate) &'a A);
+
+impl<A: Alphabet> WithoutEof<'_, A> {
+    /// The bounds of the EOF interval of the underlying alphabet.
+    fn eof_bounds(&self) -> (usize, usize) {
+        self.0.cdf(&self.0.eof())
+    }
+
+    /// Map a bound of the underlying alphabet into this one.
+    fn shift(&self, bound: usize) -> usize {
+        let (lower_bound, upper_bound) = self.eof_bounds();
+        if bound <= lower_bound {
+            bound
+        } else {
+            bound - (upper_bound - lower_bound)
+        }
+    }
+}
+
+impl<A: Alphabet> Alphabet for WithoutEof<'_, A> {
+    type S = A::S;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.0.symbols()
+    }
+
+    fn eof(&self) -> Self::S {
+        self.0.eof()
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        if *symbol == self.0.eof() {
+            0
+        } else {
+            self.0.interval_width(symbol)
+        }
+    }
+
+    fn total_interval_width(&self) -> usize {
+        self.0.total_interval_width() - self.0.interval_width(&self.0.eof())
+    }
+
+    fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+        self.shift(self.0.interval_lower_bound(symbol))
+    }
+
+    fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+        self.shift(self.0.interval_upper_bound(symbol))
+    }
+
+    fn quantile(&self, value: usize) -> &Self::S {
+        let (lower_bound, upper_bound) = self.eof_bounds();
+        if value < lower_bound {
+            self.0.quantile(value)
+        } else {
+            self.0.quantile(value + upper_bound - lower_bound)
+        }
+    }
+
+    fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        if *symbol == self.0.eof() {
+            return None;
+        }
+        let (lower_bound, upper_bound) = self.0.checked_cdf(symbol)?;
+        Some((self.shift(lower_bound), self.shift(upper_bound)))
+    }
+
+    fn contains(&self, symbol: &Self::S) -> bool {
+        *symbol != self.0.eof() && self.0.contains(symbol)
+    }
+
+    fn index(&self, symbol: &Self::S) -> usize {
+        self.0.index(symbol)
+    }
+
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        self.0.symbol_at(index)
+    }
+}
+
+/// An object-safe mirror of [`Alphabet`], allowing alphabets to be selected at
+/// runtime and stored as `Box<dyn DynAlphabet<S = ...>>`.
+///
+/// Every [`Alphabet`] implements this trait, and boxed or borrowed trait
+/// objects implement [`Alphabet`] in turn, so they can be passed directly to
+/// the encoder and decoder.
+pub trait DynAlphabet {
+    type S: Symbol;
+
+    /// See [`Alphabet::symbols`].
+    fn dyn_symbols(&self) -> Box<dyn Iterator<Item = &Self::S> + '_>;
+
+    /// See [`Alphabet::eof`].
+    fn dyn_eof(&self) -> Self::S;
+
+    /// See [`Alphabet::interval_width`].
+    fn dyn_interval_width(&self, symbol: &Self::S) -> usize;
+
+    /// See [`Alphabet::total_interval_width`].
+    fn dyn_total_interval_width(&self) -> usize;
+
+    /// See [`Alphabet::interval_lower_bound`].
+    fn dyn_interval_lower_bound(&self, symbol: &Self::S) -> usize;
+
+    /// See [`Alphabet::interval_upper_bound`].
+    fn dyn_interval_upper_bound(&self, symbol: &Self::S) -> usize;
+
+    /// See [`Alphabet::quantile`].
+    fn dyn_quantile(&self, value: usize) -> &Self::S;
+
+    /// See [`Alphabet::checked_cdf`].
+    fn dyn_checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)>;
+
+    /// See [`Alphabet::contains`].
+    fn dyn_contains(&self, symbol: &Self::S) -> bool;
+
+    /// See [`Alphabet::index`].
+    fn dyn_index(&self, symbol: &Self::S) -> usize;
+
+    /// See [`Alphabet::symbol_at`].
+    fn dyn_symbol_at(&self, index: usize) -> &Self::S;
+
+    /// See [`Alphabet::begin_message`].
+    fn dyn_begin_message(&self);
+
+    /// See [`Alphabet::max_total_interval_width`].
+    fn dyn_max_total_interval_width(&self) -> usize;
+
+    /// See [`Alphabet::update`].
+    fn dyn_update(&self, symbol: &Self::S);
+
+    /// See [`Alphabet::is_static`].
+    fn dyn_is_static(&self) -> bool;
+}
+
+impl<A: Alphabet> DynAlphabet for A {
+    type S = A::S;
+
+    fn dyn_symbols(&self) -> Box<dyn Iterator<Item = &Self::S> + '_> {
+        Box::new(self.symbols())
+    }
+
+    fn dyn_eof(&self) -> Self::S {
+        self.eof()
+    }
+
+    fn dyn_interval_width(&self, symbol: &Self::S) -> usize {
+        self.interval_width(symbol)
+    }
+
+    fn dyn_total_interval_width(&self) -> usize {
+        self.total_interval_width()
+    }
+
+    fn dyn_interval_lower_bound(&self, symbol: &Self::S) -> usize {
+        self.interval_lower_bound(symbol)
+    }
+
+    fn dyn_interval_upper_bound(&self, symbol: &Self::S) -> usize {
+        self.interval_upper_bound(symbol)
+    }
+
+    fn dyn_quantile(&self, value: usize) -> &Self::S {
+        self.quantile(value)
+    }
+
+    fn dyn_checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        self.checked_cdf(symbol)
+    }
+
+    fn dyn_contains(&self, symbol: &Self::S) -> bool {
+        self.contains(symbol)
+    }
+
+    fn dyn_index(&self, symbol: &Self::S) -> usize {
+        self.index(symbol)
+    }
+
+    fn dyn_symbol_at(&self, index: usize) -> &Self::S {
+        self.symbol_at(index)
+    }
+
+    fn dyn_begin_message(&self) {
+        self.begin_message()
+    }
+
+    fn dyn_max_total_interval_width(&self) -> usize {
+        self.max_total_interval_width()
+    }
+
+    fn dyn_update(&self, symbol: &Self::S) {
+        self.update(symbol)
+    }
+
+    fn dyn_is_static(&self) -> bool {
+        self.is_static()
+    }
+}
+
+/// Implement [`Alphabet`] for a pointer to a [`DynAlphabet`] trait object by
+/// forwarding every method (including the provided ones, so that overrides in
+/// the underlying alphabet are preserved) to the trait object behind it.
+macro_rules! impl_alphabet_for_dyn {
+    ($($pointer:ty),*) => {$(
+        impl<'a, S: Symbol> Alphabet for $pointer {
+            type S = S;
+
+            fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+                (**self).dyn_symbols()
+            }
+
+            fn eof(&self) -> Self::S {
+                (**self).dyn_eof()
+            }
+
+            fn interval_width(&self, symbol: &Self::S) -> usize {
+                (**self).dyn_interval_width(symbol)
+            }
+
+            fn total_interval_width(&self) -> usize {
+                (**self).dyn_total_interval_width()
+            }
+
+            fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+                (**self).dyn_interval_lower_bound(symbol)
+            }
+
+            fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+                (**self).dyn_interval_upper_bound(symbol)
+            }
+
+            fn quantile(&self, value: usize) -> &Self::S {
+                (**self).dyn_quantile(value)
+            }
+
+            fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+                (**self).dyn_checked_cdf(symbol)
+            }
+
+            fn contains(&self, symbol: &Self::S) -> bool {
+                (**self).dyn_contains(symbol)
+            }
+
+            fn index(&self, symbol: &Self::S) -> usize {
+                (**self).dyn_index(symbol)
+            }
+
+            fn symbol_at(&self, index: usize) -> &Self::S {
+                (**self).dyn_symbol_at(index)
+            }
+
+            fn begin_message(&self) {
+                (**self).dyn_begin_message()
+            }
+
+            fn max_total_interval_width(&self) -> usize {
+                (**self).dyn_max_total_interval_width()
+            }
+
+            fn update(&self, symbol: &Self::S) {
+                (**self).dyn_update(symbol)
+            }
+
+            fn is_static(&self) -> bool {
+                (**self).dyn_is_static()
+            }
+        }
+    )*};
+}
+
+impl_alphabet_for_dyn!(Box<dyn DynAlphabet<S = S> + 'a>, &'a dyn DynAlphabet<S = S>);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bits::Bit::{One, Zero};
+    use crate::decoder::{DecodeError, Decoder, DecoderEvent};
+    use crate::encoder::{EncodeError, Encoder};
+    use crate::example::{ExampleAlphabet, ExampleSymbol, ExampleSymbol::*};
+
+    #[test]
+    fn boxed_alphabet_matches_underlying_alphabet() {
+        let alphabet = ExampleAlphabet::new();
+        let boxed: Box<dyn DynAlphabet<S = _>> = Box::new(ExampleAlphabet::new());
+
+        assert!(alphabet.symbols().eq(boxed.symbols()));
+        assert_eq!(boxed.eof(), Eof);
+        assert_eq!(boxed.total_interval_width(), 100);
+        for symbol in alphabet.symbols() {
+            assert_eq!(
+                boxed.interval_lower_bound(symbol),
+                alphabet.interval_lower_bound(symbol)
+            );
+            assert_eq!(
+                boxed.interval_upper_bound(symbol),
+                alphabet.interval_upper_bound(symbol)
+            );
+        }
+    }
+
+    fn assert_indices_match<A: Alphabet>(alphabet: &A) {
+        for (index, symbol) in alphabet.symbols().enumerate() {
+            assert_eq!(alphabet.index(symbol), index);
+            assert_eq!(alphabet.symbol_at(index), symbol);
+        }
+    }
+
+    #[test]
+    fn indices_match_symbol_order() {
+        use crate::byte::ByteAlphabet;
+        use crate::cached::CachedAlphabet;
+        use crate::normalized::NormalizedAlphabet;
+
+        assert_indices_match(&ExampleAlphabet::new());
+        assert_indices_match(&CachedAlphabet::new(ExampleAlphabet::new()));
+        assert_indices_match(&NormalizedAlphabet::new(ExampleAlphabet::new(), 8).unwrap());
+        assert_indices_match(&ByteAlphabet::from_sample(b"abc"));
+        let boxed: Box<dyn DynAlphabet<S = _>> = Box::new(ExampleAlphabet::new());
+        assert_indices_match(&boxed);
+    }
+
+    fn assert_quantiles_match<A: Alphabet>(alphabet: &A) {
+        for value in 0..alphabet.total_interval_width() {
+            let (lower, upper) = alphabet.cdf(alphabet.quantile(value));
+            assert!(
+                lower <= value && value < upper,
+                "{value} not in [{lower}, {upper})"
+            );
+        }
+    }
+
+    #[test]
+    fn quantile_inverts_cdf() {
+        use crate::byte::ByteAlphabet;
+        use crate::frequency::FrequencyTable;
+        use crate::models::AdaptiveAlphabet;
+
+        assert_quantiles_match(&ExampleAlphabet::new());
+        assert_eq!(ExampleAlphabet::new().cdf(&C), (75, 90));
+        assert_quantiles_match(&Widths(vec![(A, 3), (B, 0), (C, 2), (Eof, 1)]));
+        assert_quantiles_match(&FrequencyTable::from_widths(
+            vec![(A, 3), (B, 0), (C, 2), (Eof, 1)],
+            Eof,
+        ));
+        assert_quantiles_match(&ByteAlphabet::from_sample(b"quantile"));
+
+        let adaptive = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof);
+        for symbol in [C, C, A, C] {
+            adaptive.update(&symbol);
+        }
+        assert_quantiles_match(&adaptive);
+        let boxed: Box<dyn DynAlphabet<S = _>> = Box::new(adaptive);
+        assert_quantiles_match(&boxed);
+    }
+
+    fn assert_checked_cdf_matches<A: Alphabet>(alphabet: &A, missing: &A::S) {
+        for symbol in alphabet.symbols() {
+            assert_eq!(alphabet.checked_cdf(symbol), Some(alphabet.cdf(symbol)));
+        }
+        assert_eq!(alphabet.checked_cdf(missing), None);
+    }
+
+    #[test]
+    fn checked_cdf_matches_cdf() {
+        use crate::cached::CachedAlphabet;
+        use crate::frequency::FrequencyTable;
+        use crate::models::AdaptiveAlphabet;
+
+        let widths = vec![(A, 3), (B, 0), (Eof, 1)];
+        assert_checked_cdf_matches(&Widths(widths.clone()), &C);
+        let table = FrequencyTable::from_widths(widths, Eof);
+        assert_checked_cdf_matches(&CachedAlphabet::new(table.clone()), &C);
+        assert_checked_cdf_matches(&table, &C);
+        // EOF isn't coded without EOF
+        assert_eq!(WithoutEof(&table).checked_cdf(&A), Some((0, 3)));
+        assert_eq!(WithoutEof(&table).checked_cdf(&Eof), None);
+
+        let adaptive = AdaptiveAlphabet::new(vec![A, B, Eof], Eof);
+        adaptive.update(&B);
+        assert_checked_cdf_matches(&adaptive, &C);
+        let boxed: Box<dyn DynAlphabet<S = _>> = Box::new(adaptive);
+        assert_checked_cdf_matches(&boxed, &C);
+    }
+
+    struct Widths(Vec<(ExampleSymbol, usize)>);
+
+    impl Alphabet for Widths {
+        type S = ExampleSymbol;
+
+        fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+            self.0.iter().map(|(symbol, _)| symbol)
+        }
+
+        fn eof(&self) -> Self::S {
+            Eof
+        }
+
+        fn interval_width(&self, symbol: &Self::S) -> usize {
+            self.0.iter().find(|(s, _)| s == symbol).unwrap().1
+        }
+    }
+
+    #[test]
+    fn entropy_of_distributions() {
+        let uniform = Widths(vec![(A, 5), (B, 5), (C, 5), (Eof, 5)]);
+        assert_eq!(uniform.entropy(), 2.0);
+        assert_eq!(uniform.symbol_cost(&C), 2.0);
+
+        let certain = Widths(vec![(A, 0), (Eof, 3)]);
+        assert_eq!(certain.entropy(), 0.0);
+
+        // -(0.25 log 0.25 + 0.5 log 0.5 + 0.15 log 0.15 + 0.1 log 0.1)
+        let entropy = ExampleAlphabet::new().entropy();
+        assert!((entropy - 1.742_738).abs() < 1e-6, "entropy={entropy}");
+    }
+
+    #[test]
+    fn encoding_is_near_message_cost() {
+        let alphabet = ExampleAlphabet::new();
+        let input: Vec<_> = [B, A, B, C, B, A]
+            .into_iter()
+            .cycle()
+            .take(1000)
+            .chain([Eof])
+            .collect();
+        let cost = alphabet.message_cost(&input);
+        let length = alphabet.encode::<_, 32>(input).count() as f64;
+        assert!(
+            cost <= length && length <= cost + 3.0,
+            "cost={cost} length={length}"
+        );
+    }
+
+    #[test]
+    fn validate_accepts_example() {
+        assert_eq!(ExampleAlphabet::new().validate(32), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_broken_alphabets() {
+        assert_eq!(
+            Widths(vec![(A, 1), (B, 1)]).validate(32),
+            Err(AlphabetError::MissingEof("Eof".to_string()))
+        );
+        assert_eq!(
+            Widths(vec![(A, 1), (Eof, 1), (A, 1)]).validate(32),
+            Err(AlphabetError::DuplicateSymbol {
+                symbol: "A".to_string(),
+                first: 0,
+                second: 2
+            })
+        );
+        assert_eq!(
+            Widths(vec![(A, 1), (B, 0), (Eof, 1)]).validate(32),
+            Err(AlphabetError::ZeroWidth {
+                symbol: "B".to_string(),
+                index: 1
+            })
+        );
+        assert_eq!(
+            Widths(vec![(A, 1 << 40), (Eof, 1)]).validate(32),
+            Err(AlphabetError::TotalTooLarge {
+                total: (1 << 40) + 1,
+                bits_of_precision: 32,
+                max: 1 << 30
+            })
+        );
+    }
+
+    #[test]
+    fn validate_limits_total_to_a_quarter() {
+        let widths = |total: usize| Widths(vec![(A, total - 1), (Eof, 1)]);
+        assert_eq!(widths(1 << 14).validate(16), Ok(()));
+        assert_eq!(
+            widths((1 << 14) + 1).validate(16),
+            Err(AlphabetError::TotalTooLarge {
+                total: (1 << 14) + 1,
+                bits_of_precision: 16,
+                max: 1 << 14
+            })
+        );
+        assert_eq!(
+            widths(1 << 16)
+                .encode_to_vec::<_, 16>([A, Eof])
+                .unwrap_err(),
+            EncodeError::InvalidAlphabet(AlphabetError::TotalTooLarge {
+                total: 1 << 16,
+                bits_of_precision: 16,
+                max: 1 << 14
+            })
+        );
+
+        // At the boundary, every symbol still gets part of the interval
+        let boundary = widths(1 << 14);
+        let message = [[A; 100].as_slice(), &[Eof; 1]].concat();
+        let bits = boundary.encode_to_vec::<_, 16>(message.clone()).unwrap();
+        let (decoded, _) = boundary.decode_to_vec::<_, 16>(bits).unwrap();
+        assert_eq!(decoded, message[..100]);
+    }
+
+    #[test]
+    fn coders_report_invalid_alphabet() {
+        let alphabet = Widths(vec![(A, 1), (B, 0), (Eof, 1)]);
+        let error = AlphabetError::ZeroWidth {
+            symbol: "B".to_string(),
+            index: 1,
+        };
+        assert_eq!(
+            alphabet.encode::<_, 32>(vec![A, Eof]).next(),
+            Some(Err(EncodeError::InvalidAlphabet(error.clone())))
+        );
+        assert_eq!(
+            alphabet
+                .decode::<_, 32>(vec![Zero, One])
+                .collect::<Vec<_>>(),
+            vec![Err(DecodeError::InvalidAlphabet(error))]
+        );
+
+        // An alphabet with no width at all is reported rather than dividing
+        // by zero
+        for alphabet in [Widths(vec![]), Widths(vec![(Eof, 0)])] {
+            let error = alphabet.validate(32).unwrap_err();
+            assert_eq!(
+                alphabet.encode::<_, 32>(vec![Eof]).next(),
+                Some(Err(EncodeError::InvalidAlphabet(error.clone())))
+            );
+            assert_eq!(
+                alphabet.decode::<_, 32>(vec![Zero]).next(),
+                Some(Err(DecodeError::InvalidAlphabet(error)))
+            );
+        }
+    }
+
+    #[test]
+    fn encode_and_decode_with_boxed_alphabet() {
+        let boxed: Box<dyn DynAlphabet<S = _>> = Box::new(ExampleAlphabet::new());
+
+        let bits: Result<Vec<_>, _> = boxed.encode::<_, 32>(vec![C, Eof]).collect();
+        assert_eq!(bits, Ok(vec![One, One, One, Zero, Zero, One, Zero]));
+
+        let events: Result<Vec<_>, _> = boxed.decode::<_, 32>(bits.unwrap()).collect();
+        assert_eq!(
+            events,
+            Ok(vec![
+                DecoderEvent::DecodedSymbol(C),
+                DecoderEvent::DecodedSymbol(Eof),
+                DecoderEvent::MessageLength(7),
+            ])
+        );
+    }
 }
@@ -0,0 +1,219 @@
+//! [quickcheck] support for writing property tests against this crate.
+//!
+//! This provides an alphabet whose size and interval widths can be generated
+//! arbitrarily (while respecting the precision constraints of the coder), along
+//! with generators for random symbol streams and bit streams.
+
+use crate::alphabet::{Alphabet, Symbol};
+use crate::bits::Bit;
+use quickcheck::{Arbitrary, Gen};
+
+/// The maximum precision that arbitrary alphabets are guaranteed to support.
+pub const MAX_BITS_OF_PRECISION: u32 = 32;
+const QUARTER: usize = 2_usize.pow(MAX_BITS_OF_PRECISION) / 4;
+
+/// A symbol type that wraps an integer, so we can easily generate
+/// arbitrary-sized alphabets composed of these symbols. EOF is represented
+/// by the value zero.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct NumSymbol(pub usize);
+
+impl Symbol for NumSymbol {}
+
+impl NumSymbol {
+    pub fn eof() -> Self {
+        NumSymbol(0)
+    }
+}
+
+/// An alphabet of [`NumSymbol`]s, where the symbol `NumSymbol(i)` has the
+/// i-th interval width.
+#[derive(Debug, Clone)]
+pub struct NumAlphabet {
+    symbols: Vec<NumSymbol>,
+    interval_widths: Vec<usize>,
+}
+
+impl NumAlphabet {
+    pub fn new(interval_widths: Vec<usize>) -> Self {
+        assert!(
+            !interval_widths.is_empty(),
+            "Alphabet must have at least one symbol"
+        );
+        assert!(
+            interval_widths.iter().all(|&width| width > 0),
+            "Interval widths must be >0"
+        );
+        Self {
+            symbols: (0..interval_widths.len()).map(NumSymbol).collect(),
+            interval_widths,
+        }
+    }
+
+    /// Generate a stream of random symbols of the indicated length, terminated
+    /// by the EOF symbol.
+    ///
+    /// The alphabet must contain at least one non-EOF symbol.
+    pub fn random_symbol_stream(&self, g: &mut Gen, length: usize) -> Vec<NumSymbol> {
+        let non_eof_symbols = &self.symbols[1..];
+
+        (0..length)
+            .map(|_| *g.choose(non_eof_symbols).expect("No non-EOF symbols"))
+            .chain(std::iter::once(NumSymbol::eof()))
+            .collect()
+    }
+}
+
+impl Alphabet for NumAlphabet {
+    type S = NumSymbol;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.symbols.iter()
+    }
+
+    fn eof(&self) -> Self::S {
+        NumSymbol::eof()
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        for (s, &interval) in self.symbols.iter().zip(self.interval_widths.iter()) {
+            if symbol == s {
+                return interval;
+            }
+        }
+
+        panic!("Symbol {symbol:?} not in alphabet {self:?}")
+    }
+}
+
+struct ShrinkingNumAlphabet {
+    alphabet: NumAlphabet,
+}
+
+impl Iterator for ShrinkingNumAlphabet {
+    type Item = NumAlphabet;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Stop removing symbols when only EOF and one other symbol is left,
+        // because this is the smallest alphabet that can support arbitrary
+        // length input streams.
+        if self.alphabet.symbols.len() <= 2 {
+            return None;
+        }
+
+        self.alphabet.symbols.pop();
+        self.alphabet.interval_widths.pop();
+
+        Some(self.alphabet.clone())
+    }
+}
+
+impl Arbitrary for NumAlphabet {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let mut interval_widths = Vec::arbitrary(g);
+
+        // Ensure that at least two interval widths are present, to guarantee
+        // that there is room for 1) an EOF symbol, and 2) a non-EOF symbol.
+        // This is the smallest possible alphabet for which we can generate
+        // arbitrary-length random input sequences. An alphabet consisting
+        // only of EOF isn't capable of this because EOF immediately terminates
+        // the input.
+        interval_widths.push(usize::arbitrary(g));
+        interval_widths.push(usize::arbitrary(g));
+
+        // Due to the calculations done in the encoder/decoder to determine
+        // subintervals, there are constraints on how large R (the sum of all
+        // interval widths) can be.
+        //
+        // Specifically, we must be able to represent 2^precision * R as a usize.
+        // i.e. 2^precision * R <= usize::MAX
+        //                    R <= usize::MAX / 2^precision
+        //
+        // R must also be at most QUARTER, as checked by Alphabet::validate.
+        let max_total_width = (usize::MAX / 2usize.pow(MAX_BITS_OF_PRECISION)).min(QUARTER);
+
+        // To ensure intervals don't sum to an R greater than the max, set a
+        // conservative upper limit by dividing the max R.
+        let max_width = max_total_width / interval_widths.len();
+
+        // To ensure intervals aren't so small relative to R that they shrink to
+        // 0 when a and b are close enough together, set a lower limit. This is
+        // determined assuming the minimum difference between a and b is
+        // QUARTER = 2^precision / 4.
+        //
+        // In order to avoid shrinking to zero, each width must satisfy:
+        // (QUARTER * width) / R >= 1
+        let min_width = max_total_width / QUARTER;
+
+        // Scale all widths to be in [min_width, max_width)
+        for width in &mut interval_widths {
+            *width = min_width + (*width % (max_width - min_width));
+        }
+
+        NumAlphabet::new(interval_widths)
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        Box::new(ShrinkingNumAlphabet {
+            alphabet: self.clone(),
+        })
+    }
+}
+
+/// An arbitrary alphabet paired with an arbitrary EOF-terminated message
+/// drawn from it.
+#[derive(Debug, Clone)]
+pub struct NumMessage {
+    pub alphabet: NumAlphabet,
+    pub symbols: Vec<NumSymbol>,
+}
+
+impl Arbitrary for NumMessage {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let alphabet = NumAlphabet::arbitrary(g);
+        let length = usize::arbitrary(g) % (g.size() + 1);
+        let symbols = alphabet.random_symbol_stream(g, length);
+        Self { alphabet, symbols }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        // Shrink the message by truncating it (keeping the EOF terminator),
+        // trying progressively longer prefixes.
+        let alphabet = self.alphabet.clone();
+        let symbols = self.symbols.clone();
+        let length = symbols.len() - 1;
+
+        Box::new((0..length).map(move |prefix_length| {
+            let mut truncated = symbols[..prefix_length].to_vec();
+            truncated.push(NumSymbol::eof());
+            NumMessage {
+                alphabet: alphabet.clone(),
+                symbols: truncated,
+            }
+        }))
+    }
+}
+
+/// An arbitrary stream of bits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitStream(pub Vec<Bit>);
+
+impl Arbitrary for BitStream {
+    fn arbitrary(g: &mut Gen) -> Self {
+        BitStream(
+            Vec::<bool>::arbitrary(g)
+                .into_iter()
+                .map(Bit::from)
+                .collect(),
+        )
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let bools: Vec<bool> = self.0.iter().map(|&bit| bit == Bit::One).collect();
+        Box::new(
+            bools
+                .shrink()
+                .map(|bools| BitStream(bools.into_iter().map(Bit::from).collect())),
+        )
+    }
+}
@@ -0,0 +1,328 @@
+//! Streams compatible with those of the [arcode][1] crate, so that archives it
+//! compressed can be decoded with an alphabet of this crate, and arcode can
+//! decode the streams coded here.
+//!
+//! arcode rescales as [`crate::encoder`] does, except that after rescaling
+//! the middle two quarters it doesn't go back to check the halves, and it
+//! narrows the interval [low, high) of a register of BITS_OF_PRECISION bits
+//! to a symbol's bounds [l, u) in floating point:
+//!
+//! ```text
+//! high = low + ((high - low) as f64 * (u as f64 / R as f64)) as u64
+//! low = low + ((high - low) as f64 * (l as f64 / R as f64)) as u64
+//! ```
+//!
+//! So the streams differ in where they round, and are coded here with the
+//! same arithmetic. The bits are packed into bytes most significant first,
+//! and the final byte is padded with zeros. arcode's decoder reads up to
+//! BITS_OF_PRECISION bits past the end of a stream as zeros, and so does
+//! [`decode`].
+//!
+//! arcode's symbols are the numbers 0 to n - 1, which here are the symbols of
+//! an alphabet in the order of [`Alphabet::symbols`]. Its adaptive models give
+//! every symbol a count of one to begin with, and add one each time a symbol
+//! is coded, as [`AdaptiveAlphabet`](crate::models::AdaptiveAlphabet) does up
+//! to its limit on the total, and a model built with `EOFKind::EndAddOne` has
+//! EOF as its last symbol. So an arcode archive of bytes is decoded by an
+//! adaptive alphabet over the bytes 0 to 255 followed by EOF, with a limit
+//! larger than the archive.
+//!
+//! As the bounds aren't computed as products, R needn't fit in the bits above
+//! the precision, but it must be at most a quarter of 2^BITS_OF_PRECISION, or
+//! of 2^32 at higher precisions, since arcode's counts are 32 bits.
+//!
+//! It can be chosen in place of the bitwise coder with
+//! [`Coder::Arcode`](crate::encoder::Coder::Arcode).
+//!
+//! [1]: https://crates.io/crates/arcode
+
+use crate::alphabet::{Alphabet, AlphabetError};
+use crate::bits::{Bit, SliceBits, bits_to_bytes};
+use crate::decoder::DecodeError;
+use crate::encoder::EncodeError;
+use std::iter;
+
+/// The interval [low, high) of the coder.
+struct Interval<const BITS_OF_PRECISION: u32> {
+    low: u64,
+    high: u64,
+}
+
+impl<const BITS_OF_PRECISION: u32> Interval<BITS_OF_PRECISION> {
+    const WHOLE: u64 = 1 << BITS_OF_PRECISION;
+    const HALF: u64 = Self::WHOLE / 2;
+    const QUARTER: u64 = Self::WHOLE / 4;
+
+    /// The precision an alphabet is validated at, which limits R to a quarter
+    /// of 2^BITS_OF_PRECISION, and to 2^30 for arcode's 32-bit counts.
+    const VALIDATE_PRECISION: u32 = if BITS_OF_PRECISION < 32 {
+        BITS_OF_PRECISION
+    } else {
+        32
+    };
+
+    fn new() -> Self {
+        Self {
+            low: 0,
+            high: Self::WHOLE,
+        }
+    }
+
+    /// The bounds of the part of the interval given to the bounds of a
+    /// symbol out of a total of R.
+    fn bounds(&self, lower_bound: usize, upper_bound: usize, total: usize) -> (u64, u64) {
+        let width = (self.high - self.low) as f64;
+        let scale = |bound: usize| self.low + (width * (bound as f64 / total as f64)) as u64;
+        (scale(lower_bound), scale(upper_bound))
+    }
+
+    /// Take the next rescaling step if the interval lies within one half,
+    /// returning the bit it emits and the offset it subtracted before
+    /// doubling.
+    fn rescale_half(&mut self) -> Option<(Bit, u64)> {
+        let (bit, offset) = if self.high < Self::HALF {
+            (Bit::Zero, 0)
+        } else if self.low > Self::HALF {
+            (Bit::One, Self::HALF)
+        } else {
+            return None;
+        };
+        self.low = (self.low - offset) << 1;
+        self.high = (self.high - offset) << 1;
+        Some((bit, offset))
+    }
+
+    /// Take the next rescaling step if the interval lies within the middle
+    /// two quarters, returning the offset it subtracted before doubling.
+    fn rescale_middle(&mut self) -> Option<u64> {
+        if self.low > Self::QUARTER && self.high < Self::HALF + Self::QUARTER {
+            self.low = (self.low - Self::QUARTER) << 1;
+            self.high = (self.high - Self::QUARTER) << 1;
+            return Some(Self::QUARTER);
+        }
+        None
+    }
+}
+
+/// Check that R is small enough for every symbol to keep a part of the
+/// interval, as [`Alphabet::validate`] does.
+fn check_total<const BITS_OF_PRECISION: u32>(total: usize) -> Result<(), AlphabetError> {
+    let bits_of_precision = Interval::<BITS_OF_PRECISION>::VALIDATE_PRECISION;
+    let max = 1 << (bits_of_precision - 2);
+    if total > max {
+        return Err(AlphabetError::TotalTooLarge {
+            total,
+            bits_of_precision,
+            max,
+        });
+    }
+    Ok(())
+}
+
+/// Emit a bit, followed by the pending bits as its opposite.
+fn emit(bits: &mut Vec<bool>, bit: Bit, pending: &mut usize) {
+    let bit = bool::from(bit);
+    bits.push(bit);
+    bits.extend(iter::repeat_n(!bit, *pending));
+    *pending = 0;
+}
+
+/// Encode a message as arcode would, which must be terminated by EOF.
+pub fn encode<A: Alphabet, const BITS_OF_PRECISION: u32>(
+    alphabet: &A,
+    input: &[A::S],
+) -> Result<Vec<u8>, EncodeError> {
+    alphabet.begin_message();
+    alphabet.validate(Interval::<BITS_OF_PRECISION>::VALIDATE_PRECISION)?;
+
+    let mut bits = Vec::new();
+    let mut pending = 0;
+    let mut interval = Interval::<BITS_OF_PRECISION>::new();
+    let mut input = input.iter().enumerate();
+    loop {
+        let (position, symbol) = input.next().ok_or(EncodeError::UnterminatedStream)?;
+        let (lower_bound, upper_bound) = alphabet
+            .checked_cdf(symbol)
+            .ok_or(EncodeError::SymbolNotInAlphabet { position })?;
+        let total = alphabet.total_interval_width();
+        check_total::<BITS_OF_PRECISION>(total)?;
+        alphabet.update(symbol);
+
+        (interval.low, interval.high) = interval.bounds(lower_bound, upper_bound, total);
+        while let Some((bit, _)) = interval.rescale_half() {
+            emit(&mut bits, bit, &mut pending);
+        }
+        while interval.rescale_middle().is_some() {
+            pending += 1;
+        }
+
+        if *symbol == alphabet.eof() {
+            break;
+        }
+    }
+
+    pending += 1;
+    let bit = match interval.low <= Interval::<BITS_OF_PRECISION>::QUARTER {
+        true => Bit::Zero,
+        false => Bit::One,
+    };
+    emit(&mut bits, bit, &mut pending);
+    Ok(bits_to_bytes(bits))
+}
+
+/// Decode a message coded by arcode (or [`encode`]), returning its symbols
+/// (including EOF).
+pub fn decode<A: Alphabet, const BITS_OF_PRECISION: u32>(
+    alphabet: &A,
+    input: &[u8],
+) -> Result<Vec<A::S>, DecodeError> {
+    alphabet.begin_message();
+    alphabet.validate(Interval::<BITS_OF_PRECISION>::VALIDATE_PRECISION)?;
+
+    let padding = iter::repeat_n(Bit::Zero, BITS_OF_PRECISION as usize);
+    let mut bits = SliceBits::new(input).chain(padding);
+    let mut code = 0;
+    for _ in 0..BITS_OF_PRECISION {
+        let bit = bits.next().ok_or(DecodeError::UnexpectedEndOfInput(0))?;
+        code = (code << 1) | bit as u64;
+    }
+
+    let mut output = Vec::new();
+    let mut interval = Interval::<BITS_OF_PRECISION>::new();
+    loop {
+        let total = alphabet.total_interval_width();
+        check_total::<BITS_OF_PRECISION>(total)?;
+        // Estimate the value with exact arithmetic, and then step to the
+        // symbol whose rounded bounds contain the code. The code always lies
+        // within the interval, so there is one.
+        let width = interval.high - interval.low;
+        let estimate = (code - interval.low) as u128 * total as u128 / width as u128;
+        let mut value = (estimate as usize).min(total - 1);
+        let (symbol, bounds) = loop {
+            let symbol = alphabet.quantile(value);
+            let (lower_bound, upper_bound) = alphabet.cdf(symbol);
+            let (low, high) = interval.bounds(lower_bound, upper_bound, total);
+            if code < low {
+                value = lower_bound - 1;
+            } else if code >= high {
+                value = upper_bound;
+            } else {
+                break (symbol.clone(), (low, high));
+            }
+        };
+        alphabet.update(&symbol);
+
+        let eof = symbol == alphabet.eof();
+        output.push(symbol);
+        if eof {
+            return Ok(output);
+        }
+
+        (interval.low, interval.high) = bounds;
+        let mut next_bit = || {
+            bits.next()
+                .map(|bit| bit as u64)
+                .ok_or(DecodeError::UnexpectedEndOfInput(output.len()))
+        };
+        while let Some((_, offset)) = interval.rescale_half() {
+            code = ((code - offset) << 1) | next_bit()?;
+        }
+        while let Some(offset) = interval.rescale_middle() {
+            code = ((code - offset) << 1) | next_bit()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::byte::{ByteAlphabet, ByteSymbol};
+    use crate::decoder::Decoder;
+    use crate::encoder::{Coder, Encoder};
+    use crate::example::{ExampleAlphabet, ExampleSymbol::*};
+    use crate::models::AdaptiveAlphabet;
+
+    /// An alphabet which codes bytes as arcode's adaptive model over the
+    /// bytes does
+    fn arcode_bytes() -> AdaptiveAlphabet<ByteSymbol> {
+        AdaptiveAlphabet::new(
+            ByteAlphabet::message(0..=u8::MAX).collect(),
+            ByteSymbol::Eof,
+        )
+        .with_max_total(1 << 30)
+    }
+
+    #[test]
+    fn round_trip() {
+        let alphabet = ExampleAlphabet::new();
+        for message in [
+            vec![Eof],
+            vec![B, A, C, Eof],
+            [vec![C; 1000], vec![Eof]].concat(),
+        ] {
+            let bytes = encode::<_, 32>(&alphabet, &message).unwrap();
+            assert_eq!(decode::<_, 32>(&alphabet, &bytes), Ok(message));
+        }
+
+        let alphabet = arcode_bytes();
+        let text = b"she sells sea shells by the sea shore".repeat(20);
+        let message: Vec<_> = ByteAlphabet::message(text).collect();
+        for bytes in [
+            encode::<_, 48>(&alphabet, &message).unwrap(),
+            alphabet
+                .encode_with::<_, 48>(message.clone(), Coder::Arcode)
+                .unwrap(),
+        ] {
+            assert_eq!(decode::<_, 48>(&alphabet, &bytes).unwrap(), message);
+            assert_eq!(
+                alphabet.decode_with::<48>(&bytes, Coder::Arcode).unwrap(),
+                message[..message.len() - 1]
+            );
+        }
+    }
+
+    #[test]
+    fn arcode_fixtures() {
+        // Coded by arcode 0.2.4 with the models
+        // Model::builder().num_symbols(4).eof(EOFKind::End), updated after
+        // each symbol, and Model::builder().counts(vec![25, 50, 15, 10])
+        // .eof(EOFKind::End)
+        let message = [B, A, C, B, B, A, C, C, C, B, Eof];
+        let adaptive = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof);
+        let example = ExampleAlphabet::new();
+        for (bytes, coded) in [
+            (encode::<_, 32>(&adaptive, &message), [73, 86, 224]),
+            (encode::<_, 16>(&example, &message), [90, 16, 100]),
+        ] {
+            assert_eq!(bytes, Ok(coded.to_vec()));
+        }
+        assert_eq!(decode::<_, 32>(&adaptive, &[73, 86, 224]).unwrap(), message);
+        assert_eq!(decode::<_, 16>(&example, &[90, 16, 100]).unwrap(), message);
+        assert_eq!(encode::<_, 16>(&example, &[Eof]), Ok(vec![232]));
+        assert_eq!(decode::<_, 16>(&example, &[232]), Ok(vec![Eof]));
+    }
+
+    #[test]
+    fn errors() {
+        let alphabet = ExampleAlphabet::new();
+        assert_eq!(
+            encode::<_, 32>(&alphabet, &[B; 100]),
+            Err(EncodeError::UnterminatedStream)
+        );
+
+        let alphabet = arcode_bytes();
+        let message: Vec<_> = ByteAlphabet::message(b"abracadabra".repeat(50)).collect();
+        let bytes = encode::<_, 48>(&alphabet, &message).unwrap();
+        assert!(matches!(
+            decode::<_, 48>(&alphabet, &bytes[..bytes.len() / 2]),
+            Err(DecodeError::UnexpectedEndOfInput(_))
+        ));
+
+        // Counts which could outgrow the precision are rejected
+        let growing = AdaptiveAlphabet::new(vec![A, B, Eof], Eof).with_max_total(1 << 20);
+        assert!(matches!(
+            encode::<_, 16>(&growing, &[[A; 5000].as_slice(), &[Eof]].concat()),
+            Err(EncodeError::InvalidAlphabet(_))
+        ));
+    }
+}
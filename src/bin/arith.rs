@@ -0,0 +1,185 @@
+//! Compress and decompress files from the command line.
+//!
+//! ```bash
+//! cargo run --features cli --bin arith -- compress --model adaptive in.txt out.arith
+//! cargo run --features cli --bin arith -- decompress out.arith in.txt
+//! ```
+//!
+//! A compressed file is a byte naming the model, followed by a frame (see
+//! [`arithmetic_coding::format`]) holding the compressed bytes. The static
+//! model is carried in the frame, so every file can be decompressed without
+//! any options.
+
+use arithmetic_coding::byte::{ByteAlphabet, ByteSymbol};
+use arithmetic_coding::format::{self, Checksum, Frame};
+use arithmetic_coding::frequency::FrequencyTable;
+use arithmetic_coding::models::{AdaptiveAlphabet, DEFAULT_MAX_TOTAL};
+use arithmetic_coding::normalized::NormalizedAlphabet;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::PathBuf;
+
+/// The log2 of the largest total width a static model is normalized to
+const STATIC_LOG2_TOTAL: u32 = 16;
+
+/// The log2 of the largest total width a model can have at the given
+/// precision: R must stay within a quarter of 2^precision.
+fn log2_total(bits_of_precision: u32, largest: u32) -> u32 {
+    (bits_of_precision - 2).min(largest)
+}
+
+#[derive(Parser)]
+#[command(about = "Compress files with arithmetic coding")]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compress a file
+    Compress {
+        /// The model of the file's bytes
+        #[arg(long, value_enum, default_value_t = Model::Adaptive)]
+        model: Model,
+        /// The bits of precision of the coder
+        #[arg(long, default_value_t = 32, value_parser = parse_precision)]
+        precision: u32,
+        input: PathBuf,
+        output: PathBuf,
+    },
+    /// Decompress a file written by `compress`
+    Decompress { input: PathBuf, output: PathBuf },
+}
+
+/// The models a file can be compressed with, written as the first byte of the
+/// compressed file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum Model {
+    /// Every byte is equally likely
+    Byte,
+    /// The frequencies of the bytes in the file, stored with it
+    Static,
+    /// Frequencies learned as the file is coded
+    Adaptive,
+}
+
+impl Model {
+    const ALL: [Model; 3] = [Model::Byte, Model::Static, Model::Adaptive];
+
+    fn tag(self) -> u8 {
+        Self::ALL.iter().position(|&model| model == self).unwrap() as u8
+    }
+}
+
+fn parse_precision(precision: &str) -> Result<u32, String> {
+    match precision.parse() {
+        Ok(precision @ (16 | 24 | 32)) => Ok(precision),
+        _ => Err("Precision must be 16, 24 or 32".to_string()),
+    }
+}
+
+fn adaptive_alphabet(bits_of_precision: u32) -> AdaptiveAlphabet<ByteSymbol> {
+    let symbols = ByteAlphabet::message(0..=u8::MAX).collect();
+    let max_total = 1 << log2_total(bits_of_precision, DEFAULT_MAX_TOTAL.ilog2());
+    AdaptiveAlphabet::new(symbols, ByteSymbol::Eof).with_max_total(max_total)
+}
+
+fn compress<const BITS_OF_PRECISION: u32>(
+    model: Model,
+    bytes: &[u8],
+) -> Result<Frame, Box<dyn Error>> {
+    let message = ByteAlphabet::message(bytes.iter().copied());
+    let frame = match model {
+        Model::Byte => Frame::encode::<_, BITS_OF_PRECISION>(
+            &ByteAlphabet::uniform(),
+            message,
+            Checksum::Crc32,
+        )?,
+        Model::Static => {
+            let table = FrequencyTable::from_bytes(bytes);
+            let log2_total = log2_total(BITS_OF_PRECISION, STATIC_LOG2_TOTAL);
+            let alphabet = NormalizedAlphabet::new(table, log2_total)?;
+            Frame::encode_with_model::<_, BITS_OF_PRECISION>(&alphabet, message, Checksum::Crc32)?
+        }
+        Model::Adaptive => {
+            let alphabet = adaptive_alphabet(BITS_OF_PRECISION);
+            Frame::encode::<_, BITS_OF_PRECISION>(&alphabet, message, Checksum::Crc32)?
+        }
+    };
+    Ok(frame)
+}
+
+fn decompress<const BITS_OF_PRECISION: u32>(
+    model: Model,
+    frame: &Frame,
+) -> Result<Vec<ByteSymbol>, Box<dyn Error>> {
+    let symbols = match model {
+        Model::Byte => frame.decode::<_, BITS_OF_PRECISION>(&ByteAlphabet::uniform())?,
+        Model::Static => frame.decode_with_model::<_, BITS_OF_PRECISION>()?,
+        Model::Adaptive => {
+            frame.decode::<_, BITS_OF_PRECISION>(&adaptive_alphabet(BITS_OF_PRECISION))?
+        }
+    };
+    Ok(symbols)
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        Command::Compress {
+            model,
+            precision,
+            input,
+            output,
+        } => {
+            let bytes = fs::read(&input)?;
+            let frame = match precision {
+                16 => compress::<16>(model, &bytes)?,
+                24 => compress::<24>(model, &bytes)?,
+                _ => compress::<32>(model, &bytes)?,
+            };
+            let mut compressed = vec![model.tag()];
+            format::write_frame(&mut compressed, &frame)?;
+            fs::write(&output, &compressed)?;
+            eprintln!(
+                "{} bytes to {} bytes ({:.3} bits per byte)",
+                bytes.len(),
+                compressed.len(),
+                compressed.len() as f64 * 8.0 / bytes.len().max(1) as f64
+            );
+        }
+        Command::Decompress { input, output } => {
+            let mut reader = BufReader::new(File::open(&input)?);
+            let mut tag = [0];
+            reader.read_exact(&mut tag)?;
+            let model = *Model::ALL
+                .get(tag[0] as usize)
+                .ok_or_else(|| format!("Unknown model {}", tag[0]))?;
+            let frame = format::read_frame(&mut reader)?;
+            let symbols = match frame.precision {
+                16 => decompress::<16>(model, &frame)?,
+                24 => decompress::<24>(model, &frame)?,
+                32 => decompress::<32>(model, &frame)?,
+                precision => return Err(format!("Unsupported precision {precision}").into()),
+            };
+            let bytes: Vec<u8> = symbols
+                .into_iter()
+                .filter_map(|symbol| match symbol {
+                    ByteSymbol::Byte(byte) => Some(byte),
+                    ByteSymbol::Eof => None,
+                })
+                .collect();
+            fs::write(&output, bytes)?;
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run(Args::parse()) {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,303 @@
+//! Coding values from large alphabets as sequences of binary decisions.
+//!
+//! Rather than keeping a cumulative table over every possible value, each
+//! value is binarized (decomposed into a sequence of yes/no decisions), and
+//! each decision is coded with an adaptive two-symbol model chosen by its
+//! position in the binarization, as in CABAC. Only the decisions which are
+//! actually made need a model, so the alphabet can be very large.
+//!
+//! [`coder`] codes the same decisions with a dedicated binary coder, whose
+//! probability updates and interval splits need no division.
+
+use crate::alphabet::{Alphabet, Symbol};
+use crate::models::DEFAULT_MAX_TOTAL;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+pub mod coder;
+
+/// A binary decision, or the end of a message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Decision {
+    Zero,
+    One,
+    Eof,
+}
+
+impl Symbol for Decision {}
+
+/// The number of unary positions with their own model; later positions share
+/// the last one.
+const UNARY_CONTEXTS: usize = 32;
+
+/// A way of decomposing values into binary decisions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Binarization {
+    /// A value v is coded as v ones followed by a zero, which suits values
+    /// that are usually small. Each position has its own model, up to
+    /// position 32.
+    Unary,
+    /// Values below 2^bits are coded as their bits, most significant first.
+    /// Every node of the binary tree (every prefix of bits) has its own model,
+    /// so this can learn any distribution over the values.
+    FixedLength(u32),
+}
+
+impl Binarization {
+    /// The decisions of a single value, appended to the given vector.
+    fn binarize(&self, value: usize, decisions: &mut Vec<Decision>) {
+        match *self {
+            Binarization::Unary => {
+                decisions.extend(std::iter::repeat_n(Decision::One, value));
+                decisions.push(Decision::Zero);
+            }
+            Binarization::FixedLength(bits) => {
+                assert!(
+                    value >> bits == 0,
+                    "Value {value} doesn't fit in {bits} bits"
+                );
+                decisions.extend((0..bits).rev().map(|i| match (value >> i) & 1 {
+                    0 => Decision::Zero,
+                    _ => Decision::One,
+                }));
+            }
+        }
+    }
+}
+
+/// An adaptive model of the decisions of a [`Binarization`].
+///
+/// Each context (unary position or tree node) keeps counts of zeros and ones,
+/// which start at one and are halved when their total exceeds
+/// [`DEFAULT_MAX_TOTAL`]. EOF can only occur between values, where it has a
+/// width of one.
+///
+/// The counts are reset at the start of every message, and the position within
+/// the current value is tracked through [`Alphabet::update`], so the encoder
+/// and decoder stay in sync.
+pub struct BinaryModel {
+    binarization: Binarization,
+    state: RefCell<BinaryState>,
+}
+
+#[derive(Default)]
+struct BinaryState {
+    counts: HashMap<usize, [usize; 2]>,
+    /// The decisions made so far for the current value
+    decisions: usize,
+    /// The bits decided so far for the current value, after a leading one
+    prefix: usize,
+}
+
+impl BinaryModel {
+    pub fn new(binarization: Binarization) -> Self {
+        if let Binarization::FixedLength(bits) = binarization {
+            assert!(bits < usize::BITS, "Values of {bits} bits are too large");
+        }
+        Self {
+            binarization,
+            state: RefCell::new(BinaryState {
+                prefix: 1,
+                ..BinaryState::default()
+            }),
+        }
+    }
+
+    pub fn binarization(&self) -> Binarization {
+        self.binarization
+    }
+
+    /// The decisions for the given values, terminated by EOF, ready to encode.
+    pub fn binarize<I: IntoIterator<Item = usize>>(&self, values: I) -> Vec<Decision> {
+        let mut decisions = Vec::new();
+        for value in values {
+            self.binarization.binarize(value, &mut decisions);
+        }
+        decisions.push(Decision::Eof);
+        decisions
+    }
+
+    /// The values made up by a sequence of decisions, stopping at EOF. A value
+    /// which is cut off by EOF or by the end of the decisions is dropped.
+    pub fn debinarize<I: IntoIterator<Item = Decision>>(&self, decisions: I) -> Vec<usize> {
+        let mut values = Vec::new();
+        let (mut value, mut length) = (0, 0);
+        for decision in decisions {
+            let bit = match decision {
+                Decision::Zero => 0,
+                Decision::One => 1,
+                Decision::Eof => break,
+            };
+            length += 1;
+            let done = match self.binarization {
+                Binarization::Unary => {
+                    value += bit;
+                    bit == 0
+                }
+                Binarization::FixedLength(bits) => {
+                    value = (value << 1) | bit;
+                    length == bits as usize
+                }
+            };
+            if done {
+                values.push(value);
+                (value, length) = (0, 0);
+            }
+        }
+        values
+    }
+
+    /// The context of the next decision.
+    fn context(&self, state: &BinaryState) -> usize {
+        match self.binarization {
+            Binarization::Unary => state.decisions.min(UNARY_CONTEXTS - 1),
+            Binarization::FixedLength(_) => state.prefix,
+        }
+    }
+
+    /// The counts of zeros and ones in the context of the next decision.
+    fn counts(&self) -> [usize; 2] {
+        let state = self.state.borrow();
+        state
+            .counts
+            .get(&self.context(&state))
+            .copied()
+            .unwrap_or([1, 1])
+    }
+
+    /// Whether the next decision starts a new value, so EOF may occur.
+    fn between_values(&self) -> bool {
+        self.state.borrow().decisions == 0
+    }
+}
+
+impl Alphabet for BinaryModel {
+    type S = Decision;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        [Decision::Zero, Decision::One, Decision::Eof].iter()
+    }
+
+    fn eof(&self) -> Self::S {
+        Decision::Eof
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        match symbol {
+            Decision::Zero => self.counts()[0],
+            Decision::One => self.counts()[1],
+            Decision::Eof => usize::from(self.between_values()),
+        }
+    }
+
+    fn begin_message(&self) {
+        *self.state.borrow_mut() = BinaryState {
+            prefix: 1,
+            ..BinaryState::default()
+        };
+    }
+
+    /// The counts of a context are kept within the limit, and EOF adds one.
+    fn max_total_interval_width(&self) -> usize {
+        DEFAULT_MAX_TOTAL + 1
+    }
+
+    fn update(&self, symbol: &Self::S) {
+        let bit = match symbol {
+            Decision::Zero => 0,
+            Decision::One => 1,
+            Decision::Eof => return,
+        };
+        let context = self.context(&self.state.borrow());
+        let state = &mut *self.state.borrow_mut();
+
+        let counts = state.counts.entry(context).or_insert([1, 1]);
+        counts[bit] += 1;
+        if counts[0] + counts[1] > DEFAULT_MAX_TOTAL {
+            *counts = counts.map(|count| count.div_ceil(2));
+        }
+
+        state.decisions += 1;
+        let done = match self.binarization {
+            Binarization::Unary => bit == 0,
+            Binarization::FixedLength(bits) => {
+                state.prefix = (state.prefix << 1) | bit;
+                state.decisions == bits as usize
+            }
+        };
+        if done {
+            state.decisions = 0;
+            state.prefix = 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoder::{Decoder, DecoderEvent};
+    use crate::encoder::Encoder;
+    use crate::reference;
+
+    fn round_trip(model: &BinaryModel, values: &[usize]) -> usize {
+        let decisions = model.binarize(values.iter().copied());
+        assert_eq!(reference::cross_check::<_, 32>(model, &decisions), Ok(()));
+        let bits: Vec<_> = model
+            .encode::<_, 32>(decisions)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let decoded = model.debinarize(model.decode::<_, 32>(bits.clone()).filter_map(|event| {
+            match event.unwrap() {
+                DecoderEvent::DecodedSymbol(decision) => Some(decision),
+                _ => None,
+            }
+        }));
+        assert_eq!(decoded, values);
+        bits.len()
+    }
+
+    #[test]
+    fn binarize_values() {
+        use Decision::*;
+        let unary = BinaryModel::new(Binarization::Unary);
+        assert_eq!(unary.binarize([2, 0]), vec![One, One, Zero, Zero, Eof]);
+        assert_eq!(
+            unary.debinarize([One, One, Zero, Zero, One, Eof]),
+            vec![2, 0]
+        );
+
+        let fixed = BinaryModel::new(Binarization::FixedLength(3));
+        assert_eq!(fixed.binarize([5]), vec![One, Zero, One, Eof]);
+        assert_eq!(fixed.debinarize([One, One, Zero, One]), vec![6]);
+    }
+
+    #[test]
+    fn eof_only_between_values() {
+        let model = BinaryModel::new(Binarization::FixedLength(2));
+        assert_eq!(model.interval_width(&Decision::Eof), 1);
+        model.update(&Decision::One);
+        assert_eq!(model.interval_width(&Decision::Eof), 0);
+        assert_eq!(model.interval_width(&Decision::One), 1);
+        model.update(&Decision::One);
+        assert_eq!(model.interval_width(&Decision::Eof), 1);
+        // The root node has now seen a one
+        assert_eq!(model.interval_width(&Decision::One), 2);
+    }
+
+    #[test]
+    fn round_trip_unary() {
+        let values: Vec<_> = (0..200).map(|i| [0, 0, 1, 0, 2, 0, 1, 5][i % 8]).collect();
+        // Runs beyond the last unary context share its model
+        round_trip(&BinaryModel::new(Binarization::Unary), &[100, 3]);
+        let bits = round_trip(&BinaryModel::new(Binarization::Unary), &values);
+        assert!(bits < values.len() * 2, "{bits} bits");
+    }
+
+    #[test]
+    fn round_trip_large_alphabet() {
+        // A few values out of a million possible ones
+        let values: Vec<_> = (0..300).map(|i| [123_456, 999_999, 7][i % 3]).collect();
+        let bits = round_trip(&BinaryModel::new(Binarization::FixedLength(20)), &values);
+        assert!(bits < values.len() * 20 / 4, "{bits} bits");
+    }
+}
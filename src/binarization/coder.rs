@@ -0,0 +1,360 @@
+//! A dedicated coder for binary decisions, which needs no division, as an
+//! alternative to coding a [`BinaryModel`](super::BinaryModel) with the
+//! general-purpose coders.
+//!
+//! Each context keeps the probability of a zero as a 12-bit fixed-point
+//! number, rather than counts. The range is split in proportion to it with a
+//! shift and a multiplication, and after each decision the probability moves
+//! 1/32 of the way towards the bit that occurred, with another shift, as in
+//! LZMA and CABAC. So the hot loop has no division, and runs efficiently on
+//! microcontrollers without a hardware divider.
+//!
+//! The range coding is that of [`crate::range::carry`]: a 32-bit range,
+//! scaled up by a byte once it falls below 2^24, with carries propagated into
+//! the bytes held back. The output starts with a zero byte and is always a
+//! whole number of bytes. There's no EOF, so the decoder must be told how
+//! many values to decode.
+
+use super::{Binarization, Decision, UNARY_CONTEXTS};
+use crate::bits::Bit;
+use std::collections::HashMap;
+
+/// The number of bits of a probability
+pub const PROBABILITY_BITS: u32 = 12;
+
+/// A probability moves 2^-ADAPT_SHIFT of the way towards each bit coded
+const ADAPT_SHIFT: u32 = 5;
+
+/// The range is scaled up by a byte once it falls below this
+const TOP: u32 = 1 << 24;
+
+/// Errors that can occur while decoding binary decisions
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum BinaryDecodeError {
+    #[error("Input ended after {0} values")]
+    Truncated(usize),
+}
+
+/// The adaptive probability that a decision is zero, in units of
+/// 2^-[`PROBABILITY_BITS`].
+///
+/// It stays strictly between 0 and 1, so both bits can always be coded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Probability(u16);
+
+impl Probability {
+    /// Even odds.
+    pub fn new() -> Self {
+        Self(1 << (PROBABILITY_BITS - 1))
+    }
+
+    /// The probability of a zero, in units of 2^-[`PROBABILITY_BITS`].
+    pub fn zero(&self) -> u16 {
+        self.0
+    }
+
+    /// Move the probability towards the bit which occurred.
+    pub fn update(&mut self, bit: Bit) {
+        match bit {
+            Bit::Zero => self.0 += ((1 << PROBABILITY_BITS) - self.0) >> ADAPT_SHIFT,
+            Bit::One => self.0 -= self.0 >> ADAPT_SHIFT,
+        }
+    }
+
+    /// The part of the range given to a zero.
+    fn split(&self, range: u32) -> u32 {
+        (range >> PROBABILITY_BITS) * self.0 as u32
+    }
+}
+
+impl Default for Probability {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Codes bits, each with an adaptive probability chosen by the caller.
+pub struct BinaryEncoder {
+    output: Vec<u8>,
+    /// The low end of the range, with a carry bit above the low 32 bits
+    low: u64,
+    range: u32,
+    /// The last byte shifted out of low, which a carry may still increment
+    cache: u8,
+    /// The number of bytes held back: the cache and the 0xFF bytes after it
+    cache_size: usize,
+}
+
+impl BinaryEncoder {
+    pub fn new() -> Self {
+        Self {
+            output: Vec::new(),
+            low: 0,
+            range: u32::MAX,
+            cache: 0,
+            cache_size: 1,
+        }
+    }
+
+    /// Code a bit with the given probability, then update the probability.
+    pub fn encode(&mut self, bit: Bit, probability: &mut Probability) {
+        let split = probability.split(self.range);
+        match bit {
+            Bit::Zero => self.range = split,
+            Bit::One => {
+                self.low += split as u64;
+                self.range -= split;
+            }
+        }
+        probability.update(bit);
+        while self.range < TOP {
+            self.range <<= 8;
+            self.shift_low();
+        }
+    }
+
+    /// Shift the top byte out of low, emitting the bytes held back once a
+    /// carry can no longer reach them.
+    fn shift_low(&mut self) {
+        if self.low < 0xFF00_0000 || self.low >= 1 << 32 {
+            let carry = (self.low >> 32) as u8;
+            self.output.push(self.cache.wrapping_add(carry));
+            for _ in 1..self.cache_size {
+                self.output.push(0xFF_u8.wrapping_add(carry));
+            }
+            self.cache_size = 0;
+            self.cache = (self.low >> 24) as u8;
+        }
+        self.cache_size += 1;
+        self.low = (self.low & 0x00FF_FFFF) << 8;
+    }
+
+    /// Flush the state of the encoder, returning everything it coded.
+    pub fn finish(mut self) -> Vec<u8> {
+        for _ in 0..5 {
+            self.shift_low();
+        }
+        self.output
+    }
+}
+
+impl Default for BinaryEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decodes the bits coded by a [`BinaryEncoder`], given the same
+/// probabilities.
+pub struct BinaryDecoder<'a> {
+    input: std::slice::Iter<'a, u8>,
+    /// The code, relative to the low end of the range
+    code: u32,
+    range: u32,
+}
+
+impl<'a> BinaryDecoder<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        // The first byte is always the encoder's initial cache
+        let mut input = input.get(1..).unwrap_or_default().iter();
+        let mut code = 0_u32;
+        for _ in 0..4 {
+            code = (code << 8) | input.next().copied().unwrap_or(0) as u32;
+        }
+        Self {
+            input,
+            code,
+            range: u32::MAX,
+        }
+    }
+
+    /// Decode a bit with the given probability, then update the probability,
+    /// or return `None` if the input ends too soon.
+    pub fn decode(&mut self, probability: &mut Probability) -> Option<Bit> {
+        let split = probability.split(self.range);
+        let bit = if self.code < split {
+            self.range = split;
+            Bit::Zero
+        } else {
+            self.code -= split;
+            self.range -= split;
+            Bit::One
+        };
+        probability.update(bit);
+        while self.range < TOP {
+            // The encoder emits every byte the decoder reads
+            self.code = (self.code << 8) | *self.input.next()? as u32;
+            self.range <<= 8;
+        }
+        Some(bit)
+    }
+}
+
+/// The probabilities of the contexts of a [`Binarization`], which are those of
+/// a [`BinaryModel`](super::BinaryModel).
+struct Contexts {
+    binarization: Binarization,
+    probabilities: HashMap<usize, Probability>,
+}
+
+impl Contexts {
+    fn new(binarization: Binarization) -> Self {
+        if let Binarization::FixedLength(bits) = binarization {
+            assert!(bits < usize::BITS, "Values of {bits} bits are too large");
+        }
+        Self {
+            binarization,
+            probabilities: HashMap::new(),
+        }
+    }
+
+    /// The probability of the decision at the given position of a value,
+    /// after the given bits.
+    fn probability(&mut self, position: usize, prefix: usize) -> &mut Probability {
+        let context = match self.binarization {
+            Binarization::Unary => position.min(UNARY_CONTEXTS - 1),
+            Binarization::FixedLength(_) => prefix,
+        };
+        self.probabilities.entry(context).or_default()
+    }
+}
+
+/// Code values with the given binarization, each decision with the
+/// probability of its context.
+pub fn encode(binarization: Binarization, values: &[usize]) -> Vec<u8> {
+    let mut contexts = Contexts::new(binarization);
+    let mut encoder = BinaryEncoder::new();
+    let mut decisions = Vec::new();
+    for &value in values {
+        decisions.clear();
+        binarization.binarize(value, &mut decisions);
+        let mut prefix = 1;
+        for (position, decision) in decisions.iter().enumerate() {
+            let bit = match decision {
+                Decision::Zero => Bit::Zero,
+                _ => Bit::One,
+            };
+            encoder.encode(bit, contexts.probability(position, prefix));
+            prefix = (prefix << 1) | bit as usize;
+        }
+    }
+    encoder.finish()
+}
+
+/// Decode `len` values coded by [`encode`] with the same binarization.
+pub fn decode(
+    binarization: Binarization,
+    input: &[u8],
+    len: usize,
+) -> Result<Vec<usize>, BinaryDecodeError> {
+    let mut contexts = Contexts::new(binarization);
+    let mut decoder = BinaryDecoder::new(input);
+    let mut values = Vec::with_capacity(len);
+    while values.len() < len {
+        let decoded = values.len();
+        let (mut position, mut prefix) = (0, 1);
+        let mut next_bit = || {
+            let bit = decoder
+                .decode(contexts.probability(position, prefix))
+                .ok_or(BinaryDecodeError::Truncated(decoded))?;
+            position += 1;
+            prefix = (prefix << 1) | bit as usize;
+            Ok(bit)
+        };
+        let value = match binarization {
+            Binarization::Unary => {
+                let mut value = 0;
+                while next_bit()? == Bit::One {
+                    value += 1;
+                }
+                value
+            }
+            Binarization::FixedLength(bits) => {
+                let mut value = 0;
+                for _ in 0..bits {
+                    value = (value << 1) | next_bit()? as usize;
+                }
+                value
+            }
+        };
+        values.push(value);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::binarization::BinaryModel;
+    use crate::encoder::Encoder;
+    use crate::rng::SplitMix64;
+
+    #[test]
+    fn probabilities_stay_in_range() {
+        let mut probability = Probability::new();
+        for _ in 0..1000 {
+            probability.update(Bit::Zero);
+        }
+        assert!(probability.zero() < 1 << PROBABILITY_BITS);
+        for _ in 0..1000 {
+            probability.update(Bit::One);
+        }
+        assert!(probability.zero() > 0);
+    }
+
+    #[test]
+    fn round_trip_bits() {
+        let mut rng = SplitMix64(3);
+        // Heavily skewed bits make for many carries
+        for skew in [2, 50, 1000] {
+            let bits: Vec<_> = (0..5000)
+                .map(|_| match rng.below(skew) {
+                    0 => Bit::One,
+                    _ => Bit::Zero,
+                })
+                .collect();
+            let mut encoder = BinaryEncoder::new();
+            let mut probability = Probability::new();
+            for &bit in &bits {
+                encoder.encode(bit, &mut probability);
+            }
+            let bytes = encoder.finish();
+
+            let mut decoder = BinaryDecoder::new(&bytes);
+            let mut probability = Probability::new();
+            for &bit in &bits {
+                assert_eq!(decoder.decode(&mut probability), Some(bit));
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_values() {
+        let values: Vec<_> = (0..500).map(|i| [0, 0, 1, 0, 2, 0, 1, 40][i % 8]).collect();
+        let bytes = encode(Binarization::Unary, &values);
+        assert_eq!(
+            decode(Binarization::Unary, &bytes, values.len()),
+            Ok(values)
+        );
+
+        let values: Vec<_> = (0..3000).map(|i| [123_456, 999_999, 7][i % 3]).collect();
+        let fixed = Binarization::FixedLength(20);
+        let bytes = encode(fixed, &values);
+        assert_eq!(decode(fixed, &bytes, values.len()), Ok(values.clone()));
+        assert!(matches!(
+            decode(fixed, &bytes[..bytes.len() - 1], values.len()),
+            Err(BinaryDecodeError::Truncated(_))
+        ));
+        assert_eq!(decode(fixed, &[], 0), Ok(vec![]));
+        let empty = Binarization::FixedLength(0);
+        assert_eq!(decode(empty, &encode(empty, &[0; 5]), 5), Ok(vec![0; 5]));
+
+        // Not far off coding the model with the bitwise coder, whose counts
+        // learn a new context faster than the shifts
+        let model = BinaryModel::new(fixed);
+        let bits = model
+            .encode::<_, 32>(model.binarize(values.iter().copied()))
+            .count();
+        assert!(bytes.len() * 8 < bits * 3 / 2, "{} bytes", bytes.len());
+    }
+}
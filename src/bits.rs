@@ -0,0 +1,461 @@
+//! The [`Bit`] type, and sources and sinks of bits, which the decoder reads
+//! its input from and the encoder can write its output to.
+//!
+//! Any iterator of [`Bit`]s or `bool`s is a [`BitSource`], and bytes can be
+//! read bit by bit (most significant bit first) from a slice with
+//! [`SliceBits`] or from an [`io::Read`] with [`ReadBits`]. On the output side,
+//! [`BitSink`] is implemented for vectors of bits or `bool`s, and [`BitWriter`]
+//! packs bits into bytes for an [`io::Write`].
+//!
+//! Bits are packed into bytes most significant bit first, and the final
+//! partial byte is padded with zero bits, as by [`bits_to_bytes`] and
+//! [`bytes_to_bits`]. The decoder never needs to know about the padding:
+//! it stops reading at the end of a message, and the
+//! [`MessageLength`](crate::decoder::DecoderEvent::MessageLength) it reports
+//! is the length of the message before padding, so the exact bits of the
+//! message are the first that many bits of the unpacked bytes.
+//!
+//! [`Bit`] is the crate's own type, so no particular bit iterator crate is
+//! needed to use it. It converts to and from `bool`, and to and from the
+//! numbers 0 and 1 as `u8`. With the `biterator` feature enabled, it also
+//! converts to and from `biterator::Bit`, so iterators of those bits are
+//! sources as well.
+
+use std::fmt;
+use std::io;
+
+/// A single bit of encoded data.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Bit {
+    Zero,
+    One,
+}
+
+use Bit::{One, Zero};
+
+impl From<bool> for Bit {
+    fn from(value: bool) -> Self {
+        if value { One } else { Zero }
+    }
+}
+
+impl From<Bit> for bool {
+    fn from(bit: Bit) -> Self {
+        bit == One
+    }
+}
+
+impl From<Bit> for u8 {
+    fn from(bit: Bit) -> Self {
+        match bit {
+            Zero => 0,
+            One => 1,
+        }
+    }
+}
+
+/// The error converting a number other than 0 or 1 to a [`Bit`]
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("{0} is not a bit (0 or 1)")]
+pub struct InvalidBit(pub u8);
+
+impl TryFrom<u8> for Bit {
+    type Error = InvalidBit;
+
+    fn try_from(value: u8) -> Result<Self, InvalidBit> {
+        match value {
+            0 => Ok(Zero),
+            1 => Ok(One),
+            _ => Err(InvalidBit(value)),
+        }
+    }
+}
+
+impl fmt::Display for Bit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Zero => write!(f, "0"),
+            One => write!(f, "1"),
+        }
+    }
+}
+
+#[cfg(feature = "biterator")]
+impl From<biterator::Bit> for Bit {
+    fn from(bit: biterator::Bit) -> Self {
+        match bit {
+            biterator::Bit::Zero => Zero,
+            biterator::Bit::One => One,
+        }
+    }
+}
+
+#[cfg(feature = "biterator")]
+impl From<Bit> for biterator::Bit {
+    fn from(bit: Bit) -> Self {
+        match bit {
+            Zero => biterator::Bit::Zero,
+            One => biterator::Bit::One,
+        }
+    }
+}
+
+/// A source of bits to decode.
+pub trait BitSource {
+    /// Take the next bit from the source. None indicates the end of the input.
+    fn next_bit(&mut self) -> Option<Bit>;
+}
+
+/// Any iterator over bits (or values convertible to bits, such as `bool`) is
+/// a source of bits.
+impl<I, B> BitSource for I
+where
+    I: Iterator<Item = B>,
+    B: Into<Bit>,
+{
+    fn next_bit(&mut self) -> Option<Bit> {
+        self.next().map(Into::into)
+    }
+}
+
+/// Conversion into a [`BitSource`], accepted by the decoder so that
+/// collections of bits can be passed directly.
+pub trait IntoBitSource {
+    type Source: BitSource;
+
+    fn into_bit_source(self) -> Self::Source;
+}
+
+impl<T> IntoBitSource for T
+where
+    T: IntoIterator,
+    T::Item: Into<Bit>,
+{
+    type Source = T::IntoIter;
+
+    fn into_bit_source(self) -> Self::Source {
+        self.into_iter()
+    }
+}
+
+/// A destination for encoded bits.
+///
+/// Writing a bit can't fail; sinks backed by fallible IO (see [`BitWriter`])
+/// report errors when they are finished.
+pub trait BitSink {
+    /// Write the next bit to the sink.
+    fn write_bit(&mut self, bit: Bit);
+}
+
+impl BitSink for Vec<Bit> {
+    fn write_bit(&mut self, bit: Bit) {
+        self.push(bit);
+    }
+}
+
+impl BitSink for Vec<bool> {
+    fn write_bit(&mut self, bit: Bit) {
+        self.push(bit.into());
+    }
+}
+
+#[cfg(feature = "biterator")]
+impl BitSink for Vec<biterator::Bit> {
+    fn write_bit(&mut self, bit: Bit) {
+        self.push(bit.into());
+    }
+}
+
+/// Extract the bit at the given position (0 is the most significant) of a byte.
+fn bit_of(byte: u8, position: u8) -> Bit {
+    if byte & (0x80 >> position) != 0 {
+        One
+    } else {
+        Zero
+    }
+}
+
+/// The bits of a byte slice, most significant bit of each byte first.
+#[derive(Clone, Debug)]
+pub struct SliceBits<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SliceBits<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+}
+
+impl Iterator for SliceBits<'_> {
+    type Item = Bit;
+
+    fn next(&mut self) -> Option<Bit> {
+        let byte = *self.bytes.get(self.position / 8)?;
+        let bit = bit_of(byte, (self.position % 8) as u8);
+        self.position += 1;
+        Some(bit)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.bytes.len() * 8 - self.position;
+        (remaining, Some(remaining))
+    }
+}
+
+/// The bits of the bytes read from an [`io::Read`], most significant bit of
+/// each byte first.
+///
+/// An IO error ends the stream of bits; it can be retrieved with
+/// [`ReadBits::take_error`].
+#[derive(Debug)]
+pub struct ReadBits<R: io::Read> {
+    reader: R,
+    byte: u8,
+    position: u8,
+    error: Option<io::Error>,
+}
+
+impl<R: io::Read> ReadBits<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            byte: 0,
+            position: 8,
+            error: None,
+        }
+    }
+
+    /// Take the IO error that ended the stream of bits, if there was one.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+}
+
+impl<R: io::Read> Iterator for ReadBits<R> {
+    type Item = Bit;
+
+    fn next(&mut self) -> Option<Bit> {
+        if self.error.is_some() {
+            return None;
+        }
+
+        if self.position == 8 {
+            let mut buffer = [0];
+            loop {
+                match self.reader.read(&mut buffer) {
+                    Ok(0) => return None,
+                    Ok(_) => break,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => {
+                        self.error = Some(e);
+                        return None;
+                    }
+                }
+            }
+            self.byte = buffer[0];
+            self.position = 0;
+        }
+
+        let bit = bit_of(self.byte, self.position);
+        self.position += 1;
+        Some(bit)
+    }
+}
+
+/// Pack bits into bytes, most significant bit first, padding the final byte
+/// with zeros.
+pub fn bits_to_bytes<B: Into<Bit>>(bits: impl IntoIterator<Item = B>) -> Vec<u8> {
+    let mut writer = BitWriter::new(Vec::new());
+    for bit in bits {
+        writer.write_bit(bit.into());
+    }
+    writer.finish().expect("Writing to a Vec can't fail")
+}
+
+/// Unpack bytes into bits, most significant bit first, including any padding
+/// of the final byte.
+pub fn bytes_to_bits(bytes: &[u8]) -> Vec<Bit> {
+    SliceBits::new(bytes).collect()
+}
+
+/// A [`BitSink`] which packs bits into bytes (most significant bit first) and
+/// writes them to an [`io::Write`].
+///
+/// Call [`BitWriter::finish`] once all bits have been written, to pad the final
+/// partial byte with zeros, write it, and check for IO errors. After an error,
+/// subsequent bits are discarded.
+#[derive(Debug)]
+pub struct BitWriter<W: io::Write> {
+    writer: W,
+    byte: u8,
+    filled: u8,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> BitWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            byte: 0,
+            filled: 0,
+            error: None,
+        }
+    }
+
+    /// The underlying writer. Writing to it directly interleaves its output
+    /// with the bytes of this writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Write any partially filled final byte (padded with zeros), and return
+    /// the underlying writer, or the first IO error encountered.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.filled > 0 {
+            self.write_byte();
+        }
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.writer),
+        }
+    }
+
+    fn write_byte(&mut self) {
+        if self.error.is_none()
+            && let Err(e) = self.writer.write_all(&[self.byte])
+        {
+            self.error = Some(e);
+        }
+        self.byte = 0;
+        self.filled = 0;
+    }
+}
+
+impl<W: io::Write> BitSink for BitWriter<W> {
+    fn write_bit(&mut self, bit: Bit) {
+        if bit == One {
+            self.byte |= 0x80 >> self.filled;
+        }
+        self.filled += 1;
+        if self.filled == 8 {
+            self.write_byte();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bool_iterator_is_a_source() {
+        let mut source = vec![true, false].into_bit_source();
+        assert_eq!(source.next_bit(), Some(One));
+        assert_eq!(source.next_bit(), Some(Zero));
+        assert_eq!(source.next_bit(), None);
+    }
+
+    #[test]
+    fn slice_bits_most_significant_first() {
+        let bits: Vec<_> = SliceBits::new(&[0b1010_0000, 0b0000_0001]).collect();
+        assert_eq!(
+            bits,
+            vec![
+                One, Zero, One, Zero, Zero, Zero, Zero, Zero, //
+                Zero, Zero, Zero, Zero, Zero, Zero, Zero, One,
+            ]
+        );
+    }
+
+    #[test]
+    fn read_bits_matches_slice_bits() {
+        let bytes = [0x5a, 0xff, 0x00, 0x81];
+        let from_reader: Vec<_> = ReadBits::new(&bytes[..]).collect();
+        let from_slice: Vec<_> = SliceBits::new(&bytes).collect();
+        assert_eq!(from_reader, from_slice);
+    }
+
+    #[test]
+    fn bit_writer_pads_final_byte() {
+        let mut writer = BitWriter::new(Vec::new());
+        for bit in SliceBits::new(&[0xa5]).chain([One, One, Zero]) {
+            writer.write_bit(bit);
+        }
+        assert_eq!(writer.finish().unwrap(), vec![0xa5, 0b1100_0000]);
+    }
+
+    #[test]
+    fn pack_and_unpack_bits() {
+        let bits = [One, Zero, One, Zero, Zero, One, Zero, One, One, One];
+        let bytes = bits_to_bytes(bits);
+        assert_eq!(bytes, vec![0b1010_0101, 0b1100_0000]);
+        let unpacked = bytes_to_bits(&bytes);
+        assert_eq!(unpacked.len(), 16);
+        assert_eq!(unpacked[..bits.len()], bits);
+        assert!(unpacked[bits.len()..].iter().all(|&bit| bit == Zero));
+        assert_eq!(bits_to_bytes([true; 8]), vec![0xff]);
+        assert!(bits_to_bytes(Vec::<Bit>::new()).is_empty());
+    }
+
+    #[test]
+    fn padding_preserves_message_length() {
+        use crate::decoder::{Decoder, DecoderEvent};
+        use crate::encoder::Encoder;
+        use crate::example::{ExampleAlphabet, ExampleSymbol::*};
+
+        let alphabet = ExampleAlphabet::new();
+        let bits = alphabet.encode_to_vec::<_, 32>([B, A, C, C, Eof]).unwrap();
+        assert_ne!(bits.len() % 8, 0);
+        let unpacked = bytes_to_bits(&bits_to_bytes(bits.clone()));
+        let events: Vec<_> = alphabet
+            .decode::<_, 32>(unpacked.clone())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            events.last(),
+            Some(&DecoderEvent::MessageLength(bits.len()))
+        );
+        assert_eq!(unpacked[..bits.len()], bits);
+    }
+
+    #[test]
+    fn vector_sinks() {
+        let mut bits: Vec<Bit> = Vec::new();
+        let mut bools: Vec<bool> = Vec::new();
+        for bit in [One, Zero, One] {
+            bits.write_bit(bit);
+            bools.write_bit(bit);
+        }
+        assert_eq!(bits, vec![One, Zero, One]);
+        assert_eq!(bools, vec![true, false, true]);
+    }
+
+    #[test]
+    fn numeric_bits() {
+        assert_eq!(u8::from(One), 1);
+        assert_eq!(u8::from(Zero), 0);
+        assert_eq!(Bit::try_from(1), Ok(One));
+        assert_eq!(Bit::try_from(0), Ok(Zero));
+        assert_eq!(Bit::try_from(2), Err(InvalidBit(2)));
+    }
+
+    #[test]
+    fn display_bits() {
+        let bits: String = [One, Zero, One].iter().map(Bit::to_string).collect();
+        assert_eq!(bits, "101");
+    }
+
+    #[cfg(feature = "biterator")]
+    #[test]
+    fn biterator_interop() {
+        let mut source = vec![biterator::Bit::One, biterator::Bit::Zero].into_bit_source();
+        assert_eq!(source.next_bit(), Some(One));
+        assert_eq!(source.next_bit(), Some(Zero));
+
+        let mut sink: Vec<biterator::Bit> = Vec::new();
+        sink.write_bit(One);
+        assert_eq!(sink, vec![biterator::Bit::One]);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::alphabet::{Alphabet, Symbol};
+
+/// A byte, or the end of a byte stream.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ByteSymbol {
+    Byte(u8),
+    Eof,
+}
+
+impl Symbol for ByteSymbol {}
+
+impl From<u8> for ByteSymbol {
+    fn from(byte: u8) -> Self {
+        ByteSymbol::Byte(byte)
+    }
+}
+
+impl ByteSymbol {
+    /// The position of this symbol in a [`ByteAlphabet`]: bytes in order of
+    /// value, followed by EOF.
+    fn index(&self) -> usize {
+        match self {
+            ByteSymbol::Byte(byte) => *byte as usize,
+            ByteSymbol::Eof => 256,
+        }
+    }
+}
+
+/// An alphabet of all 256 byte values plus EOF, for compressing arbitrary
+/// binary data.
+///
+/// Symbols are indexed directly by value and cumulative bounds are precomputed,
+/// so every lookup takes constant time.
+#[derive(Clone, Debug)]
+pub struct ByteAlphabet {
+    symbols: Vec<ByteSymbol>,
+    /// bounds[i] is the lower bound of the i-th symbol, and bounds[257] is R
+    bounds: Vec<usize>,
+}
+
+impl ByteAlphabet {
+    /// An alphabet in which every byte and EOF are equally likely.
+    pub fn uniform() -> Self {
+        Self::from_weights([1; 256], 1)
+    }
+
+    /// An alphabet weighted by the number of times each byte occurs in the
+    /// given sample.
+    ///
+    /// Every byte (and EOF) is given one count in addition to its occurrences,
+    /// so that bytes missing from the sample can still be encoded. The total
+    /// interval width is the length of the sample plus 257, so a large sample
+    /// should be normalized with
+    /// [`NormalizedAlphabet`](crate::normalized::NormalizedAlphabet) to keep R
+    /// within the precision of the coder.
+    pub fn from_sample(sample: &[u8]) -> Self {
+        let mut counts = [1; 256];
+        for &byte in sample {
+            counts[byte as usize] += 1;
+        }
+        Self::from_weights(counts, 1)
+    }
+
+    /// An alphabet with the given weight for each byte value, and for EOF.
+    pub fn from_weights(weights: [usize; 256], eof_weight: usize) -> Self {
+        let mut bounds = Vec::with_capacity(258);
+        let mut sum = 0;
+        bounds.push(sum);
+        for weight in weights.into_iter().chain([eof_weight]) {
+            sum += weight;
+            bounds.push(sum);
+        }
+
+        Self {
+            symbols: (0..=u8::MAX)
+                .map(ByteSymbol::Byte)
+                .chain([ByteSymbol::Eof])
+                .collect(),
+            bounds,
+        }
+    }
+
+    /// The symbols of the given bytes, terminated by EOF, ready to encode.
+    pub fn message<I: IntoIterator<Item = u8>>(bytes: I) -> impl Iterator<Item = ByteSymbol> {
+        bytes
+            .into_iter()
+            .map(ByteSymbol::Byte)
+            .chain([ByteSymbol::Eof])
+    }
+}
+
+impl Alphabet for ByteAlphabet {
+    type S = ByteSymbol;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.symbols.iter()
+    }
+
+    fn eof(&self) -> Self::S {
+        ByteSymbol::Eof
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        let i = symbol.index();
+        self.bounds[i + 1] - self.bounds[i]
+    }
+
+    fn total_interval_width(&self) -> usize {
+        self.bounds[257]
+    }
+
+    fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+        self.bounds[symbol.index()]
+    }
+
+    fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+        self.bounds[symbol.index() + 1]
+    }
+
+    fn quantile(&self, value: usize) -> &Self::S {
+        let index = self.bounds.partition_point(|&bound| bound <= value) - 1;
+        assert!(
+            index < self.symbols.len(),
+            "Value {value} is beyond the total interval width"
+        );
+        &self.symbols[index]
+    }
+
+    fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        Some(self.cdf(symbol))
+    }
+
+    fn contains(&self, _symbol: &Self::S) -> bool {
+        true
+    }
+
+    fn index(&self, symbol: &Self::S) -> usize {
+        symbol.index()
+    }
+
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        &self.symbols[index]
+    }
+
+    fn is_static(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoder::{Decoder, DecoderEvent};
+    use crate::encoder::Encoder;
+    use crate::reference;
+
+    #[test]
+    fn sample_weights_bytes() {
+        let alphabet = ByteAlphabet::from_sample(b"hello");
+        assert_eq!(alphabet.total_interval_width(), 257 + 5);
+        assert_eq!(alphabet.interval_width(&ByteSymbol::Byte(b'l')), 3);
+        assert_eq!(alphabet.interval_width(&ByteSymbol::Byte(b'z')), 1);
+        assert_eq!(alphabet.interval_width(&ByteSymbol::Eof), 1);
+        assert_eq!(alphabet.interval_lower_bound(&ByteSymbol::Byte(1)), 1);
+        assert_eq!(alphabet.interval_upper_bound(&ByteSymbol::Eof), 262);
+    }
+
+    #[test]
+    fn byte_alphabets_match_reference() {
+        let sampled = ByteAlphabet::from_sample(b"the quick brown fox jumps over the lazy dog");
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&ByteAlphabet::uniform(), 50, 100, 5),
+            Ok(())
+        );
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&sampled, 50, 100, 6),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn round_trip_bytes() {
+        let text = b"she sells sea shells by the sea shore";
+        let alphabet = ByteAlphabet::from_sample(text);
+        let bits: Vec<_> = alphabet
+            .encode::<_, 32>(ByteAlphabet::message(text.iter().copied()))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(bits.len() < text.len() * 8);
+
+        let decoded: Vec<_> = alphabet
+            .decode::<_, 32>(bits)
+            .filter_map(|event| match event.unwrap() {
+                DecoderEvent::DecodedSymbol(ByteSymbol::Byte(byte)) => Some(byte),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(decoded, text);
+    }
+}
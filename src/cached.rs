@@ -0,0 +1,149 @@
+use crate::alphabet::Alphabet;
+use crate::lookup::SymbolLookup;
+
+/// An alphabet wrapper which precomputes the cumulative interval bounds of
+/// another alphabet, so that R, c_j, and d_j are looked up rather than summed.
+///
+/// The default implementations of [`Alphabet::interval_lower_bound`] and
+/// [`Alphabet::total_interval_width`] sum widths over the whole alphabet on
+/// every call, which the encoder and decoder make for every symbol. Wrapping a
+/// static alphabet in a `CachedAlphabet` does that work once.
+///
+/// Bounds are found by locating the symbol in the alphabet. As the decoder
+/// visits symbols in order, the position of the previous lookup is checked
+/// first, so a scan over the alphabet costs O(1) per symbol.
+///
+/// The wrapped alphabet must be static: its widths are read once, and the
+/// [`Alphabet::begin_message`] and [`Alphabet::update`] hooks are not
+/// forwarded to it.
+pub struct CachedAlphabet<A: Alphabet> {
+    alphabet: A,
+    symbols: SymbolLookup<A::S>,
+    /// bounds[i] is the lower bound of the i-th symbol, and bounds[n] is R
+    bounds: Vec<usize>,
+}
+
+impl<A: Alphabet> CachedAlphabet<A> {
+    pub fn new(alphabet: A) -> Self {
+        let symbols: Vec<A::S> = alphabet.symbols().cloned().collect();
+        let mut bounds = Vec::with_capacity(symbols.len() + 1);
+        let mut sum = 0;
+        bounds.push(sum);
+        for symbol in &symbols {
+            sum += alphabet.interval_width(symbol);
+            bounds.push(sum);
+        }
+
+        Self {
+            alphabet,
+            symbols: SymbolLookup::new(symbols),
+            bounds,
+        }
+    }
+
+    /// The underlying alphabet.
+    pub fn inner(&self) -> &A {
+        &self.alphabet
+    }
+}
+
+impl<A: Alphabet> Alphabet for CachedAlphabet<A> {
+    type S = A::S;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.symbols.symbols().iter()
+    }
+
+    fn eof(&self) -> Self::S {
+        self.alphabet.eof()
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        let i = self.symbols.index_of(symbol);
+        self.bounds[i + 1] - self.bounds[i]
+    }
+
+    fn total_interval_width(&self) -> usize {
+        self.bounds[self.symbols.len()]
+    }
+
+    fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+        self.bounds[self.symbols.index_of(symbol)]
+    }
+
+    fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+        self.bounds[self.symbols.index_of(symbol) + 1]
+    }
+
+    fn quantile(&self, value: usize) -> &Self::S {
+        let index = self.bounds.partition_point(|&bound| bound <= value) - 1;
+        assert!(
+            index < self.symbols.len(),
+            "Value {value} is beyond the total interval width"
+        );
+        &self.symbols.symbols()[index]
+    }
+
+    fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        let index = self.symbols.position(symbol)?;
+        Some((self.bounds[index], self.bounds[index + 1]))
+    }
+
+    fn contains(&self, symbol: &Self::S) -> bool {
+        self.symbols.contains(symbol)
+    }
+
+    fn index(&self, symbol: &Self::S) -> usize {
+        self.symbols.index_of(symbol)
+    }
+
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        &self.symbols.symbols()[index]
+    }
+
+    fn is_static(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::example::{ExampleAlphabet, ExampleSymbol::*};
+    use crate::reference;
+
+    #[test]
+    fn bounds_match_underlying_alphabet() {
+        let underlying = ExampleAlphabet::new();
+        let cached = CachedAlphabet::new(ExampleAlphabet::new());
+
+        assert_eq!(
+            cached.total_interval_width(),
+            underlying.total_interval_width()
+        );
+        // Look symbols up out of order, as well as in order
+        for symbol in [Eof, A, C, B, A, B, C, Eof] {
+            assert_eq!(
+                cached.interval_lower_bound(&symbol),
+                underlying.interval_lower_bound(&symbol)
+            );
+            assert_eq!(
+                cached.interval_upper_bound(&symbol),
+                underlying.interval_upper_bound(&symbol)
+            );
+            assert_eq!(
+                cached.interval_width(&symbol),
+                underlying.interval_width(&symbol)
+            );
+        }
+    }
+
+    #[test]
+    fn cached_alphabet_matches_reference() {
+        let alphabet = CachedAlphabet::new(ExampleAlphabet::new());
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&alphabet, 100, 50, 3),
+            Ok(())
+        );
+    }
+}
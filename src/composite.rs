@@ -0,0 +1,206 @@
+use crate::alphabet::{Alphabet, AlphabetError};
+use std::cell::Cell;
+
+/// An alphabet made up of several alphabets over the same symbols, one of
+/// which is active at a time, for messages whose sections follow different
+/// distributions (such as a header followed by a payload).
+///
+/// Each message starts in the first alphabet. Coding the designated switch
+/// symbol moves on to the next alphabet, and from the last back to the first.
+/// The switch happens in [`Alphabet::update`], so the encoder and decoder
+/// change alphabets at the same point in the message.
+///
+/// Every alphabet must contain the switch symbol and the same EOF symbol. To
+/// combine alphabets of different types, box them as
+/// [`DynAlphabet`](crate::alphabet::DynAlphabet)s.
+pub struct CompositeAlphabet<A: Alphabet> {
+    alphabets: Vec<A>,
+    switch: A::S,
+    active: Cell<usize>,
+}
+
+impl<A: Alphabet> CompositeAlphabet<A> {
+    /// Combine the given alphabets, in the order they are used, switching
+    /// between them on the given symbol.
+    pub fn new(alphabets: Vec<A>, switch: A::S) -> Self {
+        assert!(!alphabets.is_empty(), "No alphabets to combine");
+        let eof = alphabets[0].eof();
+        for alphabet in &alphabets {
+            assert!(
+                alphabet.symbols().any(|symbol| *symbol == switch),
+                "Switch symbol {switch:?} missing from an alphabet"
+            );
+            assert!(
+                alphabet.eof() == eof,
+                "Alphabets have different EOF symbols"
+            );
+        }
+        Self {
+            alphabets,
+            switch,
+            active: Cell::new(0),
+        }
+    }
+
+    /// The symbol which switches to the next alphabet.
+    pub fn switch(&self) -> &A::S {
+        &self.switch
+    }
+
+    /// The position of the alphabet currently in use.
+    pub fn active(&self) -> usize {
+        self.active.get()
+    }
+
+    fn alphabet(&self) -> &A {
+        &self.alphabets[self.active.get()]
+    }
+}
+
+impl<A: Alphabet> Alphabet for CompositeAlphabet<A> {
+    type S = A::S;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.alphabet().symbols()
+    }
+
+    fn eof(&self) -> Self::S {
+        self.alphabet().eof()
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        self.alphabet().interval_width(symbol)
+    }
+
+    fn total_interval_width(&self) -> usize {
+        self.alphabet().total_interval_width()
+    }
+
+    fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+        self.alphabet().interval_lower_bound(symbol)
+    }
+
+    fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+        self.alphabet().interval_upper_bound(symbol)
+    }
+
+    fn quantile(&self, value: usize) -> &Self::S {
+        self.alphabet().quantile(value)
+    }
+
+    fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        self.alphabet().checked_cdf(symbol)
+    }
+
+    fn contains(&self, symbol: &Self::S) -> bool {
+        self.alphabet().contains(symbol)
+    }
+
+    fn index(&self, symbol: &Self::S) -> usize {
+        self.alphabet().index(symbol)
+    }
+
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        self.alphabet().symbol_at(index)
+    }
+
+    fn begin_message(&self) {
+        self.active.set(0);
+        for alphabet in &self.alphabets {
+            alphabet.begin_message();
+        }
+    }
+
+    fn update(&self, symbol: &Self::S) {
+        self.alphabet().update(symbol);
+        if *symbol == self.switch {
+            self.active
+                .set((self.active.get() + 1) % self.alphabets.len());
+        }
+    }
+
+    /// Validate every alphabet, not only the one in use at the start of a
+    /// message.
+    fn validate(&self, bits_of_precision: u32) -> Result<(), AlphabetError> {
+        self.alphabets
+            .iter()
+            .try_for_each(|alphabet| alphabet.validate(bits_of_precision))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoder::{Decoder, DecoderEvent};
+    use crate::encoder::Encoder;
+    use crate::example::{ExampleSymbol, ExampleSymbol::*};
+    use crate::frequency::FrequencyTable;
+    use crate::reference;
+
+    /// A header of mostly A, then a payload of mostly B, separated by C
+    fn header_and_payload() -> CompositeAlphabet<FrequencyTable<ExampleSymbol>> {
+        CompositeAlphabet::new(
+            vec![
+                FrequencyTable::from_widths(vec![(A, 60), (B, 2), (C, 1), (Eof, 1)], Eof),
+                FrequencyTable::from_widths(vec![(A, 2), (B, 60), (C, 1), (Eof, 1)], Eof),
+            ],
+            C,
+        )
+    }
+
+    #[test]
+    fn switch_symbol_changes_alphabet() {
+        let alphabet = header_and_payload();
+        alphabet.begin_message();
+        assert_eq!(alphabet.interval_width(&A), 60);
+        alphabet.update(&A);
+        alphabet.update(&C);
+        assert_eq!(alphabet.active(), 1);
+        assert_eq!(alphabet.interval_width(&A), 2);
+        alphabet.update(&C);
+        assert_eq!(alphabet.active(), 0);
+        alphabet.update(&C);
+        alphabet.begin_message();
+        assert_eq!(alphabet.active(), 0);
+    }
+
+    #[test]
+    fn round_trip_sections() {
+        let alphabet = header_and_payload();
+        let input = [vec![A; 20], vec![C], vec![B; 40], vec![Eof]].concat();
+        let bits: Vec<_> = alphabet
+            .encode::<_, 32>(input.clone())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        // Each section is cheap in its own alphabet
+        assert!(bits.len() < 30, "{} bits", bits.len());
+
+        let decoded: Vec<_> = alphabet
+            .decode::<_, 32>(bits)
+            .filter_map(|event| match event.unwrap() {
+                DecoderEvent::DecodedSymbol(symbol) => Some(symbol),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(decoded, input);
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&alphabet, 50, 100, 17),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn every_alphabet_is_validated() {
+        let alphabet = CompositeAlphabet::new(
+            vec![
+                FrequencyTable::from_widths(vec![(A, 1), (C, 1), (Eof, 1)], Eof),
+                FrequencyTable::from_widths(vec![(A, 1), (C, 0), (Eof, 1)], Eof),
+            ],
+            C,
+        );
+        assert!(matches!(
+            alphabet.validate(32),
+            Err(AlphabetError::ZeroWidth { index: 1, .. })
+        ));
+    }
+}
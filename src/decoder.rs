@@ -1,6 +1,24 @@
-use crate::alphabet::{Alphabet, Symbol};
-use biterator::Bit::{self, One, Zero};
+use crate::alphabet::{Alphabet, AlphabetError, AlphabetRef, Symbol, WithoutEof};
+use crate::arcode;
+use crate::bits::Bit::{self, One, Zero};
+use crate::bits::{BitSource, IntoBitSource, ReadBits, SliceBits};
+use crate::encoder::Coder;
+use crate::models::{Model, ModelAlphabet};
+use crate::range::{self, RangeDecodeError};
+use crate::reciprocal::Reciprocal;
+use crate::rescale::{MIN_BITS_OF_PRECISION, RescaleRun};
 use log::debug;
+use std::fmt;
+use std::io;
+use std::iter::{self, FusedIterator};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+mod feed;
+mod reader;
+
+pub use feed::DecoderFeed;
+pub use reader::DecoderReader;
 
 /// Decoder Algorithm
 /// Adapted from mathematicalmonk's ["Finite-precision arithmetic coding - Decoder"][1]
@@ -55,8 +73,11 @@ use log::debug;
 /// <-------------------------------------------------------- CalculateLength
 /// <-------------------------------------------------------- Final
 /// ```
-#[derive(PartialEq, Debug)]
-enum DecoderState {
+///
+/// The states are marked above. The state the decoder will execute next is
+/// reported by [`DecoderOutput::snapshot`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum DecoderState {
     Initial,
     TopOfSymbolLoop,
     Rescaling,
@@ -66,6 +87,68 @@ enum DecoderState {
 
 use DecoderState::*;
 
+/// Errors that can occur while decoding
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum DecodeError {
+    #[error("Decoding exceeded its work budget of {0} operations")]
+    BudgetExceeded(usize),
+    #[error("Decoding exceeded its limit of {0}")]
+    LimitExceeded(Limit),
+    #[error("Decoding was cancelled")]
+    Cancelled,
+    #[error("More input is needed to continue decoding")]
+    NeedMoreBits,
+    #[error("Input ended before EOF, after {0} symbols")]
+    UnexpectedEndOfInput(usize),
+    #[error("Input is corrupt: z ({z}) lies outside the interval [{a}, {b})")]
+    CorruptInput { a: usize, b: usize, z: usize },
+    #[error("No symbol's interval contains the value {0}")]
+    NoMatchingSymbol(usize),
+    #[error("Decoding has already failed")]
+    AlreadyFailed,
+    #[error("A bypass bit can only be decoded within a message, once any pending event is taken")]
+    MisplacedBypass,
+    #[error(transparent)]
+    InvalidAlphabet(#[from] AlphabetError),
+}
+
+/// A limit on the size of a decoded message. See
+/// [`DecoderOutput::with_max_symbols`] and [`DecoderOutput::with_max_bits`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Symbols(usize),
+    Bits(usize),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Symbols(max) => write!(f, "{max} symbols"),
+            Limit::Bits(max) => write!(f, "{max} bits"),
+        }
+    }
+}
+
+/// A snapshot of the internal state of a decoder, from
+/// [`DecoderOutput::snapshot`], for debugging.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DecoderSnapshot {
+    /// The state the decoder will execute next
+    pub state: DecoderState,
+    /// The lower bound of the current interval
+    pub a: usize,
+    /// The upper bound of the current interval
+    pub b: usize,
+    /// The window of the input, with its unread bits taken to be zero
+    pub z: usize,
+    /// The number of low bits of z which haven't been read from the input yet
+    pub unread_bits: u32,
+    /// The number of bits read while decoding the current message
+    pub bits_read: usize,
+    /// The number of symbols decoded in the current message
+    pub symbols_decoded: usize,
+}
+
 #[derive(PartialEq, Debug)]
 pub enum DecoderEvent<S: Symbol> {
     /// A symbol was decoded from the input stream.
@@ -79,16 +162,84 @@ pub struct DecoderOutput<'a, S, A, I, const BITS_OF_PRECISION: u32>
 where
     S: Symbol,
     A: Alphabet<S = S>,
-    I: Iterator<Item = Bit>,
+    I: BitSource,
 {
     input: I,
-    alphabet: &'a A,
+    alphabet: AlphabetRef<'a, A>,
     state: DecoderState,
     event_to_emit: Option<DecoderEvent<S>>,
+    total_interval_width: Reciprocal,
     a: usize,
     b: usize,
     z: usize,
     z_rescale_counter: usize,
+    unread_bits: u32,
+    input_exhausted: bool,
+    bits_read: usize,
+    bits_consumed: usize,
+    virtual_zeros: usize,
+    symbols_decoded: usize,
+    symbol_count: Option<usize>,
+    message_length: Option<usize>,
+    streaming: bool,
+    work_budget: Option<usize>,
+    work_done: usize,
+    max_symbols: Option<usize>,
+    max_bits: Option<usize>,
+    keep_model: bool,
+    cancelled: Option<Arc<AtomicBool>>,
+    bound_table: Option<BoundTable<S>>,
+    alphabet_validated: bool,
+}
+
+/// The cumulative interval bounds of a static alphabet, tabulated so that the
+/// subinterval containing a value can be found by binary search.
+struct BoundTable<S: Symbol> {
+    symbols: Vec<S>,
+    /// bounds[i] is the lower bound of the i-th symbol, and bounds[n] is R
+    bounds: Vec<usize>,
+}
+
+impl<S: Symbol> BoundTable<S> {
+    fn new<A: Alphabet<S = S>>(alphabet: &A) -> Self {
+        let symbols: Vec<S> = alphabet.symbols().cloned().collect();
+        let mut table = Self {
+            bounds: Vec::with_capacity(symbols.len() + 1),
+            symbols,
+        };
+        table.refresh(alphabet);
+        table
+    }
+
+    /// Tabulate the current widths of the alphabet again.
+    fn refresh<A: Alphabet<S = S>>(&mut self, alphabet: &A) {
+        self.bounds.clear();
+        let mut sum = 0;
+        self.bounds.push(sum);
+        for symbol in &self.symbols {
+            sum += alphabet.interval_width(symbol);
+            self.bounds.push(sum);
+        }
+    }
+
+    fn total_interval_width(&self) -> usize {
+        self.bounds[self.symbols.len()]
+    }
+
+    fn index(&self, value: usize) -> usize {
+        self.bounds.partition_point(|&bound| bound <= value) - 1
+    }
+
+    /// The symbol whose interval contains the value.
+    fn quantile(&self, value: usize) -> &S {
+        &self.symbols[self.index(value)]
+    }
+
+    /// The bounds of the interval containing the value.
+    fn cdf(&self, value: usize) -> (usize, usize) {
+        let index = self.index(value);
+        (self.bounds[index], self.bounds[index + 1])
+    }
 }
 
 impl<S, A, I, const BITS_OF_PRECISION: u32> Iterator
@@ -96,27 +247,58 @@ impl<S, A, I, const BITS_OF_PRECISION: u32> Iterator
 where
     S: Symbol,
     A: Alphabet<S = S>,
-    I: Iterator<Item = Bit>,
+    I: BitSource,
 {
-    type Item = DecoderEvent<S>;
+    type Item = Result<DecoderEvent<S>, DecodeError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.next_event()
     }
+
+    /// A pending event is certain. Until the message has ended, at least one
+    /// more event (its MessageLength, or an error) follows, and there is no
+    /// upper bound unless the number of symbols in the message is known.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let pending = usize::from(self.event_to_emit.is_some());
+        let remaining = match (&self.state, self.symbol_count) {
+            (Final, _) => return (pending, Some(pending)),
+            // More bits may be awaited any number of times
+            _ if self.streaming => None,
+            (CalculateLength, _) => Some(1),
+            (_, Some(count)) => Some(count - self.symbols_decoded + 1),
+            (_, None) => None,
+        };
+        (pending + 1, remaining.map(|remaining| pending + remaining))
+    }
+}
+
+impl<S, A, I, const BITS_OF_PRECISION: u32> FusedIterator
+    for DecoderOutput<'_, S, A, I, BITS_OF_PRECISION>
+where
+    S: Symbol,
+    A: Alphabet<S = S>,
+    I: BitSource,
+{
 }
 
 impl<'a, S, A, I, const BITS_OF_PRECISION: u32> DecoderOutput<'a, S, A, I, BITS_OF_PRECISION>
 where
     S: Symbol,
     A: Alphabet<S = S>,
-    I: Iterator<Item = Bit>,
+    I: BitSource,
 {
     const WHOLE: usize = 2_usize.pow(BITS_OF_PRECISION);
     const HALF: usize = Self::WHOLE / 2;
     const QUARTER: usize = Self::WHOLE / 4;
 
     /// Construct a new DecoderOutput from a stream of bits and an alphabet
-    fn new(input: I, alphabet: &'a A) -> Self {
+    fn new(input: I, alphabet: impl Into<AlphabetRef<'a, A>>) -> Self {
+        const {
+            assert!(
+                BITS_OF_PRECISION >= MIN_BITS_OF_PRECISION,
+                "At least 2 bits of precision are needed"
+            )
+        };
         debug!(
             "Decoding with {BITS_OF_PRECISION} bits (whole={} half={} quarter={})",
             Self::WHOLE,
@@ -125,44 +307,441 @@ where
         );
         DecoderOutput {
             input,
-            alphabet,
+            alphabet: alphabet.into(),
             state: Initial,
             event_to_emit: None,
+            // Replaced once the alphabet is validated, as R may be zero until then
+            total_interval_width: Reciprocal::new(1),
             a: 0,
             b: 0,
             z: 0,
             z_rescale_counter: 0,
+            unread_bits: 0,
+            input_exhausted: false,
+            bits_read: 0,
+            bits_consumed: 0,
+            virtual_zeros: 0,
+            symbols_decoded: 0,
+            symbol_count: None,
+            message_length: None,
+            streaming: false,
+            work_budget: None,
+            work_done: 0,
+            max_symbols: None,
+            max_bits: None,
+            keep_model: false,
+            cancelled: None,
+            bound_table: None,
+            alphabet_validated: false,
+        }
+    }
+
+    /// Bound the amount of work the decoder may do, so that the CPU time spent
+    /// decoding untrusted input can be limited.
+    ///
+    /// Every execution of a state in the decoder state machine counts as one
+    /// operation, as does every individual rescaling step. Once the budget is
+    /// exhausted, decoding stops with [`DecodeError::BudgetExceeded`].
+    pub fn with_work_budget(mut self, budget: usize) -> Self {
+        self.work_budget = Some(budget);
+        self
+    }
+
+    /// Limit the number of symbols decoded per message (including EOF), so
+    /// that untrusted input can't expand into an unbounded number of them.
+    ///
+    /// Decoding stops with [`DecodeError::LimitExceeded`] before decoding a
+    /// symbol beyond the limit.
+    pub fn with_max_symbols(mut self, max: usize) -> Self {
+        self.max_symbols = Some(max);
+        self
+    }
+
+    /// Limit the number of bits read from the input per message.
+    ///
+    /// Decoding stops with [`DecodeError::LimitExceeded`] before reading a
+    /// bit beyond the limit.
+    pub fn with_max_bits(mut self, max: usize) -> Self {
+        self.max_bits = Some(max);
+        self
+    }
+
+    /// Tabulate the cumulative bounds of the alphabet now, so that each symbol
+    /// is found by binary search over the table, rather than with
+    /// [`Alphabet::quantile`] and [`Alphabet::cdf`].
+    ///
+    /// This speeds up decoding with large static alphabets whose quantile
+    /// scans the symbols (the default). Unless the alphabet is known to be
+    /// static (see [`Alphabet::is_static`]), the table is refreshed after
+    /// every [`Alphabet::begin_message`] and [`Alphabet::update`], so
+    /// adaptive alphabets decode correctly, if no faster.
+    pub fn with_bound_table(mut self) -> Self {
+        self.alphabet.begin_message();
+        self.bound_table = Some(BoundTable::new(&*self.alphabet));
+        self
+    }
+
+    /// Stop decoding with [`DecodeError::Cancelled`] once the given flag is
+    /// set, so that a long decode can be aborted from another thread.
+    ///
+    /// The flag is checked before each step of the decoder state machine.
+    pub fn with_cancellation(mut self, cancelled: Arc<AtomicBool>) -> Self {
+        self.cancelled = Some(cancelled);
+        self
+    }
+
+    /// Prepare to decode the next message from the remaining input, reusing
+    /// the setup done for the alphabet (such as the precomputed reciprocal of
+    /// R) and keeping any configured work budget, which applies to each
+    /// message separately.
+    ///
+    /// As the decoder never reads past the end of a message, the next message
+    /// starts immediately after the previous one. Note that a message may be
+    /// fully determined before all of its bits have been read, so if messages
+    /// are concatenated, the bits following a message (up to its
+    /// [`DecoderEvent::MessageLength`]) must be skipped before resetting.
+    pub fn reset(&mut self) {
+        self.state = Initial;
+        self.event_to_emit = None;
+        self.a = 0;
+        self.b = 0;
+        self.z = 0;
+        self.z_rescale_counter = 0;
+        self.unread_bits = 0;
+        self.input_exhausted = false;
+        self.bits_read = 0;
+        self.virtual_zeros = 0;
+        self.symbols_decoded = 0;
+        self.message_length = None;
+        self.work_done = 0;
+    }
+
+    /// Prepare to decode the next message from the remaining input, like
+    /// [`DecoderOutput::reset`], but without starting the model afresh with
+    /// [`Alphabet::begin_message`], to decode messages encoded with
+    /// [`EncoderOutput::reset_keeping_model`](crate::encoder::EncoderOutput::reset_keeping_model)
+    /// or [`EncoderSink::flush`](crate::encoder::EncoderSink::flush).
+    pub fn reset_keeping_model(&mut self) {
+        self.reset();
+        self.keep_model = true;
+    }
+
+    /// Prepare to decode a message from a new input stream, reusing the setup
+    /// done for the alphabet.
+    pub fn reset_with(&mut self, input: I) {
+        self.input = input;
+        self.bits_consumed = 0;
+        self.reset();
+    }
+
+    /// The number of bits of input read while decoding the current message.
+    ///
+    /// This never exceeds the message length, so any bits following the
+    /// message remain in the input.
+    pub fn bits_read(&self) -> usize {
+        self.bits_read
+    }
+
+    /// Decode up to the given number of symbols, stopping early if the
+    /// message ends, and return them.
+    ///
+    /// Decoding can be resumed from the same point, by this or any other
+    /// method, including after [`DecoderOutput::suspend`]. The message's
+    /// MessageLength event isn't returned, and remains to be taken.
+    pub fn decode_symbols(&mut self, count: usize) -> Result<Vec<S>, DecodeError> {
+        let mut symbols = Vec::new();
+        while symbols.len() < count && self.peek()?.is_some() {
+            match self.next() {
+                Some(Ok(DecoderEvent::DecodedSymbol(symbol))) => symbols.push(symbol),
+                Some(Err(e)) => return Err(e),
+                _ => unreachable!("A peeked symbol is decoded next"),
+            }
+        }
+        Ok(symbols)
+    }
+
+    /// Decode the rest of the message only as far as needed to find its end,
+    /// without producing its events, and return its length in bits.
+    ///
+    /// The coder still has to narrow its interval for every symbol, but this
+    /// avoids collecting or matching on them, for callers which only need to
+    /// get past the message, such as to seek to a later message (see
+    /// [`DecoderMessages::skip_message`]). If decoding has already failed,
+    /// this returns [`DecodeError::AlreadyFailed`].
+    pub fn skip_message(&mut self) -> Result<usize, DecodeError> {
+        loop {
+            if let Some(DecoderEvent::MessageLength(length)) = self.event_to_emit.take() {
+                return Ok(length);
+            }
+            if self.state == Final {
+                return self.message_length.ok_or(DecodeError::AlreadyFailed);
+            }
+
+            match self.execute() {
+                Err(DecodeError::NeedMoreBits) => return Err(DecodeError::NeedMoreBits),
+                Err(e) => {
+                    self.state = Final;
+                    return Err(e);
+                }
+                Ok(next_state) => self.state = next_state,
+            }
+        }
+    }
+
+    /// Pair each event with the number of bits of input attributable to it,
+    /// to profile which parts of a message compress poorly.
+    ///
+    /// A symbol is charged with the bits read since the previous event,
+    /// which are the bits needed to identify it. As the decoder reads no
+    /// further than it needs to, the MessageLength event is charged with the
+    /// rest of the message, so the costs of a message add up to its length.
+    pub fn costs(self) -> DecoderCosts<'a, S, A, I, BITS_OF_PRECISION> {
+        DecoderCosts {
+            bits_read: self.bits_read,
+            output: self,
+        }
+    }
+
+    /// Pause decoding, separating the decoder's state from its input, which
+    /// is returned positioned at the first bit the decoder hasn't read.
+    ///
+    /// Decoding continues with [`SuspendedDecoder::resume`], from the rest of
+    /// the input or any other source of the same bits, such as the next
+    /// buffer of a file read in pieces. The input must not have run out
+    /// already (see [`DecoderOutput::virtual_zeros`]), as the zeros assumed
+    /// in its place have been decoded.
+    pub fn suspend(self) -> (SuspendedDecoder<'a, S, A, BITS_OF_PRECISION>, I) {
+        let (output, input) = self.with_input(iter::empty());
+        (SuspendedDecoder { output }, input)
+    }
+
+    /// Move the decoder's state onto a different input, returning the old one.
+    fn with_input<J: BitSource>(
+        self,
+        input: J,
+    ) -> (DecoderOutput<'a, S, A, J, BITS_OF_PRECISION>, I) {
+        let output = DecoderOutput {
+            input,
+            alphabet: self.alphabet,
+            state: self.state,
+            event_to_emit: self.event_to_emit,
+            total_interval_width: self.total_interval_width,
+            a: self.a,
+            b: self.b,
+            z: self.z,
+            z_rescale_counter: self.z_rescale_counter,
+            unread_bits: self.unread_bits,
+            input_exhausted: self.input_exhausted,
+            bits_read: self.bits_read,
+            bits_consumed: self.bits_consumed,
+            virtual_zeros: self.virtual_zeros,
+            symbols_decoded: self.symbols_decoded,
+            symbol_count: self.symbol_count,
+            message_length: self.message_length,
+            streaming: self.streaming,
+            work_budget: self.work_budget,
+            work_done: self.work_done,
+            max_symbols: self.max_symbols,
+            max_bits: self.max_bits,
+            keep_model: self.keep_model,
+            cancelled: self.cancelled,
+            bound_table: self.bound_table,
+            alphabet_validated: self.alphabet_validated,
+        };
+        (output, self.input)
+    }
+
+    /// Take a snapshot of the decoder's internal state, such as its current
+    /// interval, for debugging (for instance, to find where an encoder and
+    /// decoder using mismatched models diverge).
+    pub fn snapshot(&self) -> DecoderSnapshot {
+        DecoderSnapshot {
+            state: self.state,
+            a: self.a,
+            b: self.b,
+            z: self.z,
+            unread_bits: self.unread_bits,
+            bits_read: self.bits_read,
+            symbols_decoded: self.symbols_decoded,
+        }
+    }
+
+    /// The number of bits of input read by the decoder since it was created
+    /// (or given a new input with [`DecoderOutput::reset_with`]), across all
+    /// the messages decoded, for reporting progress through the input.
+    ///
+    /// Unlike [`DecoderOutput::bits_read`], this isn't cleared by
+    /// [`DecoderOutput::reset`]. Bits skipped by the caller between messages
+    /// aren't counted.
+    pub fn bits_consumed(&self) -> usize {
+        self.bits_consumed
+    }
+
+    /// Consume the decoder, returning its input positioned at the first bit
+    /// after the message.
+    ///
+    /// Once the message has been decoded (up to its
+    /// [`DecoderEvent::MessageLength`]), any bits of the message which
+    /// weren't needed to decode it are skipped. Before then, the input is
+    /// returned as it is, following the bits read so far.
+    pub fn into_remainder(mut self) -> I {
+        if let Some(length) = self.message_length {
+            for _ in self.bits_read + self.virtual_zeros..length {
+                self.input.next_bit();
+            }
+        }
+        self.input
+    }
+
+    /// The number of zero bits the decoder has assumed past the end of the
+    /// input while decoding the current message.
+    ///
+    /// The encoder's output never needs padding, so this is only nonzero when
+    /// the input is shorter than the encoded message, for instance when
+    /// trailing zeros have been stripped from a tiny message. Once more zeros
+    /// than the bits of precision have been assumed, the input is taken to
+    /// be truncated, and decoding stops with
+    /// [`DecodeError::UnexpectedEndOfInput`].
+    pub fn virtual_zeros(&self) -> usize {
+        self.virtual_zeros
+    }
+
+    /// Decode a raw bit coded with
+    /// [`EncoderSink::push_bypass`](crate::encoder::EncoderSink::push_bypass)
+    /// at this point in the message: before the first symbol, or after the
+    /// event for a symbol other than EOF has been taken.
+    ///
+    /// Returns [`DecodeError::MisplacedBypass`], leaving the decoder as it
+    /// was, if an event is pending or the message has already ended.
+    pub fn decode_bypass(&mut self) -> Result<Bit, DecodeError> {
+        if self.event_to_emit.is_some() {
+            return Err(DecodeError::MisplacedBypass);
+        }
+        let result = self.execute_bypass();
+        if matches!(&result, Err(e) if *e != DecodeError::MisplacedBypass) {
+            self.state = Final;
+        }
+        result
+    }
+
+    fn execute_bypass(&mut self) -> Result<Bit, DecodeError> {
+        if self.state == Initial {
+            self.state = self.execute()?;
+        }
+        if self.state == Rescaling {
+            self.state = self.execute()?;
+        }
+        if self.state != TopOfSymbolLoop {
+            return Err(DecodeError::MisplacedBypass);
+        }
+        self.spend_work(1)?;
+
+        let middle = self.a + (self.b - self.a) / 2;
+        let bit = loop {
+            if self.z >= middle {
+                break One;
+            } else if self.z_max() < middle {
+                break Zero;
+            }
+            self.read_bit_into_z()?;
+        };
+        debug!("Decoded bypass bit {bit}");
+        match bit {
+            Zero => self.b = middle,
+            One => self.a = middle,
+        }
+        self.state = Rescaling;
+        Ok(bit)
+    }
+
+    /// Find the next symbol of the message without decoding it, returning None
+    /// if the message has ended.
+    ///
+    /// The interval isn't narrowed and the alphabet isn't updated, so the
+    /// symbol is still decoded as usual by the next call to `next`. Bits of
+    /// input may be read to identify the symbol, but they're kept in z for
+    /// decoding it.
+    ///
+    /// As with `next`, an error ends decoding, except when more bits are
+    /// awaited (see [`DecoderFeed`]), in which case peeking again once they
+    /// arrive finds the symbol.
+    pub fn peek(&mut self) -> Result<Option<S>, DecodeError> {
+        match self.execute_peek() {
+            Err(DecodeError::NeedMoreBits) => Err(DecodeError::NeedMoreBits),
+            Err(e) => {
+                self.state = Final;
+                Err(e)
+            }
+            Ok(symbol) => Ok(symbol),
+        }
+    }
+
+    fn execute_peek(&mut self) -> Result<Option<S>, DecodeError> {
+        while matches!(self.state, Initial | Rescaling) {
+            self.state = self.execute()?;
+        }
+        if self.state != TopOfSymbolLoop {
+            return Ok(None);
+        }
+        let (symbol, _, _) = self.find_next_symbol()?;
+        Ok(Some(symbol))
+    }
+
+    /// Account for the given number of operations against the work budget.
+    fn spend_work(&mut self, operations: usize) -> Result<(), DecodeError> {
+        self.work_done += operations;
+        match self.work_budget {
+            Some(budget) if self.work_done > budget => Err(DecodeError::BudgetExceeded(budget)),
+            _ => Ok(()),
         }
     }
 
     /// Continue the decoding process until the next event is emitted. None
     /// indicates no more events are available.
-    fn next_event(&mut self) -> Option<DecoderEvent<S>> {
+    ///
+    /// After an error, decoding cannot continue, so no further events follow,
+    /// except when more bits are awaited (see [`DecoderFeed`]), in which case
+    /// decoding resumes from the same point.
+    fn next_event(&mut self) -> Option<Result<DecoderEvent<S>, DecodeError>> {
         loop {
             if let Some(event) = self.event_to_emit.take() {
                 debug!("Emitting event: {event:?}");
-                return Some(event);
+                return Some(Ok(event));
             }
 
             if self.state == Final {
                 return None;
             }
 
-            self.state = self.execute();
+            match self.execute() {
+                Err(DecodeError::NeedMoreBits) => return Some(Err(DecodeError::NeedMoreBits)),
+                Err(e) => {
+                    debug!("Decoding failed: {e}");
+                    self.state = Final;
+                    return Some(Err(e));
+                }
+                Ok(next_state) => self.state = next_state,
+            }
         }
     }
 
     /// Execute the decoder state machine from its current state, producing the
-    /// next state.
-    fn execute(&mut self) -> DecoderState {
+    /// next state or an error.
+    fn execute(&mut self) -> Result<DecoderState, DecodeError> {
         debug!("Executing {:?} state", self.state);
         debug!("[pre]  a={:<12} b={:<12} z={:<12}", self.a, self.b, self.z);
+        if let Some(cancelled) = &self.cancelled
+            && cancelled.load(Ordering::Relaxed)
+        {
+            return Err(DecodeError::Cancelled);
+        }
+        self.spend_work(1)?;
         let next = match self.state {
             Initial => self.execute_initial(),
             Rescaling => self.execute_rescaling(),
             TopOfSymbolLoop => self.execute_top_of_symbol_loop(),
             CalculateLength => self.execute_calculate_length(),
-            Final => Final,
+            Final => Ok(Final),
         };
         debug!("[post] a={:<12} b={:<12} z={:<12}", self.a, self.b, self.z);
         next
@@ -170,72 +749,212 @@ where
 
     /// Execute from the Initial state, which initializes state variables.
     ///
-    /// Returns the next state.
-    fn execute_initial(&mut self) -> DecoderState {
+    /// Returns the next state, or an error.
+    fn execute_initial(&mut self) -> Result<DecoderState, DecodeError> {
+        if !std::mem::take(&mut self.keep_model) {
+            self.alphabet.begin_message();
+            self.refresh_bound_table();
+        }
+        if !self.alphabet_validated {
+            self.alphabet.validate(BITS_OF_PRECISION)?;
+            self.alphabet_validated = true;
+            self.total_interval_width = Reciprocal::new(self.alphabet.total_interval_width());
+        }
         self.a = 0;
         self.b = Self::WHOLE;
         self.initialize_z();
-        TopOfSymbolLoop
+        if self.symbol_count == Some(0) {
+            return Ok(CalculateLength);
+        }
+        Ok(TopOfSymbolLoop)
     }
 
-    /// Set z to its initial value by reading bits from the input and shifting
-    /// them into their appropriate positions.
+    /// Set z to its initial value. No bits are read yet: z starts out with all
+    /// of its bits unread, and they are read as symbols need them.
     fn initialize_z(&mut self) {
         self.z = 0;
-        for i in 1..=BITS_OF_PRECISION {
-            match self.input.next() {
-                None => {
-                    debug!(
-                        "Initialized z with {} bits from input (z={})",
-                        i - 1,
-                        self.z
-                    );
-                    break;
+        self.unread_bits = BITS_OF_PRECISION;
+    }
+
+    /// Read the most significant unread bit of z from the input. Once the
+    /// input is exhausted, bits are taken to be zero (without polling the
+    /// input again), and counted as virtual zeros, up to a whole window of z.
+    ///
+    /// When streaming, the input running out means that more bits are yet to
+    /// come, so nothing is read, and the state being executed is repeated
+    /// once they have arrived. Every state can be repeated from the point at
+    /// which it needed a bit, as the bits read so far are kept in z.
+    fn read_bit_into_z(&mut self) -> Result<(), DecodeError> {
+        if self.unread_bits == 0 {
+            return Ok(());
+        }
+
+        if let Some(max) = self.max_bits
+            && self.bits_read == max
+            && !self.input_exhausted
+        {
+            return Err(DecodeError::LimitExceeded(Limit::Bits(max)));
+        }
+        let bit = if self.input_exhausted {
+            None
+        } else {
+            self.input.next_bit()
+        };
+        if bit.is_none() && self.streaming {
+            return Err(DecodeError::NeedMoreBits);
+        }
+        self.unread_bits -= 1;
+        match bit {
+            None => {
+                if !self.input_exhausted {
+                    debug!("Input exhausted after {} bits", self.bits_read);
+                    self.input_exhausted = true;
+                }
+                self.virtual_zeros += 1;
+                if self.virtual_zeros > BITS_OF_PRECISION as usize {
+                    return Err(DecodeError::UnexpectedEndOfInput(self.symbols_decoded));
+                }
+            }
+            Some(bit) => {
+                self.bits_read += 1;
+                self.bits_consumed += 1;
+                if bit == One {
+                    self.z += 1 << self.unread_bits;
                 }
-                Some(Zero) => continue,
-                Some(One) => self.z += 2usize.pow(BITS_OF_PRECISION - i),
             }
         }
+        Ok(())
     }
 
     /// Execute from the TopOfSymbolLoop state, searching for the symbol
     /// identified by the subinterval containing the current value of z.
     ///
-    /// Returns the next state.
-    fn execute_top_of_symbol_loop(&mut self) -> DecoderState {
-        for symbol in self.alphabet.symbols() {
-            let (sub_a, sub_b) = self.subinterval_for_symbol(symbol);
+    /// Only the bits of z that have been read so far are known, so z could be
+    /// anywhere from its current value up to that value with all unread bits
+    /// set. Bits are read one at a time until that whole range falls within a
+    /// single subinterval, which ensures the decoder never reads further into
+    /// the input than the message itself. The subinterval containing a value
+    /// is found directly, with [`Alphabet::quantile`], or by binary search
+    /// if the bounds have been tabulated (see
+    /// [`DecoderOutput::with_bound_table`]).
+    ///
+    /// Returns the next state, or an error.
+    fn execute_top_of_symbol_loop(&mut self) -> Result<DecoderState, DecodeError> {
+        if let Some(max) = self.max_symbols
+            && self.symbols_decoded == max
+        {
+            return Err(DecodeError::LimitExceeded(Limit::Symbols(max)));
+        }
+        let (symbol, lower_bound, upper_bound) = self.find_next_symbol()?;
+        let (sub_a, sub_b) = self.subinterval(lower_bound, upper_bound);
+        self.a = sub_a;
+        self.b = sub_b;
+        self.event_to_emit = Some(DecoderEvent::DecodedSymbol(symbol.clone()));
+        self.symbols_decoded += 1;
+        self.alphabet.update(&symbol);
+        self.refresh_bound_table();
+
+        let ended = match self.symbol_count {
+            Some(count) => self.symbols_decoded == count,
+            None => symbol == self.alphabet.eof(),
+        };
+        if ended {
+            Ok(CalculateLength)
+        } else {
+            Ok(Rescaling)
+        }
+    }
 
-            if (sub_a..sub_b).contains(&self.z) {
-                self.event_to_emit = Some(DecoderEvent::DecodedSymbol(*symbol));
-                self.a = sub_a;
-                self.b = sub_b;
+    /// Tabulate the bounds again, if they have been tabulated and the
+    /// alphabet may have changed.
+    fn refresh_bound_table(&mut self) {
+        if let Some(table) = &mut self.bound_table
+            && !self.alphabet.is_static()
+        {
+            table.refresh(&*self.alphabet);
+        }
+    }
 
-                if *symbol == self.alphabet.eof() {
-                    return CalculateLength;
-                } else {
-                    return Rescaling;
-                }
+    /// Find the symbol whose subinterval contains z, and its bounds, c_j and
+    /// d_j, without narrowing the interval.
+    fn find_next_symbol(&mut self) -> Result<(S, usize, usize), DecodeError> {
+        let bound_table = self.bound_table.take();
+        let alphabet = self.alphabet.clone();
+        let found = match self.symbol_count {
+            Some(_) => self.find_symbol(&WithoutEof(&*alphabet), None),
+            None => self.find_symbol(&*alphabet, bound_table.as_ref()),
+        };
+        self.bound_table = bound_table;
+        found
+    }
+
+    /// Find the symbol whose subinterval contains z, with the bounds given by
+    /// the alphabet (which is without EOF when decoding a fixed number of
+    /// symbols) or by the table of its bounds.
+    fn find_symbol<B: Alphabet<S = S>>(
+        &mut self,
+        alphabet: &B,
+        bound_table: Option<&BoundTable<S>>,
+    ) -> Result<(S, usize, usize), DecodeError> {
+        let total = match bound_table {
+            Some(table) => table.total_interval_width(),
+            None => alphabet.total_interval_width(),
+        };
+        self.total_interval_width.refresh(total);
+
+        let quantile = |value| match bound_table {
+            Some(table) => table.quantile(value),
+            None => alphabet.quantile(value),
+        };
+        let (symbol, value) = loop {
+            // As long as z is within [a, b), some subinterval contains it
+            if self.z >= self.b {
+                return Err(DecodeError::CorruptInput {
+                    a: self.a,
+                    b: self.b,
+                    z: self.z,
+                });
+            }
+
+            let value = self.scale(self.z, total);
+            let symbol = quantile(value);
+            let z_max = self.z_max();
+            if z_max == self.z || (z_max < self.b && quantile(self.scale(z_max, total)) == symbol) {
+                break (symbol, value);
             }
+            self.read_bit_into_z()?;
+        };
+
+        // An alphabet whose quantile disagrees with its bounds (or which has
+        // changed without being updated) can't be decoded from
+        let (lower_bound, upper_bound) = match bound_table {
+            Some(table) => table.cdf(value),
+            None => alphabet.cdf(symbol),
+        };
+        if !(lower_bound..upper_bound).contains(&value) {
+            return Err(DecodeError::NoMatchingSymbol(value));
         }
+        Ok((symbol.clone(), lower_bound, upper_bound))
+    }
 
-        // As z is within [a, b), some subinterval must contain it
-        unreachable!(
-            "No subinterval of [a, b) contained z (z={:<12} a={:<12} b={:<12})",
-            self.z, self.a, self.b
-        );
+    /// Map a value in [a, b) to the value in [0, R) which identifies the
+    /// subinterval containing it: the largest c such that a + floor(w * c / R)
+    /// is at most the value.
+    fn scale(&self, value: usize, total: usize) -> usize {
+        ((value - self.a + 1) * total - 1) / (self.b - self.a)
     }
 
-    /// Determine the lower and upper bounds for the subinterval corresponding
-    /// to the given symbol.
-    fn subinterval_for_symbol(&self, symbol: &S) -> (usize, usize) {
-        let total_interval_width = self.alphabet.total_interval_width();
-        let upper_bound = self.alphabet.interval_upper_bound(symbol);
-        let lower_bound = self.alphabet.interval_lower_bound(symbol);
+    /// The largest value z could have once all of its unread bits are read.
+    fn z_max(&self) -> usize {
+        self.z + ((1 << self.unread_bits) - 1)
+    }
 
+    /// Determine the lower and upper bounds for the subinterval corresponding
+    /// to a symbol with the given bounds, c_j and d_j.
+    fn subinterval(&self, lower_bound: usize, upper_bound: usize) -> (usize, usize) {
         let w = self.b - self.a;
-        let sub_b = self.a + (w * upper_bound) / total_interval_width;
-        let sub_a = self.a + (w * lower_bound) / total_interval_width;
+        let sub_b = self.a + self.total_interval_width.divide(w * upper_bound);
+        let sub_a = self.a + self.total_interval_width.divide(w * lower_bound);
 
         (sub_a, sub_b)
     }
@@ -243,171 +962,587 @@ where
     /// Execute from the Rescaling state, performing rescaling operations as
     /// necessary to prevent a and b from nearing too close to each other.
     ///
-    /// Returns the next state.
-    fn execute_rescaling(&mut self) -> DecoderState {
-        self.side_rescaling();
-        self.middle_rescaling();
-        TopOfSymbolLoop
-    }
-
-    /// Perform "side rescaling" by identifying scenarios in which the a-b range
-    /// lies entirely in the lower or upper half of the total region (from 0-WHOLE).
-    fn side_rescaling(&mut self) {
-        while self.b < Self::HALF || self.a > Self::HALF {
-            if self.b < Self::HALF {
-                debug!("Interval fully contained in 0 half");
-                self.a *= 2;
-                self.b *= 2;
-                self.z *= 2;
-            } else if self.a > Self::HALF {
-                debug!("Interval fully contained in 1 half");
-                self.a = 2 * (self.a - Self::HALF);
-                self.b = 2 * (self.b - Self::HALF);
-                self.z = 2 * (self.z - Self::HALF);
+    /// Side rescaling (the interval lies within one half) and middle rescaling
+    /// (the interval straddles the midpoint closely) are handled by a single
+    /// loop, as both double the interval and shift one new bit of input into
+    /// z per step; they differ only in the point the doubling is centered on.
+    ///
+    /// The new bits of z are left unread until a symbol needs them, but any
+    /// unread bits that would be shifted out of z are read first, so that the
+    /// decoder stays in step with the input.
+    ///
+    /// Returns the next state, or an error.
+    fn execute_rescaling(&mut self) -> Result<DecoderState, DecodeError> {
+        while let Some(run) = RescaleRun::next(self.a, self.b, BITS_OF_PRECISION) {
+            debug!("Rescaling: {run:?}");
+            self.spend_work(run.steps() as usize)?;
+            while self.unread_bits + run.steps() > BITS_OF_PRECISION {
+                self.read_bit_into_z()?;
             }
 
-            self.add_next_bit_to_z();
-        }
-    }
-
-    /// Perform "middle rescaling" by identifying scenarios in which a and b are
-    /// straddling the midpoint of the 0-WHOLE region and have grown close enough
-    /// together.
-    fn middle_rescaling(&mut self) {
-        while self.a > Self::QUARTER && self.b < 3 * Self::QUARTER {
-            debug!(
-                "Middle rescaling a={:<12} b={:<12} z={:<12}",
-                self.a, self.b, self.z
-            );
-            self.a = 2 * (self.a - Self::QUARTER);
-            self.b = 2 * (self.b - Self::QUARTER);
-            self.z = 2 * (self.z - Self::QUARTER);
-            self.add_next_bit_to_z();
-        }
-    }
-
-    /// Take the next bit from the input stream, and add it as the least
-    /// significant bit of z.
-    fn add_next_bit_to_z(&mut self) {
-        self.z_rescale_counter += 1;
-        if let Some(One) = self.input.next() {
-            self.z += 1;
+            self.a = run.apply(self.a, BITS_OF_PRECISION);
+            self.b = run.apply_upper(self.b, BITS_OF_PRECISION);
+            self.z = run.apply(self.z, BITS_OF_PRECISION);
+            self.z_rescale_counter += run.steps() as usize;
+            self.unread_bits += run.steps();
         }
-
-        debug!("Next bit: {}", self.z & 1);
+        Ok(TopOfSymbolLoop)
     }
 
     /// Determine the number of bits that were used to encode the message that
     /// was just decoded.
     ///
-    /// We do this by determining the number of bits of z that are necessary
-    /// for unambiguously indicating the [a, b) interval, and add this to the
-    /// number of bits of z that we've already discarded via rescaling.
-    fn execute_calculate_length(&mut self) -> DecoderState {
-        let prefix_size = self.minimal_z_prefix_size() as usize;
-        debug!("Minimal prefix of z: {prefix_size} bits");
+    /// Every rescaling step performed by the encoder emits exactly one bit
+    /// (immediately for side rescaling, or deferred via s for middle
+    /// rescaling), and the encoder terminates the message with s + 2 further
+    /// bits. The decoder performs the same rescaling steps as the encoder, so
+    /// the length is the number of steps taken so far, plus those the encoder
+    /// performed after the EOF symbol, plus the two final bits.
+    ///
+    /// Returns the next state, or an error.
+    fn execute_calculate_length(&mut self) -> Result<DecoderState, DecodeError> {
+        let mut remaining_steps = 0;
+        let (mut a, mut b) = (self.a, self.b);
+        while let Some(run) = RescaleRun::next(a, b, BITS_OF_PRECISION) {
+            a = run.apply(a, BITS_OF_PRECISION);
+            b = run.apply_upper(b, BITS_OF_PRECISION);
+            remaining_steps += run.steps() as usize;
+        }
+        debug!(
+            "Rescaling steps: {} before EOF, {remaining_steps} after",
+            self.z_rescale_counter
+        );
 
-        let encoded_message_length = prefix_size + self.z_rescale_counter;
+        let encoded_message_length = self.z_rescale_counter + remaining_steps + 2;
+        self.message_length = Some(encoded_message_length);
         self.event_to_emit = Some(DecoderEvent::MessageLength(encoded_message_length));
-        Final
+        Ok(Final)
     }
+}
 
-    /// Find the size of the smallest prefix of z that describes an interval
-    /// contained in [a, b).
-    fn minimal_z_prefix_size(&self) -> u32 {
-        for bit_position in (0..BITS_OF_PRECISION).rev() {
-            // Generate a mask for the N most significant bits of z
-            let prefix_size = BITS_OF_PRECISION - bit_position;
-            let prefix_mask: usize = !((1 << bit_position) - 1);
-
-            let lower_bound = self.z & prefix_mask;
-            let upper_bound = (self.z & prefix_mask) | !prefix_mask;
+/// A bit source which can look at its next bit without taking it.
+pub struct PeekableBits<I: BitSource> {
+    input: I,
+    peeked: Option<Option<Bit>>,
+}
 
-            if lower_bound >= self.a && upper_bound < self.b {
-                // The extra +2 is for the "0b" prefix
-                let width = (BITS_OF_PRECISION + 2) as usize;
+impl<I: BitSource> PeekableBits<I> {
+    fn peek(&mut self) -> Option<Bit> {
+        *self.peeked.get_or_insert_with(|| self.input.next_bit())
+    }
+}
 
-                debug!("Found minimal prefix ({prefix_size} bits)");
-                debug!("z:          {:#0width$b} ({})", self.z, self.z);
-                debug!("Lower bound {lower_bound:#0width$b} ({lower_bound})");
-                debug!("Upper bound {upper_bound:#0width$b} ({upper_bound})");
-                return prefix_size;
-            }
-        }
+impl<I: BitSource> Iterator for PeekableBits<I> {
+    type Item = Bit;
 
-        // As z is in [a, b), the prefix containing all of z's bits is necessarily
-        // also contained in this interval
-        unreachable!(
-            "No prefix of z is within [a, b) (z={:<12} a={:<12} b={:<12})",
-            self.z, self.a, self.b
-        );
+    fn next(&mut self) -> Option<Bit> {
+        self.peeked.take().unwrap_or_else(|| self.input.next_bit())
     }
 }
 
-pub trait Decoder<S, A>
+/// The events of a decoder paired with their costs in bits. See
+/// [`DecoderOutput::costs`].
+pub struct DecoderCosts<'a, S, A, I, const BITS_OF_PRECISION: u32>
 where
     S: Symbol,
     A: Alphabet<S = S>,
+    I: BitSource,
 {
-    /// Decode a stream of bits as a stream of symbols.
-    ///
-    /// This method will decode a single message, yielding all the decoded
-    /// symbols (including the EOF symbol), and then indicating completion
-    /// with the MessageLength event.
-    fn decode<IntoI, const BITS_OF_PRECISION: u32>(
-        &self,
-        input: IntoI,
-    ) -> DecoderOutput<'_, S, A, IntoI::IntoIter, BITS_OF_PRECISION>
-    where
-        IntoI: IntoIterator<Item = Bit>;
+    output: DecoderOutput<'a, S, A, I, BITS_OF_PRECISION>,
+    bits_read: usize,
 }
 
-impl<S, A> Decoder<S, A> for A
+impl<S, A, I, const BITS_OF_PRECISION: u32> Iterator
+    for DecoderCosts<'_, S, A, I, BITS_OF_PRECISION>
 where
     S: Symbol,
     A: Alphabet<S = S>,
+    I: BitSource,
 {
-    fn decode<IntoI, const BITS_OF_PRECISION: u32>(
-        &self,
-        input: IntoI,
-    ) -> DecoderOutput<'_, S, A, IntoI::IntoIter, BITS_OF_PRECISION>
-    where
-        IntoI: IntoIterator<Item = Bit>,
-    {
-        DecoderOutput::new(input.into_iter(), self)
+    type Item = Result<(DecoderEvent<S>, usize), DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = match self.output.next()? {
+            Ok(event) => event,
+            Err(e) => return Some(Err(e)),
+        };
+        let read = match event {
+            DecoderEvent::DecodedSymbol(_) => self.output.bits_read,
+            DecoderEvent::MessageLength(length) => length,
+        };
+        let cost = read - std::mem::replace(&mut self.bits_read, read);
+        Some(Ok((event, cost)))
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::example::{ExampleAlphabet, ExampleSymbol};
-    use DecoderEvent::*;
-    use ExampleSymbol::*;
-    use biterator::Bit::{One, Zero};
-    use test_log::test;
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.output.size_hint()
+    }
+}
 
-    // The below test cases assume 32-bit precision
-    const BITS_OF_PRECISION: u32 = 32;
+/// A decoder paused part of the way through a message, without its input. See
+/// [`DecoderOutput::suspend`].
+pub struct SuspendedDecoder<'a, S, A, const BITS_OF_PRECISION: u32>
+where
+    S: Symbol,
+    A: Alphabet<S = S>,
+{
+    output: DecoderOutput<'a, S, A, iter::Empty<Bit>, BITS_OF_PRECISION>,
+}
 
-    fn decode(input: Vec<Bit>) -> Vec<DecoderEvent<ExampleSymbol>> {
-        let alphabet = ExampleAlphabet::new();
-        alphabet.decode::<_, BITS_OF_PRECISION>(input).collect()
+impl<'a, S, A, const BITS_OF_PRECISION: u32> SuspendedDecoder<'a, S, A, BITS_OF_PRECISION>
+where
+    S: Symbol,
+    A: Alphabet<S = S>,
+{
+    /// Continue decoding from the given input, which must hold the bits
+    /// following those the decoder had read when it was suspended.
+    pub fn resume<IntoI: IntoBitSource>(
+        self,
+        input: IntoI,
+    ) -> DecoderOutput<'a, S, A, IntoI::Source, BITS_OF_PRECISION> {
+        self.output.with_input(input.into_bit_source()).0
     }
 
-    #[test]
-    fn decode_empty_message() {
-        assert_eq!(
-            decode(vec![One, One, One, Zero, One]),
-            vec![DecodedSymbol(Eof), MessageLength(5)],
-        );
+    /// A snapshot of the suspended decoder's state.
+    pub fn snapshot(&self) -> DecoderSnapshot {
+        self.output.snapshot()
     }
+}
 
-    #[test]
-    fn decode_very_small_message() {
-        assert_eq!(
+/// The output of a decoder decoding every message of its input one after
+/// another. See [`Decoder::decode_all`].
+pub struct DecoderMessages<'a, S, A, I, const BITS_OF_PRECISION: u32>
+where
+    S: Symbol,
+    A: Alphabet<S = S>,
+    I: BitSource,
+{
+    output: DecoderOutput<'a, S, A, PeekableBits<I>, BITS_OF_PRECISION>,
+    failed: bool,
+}
+
+impl<S, A, I, const BITS_OF_PRECISION: u32> DecoderMessages<'_, S, A, I, BITS_OF_PRECISION>
+where
+    S: Symbol,
+    A: Alphabet<S = S>,
+    I: BitSource,
+{
+    /// The number of bits of input consumed so far, including the bits
+    /// skipped at the end of each message.
+    ///
+    /// After a message's [`DecoderEvent::MessageLength`], this is the
+    /// position in the input at which the next message starts, so the input
+    /// can be split at message boundaries.
+    pub fn bits_consumed(&self) -> usize {
+        self.output.bits_consumed
+    }
+
+    /// Skip the rest of the current message without producing its events
+    /// (see [`DecoderOutput::skip_message`]), returning its length in bits,
+    /// or None if the input has ended.
+    pub fn skip_message(&mut self) -> Option<Result<usize, DecodeError>> {
+        if self.failed || (self.output.state == Initial && self.output.input.peek().is_none()) {
+            return None;
+        }
+        let result = self.output.skip_message();
+        match result {
+            Ok(length) => self.end_message(length),
+            Err(_) => self.failed = true,
+        }
+        Some(result)
+    }
+
+    /// Skip any bits of the message which weren't needed to decode it, and
+    /// prepare to decode the next.
+    fn end_message(&mut self, length: usize) {
+        let skip = length - self.output.bits_read;
+        self.output.bits_consumed += self.output.input.by_ref().take(skip).count();
+        self.output.reset();
+    }
+}
+
+impl<S, A, I, const BITS_OF_PRECISION: u32> Iterator
+    for DecoderMessages<'_, S, A, I, BITS_OF_PRECISION>
+where
+    S: Symbol,
+    A: Alphabet<S = S>,
+    I: BitSource,
+{
+    type Item = Result<DecoderEvent<S>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        // The input ends at a message boundary
+        if self.output.state == Initial {
+            self.output.input.peek()?;
+        }
+
+        let event = self.output.next()?;
+        match &event {
+            Err(_) => self.failed = true,
+            Ok(DecoderEvent::MessageLength(length)) => self.end_message(*length),
+            Ok(DecoderEvent::DecodedSymbol(_)) => {}
+        }
+        Some(event)
+    }
+}
+
+pub trait Decoder<S, A>
+where
+    S: Symbol,
+    A: Alphabet<S = S>,
+{
+    /// Decode a stream of bits as a stream of symbols.
+    ///
+    /// The input can be anything convertible to a [`BitSource`], such as a
+    /// collection of bits or booleans, or the bits of some bytes (see
+    /// [`crate::bits`]).
+    ///
+    /// This method will decode a single message, yielding all the decoded
+    /// symbols (including the EOF symbol), and then indicating completion
+    /// with the MessageLength event.
+    ///
+    /// The alphabet is updated with each decoded symbol as it was during
+    /// encoding (see [`Encoder::encode`](crate::encoder::Encoder::encode)).
+    fn decode<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> DecoderOutput<'_, S, A, IntoI::Source, BITS_OF_PRECISION>
+    where
+        IntoI: IntoBitSource;
+
+    /// Decode a single message, collecting its symbols (not including EOF)
+    /// into a vector, along with the number of bits of input it spans.
+    fn decode_to_vec<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> Result<(Vec<S>, usize), DecodeError>
+    where
+        IntoI: IntoBitSource,
+    {
+        let mut symbols = Vec::new();
+        for event in self.decode::<_, BITS_OF_PRECISION>(input) {
+            match event? {
+                DecoderEvent::DecodedSymbol(symbol) => symbols.push(symbol),
+                DecoderEvent::MessageLength(length) => {
+                    // The message ends with EOF
+                    symbols.pop();
+                    return Ok((symbols, length));
+                }
+            }
+        }
+        unreachable!("A message always ends with its length, or an error")
+    }
+
+    /// Decode a message from packed bytes, such as the output of
+    /// [`EncoderOutput::bytes`](crate::encoder::EncoderOutput::bytes), reading
+    /// the most significant bit of each byte first.
+    fn decode_bytes<'b, const BITS_OF_PRECISION: u32>(
+        &self,
+        bytes: &'b [u8],
+    ) -> DecoderOutput<'_, S, A, SliceBits<'b>, BITS_OF_PRECISION>;
+
+    /// Decode a message from the packed bytes read from a reader, such as a
+    /// compressed file, reading the most significant bit of each byte first.
+    ///
+    /// An IO error ends the input, so decoding usually fails with
+    /// [`DecodeError::UnexpectedEndOfInput`]; the error itself can be
+    /// retrieved from the input with [`ReadBits::take_error`] (see
+    /// [`DecoderOutput::into_remainder`]).
+    fn decode_reader<R: io::Read, const BITS_OF_PRECISION: u32>(
+        &self,
+        reader: R,
+    ) -> DecoderOutput<'_, S, A, ReadBits<R>, BITS_OF_PRECISION>;
+
+    /// Decode a message of the given number of symbols, encoded without EOF
+    /// by [`Encoder::encode_without_eof`](crate::encoder::Encoder::encode_without_eof).
+    ///
+    /// Decoding stops after that many symbols, with the MessageLength event,
+    /// as it would after EOF.
+    fn decode_without_eof<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+        symbols: usize,
+    ) -> DecoderOutput<'_, S, A, IntoI::Source, BITS_OF_PRECISION>
+    where
+        IntoI: IntoBitSource;
+
+    /// Decode a message of exactly the given number of symbols, encoded
+    /// without EOF, from the front of an input which may hold other data
+    /// after it, returning the symbols and the rest of the input.
+    ///
+    /// No bit after the message is read, even while identifying its last
+    /// symbols, so the input can be shared with other readers: the returned
+    /// input starts at the first bit after the message's
+    /// [`DecoderEvent::MessageLength`].
+    fn decode_exact<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+        symbols: usize,
+    ) -> Result<(Vec<S>, IntoI::Source), DecodeError>
+    where
+        IntoI: IntoBitSource,
+    {
+        let mut decoder = self.decode_without_eof::<_, BITS_OF_PRECISION>(input, symbols);
+        let decoded = decoder.decode_symbols(symbols)?;
+        match decoder.next() {
+            Some(Ok(DecoderEvent::MessageLength(_))) => Ok((decoded, decoder.into_remainder())),
+            Some(Err(e)) => Err(e),
+            _ => unreachable!("A message always ends with its length, or an error"),
+        }
+    }
+
+    /// Decode every message in a stream of bits, such as the output of
+    /// [`Encoder::encode_all`](crate::encoder::Encoder::encode_all), starting
+    /// the decoder afresh for each message.
+    ///
+    /// Each message's events end with its MessageLength, after which the
+    /// bits of the message which weren't needed to decode it are skipped.
+    /// Decoding ends when the input does, which must be at the end of a
+    /// message: any padding following the last message (such as that of
+    /// [`BitWriter`](crate::bits::BitWriter)) would be decoded as another.
+    fn decode_all<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> DecoderMessages<'_, S, A, IntoI::Source, BITS_OF_PRECISION>
+    where
+        IntoI: IntoBitSource;
+
+    /// Decode a single message from bytes encoded with the given coder by
+    /// [`Encoder::encode_with`](crate::encoder::Encoder::encode_with),
+    /// returning its symbols (not including EOF).
+    fn decode_with<const BITS_OF_PRECISION: u32>(
+        &self,
+        bytes: &[u8],
+        coder: Coder,
+    ) -> Result<Vec<S>, DecodeError>;
+}
+
+impl From<RangeDecodeError> for DecodeError {
+    fn from(error: RangeDecodeError) -> Self {
+        match error {
+            RangeDecodeError::Truncated(symbols) => DecodeError::UnexpectedEndOfInput(symbols),
+            RangeDecodeError::InvalidAlphabet(e) => DecodeError::InvalidAlphabet(e),
+        }
+    }
+}
+
+impl<S, A> Decoder<S, A> for A
+where
+    S: Symbol,
+    A: Alphabet<S = S>,
+{
+    fn decode<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> DecoderOutput<'_, S, A, IntoI::Source, BITS_OF_PRECISION>
+    where
+        IntoI: IntoBitSource,
+    {
+        DecoderOutput::new(input.into_bit_source(), self)
+    }
+
+    fn decode_bytes<'b, const BITS_OF_PRECISION: u32>(
+        &self,
+        bytes: &'b [u8],
+    ) -> DecoderOutput<'_, S, A, SliceBits<'b>, BITS_OF_PRECISION> {
+        DecoderOutput::new(SliceBits::new(bytes), self)
+    }
+
+    fn decode_reader<R: io::Read, const BITS_OF_PRECISION: u32>(
+        &self,
+        reader: R,
+    ) -> DecoderOutput<'_, S, A, ReadBits<R>, BITS_OF_PRECISION> {
+        DecoderOutput::new(ReadBits::new(reader), self)
+    }
+
+    fn decode_without_eof<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+        symbols: usize,
+    ) -> DecoderOutput<'_, S, A, IntoI::Source, BITS_OF_PRECISION>
+    where
+        IntoI: IntoBitSource,
+    {
+        DecoderOutput {
+            symbol_count: Some(symbols),
+            ..DecoderOutput::new(input.into_bit_source(), self)
+        }
+    }
+
+    fn decode_all<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> DecoderMessages<'_, S, A, IntoI::Source, BITS_OF_PRECISION>
+    where
+        IntoI: IntoBitSource,
+    {
+        let input = PeekableBits {
+            input: input.into_bit_source(),
+            peeked: None,
+        };
+        DecoderMessages {
+            output: DecoderOutput::new(input, self),
+            failed: false,
+        }
+    }
+
+    fn decode_with<const BITS_OF_PRECISION: u32>(
+        &self,
+        bytes: &[u8],
+        coder: Coder,
+    ) -> Result<Vec<S>, DecodeError> {
+        let mut symbols = match coder {
+            Coder::Arithmetic => {
+                return Ok(self
+                    .decode_to_vec::<_, BITS_OF_PRECISION>(SliceBits::new(bytes))?
+                    .0);
+            }
+            Coder::Range => range::decode(self, bytes)?,
+            Coder::RangeWithCarry => range::carry::decode(self, bytes)?,
+            Coder::Arcode => arcode::decode::<_, BITS_OF_PRECISION>(self, bytes)?,
+        };
+        // The message ends with EOF
+        symbols.pop();
+        Ok(symbols)
+    }
+}
+
+/// Implementation of Decoder for any [`Model`], which decodes each message
+/// with a [`ModelAlphabet`] of its own, over a clone of the model.
+impl<M> Decoder<M::S, ModelAlphabet<M>> for M
+where
+    M: Model + Clone,
+{
+    fn decode<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> DecoderOutput<'_, M::S, ModelAlphabet<M>, IntoI::Source, BITS_OF_PRECISION>
+    where
+        IntoI: IntoBitSource,
+    {
+        DecoderOutput::new(input.into_bit_source(), ModelAlphabet::shared(self))
+    }
+
+    fn decode_bytes<'b, const BITS_OF_PRECISION: u32>(
+        &self,
+        bytes: &'b [u8],
+    ) -> DecoderOutput<'_, M::S, ModelAlphabet<M>, SliceBits<'b>, BITS_OF_PRECISION> {
+        DecoderOutput::new(SliceBits::new(bytes), ModelAlphabet::shared(self))
+    }
+
+    fn decode_reader<R: io::Read, const BITS_OF_PRECISION: u32>(
+        &self,
+        reader: R,
+    ) -> DecoderOutput<'_, M::S, ModelAlphabet<M>, ReadBits<R>, BITS_OF_PRECISION> {
+        DecoderOutput::new(ReadBits::new(reader), ModelAlphabet::shared(self))
+    }
+
+    fn decode_without_eof<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+        symbols: usize,
+    ) -> DecoderOutput<'_, M::S, ModelAlphabet<M>, IntoI::Source, BITS_OF_PRECISION>
+    where
+        IntoI: IntoBitSource,
+    {
+        DecoderOutput {
+            symbol_count: Some(symbols),
+            ..DecoderOutput::new(input.into_bit_source(), ModelAlphabet::shared(self))
+        }
+    }
+
+    fn decode_all<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> DecoderMessages<'_, M::S, ModelAlphabet<M>, IntoI::Source, BITS_OF_PRECISION>
+    where
+        IntoI: IntoBitSource,
+    {
+        let input = PeekableBits {
+            input: input.into_bit_source(),
+            peeked: None,
+        };
+        DecoderMessages {
+            output: DecoderOutput::new(input, ModelAlphabet::shared(self)),
+            failed: false,
+        }
+    }
+
+    fn decode_with<const BITS_OF_PRECISION: u32>(
+        &self,
+        bytes: &[u8],
+        coder: Coder,
+    ) -> Result<Vec<M::S>, DecodeError> {
+        ModelAlphabet::new(self.clone()).decode_with::<BITS_OF_PRECISION>(bytes, coder)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bits::SliceBits;
+    use crate::encoder::{Encoder, EncoderSink};
+    use crate::example::{ExampleAlphabet, ExampleSymbol};
+    use crate::frequency::FrequencyTable;
+    use crate::models::AdaptiveAlphabet;
+    use DecoderEvent::*;
+    use ExampleSymbol::*;
+    use test_log::test;
+
+    // The below test cases assume 32-bit precision
+    const BITS_OF_PRECISION: u32 = 32;
+
+    /// Convenience function for decoding a vector of bits using the example
+    /// alphabet definition, and collecting the events into a single Result.
+    fn decode(input: Vec<Bit>) -> Result<Vec<DecoderEvent<ExampleSymbol>>, DecodeError> {
+        let alphabet = ExampleAlphabet::new();
+        alphabet.decode::<_, BITS_OF_PRECISION>(input).collect()
+    }
+
+    #[test]
+    fn decode_bypass_bits() {
+        let alphabet = ExampleAlphabet::new();
+        let raw = [One, Zero, One, One, Zero, Zero, Zero, One];
+        let mut sink = EncoderSink::<_, BITS_OF_PRECISION>::new(&alphabet).unwrap();
+        let mut bits: Vec<_> = sink.push_bypass(One).collect();
+        bits.extend(sink.push(A).unwrap());
+        for bit in raw {
+            bits.extend(sink.push_bypass(bit));
+        }
+        bits.extend(sink.push(C).unwrap());
+        bits.extend(sink.finish());
+
+        let mut decoder = alphabet.decode::<_, BITS_OF_PRECISION>(bits.clone());
+        assert_eq!(decoder.decode_bypass(), Ok(One));
+        assert_eq!(decoder.next(), Some(Ok(DecodedSymbol(A))));
+        let decoded: Result<Vec<_>, _> = (0..raw.len()).map(|_| decoder.decode_bypass()).collect();
+        assert_eq!(decoded, Ok(raw.to_vec()));
+        assert_eq!(decoder.next(), Some(Ok(DecodedSymbol(C))));
+        assert_eq!(decoder.next(), Some(Ok(DecodedSymbol(Eof))));
+
+        // Misplaced bypass bits are reported without disturbing the decoder
+        assert_eq!(decoder.decode_bypass(), Err(DecodeError::MisplacedBypass));
+        assert_eq!(decoder.next(), Some(Ok(MessageLength(bits.len()))));
+        assert_eq!(decoder.decode_bypass(), Err(DecodeError::MisplacedBypass));
+        assert_eq!(decoder.next(), None);
+    }
+
+    #[test]
+    fn decode_empty_message() {
+        assert_eq!(
+            decode(vec![One, One, One, Zero, One]),
+            Ok(vec![DecodedSymbol(Eof), MessageLength(5)]),
+        );
+    }
+
+    #[test]
+    fn decode_very_small_message() {
+        assert_eq!(
             decode(vec![One, One, One, Zero, Zero, One, Zero]),
-            vec![DecodedSymbol(C), DecodedSymbol(Eof), MessageLength(7)],
+            Ok(vec![DecodedSymbol(C), DecodedSymbol(Eof), MessageLength(7)]),
         );
     }
 
@@ -415,13 +1550,13 @@ mod test {
     fn decode_small_message() {
         assert_eq!(
             decode(vec![Zero, One, Zero, One, One, One, Zero, Zero, One, Zero]),
-            vec![
+            Ok(vec![
                 DecodedSymbol(B),
                 DecodedSymbol(A),
                 DecodedSymbol(C),
                 DecodedSymbol(Eof),
                 MessageLength(10),
-            ],
+            ]),
         );
     }
 
@@ -429,12 +1564,31 @@ mod test {
     fn decode_message_with_middle_rescaling() {
         assert_eq!(
             decode(vec![One, Zero, Zero, One, One, One]),
-            vec![
+            Ok(vec![
                 DecodedSymbol(B),
                 DecodedSymbol(B),
                 DecodedSymbol(Eof),
                 MessageLength(6),
-            ],
+            ]),
+        );
+    }
+
+    #[test]
+    fn round_trip_at_smallest_precision() {
+        // Two bits leave room for EOF alone, and three for one more symbol
+        let alphabet = FrequencyTable::from_widths(vec![(Eof, 1)], Eof);
+        let bits = alphabet.encode_to_vec::<_, 2>(vec![Eof]).unwrap();
+        assert_eq!(
+            alphabet.decode_to_vec::<_, 2>(bits.clone()),
+            Ok((vec![], bits.len()))
+        );
+
+        let alphabet = FrequencyTable::from_widths(vec![(A, 1), (Eof, 1)], Eof);
+        let message = [vec![A; 20], vec![Eof]].concat();
+        let bits = alphabet.encode_to_vec::<_, 3>(message).unwrap();
+        assert_eq!(
+            alphabet.decode_to_vec::<_, 3>(bits.clone()),
+            Ok((vec![A; 20], bits.len()))
         );
     }
 
@@ -450,7 +1604,654 @@ mod test {
                 // Second message: B, A, C, Eof
                 Zero, One, Zero, One, One, One, Zero, Zero, One, Zero
             ]),
-            vec![DecodedSymbol(C), DecodedSymbol(Eof), MessageLength(7)],
+            Ok(vec![DecodedSymbol(C), DecodedSymbol(Eof), MessageLength(7)]),
         )
     }
+
+    #[test]
+    fn peek_next_symbol() {
+        let alphabet = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof);
+        let message = [A, A, B, A, C, B, B, Eof];
+        let bits = alphabet
+            .encode_to_vec::<_, BITS_OF_PRECISION>(message)
+            .unwrap();
+        let mut decoder = alphabet.decode::<_, BITS_OF_PRECISION>(bits.clone());
+        for symbol in message {
+            assert_eq!(decoder.peek(), Ok(Some(symbol)));
+            assert_eq!(decoder.peek(), Ok(Some(symbol)));
+            assert_eq!(decoder.next(), Some(Ok(DecodedSymbol(symbol))));
+        }
+        assert_eq!(decoder.peek(), Ok(None));
+        assert_eq!(decoder.next(), Some(Ok(MessageLength(bits.len()))));
+        assert_eq!(decoder.peek(), Ok(None));
+    }
+
+    #[test]
+    fn suspend_and_resume() {
+        let alphabet = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof);
+        let message = [A, A, B, A, C, B, B, A, C, Eof];
+        let bits = alphabet
+            .encode_to_vec::<_, BITS_OF_PRECISION>(message)
+            .unwrap();
+
+        let mut decoder = alphabet.decode::<_, BITS_OF_PRECISION>(bits.clone());
+        let mut decoded = decoder.decode_symbols(3).unwrap();
+        assert_eq!(decoded, message[..3]);
+
+        // Resume from a separate copy of the rest of the input
+        let (suspended, input) = decoder.suspend();
+        let read = suspended.snapshot().bits_read;
+        assert_eq!(input.as_slice(), &bits[read..]);
+        let mut decoder = suspended.resume(bits[read..].to_vec());
+        decoded.extend(decoder.decode_symbols(4).unwrap());
+        decoded.extend(decoder.decode_symbols(10).unwrap());
+        assert_eq!(decoded, message);
+        assert_eq!(decoder.next(), Some(Ok(MessageLength(bits.len()))));
+        assert_eq!(decoder.decode_symbols(1), Ok(vec![]));
+    }
+
+    #[test]
+    fn costs_add_up_to_length() {
+        let alphabet = ExampleAlphabet::new();
+        let message = [vec![B, A, C], vec![C; 20], vec![A; 20], vec![Eof]].concat();
+        let bits = alphabet
+            .encode_to_vec::<_, BITS_OF_PRECISION>(message.clone())
+            .unwrap();
+        let costs: Vec<_> = alphabet
+            .decode::<_, BITS_OF_PRECISION>(bits.clone())
+            .costs()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(costs.len(), message.len() + 1);
+        assert_eq!(
+            costs.iter().map(|(_, cost)| cost).sum::<usize>(),
+            bits.len()
+        );
+
+        // Unlikely symbols cost more
+        let average = |symbol| {
+            let (count, total) = costs
+                .iter()
+                .filter(|(event, _)| *event == DecodedSymbol(symbol))
+                .fold((0, 0), |(count, total), (_, cost)| {
+                    (count + 1, total + cost)
+                });
+            total as f64 / count as f64
+        };
+        assert!(average(C) > average(A));
+    }
+
+    #[test]
+    fn decode_to_vec() {
+        let alphabet = ExampleAlphabet::new();
+        let mut bits = vec![Zero, One, Zero, One, One, One, Zero, Zero, One, Zero];
+        assert_eq!(
+            alphabet.decode_to_vec::<_, BITS_OF_PRECISION>(bits.clone()),
+            Ok((vec![B, A, C], 10))
+        );
+        // Following bits aren't part of the message
+        bits.extend([One; 8]);
+        assert_eq!(
+            alphabet.decode_to_vec::<_, BITS_OF_PRECISION>(bits),
+            Ok((vec![B, A, C], 10))
+        );
+        assert_eq!(
+            alphabet.decode_to_vec::<_, BITS_OF_PRECISION>(vec![Zero; 3]),
+            Err(DecodeError::UnexpectedEndOfInput(17))
+        );
+    }
+
+    #[test]
+    fn decode_from_bytes() {
+        // C, Eof is encoded as 1110010, padded with a zero
+        let alphabet = ExampleAlphabet::new();
+        let events: Result<Vec<_>, _> = alphabet
+            .decode::<_, BITS_OF_PRECISION>(SliceBits::new(&[0b1110_0100]))
+            .collect();
+        assert_eq!(
+            events,
+            Ok(vec![DecodedSymbol(C), DecodedSymbol(Eof), MessageLength(7)])
+        );
+
+        let message = [vec![B, A, C], vec![A; 40], vec![Eof]].concat();
+        let bytes: Vec<u8> = alphabet
+            .encode::<_, BITS_OF_PRECISION>(message.clone())
+            .bytes()
+            .map(Result::unwrap)
+            .collect();
+        let length = alphabet
+            .measure::<_, BITS_OF_PRECISION>(message.clone())
+            .unwrap() as usize;
+        let expected = || {
+            let mut events: Vec<_> = message.iter().copied().map(DecodedSymbol).collect();
+            events.push(MessageLength(length));
+            Ok(events)
+        };
+        let events: Result<Vec<_>, _> =
+            alphabet.decode_bytes::<BITS_OF_PRECISION>(&bytes).collect();
+        assert_eq!(events, expected());
+        let events: Result<Vec<_>, _> = alphabet
+            .decode_reader::<_, BITS_OF_PRECISION>(bytes.as_slice())
+            .collect();
+        assert_eq!(events, expected());
+    }
+
+    #[test]
+    fn size_hint_bounds_events() {
+        let alphabet = ExampleAlphabet::new();
+        let bits = alphabet
+            .encode_to_vec::<_, BITS_OF_PRECISION>(vec![B, A, C, Eof])
+            .unwrap();
+        let mut decoder = alphabet.decode::<_, BITS_OF_PRECISION>(bits);
+        assert_eq!(decoder.size_hint(), (1, None));
+        let mut remaining = 5;
+        while let Some(event) = decoder.next() {
+            assert!(event.is_ok());
+            remaining -= 1;
+            let (lower, upper) = decoder.size_hint();
+            assert!(lower <= remaining && upper.is_none_or(|upper| upper >= remaining));
+        }
+        assert_eq!(remaining, 0);
+        assert_eq!(decoder.size_hint(), (0, Some(0)));
+        assert_eq!(decoder.next(), None);
+
+        // The number of events is known when the number of symbols is
+        let bits = alphabet
+            .encode_without_eof::<_, BITS_OF_PRECISION>(vec![B, A, C])
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        let decoder = alphabet.decode_without_eof::<_, BITS_OF_PRECISION>(bits, 3);
+        assert_eq!(decoder.size_hint(), (1, Some(4)));
+        assert_eq!(decoder.count(), 4);
+
+        // Errors end the output
+        let mut decoder = alphabet.decode::<_, BITS_OF_PRECISION>(vec![Zero; 3]);
+        assert!(decoder.any(|event| event.is_err()));
+        assert_eq!(decoder.size_hint(), (0, Some(0)));
+        assert_eq!(decoder.next(), None);
+    }
+
+    #[test]
+    fn snapshot_decoder_state() {
+        let alphabet = ExampleAlphabet::new();
+        let mut decoder =
+            alphabet.decode::<_, BITS_OF_PRECISION>(vec![One, One, One, Zero, Zero, One, Zero]);
+        assert_eq!(decoder.snapshot().state, DecoderState::Initial);
+
+        assert_eq!(decoder.next(), Some(Ok(DecodedSymbol(C))));
+        // Only the bits needed to identify C are read
+        let snapshot = decoder.snapshot();
+        assert_eq!(snapshot.state, DecoderState::Rescaling);
+        assert_eq!(snapshot.symbols_decoded, 1);
+        assert_eq!(snapshot.bits_read, 7);
+        assert_eq!(snapshot.unread_bits, BITS_OF_PRECISION - 7);
+        assert_eq!(snapshot.z, 0b1110010 << (BITS_OF_PRECISION - 7));
+        assert!(snapshot.a <= snapshot.z && snapshot.z < snapshot.b);
+
+        decoder.by_ref().for_each(drop);
+        assert_eq!(decoder.snapshot().state, DecoderState::Final);
+    }
+
+    #[test]
+    fn decode_from_booleans() {
+        let alphabet = ExampleAlphabet::new();
+        let events: Result<Vec<_>, _> = alphabet
+            .decode::<_, BITS_OF_PRECISION>(vec![true, true, true, false, true])
+            .collect();
+        assert_eq!(events, Ok(vec![DecodedSymbol(Eof), MessageLength(5)]));
+    }
+
+    #[test]
+    fn decode_with_bound_table() {
+        let alphabet = ExampleAlphabet::new();
+        for message in [
+            vec![Eof],
+            vec![B, A, C, Eof],
+            [vec![A; 300], vec![Eof]].concat(),
+        ] {
+            let bits = alphabet
+                .encode_to_vec::<_, BITS_OF_PRECISION>(message)
+                .unwrap();
+            let expected: Result<Vec<_>, _> = decode(bits.clone());
+            let events: Result<Vec<_>, _> = alphabet
+                .decode::<_, BITS_OF_PRECISION>(bits)
+                .with_bound_table()
+                .collect();
+            assert_eq!(events, expected);
+        }
+
+        let text = b"the table is built once for the whole message";
+        let alphabet = crate::byte::ByteAlphabet::from_sample(text);
+        let message: Vec<_> = crate::byte::ByteAlphabet::message(text.iter().copied()).collect();
+        let bits = alphabet
+            .encode_to_vec::<_, BITS_OF_PRECISION>(message.clone())
+            .unwrap();
+        let symbols: Result<Vec<_>, _> = alphabet
+            .decode::<_, BITS_OF_PRECISION>(bits)
+            .with_bound_table()
+            .filter_map(|event| match event {
+                Ok(DecodedSymbol(symbol)) => Some(Ok(symbol)),
+                Ok(MessageLength(_)) => None,
+                Err(e) => Some(Err(e)),
+            })
+            .collect();
+        assert_eq!(symbols, Ok(message));
+
+        // The table follows an adaptive alphabet as it changes, message after
+        // message
+        let adaptive = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof);
+        assert!(!adaptive.is_static());
+        let message = [vec![C; 50], vec![A, B, A], vec![C; 50], vec![Eof]].concat();
+        let bits = adaptive
+            .encode_all::<_, BITS_OF_PRECISION>(message.repeat(2))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let mut decoder = adaptive
+            .decode::<_, BITS_OF_PRECISION>(bits.clone())
+            .with_bound_table();
+        for _ in 0..2 {
+            let symbols = decoder.decode_symbols(message.len()).unwrap();
+            assert_eq!(symbols, message);
+            let Some(Ok(MessageLength(length))) = decoder.next() else {
+                panic!("Expected the message length");
+            };
+            assert_eq!(decoder.bits_read(), length);
+            decoder.reset();
+        }
+    }
+
+    #[test]
+    fn reset_with_decodes_new_input() {
+        let alphabet = ExampleAlphabet::new();
+        let mut decoder =
+            alphabet.decode::<_, BITS_OF_PRECISION>(vec![One, One, One, Zero, Zero, One, Zero]);
+        let first: Result<Vec<_>, _> = decoder.by_ref().collect();
+        assert_eq!(
+            first,
+            Ok(vec![DecodedSymbol(C), DecodedSymbol(Eof), MessageLength(7)])
+        );
+
+        decoder.reset_with(vec![One, One, One, Zero, One].into_iter());
+        let second: Result<Vec<_>, _> = decoder.collect();
+        assert_eq!(second, Ok(vec![DecodedSymbol(Eof), MessageLength(5)]));
+    }
+
+    #[test]
+    fn decode_without_eof() {
+        let alphabet = ExampleAlphabet::new();
+        for message in [vec![], vec![C], vec![B, A, C], vec![A; 40]] {
+            let bits = alphabet
+                .encode_without_eof::<_, BITS_OF_PRECISION>(message.clone())
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            let mut expected: Vec<_> = message.iter().copied().map(DecodedSymbol).collect();
+            expected.push(MessageLength(bits.len()));
+
+            let mut input = bits.clone();
+            input.extend([One; 10]);
+            let events: Result<Vec<_>, _> = alphabet
+                .decode_without_eof::<_, BITS_OF_PRECISION>(input, message.len())
+                .collect();
+            assert_eq!(events, Ok(expected));
+        }
+    }
+
+    #[test]
+    fn decode_exact_leaves_trailing_data() {
+        let alphabet = ExampleAlphabet::new();
+        let trailing = vec![One, Zero, Zero, One, One];
+        for message in [vec![], vec![C], vec![B, A, C], vec![A; 40]] {
+            let mut input = alphabet
+                .encode_without_eof::<_, BITS_OF_PRECISION>(message.clone())
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            input.extend(&trailing);
+
+            let (decoded, remainder) = alphabet
+                .decode_exact::<_, BITS_OF_PRECISION>(input, message.len())
+                .unwrap();
+            assert_eq!(decoded, message);
+            assert_eq!(remainder.collect::<Vec<_>>(), trailing);
+        }
+
+        assert!(matches!(
+            alphabet.decode_exact::<_, BITS_OF_PRECISION>(vec![One], 100),
+            Err(DecodeError::UnexpectedEndOfInput(_))
+        ));
+    }
+
+    #[test]
+    fn into_remainder_follows_message() {
+        let alphabet = ExampleAlphabet::new();
+        // C, Eof followed by B, A, C, Eof
+        let input = vec![
+            One, One, One, Zero, Zero, One, Zero, Zero, One, Zero, One, One, One, Zero, Zero, One,
+            Zero,
+        ];
+        let mut decoder = alphabet.decode::<_, BITS_OF_PRECISION>(input.clone());
+        assert_eq!(decoder.by_ref().count(), 3);
+        let remainder: Vec<_> = decoder.into_remainder().collect();
+        assert_eq!(remainder, input[7..]);
+
+        // Before the message ends, only the bits read so far are consumed
+        let mut decoder = alphabet.decode::<_, BITS_OF_PRECISION>(input.clone());
+        assert_eq!(decoder.next(), Some(Ok(DecodedSymbol(C))));
+        let read = decoder.bits_read();
+        assert_eq!(decoder.into_remainder().len(), input.len() - read);
+    }
+
+    #[test]
+    fn decode_all_splits_messages() {
+        let alphabet = ExampleAlphabet::new();
+        let messages = [
+            vec![C, Eof],
+            vec![B, A, C, Eof],
+            vec![Eof],
+            [vec![C; 30], vec![Eof]].concat(),
+        ];
+        let bits: Vec<_> = alphabet
+            .encode_all::<_, BITS_OF_PRECISION>(messages.concat())
+            .map(Result::unwrap)
+            .collect();
+
+        let mut expected = Vec::new();
+        for message in &messages {
+            expected.extend(message.iter().copied().map(DecodedSymbol));
+            let length = alphabet
+                .measure::<_, BITS_OF_PRECISION>(message.clone())
+                .unwrap();
+            expected.push(MessageLength(length as usize));
+        }
+        let events: Result<Vec<_>, _> = alphabet
+            .decode_all::<_, BITS_OF_PRECISION>(bits.clone())
+            .collect();
+        assert_eq!(events, Ok(expected));
+
+        // Each message ends where the next begins
+        let mut decoder = alphabet.decode_all::<_, BITS_OF_PRECISION>(bits.clone());
+        let mut boundaries = Vec::new();
+        while let Some(event) = decoder.next() {
+            assert!(decoder.bits_consumed() <= bits.len());
+            if let Ok(MessageLength(length)) = event {
+                let start = boundaries.last().copied().unwrap_or(0);
+                assert_eq!(decoder.bits_consumed(), start + length);
+                boundaries.push(decoder.bits_consumed());
+            }
+        }
+        assert_eq!(boundaries.len(), messages.len());
+        assert_eq!(boundaries.last(), Some(&bits.len()));
+
+        // Seek to the third message
+        let mut decoder = alphabet.decode_all::<_, BITS_OF_PRECISION>(bits);
+        assert_eq!(decoder.skip_message(), Some(Ok(boundaries[0])));
+        assert_eq!(decoder.next(), Some(Ok(DecodedSymbol(B))));
+        assert_eq!(
+            decoder.skip_message(),
+            Some(Ok(boundaries[1] - boundaries[0]))
+        );
+        assert_eq!(decoder.bits_consumed(), boundaries[1]);
+        assert_eq!(decoder.next(), Some(Ok(DecodedSymbol(Eof))));
+        let lengths: Vec<_> = boundaries
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .collect();
+        assert_eq!(decoder.skip_message(), Some(Ok(lengths[1])));
+        assert_eq!(decoder.skip_message(), Some(Ok(lengths[2])));
+        assert_eq!(decoder.skip_message(), None);
+        assert_eq!(
+            alphabet
+                .decode_all::<_, BITS_OF_PRECISION>(Vec::<Bit>::new())
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn reset_keeping_model_decodes_flushed_messages() {
+        let alphabet = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof);
+        let mut sink = EncoderSink::<_, BITS_OF_PRECISION>::new(&alphabet).unwrap();
+        let mut messages = Vec::new();
+        for message in [vec![A, C, C], vec![C, C, B]] {
+            let mut bits = Vec::new();
+            for symbol in message {
+                bits.extend(sink.push(symbol).unwrap());
+            }
+            bits.extend(sink.flush());
+            messages.push(bits);
+        }
+
+        let length = messages[0].len();
+        let mut decoder = alphabet.decode::<_, BITS_OF_PRECISION>(messages.concat());
+        let first: Vec<_> = decoder.by_ref().map(Result::unwrap).collect();
+        assert_eq!(first[..4], [A, C, C, Eof].map(DecodedSymbol));
+
+        // Skip any bits of the first message which weren't needed to decode it
+        let skip = length - decoder.bits_read();
+        decoder.input.by_ref().take(skip).for_each(drop);
+        decoder.reset_keeping_model();
+        let second: Vec<_> = decoder.map(Result::unwrap).collect();
+        assert_eq!(second[..4], [C, C, B, Eof].map(DecodedSymbol));
+    }
+
+    #[test]
+    fn does_not_read_past_message() {
+        let alphabet = ExampleAlphabet::new();
+        #[rustfmt::skip]
+        let mut input = vec![
+            // First message: C, Eof
+            One, One, One, Zero, Zero, One, Zero,
+            // Second message: B, A, C, Eof
+            Zero, One, Zero, One, One, One, Zero, Zero, One, Zero,
+        ]
+        .into_iter();
+
+        let mut decoder = alphabet.decode::<_, BITS_OF_PRECISION>(input.by_ref());
+        let first: Result<Vec<_>, _> = decoder.by_ref().collect();
+        assert_eq!(
+            first,
+            Ok(vec![DecodedSymbol(C), DecodedSymbol(Eof), MessageLength(7)])
+        );
+        assert!(decoder.bits_read() <= 7);
+
+        // Skip any bits of the first message which weren't needed to decode it
+        let skip = 7 - decoder.bits_read();
+        input.by_ref().take(skip).for_each(drop);
+        assert_eq!(input.len(), 10);
+
+        let second: Result<Vec<_>, _> = alphabet.decode::<_, BITS_OF_PRECISION>(input).collect();
+        assert_eq!(
+            second,
+            Ok(vec![
+                DecodedSymbol(B),
+                DecodedSymbol(A),
+                DecodedSymbol(C),
+                DecodedSymbol(Eof),
+                MessageLength(10),
+            ])
+        );
+    }
+
+    #[test]
+    fn decode_without_trailing_zeros() {
+        // The encoding of C, Eof is 1110010, but the final zero can be left out
+        let alphabet = ExampleAlphabet::new();
+        let mut decoder =
+            alphabet.decode::<_, BITS_OF_PRECISION>(vec![One, One, One, Zero, Zero, One]);
+        let events: Result<Vec<_>, _> = decoder.by_ref().collect();
+        assert_eq!(
+            events,
+            Ok(vec![DecodedSymbol(C), DecodedSymbol(Eof), MessageLength(7)])
+        );
+        assert_eq!(decoder.bits_read() + decoder.virtual_zeros(), 7);
+
+        let mut decoder = alphabet.decode::<_, BITS_OF_PRECISION>(vec![One, One, One, Zero, One]);
+        decoder.by_ref().for_each(drop);
+        assert_eq!(decoder.virtual_zeros(), 0);
+    }
+
+    /// An alphabet whose quantile always finds the symbol at the given index,
+    /// disagreeing with its bounds
+    struct Mismatched(ExampleAlphabet, usize);
+
+    impl Alphabet for Mismatched {
+        type S = ExampleSymbol;
+
+        fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+            self.0.symbols()
+        }
+
+        fn eof(&self) -> Self::S {
+            Eof
+        }
+
+        fn interval_width(&self, symbol: &Self::S) -> usize {
+            self.0.interval_width(symbol)
+        }
+
+        fn quantile(&self, _value: usize) -> &Self::S {
+            self.0.symbol_at(self.1)
+        }
+    }
+
+    #[test]
+    fn error_on_mismatched_alphabet() {
+        let input = vec![One, One, One, Zero, One];
+        let alphabet = Mismatched(ExampleAlphabet::new(), 2);
+        let events: Vec<_> = alphabet
+            .decode::<_, BITS_OF_PRECISION>(input.clone())
+            .collect();
+        assert_eq!(events, vec![Err(DecodeError::NoMatchingSymbol(0))]);
+
+        // Decoding A (which covers the start of the interval) leaves z beyond
+        // its subinterval
+        let alphabet = Mismatched(ExampleAlphabet::new(), 0);
+        let events: Vec<_> = alphabet.decode::<_, BITS_OF_PRECISION>(input).collect();
+        assert_eq!(
+            events,
+            vec![
+                Ok(DecodedSymbol(A)),
+                Err(DecodeError::CorruptInput {
+                    a: 0,
+                    b: 1 << 31,
+                    z: 1 << 31
+                })
+            ]
+        );
+    }
+
+    #[test]
+    fn skip_message_after_error() {
+        let alphabet = ExampleAlphabet::new();
+        let mut decoder = alphabet
+            .decode::<_, BITS_OF_PRECISION>(vec![Zero, One, Zero, One])
+            .with_max_symbols(1);
+        assert_eq!(
+            decoder.skip_message(),
+            Err(DecodeError::LimitExceeded(Limit::Symbols(1)))
+        );
+        assert_eq!(decoder.skip_message(), Err(DecodeError::AlreadyFailed));
+    }
+
+    #[test]
+    fn error_on_truncated_input() {
+        let alphabet = ExampleAlphabet::new();
+        let bits = alphabet
+            .encode_to_vec::<_, BITS_OF_PRECISION>([vec![C; 100], vec![Eof]].concat())
+            .unwrap();
+        let events: Vec<_> = alphabet
+            .decode::<_, BITS_OF_PRECISION>(bits[..bits.len() / 2].to_vec())
+            .collect();
+        let symbols = events.len() - 1;
+        assert!(symbols < 100);
+        assert_eq!(
+            events.last(),
+            Some(&Err(DecodeError::UnexpectedEndOfInput(symbols)))
+        );
+    }
+
+    #[test]
+    fn error_on_exceeded_work_budget() {
+        let alphabet = ExampleAlphabet::new();
+        let events: Vec<_> = alphabet
+            .decode::<_, BITS_OF_PRECISION>(vec![
+                Zero, One, Zero, One, One, One, Zero, Zero, One, Zero,
+            ])
+            .with_work_budget(5)
+            .collect();
+
+        // Decoding stops at the error
+        assert_eq!(
+            events,
+            vec![
+                Ok(DecodedSymbol(B)),
+                Ok(DecodedSymbol(A)),
+                Err(DecodeError::BudgetExceeded(5))
+            ]
+        );
+    }
+
+    #[test]
+    fn error_on_cancellation() {
+        let alphabet = ExampleAlphabet::new();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut decoder = alphabet
+            .decode::<_, BITS_OF_PRECISION>(vec![
+                Zero, One, Zero, One, One, One, Zero, Zero, One, Zero,
+            ])
+            .with_cancellation(cancelled.clone());
+        assert_eq!(decoder.next(), Some(Ok(DecodedSymbol(B))));
+        cancelled.store(true, Ordering::Relaxed);
+        assert_eq!(decoder.next(), Some(Err(DecodeError::Cancelled)));
+        assert_eq!(decoder.next(), None);
+    }
+
+    #[test]
+    fn error_on_exceeded_limits() {
+        let alphabet = ExampleAlphabet::new();
+        // B, A, C, Eof
+        let input = vec![Zero, One, Zero, One, One, One, Zero, Zero, One, Zero];
+        let events: Vec<_> = alphabet
+            .decode::<_, BITS_OF_PRECISION>(input.clone())
+            .with_max_symbols(2)
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                Ok(DecodedSymbol(B)),
+                Ok(DecodedSymbol(A)),
+                Err(DecodeError::LimitExceeded(Limit::Symbols(2)))
+            ]
+        );
+
+        let events: Vec<_> = alphabet
+            .decode::<_, BITS_OF_PRECISION>(input.clone())
+            .with_max_bits(4)
+            .collect();
+        assert_eq!(
+            events.last(),
+            Some(&Err(DecodeError::LimitExceeded(Limit::Bits(4))))
+        );
+
+        // Limits which the message fits within don't get in the way
+        let events: Result<Vec<_>, _> = alphabet
+            .decode::<_, BITS_OF_PRECISION>(input)
+            .with_max_symbols(4)
+            .with_max_bits(10)
+            .collect();
+        assert_eq!(events.unwrap().len(), 5);
+    }
+
+    #[test]
+    fn decode_within_work_budget() {
+        let alphabet = ExampleAlphabet::new();
+        let events: Result<Vec<_>, _> = alphabet
+            .decode::<_, BITS_OF_PRECISION>(vec![One, One, One, Zero, Zero, One, Zero])
+            .with_work_budget(100)
+            .collect();
+
+        assert_eq!(
+            events,
+            Ok(vec![DecodedSymbol(C), DecodedSymbol(Eof), MessageLength(7)])
+        );
+    }
 }
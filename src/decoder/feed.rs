@@ -0,0 +1,174 @@
+use super::{DecodeError, DecoderEvent, DecoderOutput};
+use crate::alphabet::Alphabet;
+use crate::bits::Bit;
+use std::collections::VecDeque;
+
+/// The bits fed to a [`DecoderFeed`] which haven't been read yet.
+struct FedBits(VecDeque<Bit>);
+
+impl Iterator for FedBits {
+    type Item = Bit;
+
+    fn next(&mut self) -> Option<Bit> {
+        self.0.pop_front()
+    }
+}
+
+/// A push-based decoder, for input which arrives in pieces (such as from a
+/// socket) rather than as a complete stream of bits.
+///
+/// Each call to [`DecoderFeed::feed`] decodes as far as the bits fed so far
+/// allow, returning the events produced. Until the message has ended,
+/// [`DecoderFeed::needs_more_bits`] indicates that decoding is waiting on
+/// more input. Call [`DecoderFeed::finish`] if the input ends before then,
+/// to decode the rest of the message as if it were followed by zeros (see
+/// [`DecoderOutput::virtual_zeros`]).
+///
+/// The events are the same as those produced by
+/// [`Decoder::decode`](super::Decoder::decode) from the complete input.
+pub struct DecoderFeed<'a, A: Alphabet, const BITS_OF_PRECISION: u32> {
+    output: DecoderOutput<'a, A::S, A, FedBits, BITS_OF_PRECISION>,
+    done: bool,
+}
+
+impl<'a, A: Alphabet, const BITS_OF_PRECISION: u32> DecoderFeed<'a, A, BITS_OF_PRECISION> {
+    /// Prepare to decode a message, before any of its bits have arrived.
+    pub fn new(alphabet: &'a A) -> Self {
+        Self {
+            output: DecoderOutput {
+                streaming: true,
+                ..DecoderOutput::new(FedBits(VecDeque::new()), alphabet)
+            },
+            done: false,
+        }
+    }
+
+    /// Add some bits of the message, and return the events they complete.
+    ///
+    /// Bits beyond the end of the message are left unread (see
+    /// [`DecoderFeed::unread_bits`]).
+    pub fn feed<B: Into<Bit>>(
+        &mut self,
+        bits: impl IntoIterator<Item = B>,
+    ) -> Result<Vec<DecoderEvent<A::S>>, DecodeError> {
+        self.output.input.0.extend(bits.into_iter().map(Into::into));
+        self.decode()
+    }
+
+    /// End the input, returning the remaining events of the message.
+    pub fn finish(mut self) -> Result<Vec<DecoderEvent<A::S>>, DecodeError> {
+        self.output.streaming = false;
+        self.decode()
+    }
+
+    /// Whether decoding is waiting for more bits, as the message hasn't ended.
+    pub fn needs_more_bits(&self) -> bool {
+        !self.done
+    }
+
+    /// The number of bits fed which haven't been read by the decoder.
+    pub fn unread_bits(&self) -> usize {
+        self.output.input.0.len()
+    }
+
+    /// Decode until more bits are needed, or the message ends.
+    fn decode(&mut self) -> Result<Vec<DecoderEvent<A::S>>, DecodeError> {
+        let mut events = Vec::new();
+        loop {
+            match self.output.next() {
+                Some(Err(DecodeError::NeedMoreBits)) => return Ok(events),
+                Some(event) => events.push(event?),
+                None => {
+                    self.done = true;
+                    return Ok(events);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoder::Decoder;
+    use crate::encoder::Encoder;
+    use crate::example::{ExampleAlphabet, ExampleSymbol::*};
+    use crate::models::AdaptiveAlphabet;
+
+    #[test]
+    fn peek_while_waiting_for_bits() {
+        let alphabet = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof);
+        let message = [A, B, B, C, A, B, Eof];
+        let bits = alphabet.encode_to_vec::<_, 32>(message).unwrap();
+        let expected: Vec<_> = alphabet
+            .decode::<_, 32>(bits.clone())
+            .map(Result::unwrap)
+            .collect();
+
+        // The first bit leaves A and B both possible
+        let mut feed = DecoderFeed::<_, 32>::new(&alphabet);
+        feed.output.input.0.push_back(bits[0]);
+        assert_eq!(feed.output.peek(), Err(DecodeError::NeedMoreBits));
+        feed.output.input.0.extend(&bits[1..]);
+        assert_eq!(feed.output.peek(), Ok(Some(A)));
+        assert_eq!(feed.feed(Vec::<Bit>::new()).unwrap(), expected);
+
+        // Peeking between bits doesn't disturb decoding
+        let mut feed = DecoderFeed::<_, 32>::new(&alphabet);
+        let mut events = Vec::new();
+        for &bit in &bits {
+            assert_eq!(feed.output.peek(), Err(DecodeError::NeedMoreBits));
+            events.extend(feed.feed([bit]).unwrap());
+        }
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn matches_pull_decoder() {
+        let alphabet = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof);
+        let message = [A, B, B, C, A, B].repeat(20);
+        let bits = alphabet
+            .encode_to_vec::<_, 32>(message.into_iter().chain([Eof]))
+            .unwrap();
+        let expected: Vec<_> = alphabet
+            .decode::<_, 32>(bits.clone())
+            .map(Result::unwrap)
+            .collect();
+
+        for chunk_size in [1, 3, 16, bits.len()] {
+            let mut feed = DecoderFeed::<_, 32>::new(&alphabet);
+            let mut events = Vec::new();
+            for chunk in bits.chunks(chunk_size) {
+                assert!(feed.needs_more_bits());
+                events.extend(feed.feed(chunk.iter().copied()).unwrap());
+            }
+            assert!(!feed.needs_more_bits());
+            assert_eq!(events, expected);
+        }
+    }
+
+    #[test]
+    fn finish_pads_with_zeros() {
+        let alphabet = ExampleAlphabet::new();
+        // C, Eof with its final zero stripped
+        let mut feed = DecoderFeed::<_, 32>::new(&alphabet);
+        let mut events = feed.feed([true, true, true, false]).unwrap();
+        events.extend(feed.feed([false, true]).unwrap());
+        assert!(feed.needs_more_bits());
+        events.extend(feed.finish().unwrap());
+        assert_eq!(
+            events,
+            vec![
+                DecoderEvent::DecodedSymbol(C),
+                DecoderEvent::DecodedSymbol(Eof),
+                DecoderEvent::MessageLength(7)
+            ]
+        );
+
+        // Bits following the message are left unread
+        let mut feed = DecoderFeed::<_, 32>::new(&alphabet);
+        let events = feed.feed([true, true, true, false, true, false, false]);
+        assert_eq!(events.unwrap().len(), 2);
+        assert_eq!(feed.unread_bits(), 2);
+    }
+}
@@ -0,0 +1,109 @@
+use super::{DecoderEvent, DecoderOutput};
+use crate::alphabet::Alphabet;
+use crate::bits::ReadBits;
+use crate::byte::ByteSymbol;
+use std::io;
+
+/// A decoder which reads compressed bytes from an [`io::Read`] and is itself
+/// an [`io::Read`] of the decompressed bytes, so it can be placed in a
+/// pipeline of readers.
+///
+/// The input is unpacked most significant bit first, as written by
+/// [`EncoderWriter`](crate::encoder::EncoderWriter), and decoded with an
+/// alphabet of [`ByteSymbol`]s, such as a
+/// [`ByteAlphabet`](crate::byte::ByteAlphabet). Reading ends at EOF. Decoding
+/// errors are reported as [`io::ErrorKind::InvalidData`].
+pub struct DecoderReader<'a, A, R, const BITS_OF_PRECISION: u32>
+where
+    A: Alphabet<S = ByteSymbol>,
+    R: io::Read,
+{
+    output: DecoderOutput<'a, ByteSymbol, A, ReadBits<R>, BITS_OF_PRECISION>,
+}
+
+impl<'a, A, R, const BITS_OF_PRECISION: u32> DecoderReader<'a, A, R, BITS_OF_PRECISION>
+where
+    A: Alphabet<S = ByteSymbol>,
+    R: io::Read,
+{
+    /// Prepare to decode a message from the given reader.
+    pub fn new(alphabet: &'a A, reader: R) -> Self {
+        Self {
+            output: DecoderOutput::new(ReadBits::new(reader), alphabet),
+        }
+    }
+}
+
+impl<A, R, const BITS_OF_PRECISION: u32> io::Read for DecoderReader<'_, A, R, BITS_OF_PRECISION>
+where
+    A: Alphabet<S = ByteSymbol>,
+    R: io::Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut read = 0;
+        while read < buf.len() {
+            let event = self.output.next();
+            // An IO error looks like the end of the input to the decoder
+            if let Some(e) = self.output.input.take_error() {
+                return Err(e);
+            }
+            match event {
+                Some(Ok(DecoderEvent::DecodedSymbol(ByteSymbol::Byte(byte)))) => {
+                    buf[read] = byte;
+                    read += 1;
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+                None => break,
+            }
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::byte::ByteAlphabet;
+    use crate::encoder::EncoderWriter;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn round_trip_through_reader() {
+        let text = b"she sells sea shells by the sea shore";
+        let alphabet = ByteAlphabet::from_sample(text);
+        let mut encoder = EncoderWriter::<_, _, 32>::new(&alphabet, Vec::new()).unwrap();
+        encoder.write_all(text).unwrap();
+        let bytes = encoder.finish().unwrap();
+
+        let mut decoded = Vec::new();
+        let mut decoder = DecoderReader::<_, _, 32>::new(&alphabet, bytes.as_slice());
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, text);
+
+        // Reading in small pieces gives the same bytes
+        let mut decoder = DecoderReader::<_, _, 32>::new(&alphabet, bytes.as_slice());
+        let mut buffer = [0; 5];
+        let mut decoded: Vec<u8> = Vec::new();
+        loop {
+            match decoder.read(&mut buffer).unwrap() {
+                0 => break,
+                n => decoded.extend(&buffer[..n]),
+            }
+        }
+        assert_eq!(decoded, text);
+    }
+
+    #[test]
+    fn error_on_truncated_input() {
+        let text = b"she sells sea shells by the sea shore";
+        let alphabet = ByteAlphabet::from_sample(text);
+        let mut encoder = EncoderWriter::<_, _, 32>::new(&alphabet, Vec::new()).unwrap();
+        encoder.write_all(text).unwrap();
+        let bytes = encoder.finish().unwrap();
+
+        let mut decoder = DecoderReader::<_, _, 32>::new(&alphabet, &bytes[..bytes.len() / 2]);
+        let error = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,227 @@
+use crate::alphabet::{Alphabet, Symbol};
+use crate::fenwick::Fenwick;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// An alphabet whose symbols and weights can be changed at runtime.
+///
+/// Symbols are located through a hash map and their cumulative bounds are kept
+/// in a Fenwick tree, so lookups and weight changes take O(log n) time.
+/// Inserting or removing a symbol rebuilds the tree, which takes O(n).
+///
+/// Symbols are ordered by insertion, except that removing a symbol moves the
+/// last symbol into its place. The encoder and decoder must see the same
+/// alphabet, so changes should only be made between messages.
+#[derive(Clone, Debug)]
+pub struct DynamicAlphabet<S: Symbol + Eq + Hash> {
+    symbols: Vec<S>,
+    index: HashMap<S, usize>,
+    widths: Fenwick,
+    eof: S,
+}
+
+impl<S: Symbol + Eq + Hash> DynamicAlphabet<S> {
+    /// Construct an alphabet containing only the EOF symbol, with the given
+    /// weight.
+    pub fn new(eof: S, eof_weight: usize) -> Self {
+        let mut alphabet = Self {
+            symbols: Vec::new(),
+            index: HashMap::new(),
+            widths: Fenwick::from_counts(&[]),
+            eof: eof.clone(),
+        };
+        alphabet.insert(eof, eof_weight);
+        alphabet
+    }
+
+    /// Add a symbol with the given weight, or change its weight if it's
+    /// already present. Returns its previous weight, if any.
+    pub fn insert(&mut self, symbol: S, weight: usize) -> Option<usize> {
+        if let Some(previous) = self.set_weight(&symbol, weight) {
+            return Some(previous);
+        }
+
+        let mut widths = self.weights();
+        widths.push(weight);
+        self.index.insert(symbol.clone(), self.symbols.len());
+        self.symbols.push(symbol);
+        self.widths = Fenwick::from_counts(&widths);
+        None
+    }
+
+    /// Change the weight of a symbol which is already present. Returns its
+    /// previous weight, or None (leaving the alphabet unchanged) if the symbol
+    /// isn't present.
+    pub fn set_weight(&mut self, symbol: &S, weight: usize) -> Option<usize> {
+        let &i = self.index.get(symbol)?;
+        let previous = self.widths.get(i);
+        self.widths.set(i, weight);
+        Some(previous)
+    }
+
+    /// Remove a symbol, returning its weight, or None if it isn't present.
+    ///
+    /// The EOF symbol can be removed, but the alphabet can't be used for coding
+    /// until it is inserted again.
+    pub fn remove(&mut self, symbol: &S) -> Option<usize> {
+        let i = self.index.remove(symbol)?;
+        let mut widths = self.weights();
+        let weight = widths.swap_remove(i);
+        self.symbols.swap_remove(i);
+        if let Some(moved) = self.symbols.get(i) {
+            self.index.insert(moved.clone(), i);
+        }
+        self.widths = Fenwick::from_counts(&widths);
+        Some(weight)
+    }
+
+    /// The weight of a symbol, or None if it isn't present.
+    pub fn weight(&self, symbol: &S) -> Option<usize> {
+        self.index.get(symbol).map(|&i| self.widths.get(i))
+    }
+
+    pub fn contains(&self, symbol: &S) -> bool {
+        self.index.contains_key(symbol)
+    }
+
+    /// The number of symbols, including EOF.
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    fn weights(&self) -> Vec<usize> {
+        (0..self.symbols.len())
+            .map(|i| self.widths.get(i))
+            .collect()
+    }
+
+    fn index_of(&self, symbol: &S) -> usize {
+        *self
+            .index
+            .get(symbol)
+            .unwrap_or_else(|| panic!("Symbol {symbol:?} not in alphabet"))
+    }
+}
+
+impl<S: Symbol + Eq + Hash> Alphabet for DynamicAlphabet<S> {
+    type S = S;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.symbols.iter()
+    }
+
+    fn eof(&self) -> Self::S {
+        self.eof.clone()
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        self.widths.get(self.index_of(symbol))
+    }
+
+    fn total_interval_width(&self) -> usize {
+        self.widths.prefix_sum(self.widths.len())
+    }
+
+    fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+        self.widths.prefix_sum(self.index_of(symbol))
+    }
+
+    fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+        self.widths.prefix_sum(self.index_of(symbol) + 1)
+    }
+
+    fn quantile(&self, value: usize) -> &Self::S {
+        let index = self.widths.search(value);
+        assert!(
+            index < self.symbols.len(),
+            "Value {value} is beyond the total interval width"
+        );
+        &self.symbols[index]
+    }
+
+    fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        let &index = self.index.get(symbol)?;
+        Some((
+            self.widths.prefix_sum(index),
+            self.widths.prefix_sum(index + 1),
+        ))
+    }
+
+    fn contains(&self, symbol: &Self::S) -> bool {
+        DynamicAlphabet::contains(self, symbol)
+    }
+
+    fn index(&self, symbol: &Self::S) -> usize {
+        self.index_of(symbol)
+    }
+
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        &self.symbols[index]
+    }
+
+    fn is_static(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::alphabet::AlphabetError;
+    use crate::reference;
+
+    #[test]
+    fn insert_set_and_remove() {
+        let mut alphabet = DynamicAlphabet::new("eof".to_string(), 1);
+        assert_eq!(alphabet.insert("a".to_string(), 5), None);
+        assert_eq!(alphabet.insert("b".to_string(), 3), None);
+        assert_eq!(alphabet.insert("c".to_string(), 2), None);
+        assert_eq!(alphabet.total_interval_width(), 11);
+        assert_eq!(alphabet.interval_lower_bound(&"b".to_string()), 6);
+
+        assert_eq!(alphabet.insert("b".to_string(), 4), Some(3));
+        assert_eq!(alphabet.set_weight(&"a".to_string(), 1), Some(5));
+        assert_eq!(alphabet.set_weight(&"z".to_string(), 1), None);
+        assert_eq!(alphabet.total_interval_width(), 8);
+
+        // "c" moves into the place of "a"
+        assert_eq!(alphabet.remove(&"a".to_string()), Some(1));
+        assert_eq!(alphabet.remove(&"a".to_string()), None);
+        assert_eq!(
+            alphabet.symbols().collect::<Vec<_>>(),
+            vec!["eof", "c", "b"]
+        );
+        assert_eq!(alphabet.interval_lower_bound(&"b".to_string()), 3);
+        assert_eq!(alphabet.weight(&"c".to_string()), Some(2));
+        assert_eq!(alphabet.len(), 3);
+    }
+
+    #[test]
+    fn removing_eof_invalidates_alphabet() {
+        let mut alphabet = DynamicAlphabet::new(0u8.to_string(), 1);
+        alphabet.insert(1u8.to_string(), 1);
+        alphabet.remove(&0u8.to_string());
+        assert_eq!(
+            alphabet.validate(32),
+            Err(AlphabetError::MissingEof("\"0\"".to_string()))
+        );
+    }
+
+    #[test]
+    fn dynamic_alphabet_matches_reference() {
+        let mut alphabet = DynamicAlphabet::new("eof".to_string(), 2);
+        for (i, word) in ["the", "quick", "brown", "fox", "jumps"].iter().enumerate() {
+            alphabet.insert(word.to_string(), i + 1);
+        }
+        alphabet.remove(&"brown".to_string());
+        alphabet.set_weight(&"the".to_string(), 20);
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&alphabet, 50, 50, 10),
+            Ok(())
+        );
+    }
+}
@@ -1,7 +1,26 @@
-use crate::alphabet::{Alphabet, Symbol};
-use biterator::Bit::{self, One, Zero};
+use crate::alphabet::{Alphabet, AlphabetError, AlphabetRef, Symbol, WithoutEof};
+use crate::arcode;
+use crate::bits::Bit::{self, One, Zero};
+use crate::bits::BitSink;
+use crate::models::{Model, ModelAlphabet};
+use crate::range;
+use crate::reciprocal::Reciprocal;
+use crate::rescale::{
+    MIN_BITS_OF_PRECISION, middle_rescale, middle_rescale_count, side_rescale, side_rescale_count,
+    side_rescale_upper,
+};
 use log::debug;
-use std::iter::{once, repeat_n};
+use std::error::Error;
+use std::fmt;
+use std::iter::{FusedIterator, Peekable};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+mod sink;
+mod writer;
+
+pub use sink::{Checkpoint, EncoderSink};
+pub use writer::EncoderWriter;
 
 /// Encoder Algorithm
 /// Adapted from mathematicalmonk's ["Finite-precision arithmetic coding - Encoder"][1]
@@ -68,8 +87,142 @@ use EncoderState::*;
 pub enum EncodeError {
     #[error("Stream not terminated by EOF symbol")]
     UnterminatedStream,
+    #[error("Symbol at position {position} is not in the alphabet")]
+    SymbolNotInAlphabet { position: usize },
+    #[error(transparent)]
+    InvalidAlphabet(#[from] AlphabetError),
+    #[error("Output doesn't fit in a buffer of {0} bytes")]
+    BufferFull(usize),
+    #[error("Encoding was cancelled")]
+    Cancelled,
+    #[error("Error reading input: {0}")]
+    Source(SourceError),
+}
+
+/// An error produced by the input of [`Encoder::encode_fallible`].
+///
+/// Errors are compared by their message, since the underlying error type
+/// needn't implement [`PartialEq`].
+#[derive(Debug)]
+pub struct SourceError(Box<dyn Error + Send + Sync>);
+
+impl SourceError {
+    /// The underlying error, which can be downcast to its original type.
+    pub fn get_ref(&self) -> &(dyn Error + Send + Sync + 'static) {
+        &*self.0
+    }
+
+    /// Consume this error, returning the underlying error.
+    pub fn into_inner(self) -> Box<dyn Error + Send + Sync> {
+        self.0
+    }
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Error for SourceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.0)
+    }
+}
+
+impl PartialEq for SourceError {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_string() == other.0.to_string()
+    }
+}
+
+/// A summary of the encoding of a message, from [`EncoderOutput::stats`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EncoderStats {
+    /// The number of symbols encoded, not counting EOF
+    pub symbols: usize,
+    /// The number of bits emitted
+    pub bits: usize,
+}
+
+impl EncoderStats {
+    /// The average number of bits emitted per symbol, or `None` if no
+    /// symbols have been encoded.
+    pub fn bits_per_symbol(&self) -> Option<f64> {
+        (self.symbols > 0).then(|| self.bits as f64 / self.symbols as f64)
+    }
+
+    /// The ratio of the size of the symbols at 8 bits each (as if they were
+    /// bytes) to the size of the encoded output, or `None` if no bits have
+    /// been emitted.
+    pub fn compression_ratio(&self) -> Option<f64> {
+        (self.bits > 0).then(|| (self.symbols * 8) as f64 / self.bits as f64)
+    }
 }
 
+/// Bits waiting to be emitted: a bit followed by a run of its opposite (the s
+/// pending bits), then the lowest bits of a value, most significant first.
+///
+/// Everything the encoder emits at once (a run of side rescaling steps, or the
+/// final bits of a message) has this form, so it can be emitted without
+/// allocating.
+#[derive(Copy, Clone, Debug, Default)]
+struct PendingBits {
+    /// The leading bit, until it has been emitted
+    first: Option<Bit>,
+    opposite: bool,
+    opposites: usize,
+    value: usize,
+    remaining: u32,
+}
+
+impl PendingBits {
+    /// A bit followed by the given number of its opposite.
+    fn new(first: Bit, opposites: usize) -> Self {
+        Self {
+            first: Some(first),
+            opposite: first == Zero,
+            opposites,
+            value: 0,
+            remaining: 0,
+        }
+    }
+
+    /// Follow these bits with the lowest `count` bits of a value.
+    fn followed_by(self, value: usize, count: u32) -> Self {
+        Self {
+            value,
+            remaining: count,
+            ..self
+        }
+    }
+}
+
+impl Iterator for PendingBits {
+    type Item = Bit;
+
+    fn next(&mut self) -> Option<Bit> {
+        if let Some(first) = self.first.take() {
+            Some(first)
+        } else if self.opposites > 0 {
+            self.opposites -= 1;
+            Some(Bit::from(self.opposite))
+        } else if self.remaining > 0 {
+            self.remaining -= 1;
+            Some(Bit::from((self.value >> self.remaining) & 1 == 1))
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = usize::from(self.first.is_some()) + self.opposites + self.remaining as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for PendingBits {}
+
 pub struct EncoderOutput<'a, S, A, I, const BITS_OF_PRECISION: u32>
 where
     S: Symbol,
@@ -77,13 +230,20 @@ where
     I: Iterator<Item = S>,
 {
     input: I,
-    alphabet: &'a A,
+    alphabet: AlphabetRef<'a, A>,
     state: EncoderState,
-    bits_to_emit: Option<Box<dyn Iterator<Item = Bit>>>,
+    bits_to_emit: PendingBits,
+    total_interval_width: Reciprocal,
     a: usize,
     b: usize,
     s: usize,
+    position: usize,
+    bits_emitted: usize,
+    eof_free: bool,
     eof_reached: bool,
+    keep_model: bool,
+    cancelled: Option<Arc<AtomicBool>>,
+    alphabet_validated: bool,
 }
 
 impl<'a, S, A, I, const BITS_OF_PRECISION: u32> EncoderOutput<'a, S, A, I, BITS_OF_PRECISION>
@@ -97,7 +257,13 @@ where
     const QUARTER: usize = Self::WHOLE / 4;
 
     /// Construct a new EncoderOutput from an input stream of symbols and an alphabet.
-    fn new(input: I, alphabet: &'a A) -> Self {
+    fn new(input: I, alphabet: impl Into<AlphabetRef<'a, A>>) -> Self {
+        const {
+            assert!(
+                BITS_OF_PRECISION >= MIN_BITS_OF_PRECISION,
+                "At least 2 bits of precision are needed"
+            )
+        };
         debug!(
             "Encoding with {BITS_OF_PRECISION} bits (whole={} half={} quarter={})",
             Self::WHOLE,
@@ -106,31 +272,165 @@ where
         );
         EncoderOutput {
             input,
-            alphabet,
+            alphabet: alphabet.into(),
             state: Initial,
-            bits_to_emit: None,
+            bits_to_emit: PendingBits::default(),
+            // Replaced once the alphabet is validated, as R may be zero until then
+            total_interval_width: Reciprocal::new(1),
             a: 0,
             b: 0,
             s: 0,
+            position: 0,
+            bits_emitted: 0,
+            eof_free: false,
             eof_reached: false,
+            keep_model: false,
+            cancelled: None,
+            alphabet_validated: false,
         }
     }
 
+    /// Stop encoding with [`EncodeError::Cancelled`] once the given flag is
+    /// set, so that a long encode can be aborted from another thread.
+    ///
+    /// The flag is checked before each symbol is read from the input.
+    pub fn with_cancellation(mut self, cancelled: Arc<AtomicBool>) -> Self {
+        self.cancelled = Some(cancelled);
+        self
+    }
+
+    /// Encode the whole message, writing its bits to the given sink. Returns
+    /// the number of bits written.
+    pub fn write_to<K: BitSink>(self, sink: &mut K) -> Result<usize, EncodeError> {
+        let mut written = 0;
+        for bit in self {
+            sink.write_bit(bit?);
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Encode the whole message, appending its bits packed into bytes (most
+    /// significant bit first, the last byte padded with zeros) to the given
+    /// buffer, which can be reused across messages. Returns the number of
+    /// bits written.
+    pub fn encode_into(self, buffer: &mut Vec<u8>) -> Result<usize, EncodeError> {
+        let mut written = 0;
+        for bit in self {
+            let bit = bit?;
+            if written % 8 == 0 {
+                buffer.push(0);
+            }
+            *buffer.last_mut().unwrap() |= u8::from(bool::from(bit)) << (7 - written % 8);
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Encode the whole message into the start of the given buffer, packed as
+    /// by [`EncoderOutput::encode_into`], returning the number of bits
+    /// written. Fails with [`EncodeError::BufferFull`] if the buffer is too
+    /// small for the message.
+    pub fn encode_into_slice(self, buffer: &mut [u8]) -> Result<usize, EncodeError> {
+        let capacity = buffer.len();
+        let mut written = 0;
+        for bit in self {
+            let bit = bit?;
+            let byte = buffer
+                .get_mut(written / 8)
+                .ok_or(EncodeError::BufferFull(capacity))?;
+            if written % 8 == 0 {
+                *byte = 0;
+            }
+            *byte |= u8::from(bool::from(bit)) << (7 - written % 8);
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Encode the whole message, only counting its bits. This does all the
+    /// interval arithmetic, but skips producing the bits one at a time.
+    pub fn measure(mut self) -> Result<u64, EncodeError> {
+        let mut count = 0;
+        loop {
+            count += self.bits_to_emit.len() as u64;
+            self.bits_to_emit = PendingBits::default();
+            if self.state == Final {
+                return Ok(count);
+            }
+            self.state = self.execute()?;
+        }
+    }
+
+    /// Statistics about the current message, as far as it has been encoded.
+    pub fn stats(&self) -> EncoderStats {
+        EncoderStats {
+            symbols: self.position - usize::from(self.eof_reached),
+            bits: self.bits_emitted,
+        }
+    }
+
+    /// Pack the output into bytes, most significant bit first.
+    ///
+    /// The final byte is padded with zeros, as by
+    /// [`BitWriter`](crate::bits::BitWriter), which the decoder reads past
+    /// harmlessly. An error ends the output, discarding the bits of the
+    /// partial byte before it.
+    pub fn bytes(self) -> EncoderBytes<'a, S, A, I, BITS_OF_PRECISION> {
+        EncoderBytes { bits: self }
+    }
+
+    /// Prepare to encode the next message from the remaining input (the
+    /// symbols following the EOF of the previous message).
+    ///
+    /// This reuses the setup done for the alphabet, such as the precomputed
+    /// reciprocal of R, so many small messages can be encoded without
+    /// constructing a new encoder for each.
+    pub fn reset(&mut self) {
+        self.state = Initial;
+        self.bits_to_emit = PendingBits::default();
+        self.a = 0;
+        self.b = 0;
+        self.s = 0;
+        self.position = 0;
+        self.bits_emitted = 0;
+        self.eof_reached = false;
+    }
+
+    /// Prepare to encode the next message from the remaining input, like
+    /// [`EncoderOutput::reset`], but without starting the model afresh with
+    /// [`Alphabet::begin_message`], so an adaptive model carries what it
+    /// learned from the previous message over to the next.
+    ///
+    /// The decoder must do the same, with
+    /// [`DecoderOutput::reset_keeping_model`](crate::decoder::DecoderOutput::reset_keeping_model).
+    pub fn reset_keeping_model(&mut self) {
+        self.reset();
+        self.keep_model = true;
+    }
+
+    /// Prepare to encode the next message from a new input stream, reusing
+    /// the setup done for the alphabet.
+    pub fn reset_with(&mut self, input: I) {
+        self.input = input;
+        self.reset();
+    }
+
     fn assert_sanity(&self) {
         assert!(self.a < self.b, "a ({}) >= b ({})", self.a, self.b);
     }
 
     /// Determine the next bit in the encoded output. None indicates the end
     /// of the output.
+    ///
+    /// After an error, encoding cannot continue, so no further bits follow.
     fn next_bit(&mut self) -> Option<Result<Bit, EncodeError>> {
         loop {
             // If there's a bit to emit next, emit it
-            match self.bits_to_emit.as_mut().and_then(|bits| bits.next()) {
-                Some(bit) => {
-                    debug!("Emitting {bit}");
-                    return Some(Ok(bit));
-                }
-                None => self.bits_to_emit = None,
+            if let Some(bit) = self.bits_to_emit.next() {
+                debug!("Emitting {bit}");
+                self.bits_emitted += 1;
+                return Some(Ok(bit));
             }
 
             // No more bits and final state reached: end of output
@@ -140,7 +440,11 @@ where
 
             // Move to the next state in the state machine
             match self.execute() {
-                Err(e) => return Some(Err(e)),
+                Err(e) => {
+                    debug!("Encoding failed: {e}");
+                    self.state = Final;
+                    return Some(Err(e));
+                }
                 Ok(next_state) => self.state = next_state,
             }
         }
@@ -168,30 +472,62 @@ where
     ///
     /// Returns the next state, or an error.
     fn execute_initial(&mut self) -> Result<EncoderState, EncodeError> {
+        if !std::mem::take(&mut self.keep_model) {
+            self.alphabet.begin_message();
+        }
         self.a = 0;
         self.b = Self::WHOLE;
         self.s = 0;
+        self.position = 0;
+        if !self.alphabet_validated {
+            self.alphabet.validate(BITS_OF_PRECISION)?;
+            self.alphabet_validated = true;
+            self.total_interval_width = Reciprocal::new(self.alphabet.total_interval_width());
+        }
         Ok(TopOfSymbolLoop)
     }
 
     /// Execute from the TopOfSymbolLoop state, which pulls symbols from the
     /// input stream, adjusts the current interval, and jumps to rescaling.
     ///
+    /// Symbols which leave the interval straddling the midpoint don't produce
+    /// any output, so they are consumed in an inner loop (performing middle
+    /// rescaling in place) rather than bouncing through the state machine once
+    /// per symbol. This keeps the per-symbol dispatch overhead low when the
+    /// input is a cheap iterator, such as one over a slice.
+    ///
     /// Returns the next state, or an error.
     fn execute_top_of_symbol_loop(&mut self) -> Result<EncoderState, EncodeError> {
-        if self.eof_reached {
-            return Ok(AfterSymbolLoop);
-        }
-        match self.input.next() {
-            None => Err(EncodeError::UnterminatedStream),
-            Some(symbol) => {
-                debug!("Next symbol to encode: {symbol:?}");
-                if symbol == self.alphabet.eof() {
-                    self.eof_reached = true;
+        loop {
+            if self.eof_reached {
+                return Ok(AfterSymbolLoop);
+            }
+            if let Some(cancelled) = &self.cancelled
+                && cancelled.load(Ordering::Relaxed)
+            {
+                return Err(EncodeError::Cancelled);
+            }
+
+            let Some(symbol) = self.input.next() else {
+                if self.eof_free {
+                    return Ok(AfterSymbolLoop);
                 }
-                self.set_a_and_b_for_symbol(&symbol);
-                Ok(TopOfRescaleLoop)
+                return Err(EncodeError::UnterminatedStream);
+            };
+            debug!("Next symbol to encode: {symbol:?}");
+            self.set_a_and_b_for_symbol(&symbol)?;
+            self.position += 1;
+            if symbol == self.alphabet.eof() {
+                self.eof_reached = true;
+            }
+            self.alphabet.update(&symbol);
+
+            // Side rescaling emits bits, which must go through the state machine
+            if self.b < Self::HALF || self.a > Self::HALF {
+                return Ok(TopOfRescaleLoop);
             }
+
+            self.perform_middle_rescaling();
         }
     }
 
@@ -199,29 +535,26 @@ where
     /// interval if it lies completely within the left or right half of the
     /// full interval.
     ///
-    /// If the interval is straddling the midpoint, middle rescaling is
+    /// Rather than rescaling one step at a time, the number of steps that apply
+    /// is determined up front and they are performed at once. The bits emitted
+    /// by those steps are the leading bits of a that the steps shift out.
+    ///
+    /// Once the interval is straddling the midpoint, middle rescaling is
     /// performed if necessary.
     ///
     /// Returns the next state, or an error.
     fn execute_top_of_rescale_loop(&mut self) -> Result<EncoderState, EncodeError> {
-        if self.b < Self::HALF {
-            debug!("Interval fully contained in 0 half");
-            self.bits_to_emit = Some(self.zero_and_s_ones());
-            self.s = 0;
-            self.a *= 2;
-            self.b *= 2;
-            Ok(TopOfRescaleLoop)
-        } else if self.a > Self::HALF {
-            debug!("Interval fully contained in 1 half");
-            self.bits_to_emit = Some(self.one_and_s_zeros());
+        let count = side_rescale_count(self.a, self.b, BITS_OF_PRECISION);
+        if count > 0 {
+            debug!("Interval fully contained in one half for {count} rescalings");
+            self.bits_to_emit = self.side_rescaling_bits(count);
             self.s = 0;
-            self.a = 2 * (self.a - Self::HALF);
-            self.b = 2 * (self.b - Self::HALF);
-            Ok(TopOfRescaleLoop)
-        } else {
-            self.perform_middle_rescaling();
-            Ok(TopOfSymbolLoop)
+            self.a = side_rescale(self.a, count, BITS_OF_PRECISION);
+            self.b = side_rescale_upper(self.b, count, BITS_OF_PRECISION);
         }
+
+        self.perform_middle_rescaling();
+        Ok(TopOfSymbolLoop)
     }
 
     /// Execute from the AfterSymbolLoop state, which emits the final bits in
@@ -231,9 +564,9 @@ where
     fn execute_after_symbol_loop(&mut self) -> Result<EncoderState, EncodeError> {
         self.s += 1;
         if self.a <= Self::QUARTER {
-            self.bits_to_emit = Some(self.zero_and_s_ones());
+            self.bits_to_emit = PendingBits::new(Zero, self.s);
         } else {
-            self.bits_to_emit = Some(self.one_and_s_zeros());
+            self.bits_to_emit = PendingBits::new(One, self.s);
         }
 
         Ok(Final)
@@ -244,37 +577,54 @@ where
     /// You can think of this as "zooming in" the current interval to a sub-interval
     /// whose width relative to the width of the current interval is proportional
     /// to the probability of this symbol occurring in the input.
-    fn set_a_and_b_for_symbol(&mut self, symbol: &S) {
-        let total_interval_width = self.alphabet.total_interval_width();
-        let upper_bound = self.alphabet.interval_upper_bound(symbol);
-        let lower_bound = self.alphabet.interval_lower_bound(symbol);
+    ///
+    /// Fails if the symbol isn't in the alphabet, which is found out while
+    /// looking up its bounds.
+    fn set_a_and_b_for_symbol(&mut self, symbol: &S) -> Result<(), EncodeError> {
+        let (bounds, total) = if self.eof_free {
+            let alphabet = WithoutEof(&*self.alphabet);
+            (
+                alphabet.checked_cdf(symbol),
+                alphabet.total_interval_width(),
+            )
+        } else {
+            (
+                self.alphabet.checked_cdf(symbol),
+                self.alphabet.total_interval_width(),
+            )
+        };
+        let (lower_bound, upper_bound) = bounds.ok_or(EncodeError::SymbolNotInAlphabet {
+            position: self.position,
+        })?;
+        self.total_interval_width.refresh(total);
         let w = self.b - self.a;
-        self.b = self.a + (w * upper_bound) / total_interval_width;
-        self.a += (w * lower_bound) / total_interval_width;
+        self.b = self.a + self.total_interval_width.divide(w * upper_bound);
+        self.a += self.total_interval_width.divide(w * lower_bound);
+        Ok(())
     }
 
-    /// Construct an iterator that produces a one and s zeroes.
-    fn one_and_s_zeros(&self) -> Box<dyn Iterator<Item = Bit>> {
-        Box::new(once(One).chain(repeat_n(Zero, self.s)))
-    }
-
-    /// Construct an iterator that produces a zero and s ones.
-    fn zero_and_s_ones(&self) -> Box<dyn Iterator<Item = Bit>> {
-        Box::new(once(Zero).chain(repeat_n(One, self.s)))
+    /// The bits emitted by performing the given number of side rescaling
+    /// steps: the first step emits its bit along with the s pending bits, and
+    /// subsequent steps each emit a single bit. These are the leading bits of
+    /// a.
+    fn side_rescaling_bits(&self, count: u32) -> PendingBits {
+        let first = Bit::from((self.a >> (BITS_OF_PRECISION - 1)) & 1 == 1);
+        PendingBits::new(first, self.s)
+            .followed_by(self.a >> (BITS_OF_PRECISION - count), count - 1)
     }
 
     /// Scale up the current interval until it is no longer completely
     /// contained within the middle two quarters of the full interval.
     fn perform_middle_rescaling(&mut self) {
-        while self.a > Self::QUARTER && self.b < (3 * Self::QUARTER) {
+        let count = middle_rescale_count(self.a, self.b, BITS_OF_PRECISION);
+        if count > 0 {
             debug!(
-                "Middle rescaling a={:<12} b={:<12} s={:<12}",
+                "Middle rescaling {count} times a={:<12} b={:<12} s={:<12}",
                 self.a, self.b, self.s
             );
-            self.assert_sanity();
-            self.s += 1;
-            self.a = 2 * (self.a - Self::QUARTER);
-            self.b = 2 * (self.b - Self::QUARTER);
+            self.s += count as usize;
+            self.a = middle_rescale(self.a, count, BITS_OF_PRECISION);
+            self.b = middle_rescale(self.b, count, BITS_OF_PRECISION);
         }
     }
 }
@@ -287,6 +637,176 @@ impl<'a, S: Symbol, A: Alphabet<S = S>, I: Iterator<Item = S>, const BITS_OF_PRE
     fn next(&mut self) -> Option<Self::Item> {
         self.next_bit()
     }
+
+    /// The bits already determined are a lower bound. Until the message has
+    /// ended, at least one more bit (or an error) follows, and there is no
+    /// upper bound, as the input may be arbitrarily long.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let pending = self.bits_to_emit.len();
+        match self.state {
+            Final => (pending, Some(pending)),
+            _ => (pending + 1, None),
+        }
+    }
+}
+
+impl<S: Symbol, A: Alphabet<S = S>, I: Iterator<Item = S>, const BITS_OF_PRECISION: u32>
+    FusedIterator for EncoderOutput<'_, S, A, I, BITS_OF_PRECISION>
+{
+}
+
+/// The output of an encoder packed into bytes. See [`EncoderOutput::bytes`].
+pub struct EncoderBytes<'a, S, A, I, const BITS_OF_PRECISION: u32>
+where
+    S: Symbol,
+    A: Alphabet<S = S>,
+    I: Iterator<Item = S>,
+{
+    bits: EncoderOutput<'a, S, A, I, BITS_OF_PRECISION>,
+}
+
+impl<S: Symbol, A: Alphabet<S = S>, I: Iterator<Item = S>, const BITS_OF_PRECISION: u32> Iterator
+    for EncoderBytes<'_, S, A, I, BITS_OF_PRECISION>
+{
+    type Item = Result<u8, EncodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut byte = 0;
+        for filled in 0..8 {
+            match self.bits.next() {
+                Some(Ok(bit)) => byte |= u8::from(bool::from(bit)) << (7 - filled),
+                Some(Err(e)) => return Some(Err(e)),
+                None if filled == 0 => return None,
+                None => break,
+            }
+        }
+        Some(Ok(byte))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.bits.size_hint();
+        (lower.div_ceil(8), upper.map(|upper| upper.div_ceil(8)))
+    }
+}
+
+impl<S: Symbol, A: Alphabet<S = S>, I: Iterator<Item = S>, const BITS_OF_PRECISION: u32>
+    FusedIterator for EncoderBytes<'_, S, A, I, BITS_OF_PRECISION>
+{
+}
+
+/// An input of symbols which may fail, ending at the first error, which is
+/// kept to be reported by [`FallibleEncoderOutput`].
+pub struct Fallible<I, E> {
+    input: I,
+    error: Option<E>,
+}
+
+impl<S, E, I: Iterator<Item = Result<S, E>>> Iterator for Fallible<I, E> {
+    type Item = S;
+
+    fn next(&mut self) -> Option<S> {
+        if self.error.is_some() {
+            return None;
+        }
+        match self.input.next()? {
+            Ok(symbol) => Some(symbol),
+            Err(e) => {
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+}
+
+/// The output of an encoder whose input may fail. See
+/// [`Encoder::encode_fallible`].
+pub struct FallibleEncoderOutput<'a, S, A, I, E, const BITS_OF_PRECISION: u32>
+where
+    S: Symbol,
+    A: Alphabet<S = S>,
+    I: Iterator<Item = Result<S, E>>,
+{
+    output: EncoderOutput<'a, S, A, Fallible<I, E>, BITS_OF_PRECISION>,
+}
+
+impl<S, A, I, E, const BITS_OF_PRECISION: u32> Iterator
+    for FallibleEncoderOutput<'_, S, A, I, E, BITS_OF_PRECISION>
+where
+    S: Symbol,
+    A: Alphabet<S = S>,
+    I: Iterator<Item = Result<S, E>>,
+    E: Error + Send + Sync + 'static,
+{
+    type Item = Result<Bit, EncodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.output.next()? {
+            // The input ending early because of an error is reported as that
+            // error, rather than as an unterminated stream
+            Err(_) if self.output.input.error.is_some() => {
+                let e = self.output.input.error.take().unwrap();
+                Some(Err(EncodeError::Source(SourceError(Box::new(e)))))
+            }
+            result => Some(result),
+        }
+    }
+}
+
+/// The output of an encoder coding every message of its input one after
+/// another. See [`Encoder::encode_all`].
+pub struct EncoderMessages<'a, S, A, I, const BITS_OF_PRECISION: u32>
+where
+    S: Symbol,
+    A: Alphabet<S = S>,
+    I: Iterator<Item = S>,
+{
+    output: EncoderOutput<'a, S, A, Peekable<I>, BITS_OF_PRECISION>,
+    failed: bool,
+}
+
+impl<S: Symbol, A: Alphabet<S = S>, I: Iterator<Item = S>, const BITS_OF_PRECISION: u32> Iterator
+    for EncoderMessages<'_, S, A, I, BITS_OF_PRECISION>
+{
+    type Item = Result<Bit, EncodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Nothing follows an error, which may have left a message half read
+        if self.failed {
+            return None;
+        }
+        loop {
+            // The input ends at a message boundary
+            if self.output.state == Initial {
+                self.output.input.peek()?;
+            }
+            if let Some(bit) = self.output.next() {
+                self.failed = bit.is_err();
+                return Some(bit);
+            }
+            self.output.reset();
+        }
+    }
+}
+
+/// The coder a message is coded with, by [`Encoder::encode_with`] and
+/// [`Decoder::decode_with`](crate::decoder::Decoder::decode_with).
+///
+/// Each codes a message in a different format, so it must be decoded with
+/// the coder it was encoded with.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Coder {
+    /// The bitwise arithmetic coder of [`Encoder::encode`], with its bits
+    /// packed into bytes
+    #[default]
+    Arithmetic,
+    /// The carry-less range coder of [`crate::range`], which emits whole
+    /// bytes and has no pending bits
+    Range,
+    /// The range coder of [`crate::range::carry`], which propagates carries
+    RangeWithCarry,
+    /// The bitwise coder of [`crate::arcode`], whose streams are those of the
+    /// arcode crate
+    Arcode,
 }
 
 pub trait Encoder<S, A>
@@ -299,12 +819,129 @@ where
     /// The input stream must consist of symbols from the alphabet.
     /// This method will encode a single message from the stream (i.e. the
     /// symbols up until/including the EOF symbol).
+    ///
+    /// The alphabet's [`Alphabet::begin_message`] is called before the first
+    /// symbol, and [`Alphabet::update`] after each symbol, exactly as the
+    /// decoder calls them, so adaptive models (see [`crate::models`]) are
+    /// coded without any extra setup.
     fn encode<IntoI, const BITS_OF_PRECISION: u32>(
         &self,
         input: IntoI,
     ) -> EncoderOutput<'_, S, A, IntoI::IntoIter, BITS_OF_PRECISION>
     where
         IntoI: IntoIterator<Item = S>;
+
+    /// Encode a message without terminating it with EOF, leaving the
+    /// decoder to stop after the right number of symbols (see
+    /// [`Decoder::decode_without_eof`](crate::decoder::Decoder::decode_without_eof)).
+    ///
+    /// The message is all of the input, which must not contain EOF. EOF's
+    /// interval is removed from the alphabet, so no probability is spent on
+    /// it, which saves bits on short messages whose length is known anyway.
+    fn encode_without_eof<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> EncoderOutput<'_, S, A, IntoI::IntoIter, BITS_OF_PRECISION>
+    where
+        IntoI: IntoIterator<Item = S>;
+
+    /// Encode every message in a stream of symbols (each terminated by EOF)
+    /// as one concatenated stream of bits, starting the coder afresh for each
+    /// message.
+    ///
+    /// An iterator of separate messages can be flattened into such a stream.
+    /// The last message must be terminated by EOF, like any other.
+    fn encode_all<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> EncoderMessages<'_, S, A, IntoI::IntoIter, BITS_OF_PRECISION>
+    where
+        IntoI: IntoIterator<Item = S>;
+
+    /// Encode a stream of symbols which may fail, such as symbols parsed from
+    /// a reader, without collecting them first.
+    ///
+    /// The first error from the input ends the output, and is reported as
+    /// [`EncodeError::Source`].
+    fn encode_fallible<IntoI, E, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> FallibleEncoderOutput<'_, S, A, IntoI::IntoIter, E, BITS_OF_PRECISION>
+    where
+        IntoI: IntoIterator<Item = Result<S, E>>,
+    {
+        FallibleEncoderOutput {
+            output: self.encode(Fallible {
+                input: input.into_iter(),
+                error: None,
+            }),
+        }
+    }
+
+    /// The number of bits the message would be encoded as, computed without
+    /// producing them (see [`EncoderOutput::measure`]).
+    fn measure<IntoI, const BITS_OF_PRECISION: u32>(&self, input: IntoI) -> Result<u64, EncodeError>
+    where
+        IntoI: IntoIterator<Item = S>,
+    {
+        self.encode::<_, BITS_OF_PRECISION>(input).measure()
+    }
+
+    /// Encode a single message, appending it to a buffer of bytes (see
+    /// [`EncoderOutput::encode_into`]). Returns the number of bits written.
+    fn encode_into<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+        buffer: &mut Vec<u8>,
+    ) -> Result<usize, EncodeError>
+    where
+        IntoI: IntoIterator<Item = S>,
+    {
+        self.encode::<_, BITS_OF_PRECISION>(input)
+            .encode_into(buffer)
+    }
+
+    /// Encode a single message into the start of a buffer of bytes (see
+    /// [`EncoderOutput::encode_into_slice`]). Returns the number of bits
+    /// written.
+    fn encode_into_slice<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+        buffer: &mut [u8],
+    ) -> Result<usize, EncodeError>
+    where
+        IntoI: IntoIterator<Item = S>,
+    {
+        self.encode::<_, BITS_OF_PRECISION>(input)
+            .encode_into_slice(buffer)
+    }
+
+    /// Encode a single message, collecting the bits into a vector.
+    fn encode_to_vec<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> Result<Vec<Bit>, EncodeError>
+    where
+        IntoI: IntoIterator<Item = S>,
+    {
+        self.encode::<_, BITS_OF_PRECISION>(input).collect()
+    }
+
+    /// Encode a single message into bytes with the given coder, to be
+    /// decoded with the same one by
+    /// [`Decoder::decode_with`](crate::decoder::Decoder::decode_with).
+    ///
+    /// [`Coder::Arithmetic`] gives the packed bits of [`Encoder::encode`],
+    /// at the given precision. The range coders have a fixed precision, which
+    /// limits the total interval width to 2^16, and read the whole message
+    /// before coding it.
+    fn encode_with<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+        coder: Coder,
+    ) -> Result<Vec<u8>, EncodeError>
+    where
+        IntoI: IntoIterator<Item = S>;
 }
 
 /// Implementation of Encoder for any Alphabet.
@@ -322,14 +959,117 @@ where
     {
         EncoderOutput::new(input.into_iter(), self)
     }
+
+    fn encode_all<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> EncoderMessages<'_, S, A, IntoI::IntoIter, BITS_OF_PRECISION>
+    where
+        IntoI: IntoIterator<Item = S>,
+    {
+        EncoderMessages {
+            output: EncoderOutput::new(input.into_iter().peekable(), self),
+            failed: false,
+        }
+    }
+
+    fn encode_without_eof<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> EncoderOutput<'_, S, A, IntoI::IntoIter, BITS_OF_PRECISION>
+    where
+        IntoI: IntoIterator<Item = S>,
+    {
+        EncoderOutput {
+            eof_free: true,
+            ..EncoderOutput::new(input.into_iter(), self)
+        }
+    }
+
+    fn encode_with<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+        coder: Coder,
+    ) -> Result<Vec<u8>, EncodeError>
+    where
+        IntoI: IntoIterator<Item = S>,
+    {
+        match coder {
+            Coder::Arithmetic => self.encode::<_, BITS_OF_PRECISION>(input).bytes().collect(),
+            Coder::Range => range::encode(self, &input.into_iter().collect::<Vec<_>>()),
+            Coder::RangeWithCarry => {
+                range::carry::encode(self, &input.into_iter().collect::<Vec<_>>())
+            }
+            Coder::Arcode => {
+                arcode::encode::<_, BITS_OF_PRECISION>(self, &input.into_iter().collect::<Vec<_>>())
+            }
+        }
+    }
+}
+
+/// Implementation of Encoder for any [`Model`], which codes each message with
+/// a [`ModelAlphabet`] of its own, over a clone of the model.
+impl<M> Encoder<M::S, ModelAlphabet<M>> for M
+where
+    M: Model + Clone,
+{
+    fn encode<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> EncoderOutput<'_, M::S, ModelAlphabet<M>, IntoI::IntoIter, BITS_OF_PRECISION>
+    where
+        IntoI: IntoIterator<Item = M::S>,
+    {
+        EncoderOutput::new(input.into_iter(), ModelAlphabet::shared(self))
+    }
+
+    fn encode_all<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> EncoderMessages<'_, M::S, ModelAlphabet<M>, IntoI::IntoIter, BITS_OF_PRECISION>
+    where
+        IntoI: IntoIterator<Item = M::S>,
+    {
+        EncoderMessages {
+            output: EncoderOutput::new(input.into_iter().peekable(), ModelAlphabet::shared(self)),
+            failed: false,
+        }
+    }
+
+    fn encode_without_eof<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+    ) -> EncoderOutput<'_, M::S, ModelAlphabet<M>, IntoI::IntoIter, BITS_OF_PRECISION>
+    where
+        IntoI: IntoIterator<Item = M::S>,
+    {
+        EncoderOutput {
+            eof_free: true,
+            ..EncoderOutput::new(input.into_iter(), ModelAlphabet::shared(self))
+        }
+    }
+
+    fn encode_with<IntoI, const BITS_OF_PRECISION: u32>(
+        &self,
+        input: IntoI,
+        coder: Coder,
+    ) -> Result<Vec<u8>, EncodeError>
+    where
+        IntoI: IntoIterator<Item = M::S>,
+    {
+        ModelAlphabet::new(self.clone()).encode_with::<_, BITS_OF_PRECISION>(input, coder)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::bits::Bit::{One, Zero};
+    use crate::bits::BitWriter;
     use crate::example::{ExampleAlphabet, ExampleSymbol};
+    use crate::frequency::FrequencyTable;
+    use crate::models::AdaptiveAlphabet;
     use ExampleSymbol::*;
-    use biterator::Bit::{One, Zero};
     use test_log::test;
 
     // The below test cases assume 32-bit precision
@@ -339,7 +1079,7 @@ mod test {
     /// alphabet definition, and collecting the output into a single Result.
     fn encode(input: Vec<ExampleSymbol>) -> Result<Vec<Bit>, EncodeError> {
         let alphabet = ExampleAlphabet::new();
-        alphabet.encode::<_, BITS_OF_PRECISION>(input).collect()
+        alphabet.encode_to_vec::<_, BITS_OF_PRECISION>(input)
     }
 
     #[test]
@@ -371,11 +1111,300 @@ mod test {
         );
     }
 
+    #[test]
+    fn error_on_symbol_not_in_alphabet() {
+        let alphabet = FrequencyTable::from_widths(vec![(A, 1), (B, 1), (Eof, 1)], Eof);
+        assert_eq!(
+            alphabet.encode_to_vec::<_, BITS_OF_PRECISION>(vec![A, B, A, C, Eof]),
+            Err(EncodeError::SymbolNotInAlphabet { position: 3 })
+        );
+    }
+
+    #[test]
+    fn encode_into_buffers() {
+        let alphabet = ExampleAlphabet::new();
+        let mut buffer = vec![0xff];
+        assert_eq!(
+            alphabet.encode_into::<_, BITS_OF_PRECISION>(vec![B, A, C, Eof], &mut buffer),
+            Ok(10)
+        );
+        assert_eq!(buffer, vec![0xff, 0b0101_1100, 0b1000_0000]);
+
+        let mut buffer = [0xff; 2];
+        assert_eq!(
+            alphabet.encode_into_slice::<_, BITS_OF_PRECISION>(vec![B, A, C, Eof], &mut buffer),
+            Ok(10)
+        );
+        assert_eq!(buffer, [0b0101_1100, 0b1000_0000]);
+        assert_eq!(
+            alphabet
+                .encode_into_slice::<_, BITS_OF_PRECISION>(vec![B, A, C, Eof], &mut buffer[..1]),
+            Err(EncodeError::BufferFull(1))
+        );
+    }
+
+    #[test]
+    fn stats_summarize_message() {
+        let alphabet = ExampleAlphabet::new();
+        let mut encoder = alphabet.encode::<_, BITS_OF_PRECISION>(vec![B, A, C, Eof]);
+        assert_eq!(encoder.by_ref().count(), 10);
+        let stats = encoder.stats();
+        assert_eq!(
+            stats,
+            EncoderStats {
+                symbols: 3,
+                bits: 10
+            }
+        );
+        assert!((stats.bits_per_symbol().unwrap() - 10.0 / 3.0).abs() < 1e-9);
+        assert!((stats.compression_ratio().unwrap() - 2.4).abs() < 1e-9);
+
+        encoder.reset_with(vec![Eof].into_iter());
+        let stats = encoder.stats();
+        assert_eq!(
+            stats,
+            EncoderStats {
+                symbols: 0,
+                bits: 0
+            }
+        );
+        assert_eq!(stats.bits_per_symbol(), None);
+        assert_eq!(stats.compression_ratio(), None);
+
+        // An empty message emits bits for its EOF alone
+        assert_eq!(encoder.by_ref().count(), 5);
+        let stats = encoder.stats();
+        assert_eq!(stats.bits_per_symbol(), None);
+        assert_eq!(stats.compression_ratio(), Some(0.0));
+    }
+
+    #[test]
+    fn measure_counts_bits() {
+        let alphabet = ExampleAlphabet::new();
+        for message in [vec![], vec![B, A, C], vec![C; 100]] {
+            let message = [message, vec![Eof]].concat();
+            assert_eq!(
+                alphabet.measure::<_, BITS_OF_PRECISION>(message.clone()),
+                Ok(encode(message).unwrap().len() as u64)
+            );
+        }
+        assert_eq!(
+            alphabet.measure::<_, BITS_OF_PRECISION>(vec![A, B]),
+            Err(EncodeError::UnterminatedStream)
+        );
+    }
+
+    #[test]
+    fn encode_without_eof() {
+        // Without EOF's interval, the message is coded as if its last symbol
+        // were EOF in an alphabet of the remaining symbols
+        let alphabet = FrequencyTable::from_widths(vec![(A, 25), (Eof, 10), (B, 50), (C, 15)], Eof);
+        let without_eof = FrequencyTable::from_widths(vec![(A, 25), (B, 50), (C, 15)], C);
+        let bits: Result<Vec<_>, _> = alphabet
+            .encode_without_eof::<_, BITS_OF_PRECISION>(vec![B, A, B, B, C])
+            .collect();
+        assert_eq!(
+            bits,
+            without_eof.encode_to_vec::<_, BITS_OF_PRECISION>(vec![B, A, B, B, C])
+        );
+        let with_eof = alphabet.encode_to_vec::<_, BITS_OF_PRECISION>(vec![B, A, B, B, C, Eof]);
+        assert!(bits.unwrap().len() < with_eof.unwrap().len());
+
+        let bits: Result<Vec<_>, _> = alphabet
+            .encode_without_eof::<_, BITS_OF_PRECISION>(vec![A, Eof, A])
+            .collect();
+        assert_eq!(bits, Err(EncodeError::SymbolNotInAlphabet { position: 1 }));
+    }
+
+    #[test]
+    fn encode_fallible_input() {
+        let alphabet = ExampleAlphabet::new();
+        let input = [B, A, C, Eof].map(Ok::<_, std::io::Error>);
+        let bits: Result<Vec<_>, _> = alphabet
+            .encode_fallible::<_, _, BITS_OF_PRECISION>(input)
+            .collect();
+        assert_eq!(bits, encode(vec![B, A, C, Eof]));
+
+        let input = [
+            Ok(B),
+            Ok(A),
+            Err(std::io::Error::other("bad symbol")),
+            Ok(C),
+        ];
+        let bits: Result<Vec<_>, _> = alphabet
+            .encode_fallible::<_, _, BITS_OF_PRECISION>(input)
+            .collect();
+        let Err(EncodeError::Source(e)) = bits else {
+            panic!("Expected a source error, got {bits:?}");
+        };
+        assert_eq!(e.to_string(), "bad symbol");
+        assert!(e.get_ref().is::<std::io::Error>());
+    }
+
+    #[test]
+    fn error_on_cancellation() {
+        let alphabet = ExampleAlphabet::new();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut output = alphabet
+            .encode::<_, BITS_OF_PRECISION>(std::iter::repeat(C))
+            .with_cancellation(cancelled.clone());
+        assert!(output.by_ref().take(100).all(|bit| bit.is_ok()));
+        // Bits already pending are emitted before the flag is checked
+        cancelled.store(true, Ordering::Relaxed);
+        assert_eq!(
+            output.find(|bit| bit.is_err()),
+            Some(Err(EncodeError::Cancelled))
+        );
+    }
+
+    #[test]
+    fn size_hint_bounds_output() {
+        let alphabet = ExampleAlphabet::new();
+        let mut output = alphabet.encode::<_, BITS_OF_PRECISION>(vec![B, A, C, Eof]);
+        assert_eq!(output.size_hint(), (1, None));
+        let mut remaining = 10;
+        while let Some(bit) = output.next() {
+            assert!(bit.is_ok());
+            remaining -= 1;
+            let (lower, upper) = output.size_hint();
+            assert!(lower <= remaining && upper.is_none_or(|upper| upper >= remaining));
+        }
+        assert_eq!(remaining, 0);
+        assert_eq!(output.size_hint(), (0, Some(0)));
+        assert_eq!(output.next(), None);
+
+        // Errors end the output
+        let mut output = alphabet.encode::<_, BITS_OF_PRECISION>(vec![A]);
+        assert_eq!(
+            output.find(|bit| bit.is_err()),
+            Some(Err(EncodeError::UnterminatedStream))
+        );
+        assert_eq!(output.next(), None);
+    }
+
     #[test]
     fn error_on_unterminated_stream() {
         assert_eq!(encode(vec![A, B, C]), Err(EncodeError::UnterminatedStream))
     }
 
+    #[test]
+    fn pending_bits() {
+        let bits: Vec<_> = PendingBits::new(One, 2).collect();
+        assert_eq!(bits, vec![One, Zero, Zero]);
+        let bits = PendingBits::new(Zero, 1).followed_by(0b1011_0110, 4);
+        assert_eq!(bits.len(), 6);
+        assert_eq!(
+            bits.collect::<Vec<_>>(),
+            vec![Zero, One, Zero, One, One, Zero]
+        );
+        assert_eq!(PendingBits::default().next(), None);
+    }
+
+    #[test]
+    fn write_to_byte_sink() {
+        let alphabet = ExampleAlphabet::new();
+        let mut writer = BitWriter::new(Vec::new());
+        let written = alphabet
+            .encode::<_, BITS_OF_PRECISION>(vec![C, Eof])
+            .write_to(&mut writer);
+
+        assert_eq!(written, Ok(7));
+        assert_eq!(writer.finish().unwrap(), vec![0b1110_0100]);
+    }
+
+    #[test]
+    fn bytes_are_packed_bits() {
+        let alphabet = ExampleAlphabet::new();
+        let message = vec![B, A, C, Eof];
+        let mut writer = BitWriter::new(Vec::new());
+        alphabet
+            .encode::<_, BITS_OF_PRECISION>(message.clone())
+            .write_to(&mut writer)
+            .unwrap();
+        let bytes: Result<Vec<_>, _> = alphabet
+            .encode::<_, BITS_OF_PRECISION>(message)
+            .bytes()
+            .collect();
+        assert_eq!(bytes, Ok(writer.finish().unwrap()));
+        assert_eq!(bytes, Ok(vec![0b0101_1100, 0b1000_0000]));
+
+        let bytes: Result<Vec<_>, _> = alphabet
+            .encode::<_, BITS_OF_PRECISION>(vec![A, B])
+            .bytes()
+            .collect();
+        assert_eq!(bytes, Err(EncodeError::UnterminatedStream));
+    }
+
+    #[test]
+    fn reset_encodes_next_message() {
+        let alphabet = ExampleAlphabet::new();
+        let mut encoder = alphabet.encode::<_, BITS_OF_PRECISION>(vec![C, Eof, B, A, C, Eof]);
+
+        let first: Result<Vec<_>, _> = encoder.by_ref().collect();
+        assert_eq!(first, Ok(vec![One, One, One, Zero, Zero, One, Zero]));
+
+        encoder.reset();
+        let second: Result<Vec<_>, _> = encoder.by_ref().collect();
+        assert_eq!(
+            second,
+            Ok(vec![Zero, One, Zero, One, One, One, Zero, Zero, One, Zero])
+        );
+
+        encoder.reset_with(vec![Eof].into_iter());
+        let third: Result<Vec<_>, _> = encoder.collect();
+        assert_eq!(third, Ok(vec![One, One, One, Zero, One]));
+    }
+
+    #[test]
+    fn reset_keeping_model_continues_adapting() {
+        let alphabet = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof);
+        let message = [vec![C; 20], vec![Eof]].concat();
+        let mut encoder = alphabet.encode::<_, BITS_OF_PRECISION>(message.repeat(2));
+        let first = encoder.by_ref().count();
+        encoder.reset_keeping_model();
+        let second = encoder.by_ref().count();
+        assert!(second < first, "{second} bits after {first}");
+
+        // Resetting the model starts over
+        encoder.reset_with(message.clone().into_iter());
+        assert_eq!(encoder.count(), first);
+    }
+
+    #[test]
+    fn encode_all_concatenates_messages() {
+        let alphabet = ExampleAlphabet::new();
+        let bits: Result<Vec<_>, _> = alphabet
+            .encode_all::<_, BITS_OF_PRECISION>(
+                [vec![C, Eof], vec![B, A, C, Eof], vec![Eof]].concat(),
+            )
+            .collect();
+        let expected = [
+            encode(vec![C, Eof]).unwrap(),
+            encode(vec![B, A, C, Eof]).unwrap(),
+            encode(vec![Eof]).unwrap(),
+        ]
+        .concat();
+        assert_eq!(bits, Ok(expected));
+
+        assert_eq!(
+            alphabet.encode_all::<_, BITS_OF_PRECISION>(vec![]).count(),
+            0
+        );
+        let bits: Result<Vec<_>, _> = alphabet
+            .encode_all::<_, BITS_OF_PRECISION>(vec![C, Eof, A])
+            .collect();
+        assert_eq!(bits, Err(EncodeError::UnterminatedStream));
+
+        // Nothing follows an error in the middle of a message
+        let alphabet = FrequencyTable::from_widths(vec![(A, 1), (B, 1), (Eof, 1)], Eof);
+        let mut output = alphabet.encode_all::<_, BITS_OF_PRECISION>(vec![A, C, B, Eof]);
+        assert_eq!(
+            output.find(|bit| bit.is_err()),
+            Some(Err(EncodeError::SymbolNotInAlphabet { position: 1 }))
+        );
+        assert_eq!(output.next(), None);
+    }
+
     #[test]
     fn encodes_single_message() {
         // Even if the input stream contains multiple messages (terminated by
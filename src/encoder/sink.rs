@@ -0,0 +1,304 @@
+use super::{EncodeError, PendingBits};
+use crate::alphabet::Alphabet;
+use crate::bits::Bit::{self, One, Zero};
+use crate::reciprocal::Reciprocal;
+use crate::rescale::{
+    MIN_BITS_OF_PRECISION, middle_rescale, middle_rescale_count, side_rescale, side_rescale_count,
+    side_rescale_upper,
+};
+
+/// A push-based encoder, for callers which produce symbols one at a time
+/// rather than as an iterator.
+///
+/// Each call to [`EncoderSink::push`] codes a symbol and returns the bits it
+/// completed, and [`EncoderSink::finish`] codes EOF and returns the rest. The
+/// concatenated bits are the same as those produced by
+/// [`Encoder::encode`](super::Encoder::encode) for the same message.
+///
+/// The message's EOF is coded by `finish`, so it shouldn't be pushed.
+///
+/// Symbols can be coded tentatively by taking a [`Checkpoint`] first, and
+/// rolled back with [`EncoderSink::restore`] if they turn out to cost too
+/// much (see [`EncoderSink::cost`]).
+pub struct EncoderSink<'a, A: Alphabet, const BITS_OF_PRECISION: u32> {
+    alphabet: &'a A,
+    total_interval_width: Reciprocal,
+    a: usize,
+    b: usize,
+    s: usize,
+    position: usize,
+    bits_emitted: usize,
+}
+
+/// The state of an [`EncoderSink`] at some point in a message, to which it
+/// can be restored.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    a: usize,
+    b: usize,
+    s: usize,
+    position: usize,
+    bits_emitted: usize,
+}
+
+impl Checkpoint {
+    /// The number of bits the sink had emitted when the checkpoint was taken.
+    pub fn bits_emitted(&self) -> usize {
+        self.bits_emitted
+    }
+}
+
+impl<'a, A: Alphabet, const BITS_OF_PRECISION: u32> EncoderSink<'a, A, BITS_OF_PRECISION> {
+    const WHOLE: usize = 2_usize.pow(BITS_OF_PRECISION);
+    const QUARTER: usize = Self::WHOLE / 4;
+
+    /// Validate the alphabet and prepare to encode a message.
+    pub fn new(alphabet: &'a A) -> Result<Self, EncodeError> {
+        const {
+            assert!(
+                BITS_OF_PRECISION >= MIN_BITS_OF_PRECISION,
+                "At least 2 bits of precision are needed"
+            )
+        };
+        alphabet.begin_message();
+        alphabet.validate(BITS_OF_PRECISION)?;
+        Ok(Self {
+            alphabet,
+            total_interval_width: Reciprocal::new(alphabet.total_interval_width()),
+            a: 0,
+            b: Self::WHOLE,
+            s: 0,
+            position: 0,
+            bits_emitted: 0,
+        })
+    }
+
+    /// Code the next symbol of the message, returning the bits which can now
+    /// be emitted (often none).
+    pub fn push(
+        &mut self,
+        symbol: A::S,
+    ) -> Result<impl Iterator<Item = Bit> + use<A, BITS_OF_PRECISION>, EncodeError> {
+        let (lower_bound, upper_bound) =
+            self.alphabet
+                .checked_cdf(&symbol)
+                .ok_or(EncodeError::SymbolNotInAlphabet {
+                    position: self.position,
+                })?;
+        self.position += 1;
+
+        self.total_interval_width
+            .refresh(self.alphabet.total_interval_width());
+        let w = self.b - self.a;
+        self.b = self.a + self.total_interval_width.divide(w * upper_bound);
+        self.a += self.total_interval_width.divide(w * lower_bound);
+        self.alphabet.update(&symbol);
+        Ok(self.rescale())
+    }
+
+    /// Code a raw bit, taking it to be equally likely to be zero or one, and
+    /// return the bits which can now be emitted.
+    ///
+    /// This bypasses the alphabet: the interval is simply halved, which is
+    /// cheaper than coding a symbol, and suits bits (such as signs, or
+    /// random payloads) which no model could predict. The decoder must read
+    /// the bit back with [`DecoderOutput::decode_bypass`] at the same point
+    /// in the message.
+    ///
+    /// [`DecoderOutput::decode_bypass`]: crate::decoder::DecoderOutput::decode_bypass
+    pub fn push_bypass(
+        &mut self,
+        bit: Bit,
+    ) -> impl Iterator<Item = Bit> + use<A, BITS_OF_PRECISION> {
+        let middle = self.a + (self.b - self.a) / 2;
+        match bit {
+            Zero => self.b = middle,
+            One => self.a = middle,
+        }
+        self.rescale()
+    }
+
+    /// Rescale the interval after it has been narrowed, returning the bits
+    /// emitted.
+    fn rescale(&mut self) -> PendingBits {
+        let mut bits = PendingBits::default();
+        let count = side_rescale_count(self.a, self.b, BITS_OF_PRECISION);
+        if count > 0 {
+            let first = Bit::from((self.a >> (BITS_OF_PRECISION - 1)) & 1 == 1);
+            bits = PendingBits::new(first, self.s)
+                .followed_by(self.a >> (BITS_OF_PRECISION - count), count - 1);
+            self.s = 0;
+            self.a = side_rescale(self.a, count, BITS_OF_PRECISION);
+            self.b = side_rescale_upper(self.b, count, BITS_OF_PRECISION);
+        }
+
+        let count = middle_rescale_count(self.a, self.b, BITS_OF_PRECISION);
+        self.s += count as usize;
+        self.a = middle_rescale(self.a, count, BITS_OF_PRECISION);
+        self.b = middle_rescale(self.b, count, BITS_OF_PRECISION);
+
+        self.bits_emitted += bits.len();
+        bits
+    }
+
+    /// The number of bits returned by [`EncoderSink::push`] so far.
+    pub fn bits_emitted(&self) -> usize {
+        self.bits_emitted
+    }
+
+    /// The number of bits the symbols pushed so far have cost: the bits
+    /// emitted, plus the bits still held by the coder, which is fractional.
+    ///
+    /// Finishing the message adds the cost of EOF and at most two bits.
+    pub fn cost(&self) -> f64 {
+        let held = BITS_OF_PRECISION as f64 - ((self.b - self.a) as f64).log2();
+        (self.bits_emitted + self.s) as f64 + held
+    }
+
+    /// Save the current state, to return to with [`EncoderSink::restore`].
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            a: self.a,
+            b: self.b,
+            s: self.s,
+            position: self.position,
+            bits_emitted: self.bits_emitted,
+        }
+    }
+
+    /// Return to a saved state, as if the symbols pushed since had never been.
+    ///
+    /// The caller must discard the bits returned since the checkpoint, keeping
+    /// only the first [`Checkpoint::bits_emitted`]. The alphabet isn't part of
+    /// the checkpoint, so this is only exact for alphabets which aren't changed
+    /// by [`Alphabet::update`].
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        self.a = checkpoint.a;
+        self.b = checkpoint.b;
+        self.s = checkpoint.s;
+        self.position = checkpoint.position;
+        self.bits_emitted = checkpoint.bits_emitted;
+    }
+
+    /// Code EOF, ending the message, and return the remaining bits.
+    pub fn finish(mut self) -> impl Iterator<Item = Bit> + use<A, BITS_OF_PRECISION> {
+        self.flush()
+    }
+
+    /// Code EOF, ending the message, and return its remaining bits, keeping
+    /// the sink to code the next message.
+    ///
+    /// The alphabet isn't started afresh, so an adaptive model carries what
+    /// it learned over to the next message. The decoder must do the same,
+    /// with [`DecoderOutput::reset_keeping_model`].
+    ///
+    /// [`DecoderOutput::reset_keeping_model`]: crate::decoder::DecoderOutput::reset_keeping_model
+    pub fn flush(&mut self) -> impl Iterator<Item = Bit> + use<A, BITS_OF_PRECISION> {
+        let eof = self.alphabet.eof();
+        let bits = self.push(eof).expect("EOF is in the validated alphabet");
+        let last = if self.a <= Self::QUARTER { Zero } else { One };
+        let bits = bits.chain(PendingBits::new(last, self.s + 1));
+        self.bits_emitted += self.s + 2;
+        self.a = 0;
+        self.b = Self::WHOLE;
+        self.s = 0;
+        self.position = 0;
+        bits
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::encoder::Encoder;
+    use crate::example::{ExampleAlphabet, ExampleSymbol::*};
+    use crate::models::AdaptiveAlphabet;
+
+    #[test]
+    fn matches_pull_encoder() {
+        let alphabet = ExampleAlphabet::new();
+        for message in [vec![], vec![C], vec![B, A, C], vec![B; 50]] {
+            let mut sink = EncoderSink::<_, 32>::new(&alphabet).unwrap();
+            let mut bits = Vec::new();
+            for &symbol in &message {
+                bits.extend(sink.push(symbol).unwrap());
+            }
+            bits.extend(sink.finish());
+
+            let expected = alphabet.encode_to_vec::<_, 32>(message.into_iter().chain([Eof]));
+            assert_eq!(Ok(bits), expected);
+        }
+    }
+
+    #[test]
+    fn restore_checkpoint() {
+        let alphabet = ExampleAlphabet::new();
+        let mut sink = EncoderSink::<_, 32>::new(&alphabet).unwrap();
+        let mut bits = Vec::new();
+        for symbol in [A, B] {
+            bits.extend(sink.push(symbol).unwrap());
+        }
+
+        // Trying C costs more than B
+        let checkpoint = sink.checkpoint();
+        let cost = sink.cost();
+        for _ in 0..3 {
+            bits.extend(sink.push(C).unwrap());
+        }
+        let c_cost = sink.cost() - cost;
+        assert!((c_cost - 3.0 * alphabet.symbol_cost(&C)).abs() < 1e-6);
+
+        sink.restore(checkpoint);
+        bits.truncate(checkpoint.bits_emitted());
+        for _ in 0..3 {
+            bits.extend(sink.push(B).unwrap());
+        }
+        assert!(sink.cost() - cost < c_cost);
+        bits.extend(sink.finish());
+
+        let expected = alphabet.encode_to_vec::<_, 32>(vec![A, B, B, B, B, Eof]);
+        assert_eq!(Ok(bits), expected);
+    }
+
+    #[test]
+    fn flush_keeps_model() {
+        let alphabet = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof);
+        let message = [A, A, B, A, C];
+        let mut sink = EncoderSink::<_, 32>::new(&alphabet).unwrap();
+        let mut messages = Vec::new();
+        for _ in 0..2 {
+            let mut bits = Vec::new();
+            for symbol in message {
+                bits.extend(sink.push(symbol).unwrap());
+            }
+            bits.extend(sink.flush());
+            messages.push(bits);
+        }
+        assert!(messages[1].len() < messages[0].len());
+
+        let mut encoder = alphabet.encode::<_, 32>(message.into_iter().chain([Eof]).cycle());
+        let first: Vec<_> = encoder.by_ref().map(Result::unwrap).collect();
+        encoder.reset_keeping_model();
+        let second: Vec<_> = encoder.by_ref().map(Result::unwrap).collect();
+        assert_eq!(messages, vec![first, second]);
+    }
+
+    #[test]
+    fn adaptive_alphabet_and_errors() {
+        let alphabet = AdaptiveAlphabet::new(vec![A, B, Eof], Eof);
+        let message = [A, A, B, A, A, A, B, A];
+        let mut sink = EncoderSink::<_, 32>::new(&alphabet).unwrap();
+        let mut bits = Vec::new();
+        for symbol in message {
+            bits.extend(sink.push(symbol).unwrap());
+        }
+        assert_eq!(
+            sink.push(C).err(),
+            Some(EncodeError::SymbolNotInAlphabet { position: 8 })
+        );
+        bits.extend(sink.finish());
+
+        let expected = alphabet.encode_to_vec::<_, 32>(message.into_iter().chain([Eof]));
+        assert_eq!(Ok(bits), expected);
+    }
+}
@@ -0,0 +1,116 @@
+use super::{EncodeError, EncoderSink};
+use crate::alphabet::Alphabet;
+use crate::bits::{BitSink, BitWriter};
+use crate::byte::ByteSymbol;
+use std::io;
+
+/// An encoder which packs its output into bytes (most significant bit first)
+/// and writes them to an [`io::Write`].
+///
+/// Symbols are written with [`EncoderWriter::write_symbol`]. With an alphabet
+/// of [`ByteSymbol`]s, such as a [`ByteAlphabet`](crate::byte::ByteAlphabet),
+/// the encoder is itself an [`io::Write`], so it can be placed in a pipeline
+/// of writers.
+///
+/// Call [`EncoderWriter::finish`] once the message has been written, to code
+/// EOF, write the final partial byte (padded with zeros), and check for IO
+/// errors, which are only reported there.
+pub struct EncoderWriter<'a, A: Alphabet, W: io::Write, const BITS_OF_PRECISION: u32> {
+    sink: EncoderSink<'a, A, BITS_OF_PRECISION>,
+    writer: BitWriter<W>,
+}
+
+impl<'a, A: Alphabet, W: io::Write, const BITS_OF_PRECISION: u32>
+    EncoderWriter<'a, A, W, BITS_OF_PRECISION>
+{
+    /// Validate the alphabet and prepare to encode a message to the given
+    /// writer.
+    pub fn new(alphabet: &'a A, writer: W) -> Result<Self, EncodeError> {
+        Ok(Self {
+            sink: EncoderSink::new(alphabet)?,
+            writer: BitWriter::new(writer),
+        })
+    }
+
+    /// Code the next symbol of the message.
+    pub fn write_symbol(&mut self, symbol: A::S) -> Result<(), EncodeError> {
+        for bit in self.sink.push(symbol)? {
+            self.writer.write_bit(bit);
+        }
+        Ok(())
+    }
+
+    /// Code EOF and write the remaining bits, returning the underlying writer
+    /// or the first IO error encountered.
+    pub fn finish(mut self) -> io::Result<W> {
+        for bit in self.sink.finish() {
+            self.writer.write_bit(bit);
+        }
+        self.writer.finish()
+    }
+}
+
+/// Writing bytes codes each of them as a [`ByteSymbol::Byte`].
+impl<A, W, const BITS_OF_PRECISION: u32> io::Write for EncoderWriter<'_, A, W, BITS_OF_PRECISION>
+where
+    A: Alphabet<S = ByteSymbol>,
+    W: io::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.write_symbol(ByteSymbol::Byte(byte))
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        }
+        Ok(buf.len())
+    }
+
+    /// Flush the underlying writer. Bits which don't yet fill a byte can't be
+    /// written until [`EncoderWriter::finish`].
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.get_mut().flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bits::SliceBits;
+    use crate::byte::ByteAlphabet;
+    use crate::decoder::{Decoder, DecoderEvent};
+    use crate::encoder::Encoder;
+    use crate::example::{ExampleAlphabet, ExampleSymbol::*};
+    use std::io::Write;
+
+    #[test]
+    fn writes_packed_bits() {
+        let alphabet = ExampleAlphabet::new();
+        let mut encoder = EncoderWriter::<_, _, 32>::new(&alphabet, Vec::new()).unwrap();
+        encoder.write_symbol(C).unwrap();
+        assert_eq!(encoder.finish().unwrap(), vec![0b1110_0100]);
+    }
+
+    #[test]
+    fn round_trip_written_bytes() {
+        let text = b"she sells sea shells by the sea shore";
+        let alphabet = ByteAlphabet::from_sample(text);
+        let mut encoder = EncoderWriter::<_, _, 32>::new(&alphabet, Vec::new()).unwrap();
+        encoder.write_all(&text[..10]).unwrap();
+        encoder.write_all(&text[10..]).unwrap();
+        encoder.flush().unwrap();
+        let bytes = encoder.finish().unwrap();
+
+        let bits = alphabet
+            .encode_to_vec::<_, 32>(ByteAlphabet::message(text.iter().copied()))
+            .unwrap();
+        assert_eq!(bytes.len(), bits.len().div_ceil(8));
+
+        let decoded: Vec<_> = alphabet
+            .decode::<_, 32>(SliceBits::new(&bytes))
+            .filter_map(|event| match event.unwrap() {
+                DecoderEvent::DecodedSymbol(ByteSymbol::Byte(byte)) => Some(byte),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(decoded, text);
+    }
+}
@@ -0,0 +1,191 @@
+//! Entropy models for the [`constriction`] crate, built from this crate's
+//! alphabets, so code using constriction's coders can share an alphabet's
+//! probabilities rather than duplicate them in a second table.
+//!
+//! constriction's models give each symbol a probability in units of
+//! 2^-PRECISION, which must sum to exactly 1, so the widths of the alphabet
+//! are first rescaled to sum to 2^PRECISION by a [`NormalizedAlphabet`]. The
+//! model is a snapshot of the alphabet when it was built: constriction's
+//! models are immutable, so an adaptive alphabet doesn't adapt through one.
+
+use crate::alphabet::Alphabet;
+use crate::normalized::{NormalizeError, NormalizedAlphabet};
+use constriction::stream::model::{DecoderModel, EncoderModel, EntropyModel};
+use std::borrow::Borrow;
+use std::num::NonZeroU32;
+
+/// An alphabet as a constriction entropy model, with probabilities of
+/// PRECISION bits.
+///
+/// Symbols outside the alphabet, or with a width of zero, can't be encoded
+/// with the model.
+pub struct ConstrictionModel<A: Alphabet, const PRECISION: usize> {
+    alphabet: NormalizedAlphabet<A>,
+    /// The lower bound of each symbol's interval, by index, followed by
+    /// 2^PRECISION
+    bounds: Vec<u64>,
+}
+
+impl<A: Alphabet, const PRECISION: usize> ConstrictionModel<A, PRECISION> {
+    /// Build a model of the given alphabet, normalized to a total width of
+    /// 2^PRECISION.
+    ///
+    /// PRECISION must be at most 32, and no symbol can take the whole
+    /// interval when it's 32, as constriction's probabilities are `u32`s.
+    pub fn new(alphabet: A) -> Result<Self, NormalizeError> {
+        if PRECISION > u32::BITS as usize {
+            return Err(NormalizeError::TotalTooLarge(PRECISION as u32));
+        }
+        let alphabet = NormalizedAlphabet::new(alphabet, PRECISION as u32)?;
+
+        let mut bounds = vec![0];
+        for symbol in alphabet.symbols() {
+            let width = alphabet.interval_width(symbol);
+            if u32::try_from(width).is_err() {
+                return Err(NormalizeError::TotalTooLarge(PRECISION as u32));
+            }
+            bounds.push(bounds[bounds.len() - 1] + width as u64);
+        }
+        Ok(Self { alphabet, bounds })
+    }
+
+    /// The normalized alphabet the model codes with.
+    pub fn alphabet(&self) -> &NormalizedAlphabet<A> {
+        &self.alphabet
+    }
+}
+
+impl<A: Alphabet, const PRECISION: usize> EntropyModel<PRECISION>
+    for ConstrictionModel<A, PRECISION>
+{
+    type Symbol = A::S;
+    type Probability = u32;
+}
+
+impl<A: Alphabet, const PRECISION: usize> EncoderModel<PRECISION>
+    for ConstrictionModel<A, PRECISION>
+{
+    fn left_cumulative_and_probability(
+        &self,
+        symbol: impl Borrow<Self::Symbol>,
+    ) -> Option<(u32, NonZeroU32)> {
+        let symbol = symbol.borrow();
+        if !self.alphabet.contains(symbol) {
+            return None;
+        }
+        let index = self.alphabet.index(symbol);
+        let (lower, upper) = (self.bounds[index], self.bounds[index + 1]);
+        // Every width fits in a u32, and the lower bound of a symbol with a
+        // nonzero width is below 2^PRECISION
+        Some((lower as u32, NonZeroU32::new((upper - lower) as u32)?))
+    }
+}
+
+impl<A: Alphabet, const PRECISION: usize> DecoderModel<PRECISION>
+    for ConstrictionModel<A, PRECISION>
+{
+    fn quantile_function(&self, quantile: u32) -> (Self::Symbol, u32, NonZeroU32) {
+        // The last symbol whose lower bound is at most the quantile, which
+        // skips symbols of zero width as they share the next one's bound
+        let index = self
+            .bounds
+            .partition_point(|&bound| bound <= quantile as u64)
+            - 1;
+        let (lower, upper) = (self.bounds[index], self.bounds[index + 1]);
+        let probability =
+            NonZeroU32::new((upper - lower) as u32).expect("Quantiles lie in nonzero widths");
+        (
+            self.alphabet.symbol_at(index).clone(),
+            lower as u32,
+            probability,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::example::{ExampleAlphabet, ExampleSymbol::*};
+    use crate::frequency::FrequencyTable;
+    use constriction::stream::Decode;
+    use constriction::stream::model::ContiguousCategoricalEntropyModel;
+    use constriction::stream::stack::DefaultAnsCoder;
+
+    #[test]
+    fn cumulatives_and_quantiles_agree() {
+        let model = ConstrictionModel::<_, 8>::new(ExampleAlphabet::new()).unwrap();
+        // Widths of 64, 128, 38, 26, as normalized
+        let expected = [(A, 0, 64), (B, 64, 128), (C, 192, 38), (Eof, 230, 26)];
+        for (symbol, lower, width) in expected {
+            let (left, probability) = model.left_cumulative_and_probability(symbol).unwrap();
+            assert_eq!((left, probability.get()), (lower, width));
+            for quantile in [lower, lower + width - 1] {
+                assert_eq!(
+                    model.quantile_function(quantile),
+                    (symbol, left, probability)
+                );
+            }
+        }
+
+        // Zero widths can't be encoded, nor decoded
+        let table = FrequencyTable::from_widths(vec![(A, 3), (B, 0), (C, 0), (Eof, 1)], Eof);
+        let model = ConstrictionModel::<_, 4>::new(table).unwrap();
+        assert_eq!(model.left_cumulative_and_probability(B), None);
+        assert_eq!(model.quantile_function(11).0, A);
+        assert_eq!(model.quantile_function(12).0, Eof);
+    }
+
+    #[test]
+    fn round_trip_with_ans() {
+        let model = ConstrictionModel::<_, 24>::new(ExampleAlphabet::new()).unwrap();
+        let message: Vec<_> = [A, B, B, C, A, B, Eof].repeat(100);
+
+        let mut coder = DefaultAnsCoder::new();
+        coder.encode_iid_symbols_reverse(&message, &model).unwrap();
+        let compressed = coder.into_compressed().unwrap();
+        // Near the alphabet's entropy
+        let bits = compressed.len() as f64 * 32.0;
+        assert!(bits < model.alphabet().message_cost(&message) + 64.0);
+
+        // The same stream as constriction's own model of the same table
+        let probabilities: Vec<_> = model
+            .alphabet()
+            .symbols()
+            .map(|symbol| model.alphabet().interval_width(symbol) as u32)
+            .collect();
+        let categorical =
+            ContiguousCategoricalEntropyModel::<u32, _, 24>::from_nonzero_fixed_point_probabilities(
+                &probabilities,
+                false,
+            )
+            .unwrap();
+        let indices = message.iter().map(|symbol| model.alphabet().index(symbol));
+        let mut coder = DefaultAnsCoder::new();
+        coder
+            .encode_iid_symbols_reverse(indices.collect::<Vec<_>>(), &categorical)
+            .unwrap();
+        assert_eq!(coder.into_compressed().unwrap(), compressed);
+
+        let mut coder = DefaultAnsCoder::from_compressed(compressed).unwrap();
+        let decoded = coder
+            .decode_iid_symbols(message.len(), &model)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(decoded, message);
+        assert!(coder.is_empty());
+    }
+
+    #[test]
+    fn error_on_too_much_precision() {
+        assert_eq!(
+            ConstrictionModel::<_, 33>::new(ExampleAlphabet::new()).err(),
+            Some(NormalizeError::TotalTooLarge(33))
+        );
+        // EOF alone would take the whole interval
+        let table = FrequencyTable::from_widths(vec![(A, 0), (Eof, 1)], Eof);
+        assert_eq!(
+            ConstrictionModel::<_, 32>::new(table).err(),
+            Some(NormalizeError::TotalTooLarge(32))
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! Type-erased handles to coders of bytes, for interfaces (such as plugin
+//! systems or FFI layers) which can't carry the coder's generic parameters.
+//!
+//! Only the constructors are generic: a [`DynEncoder`] or [`DynDecoder`] names
+//! neither its alphabet, its IO, nor its precision. Combined with
+//! [`DynAlphabet`](crate::alphabet::DynAlphabet), the model can be chosen at
+//! runtime as well.
+
+use crate::alphabet::Alphabet;
+use crate::byte::ByteSymbol;
+use crate::decoder::DecoderReader;
+use crate::encoder::{EncodeError, EncoderWriter};
+use std::io;
+
+/// The operations of an [`EncoderWriter`] which don't depend on its types.
+trait ErasedEncoder: io::Write {
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+impl<A, W, const BITS_OF_PRECISION: u32> ErasedEncoder
+    for EncoderWriter<'_, A, W, BITS_OF_PRECISION>
+where
+    A: Alphabet<S = ByteSymbol>,
+    W: io::Write,
+{
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        EncoderWriter::finish(*self).map(drop)
+    }
+}
+
+/// An [`EncoderWriter`] of bytes, with its alphabet, writer and precision
+/// erased.
+///
+/// Writing bytes codes them, as with [`EncoderWriter`]. Call
+/// [`DynEncoder::finish`] once the message has been written.
+pub struct DynEncoder<'a>(Box<dyn ErasedEncoder + 'a>);
+
+impl<'a> DynEncoder<'a> {
+    /// Validate the alphabet and prepare to encode a message to the given
+    /// writer.
+    pub fn new<A, W, const BITS_OF_PRECISION: u32>(
+        alphabet: &'a A,
+        writer: W,
+    ) -> Result<Self, EncodeError>
+    where
+        A: Alphabet<S = ByteSymbol>,
+        W: io::Write + 'a,
+    {
+        let encoder = EncoderWriter::<_, _, BITS_OF_PRECISION>::new(alphabet, writer)?;
+        Ok(Self(Box::new(encoder)))
+    }
+
+    /// Code EOF and write the remaining bits, returning the first IO error
+    /// encountered.
+    pub fn finish(self) -> io::Result<()> {
+        self.0.finish()
+    }
+}
+
+impl io::Write for DynEncoder<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// A [`DecoderReader`] of bytes, with its alphabet, reader and precision
+/// erased.
+pub struct DynDecoder<'a>(Box<dyn io::Read + 'a>);
+
+impl<'a> DynDecoder<'a> {
+    /// Prepare to decode a message from the given reader.
+    pub fn new<A, R, const BITS_OF_PRECISION: u32>(alphabet: &'a A, reader: R) -> Self
+    where
+        A: Alphabet<S = ByteSymbol>,
+        R: io::Read + 'a,
+    {
+        Self(Box::new(DecoderReader::<_, _, BITS_OF_PRECISION>::new(
+            alphabet, reader,
+        )))
+    }
+}
+
+impl io::Read for DynDecoder<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::alphabet::DynAlphabet;
+    use crate::byte::ByteAlphabet;
+    use crate::models::AdaptiveAlphabet;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn round_trip_any_model() {
+        let text = b"the handles hide every type parameter";
+        let adaptive = AdaptiveAlphabet::new(
+            ByteAlphabet::message(0..=u8::MAX).collect(),
+            ByteSymbol::Eof,
+        );
+        let models: Vec<Box<dyn DynAlphabet<S = ByteSymbol>>> = vec![
+            Box::new(ByteAlphabet::uniform()),
+            Box::new(ByteAlphabet::from_sample(text)),
+            Box::new(adaptive),
+        ];
+
+        for model in &models {
+            let mut bytes = Vec::new();
+            let mut encoder = DynEncoder::new::<_, _, 32>(model, &mut bytes).unwrap();
+            encoder.write_all(text).unwrap();
+            encoder.finish().unwrap();
+
+            let mut decoded = Vec::new();
+            let mut decoder = DynDecoder::new::<_, _, 32>(model, bytes.as_slice());
+            decoder.read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, text);
+        }
+    }
+}
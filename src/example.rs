@@ -51,6 +51,10 @@ impl Alphabet for ExampleAlphabet {
             ExampleSymbol::Eof => 10,
         }
     }
+
+    fn is_static(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -90,4 +94,58 @@ mod test {
         assert_eq!(alphabet.interval_upper_bound(&C), 90);
         assert_eq!(alphabet.interval_upper_bound(&Eof), 100);
     }
+
+    #[cfg(feature = "derive")]
+    mod derived {
+        use crate::alphabet::Alphabet;
+        use crate::reference;
+
+        // The example alphabet, derived
+        #[derive(Alphabet, PartialEq, Debug, Copy, Clone)]
+        enum Derived {
+            #[weight(25)]
+            A,
+            #[weight(50)]
+            B,
+            #[weight(15)]
+            C,
+            #[eof]
+            #[weight(10)]
+            Eof,
+        }
+
+        #[test]
+        fn derived_alphabet_matches_example() {
+            let example = super::ExampleAlphabet::new();
+            let derived: Vec<_> = DerivedAlphabet
+                .symbols()
+                .map(|symbol| {
+                    (
+                        DerivedAlphabet.interval_lower_bound(symbol),
+                        DerivedAlphabet.interval_upper_bound(symbol),
+                    )
+                })
+                .collect();
+            let expected: Vec<_> = example
+                .symbols()
+                .map(|symbol| {
+                    (
+                        example.interval_lower_bound(symbol),
+                        example.interval_upper_bound(symbol),
+                    )
+                })
+                .collect();
+
+            assert_eq!(derived, expected);
+            assert_eq!(DerivedAlphabet.eof(), Derived::Eof);
+            assert_eq!(DerivedAlphabet.total_interval_width(), 100);
+            assert_eq!(DerivedAlphabet.interval_width(&Derived::B), 50);
+            assert_eq!(DerivedAlphabet.index(&Derived::C), 2);
+            assert_eq!(DerivedAlphabet.symbol_at(3), &Derived::Eof);
+            assert_eq!(
+                reference::cross_check_random::<_, 32>(&DerivedAlphabet, 50, 50, 8),
+                Ok(())
+            );
+        }
+    }
 }
@@ -0,0 +1,143 @@
+/// A Fenwick (binary indexed) tree over symbol counts, supporting prefix sums
+/// and updates of individual counts in O(log n).
+#[derive(Clone, Debug)]
+pub(crate) struct Fenwick {
+    /// tree[i] (1-indexed) holds the sum of the counts in (i - lowbit(i), i]
+    tree: Vec<usize>,
+}
+
+impl Fenwick {
+    /// Build a tree over the given counts in O(n).
+    pub(crate) fn from_counts(counts: &[usize]) -> Self {
+        let mut tree = vec![0; counts.len() + 1];
+        for (i, &count) in counts.iter().enumerate() {
+            let i = i + 1;
+            tree[i] += count;
+            let parent = i + lowbit(i);
+            if parent < tree.len() {
+                tree[parent] += tree[i];
+            }
+        }
+        Self { tree }
+    }
+
+    /// The number of counts in the tree.
+    pub(crate) fn len(&self) -> usize {
+        self.tree.len() - 1
+    }
+
+    /// Add to the count at the given index.
+    pub(crate) fn add(&mut self, index: usize, delta: usize) {
+        let mut i = index + 1;
+        while i < self.tree.len() {
+            self.tree[i] += delta;
+            i += lowbit(i);
+        }
+    }
+
+    /// Replace the count at the given index.
+    pub(crate) fn set(&mut self, index: usize, count: usize) {
+        let current = self.get(index);
+        let mut i = index + 1;
+        while i < self.tree.len() {
+            self.tree[i] = self.tree[i] - current + count;
+            i += lowbit(i);
+        }
+    }
+
+    /// Halve every count, rounding up so that nonzero counts stay nonzero.
+    pub(crate) fn halve(&mut self) {
+        let counts: Vec<usize> = (0..self.len()).map(|i| self.get(i).div_ceil(2)).collect();
+        *self = Self::from_counts(&counts);
+    }
+
+    /// The sum of all counts.
+    pub(crate) fn total(&self) -> usize {
+        self.prefix_sum(self.len())
+    }
+
+    /// The sum of the counts before the given index.
+    pub(crate) fn prefix_sum(&self, index: usize) -> usize {
+        let mut sum = 0;
+        let mut i = index;
+        while i > 0 {
+            sum += self.tree[i];
+            i -= lowbit(i);
+        }
+        sum
+    }
+
+    /// The index of the count whose cumulative interval contains the given
+    /// value: the i such that prefix_sum(i) <= value < prefix_sum(i + 1), or
+    /// the number of counts if the value is at least the total. Takes
+    /// O(log n).
+    pub(crate) fn search(&self, value: usize) -> usize {
+        let mut position = 0;
+        let mut remaining = value;
+        let mut step = self.len().checked_ilog2().map_or(0, |log| 1 << log);
+        while step > 0 {
+            let next = position + step;
+            if next < self.tree.len() && self.tree[next] <= remaining {
+                position = next;
+                remaining -= self.tree[next];
+            }
+            step >>= 1;
+        }
+        position
+    }
+
+    /// The count at the given index.
+    pub(crate) fn get(&self, index: usize) -> usize {
+        self.prefix_sum(index + 1) - self.prefix_sum(index)
+    }
+}
+
+/// The value of the lowest set bit of i.
+fn lowbit(i: usize) -> usize {
+    i & i.wrapping_neg()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn prefix_sums_match_naive() {
+        let mut counts = vec![3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5];
+        let mut tree = Fenwick::from_counts(&counts);
+        for (index, delta) in [(0, 2), (5, 1), (10, 7), (7, 3)] {
+            tree.add(index, delta);
+            counts[index] += delta;
+            for i in 0..=counts.len() {
+                assert_eq!(tree.prefix_sum(i), counts[..i].iter().sum::<usize>());
+            }
+            for (i, &count) in counts.iter().enumerate() {
+                assert_eq!(tree.get(i), count);
+            }
+        }
+        assert_eq!(tree.len(), counts.len());
+
+        for (index, count) in [(3, 0), (6, 10), (10, 1)] {
+            tree.set(index, count);
+            counts[index] = count;
+        }
+        for i in 0..=counts.len() {
+            assert_eq!(tree.prefix_sum(i), counts[..i].iter().sum::<usize>());
+        }
+
+        for value in 0..tree.total() {
+            let i = tree.search(value);
+            assert!(tree.prefix_sum(i) <= value && value < tree.prefix_sum(i + 1));
+        }
+        assert_eq!(tree.search(tree.total()), counts.len());
+
+        tree.halve();
+        for (i, &count) in counts.iter().enumerate() {
+            assert_eq!(tree.get(i), count.div_ceil(2));
+        }
+        assert_eq!(
+            tree.total(),
+            counts.iter().map(|count| count.div_ceil(2)).sum::<usize>()
+        );
+    }
+}
@@ -0,0 +1,668 @@
+//! A simple framed container for encoded messages, so that a message written
+//! by one program can be read by another without sharing conventions beyond
+//! the model.
+//!
+//! A frame is laid out as:
+//!
+//! ```text
+//! magic      4 bytes   "ACF1"
+//! version    1 byte    the format version, currently 1
+//! precision  1 byte    the bits of precision the payload was encoded with
+//! flags      1 byte    features of the frame (see below)
+//! model id   optional  with FLAG_MODEL_ID: the id of the model in a
+//!                      ModelRegistry (varint)
+//! model      optional  with FLAG_MODEL: the length of the model in bytes
+//!                      (varint), then the model
+//! rice       optional  with FLAG_RICE: the Rice parameter the payload was
+//!                      coded with, 1 byte
+//! checksum   optional  with FLAG_CHECKSUM: the CRC32 of the content of
+//!                      the message, 4 bytes, little endian
+//! length     varint    the length of the payload in bytes
+//! payload    length    the encoded message, packed most significant bit
+//!                      first (see crate::bits)
+//! ```
+//!
+//! The lengths are LEB128 varints, as in [`crate::wire`].
+//!
+//! A frame can carry its own model, with [`FLAG_MODEL`] set: the symbols and
+//! weights of a [`FrequencyTable`], as written by [`FrequencyTable::write_to`].
+//! Such a frame is self-describing, so the reader needs no out-of-band
+//! agreement on the model, at the cost of a larger header. Alternatively, with
+//! [`FLAG_MODEL_ID`] set, a frame names its model by an id in a
+//! [`ModelRegistry`] of the models an application supports, so that it can be
+//! decoded with whichever of them encoded it. A block stream can let each
+//! block pick the registered model which codes it best, with
+//! [`ModelRegistry::encode_best`].
+//!
+//! A frame can also carry a checksum of its message, with [`FLAG_CHECKSUM`]
+//! set, which [`Frame::decode`] verifies. Arithmetic coding doesn't detect
+//! corruption by itself, so without one a damaged payload usually decodes to
+//! plausible but wrong symbols. The checksum is the CRC32 of the content of
+//! the decoded message: its symbols (not including EOF) as written by
+//! [`WireSymbol::write_content`], which for a message of bytes is just the
+//! bytes. Checksums are computed with [crc32fast], so they need the `crc32`
+//! feature.
+//!
+//! [crc32fast]: https://crates.io/crates/crc32fast
+//!
+//! A long message can be split into blocks of frames, followed by an index of
+//! the blocks (see [`BlockIndex`]), for random access to any part of it.
+//!
+//! A frame of numeric values, such as prediction residuals, can instead be
+//! Golomb–Rice coded (see [`crate::rice`]), with [`FLAG_RICE`] set. Its
+//! payload is then the number of values (varint) followed by the values
+//! coded with the parameter in the header, and its checksum is computed over
+//! the values as 8-byte little-endian integers. A block of values can be
+//! coded either way, whichever is smaller, with [`encode_values`].
+//!
+//! For bytes, [`compress_with_level`] picks all of this for you: a model and
+//! a block size, by a [`Level`] from `Level::Fast` to `Level::Best`, writing a
+//! block stream which [`decompress`] reads back.
+
+use crate::alphabet::Alphabet;
+use crate::bits::SliceBits;
+use crate::decoder::{DecodeError, Decoder};
+use crate::encoder::{EncodeError, Encoder};
+use crate::frequency::FrequencyTable;
+use crate::rice::{self, RiceError};
+use crate::wire::{WireSymbol, invalid_data, read_usize, read_varint, write_varint};
+use std::io::{self, Read};
+
+#[cfg(feature = "crc32")]
+use crc32fast::Hasher;
+
+/// Stands in for the hasher without the `crc32` feature, when no checksum
+/// can be computed.
+#[cfg(not(feature = "crc32"))]
+enum Hasher {}
+
+#[cfg(not(feature = "crc32"))]
+impl Hasher {
+    fn update(&mut self, _bytes: &[u8]) {
+        match *self {}
+    }
+
+    fn finalize(self) -> u32 {
+        match self {}
+    }
+}
+
+mod blocks;
+mod level;
+mod numeric;
+mod registry;
+
+pub use blocks::{
+    BlockEntry, BlockIndex, INDEX_MAGIC, read_block, read_block_with, read_range, read_range_with,
+    write_blocks, write_blocks_with,
+};
+pub use level::{Level, compress_with_level, decompress};
+pub use numeric::{decode_rice, decode_values, encode_rice, encode_values};
+pub use registry::ModelRegistry;
+
+/// The bytes every frame starts with
+pub const MAGIC: [u8; 4] = *b"ACF1";
+/// The version of the format written by [`write_frame`]
+pub const VERSION: u8 = 1;
+/// The flag set when the frame carries its model
+pub const FLAG_MODEL: u8 = 0x01;
+/// The flag set when the frame carries a checksum of its message
+pub const FLAG_CHECKSUM: u8 = 0x02;
+/// The flag set when the frame names its model by id
+pub const FLAG_MODEL_ID: u8 = 0x04;
+/// The flag set when the payload is Rice coded, rather than arithmetic coded
+pub const FLAG_RICE: u8 = 0x08;
+/// The flags which are set exactly when the frame has the section they name
+const SECTION_FLAGS: u8 = FLAG_MODEL | FLAG_CHECKSUM | FLAG_MODEL_ID | FLAG_RICE;
+/// The flags understood by this version of the format
+const KNOWN_FLAGS: u8 = SECTION_FLAGS;
+
+/// Errors that can occur while reading a frame
+#[derive(thiserror::Error, Debug)]
+pub enum FormatError {
+    #[error("Input is not a frame (bad magic bytes)")]
+    BadMagic,
+    #[error("Unsupported format version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Unknown flags {0:#04x}")]
+    UnknownFlags(u8),
+    #[error("Frame was encoded with {found} bits of precision, not {expected}")]
+    PrecisionMismatch { expected: u32, found: u32 },
+    #[error("Frame doesn't carry its model")]
+    MissingModel,
+    #[error("Frame doesn't name its model")]
+    MissingModelId,
+    #[error("No model is registered with id {0}")]
+    UnknownModel(u32),
+    #[error("No models are registered")]
+    NoModels,
+    #[error("Checksum mismatch: frame has {expected:#010x}, message has {found:#010x}")]
+    ChecksumMismatch { expected: u32, found: u32 },
+    #[error("Frame has a checksum, which can't be verified without the crc32 feature")]
+    ChecksumUnsupported,
+    #[error("Block should hold {expected} symbols, but decoded to {found}")]
+    BlockLengthMismatch { expected: usize, found: usize },
+    #[error("Frame is Rice coded, not arithmetic coded")]
+    RiceCoded,
+    #[error("Frame is arithmetic coded, not Rice coded")]
+    NotRiceCoded,
+    #[error(transparent)]
+    Rice(#[from] RiceError),
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Whether a frame carries a checksum of its message.
+///
+/// The default is [`Checksum::Crc32`] with the `crc32` feature, and
+/// [`Checksum::None`] without it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Checksum {
+    None,
+    /// The CRC32 of the content of the message
+    #[cfg(feature = "crc32")]
+    Crc32,
+}
+
+impl Default for Checksum {
+    #[cfg(feature = "crc32")]
+    fn default() -> Self {
+        Checksum::Crc32
+    }
+
+    #[cfg(not(feature = "crc32"))]
+    fn default() -> Self {
+        Checksum::None
+    }
+}
+
+impl Checksum {
+    /// Start computing the checksum, if there is one.
+    fn hasher(self) -> Option<Hasher> {
+        match self {
+            Checksum::None => None,
+            #[cfg(feature = "crc32")]
+            Checksum::Crc32 => Some(Hasher::new()),
+        }
+    }
+}
+
+/// An encoded message with the header describing it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// The bits of precision the payload was encoded with
+    pub precision: u32,
+    /// Features of the frame, which must be known to the reader, other than
+    /// [`FLAG_MODEL`], [`FLAG_MODEL_ID`], [`FLAG_RICE`] and [`FLAG_CHECKSUM`]
+    /// (which are set exactly when there's a model, a model id, a Rice
+    /// parameter or a checksum)
+    pub flags: u8,
+    /// The id of the model the payload was encoded with, in a
+    /// [`ModelRegistry`], if the frame names it
+    pub model_id: Option<u32>,
+    /// The serialized [`FrequencyTable`] the payload was encoded with, if the
+    /// frame carries its model
+    pub model: Option<Vec<u8>>,
+    /// The parameter the payload was Rice coded with, if it was Rice coded
+    /// rather than arithmetic coded
+    pub rice_parameter: Option<u32>,
+    /// The checksum of the message, if the frame carries one
+    pub checksum: Option<u32>,
+    /// The encoded message, packed into bytes
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Encode a message (terminated by EOF) into a frame, with the given
+    /// checksum of the message.
+    pub fn encode<A, const BITS_OF_PRECISION: u32>(
+        alphabet: &A,
+        message: impl IntoIterator<Item = A::S>,
+        checksum: Checksum,
+    ) -> Result<Self, EncodeError>
+    where
+        A: Alphabet,
+        A::S: WireSymbol,
+    {
+        let mut hasher = checksum.hasher();
+        let mut content = Vec::new();
+        let eof = alphabet.eof();
+        let message = message.into_iter().inspect(|symbol| {
+            if let Some(hasher) = &mut hasher
+                && *symbol != eof
+            {
+                add_to_checksum(hasher, &mut content, symbol);
+            }
+        });
+        let payload = alphabet
+            .encode::<_, BITS_OF_PRECISION>(message)
+            .bytes()
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            precision: BITS_OF_PRECISION,
+            flags: 0,
+            model_id: None,
+            model: None,
+            rice_parameter: None,
+            checksum: hasher.map(Hasher::finalize),
+            payload,
+        })
+    }
+
+    /// Encode a message (terminated by EOF) into a frame which carries the
+    /// alphabet's weights, so it can be decoded with
+    /// [`Frame::decode_with_model`] alone.
+    ///
+    /// The message is encoded with a static snapshot of the weights at the
+    /// start of a message, so an adaptive alphabet doesn't adapt.
+    pub fn encode_with_model<A, const BITS_OF_PRECISION: u32>(
+        alphabet: &A,
+        message: impl IntoIterator<Item = A::S>,
+        checksum: Checksum,
+    ) -> Result<Self, EncodeError>
+    where
+        A: Alphabet,
+        A::S: WireSymbol,
+    {
+        alphabet.begin_message();
+        let widths = alphabet
+            .symbols()
+            .map(|symbol| (symbol.clone(), alphabet.interval_width(symbol)))
+            .collect();
+        let table = FrequencyTable::from_widths(widths, alphabet.eof());
+
+        let mut model = Vec::new();
+        table
+            .write_to(&mut model)
+            .expect("Writing to a Vec can't fail");
+        Ok(Self {
+            model: Some(model),
+            ..Self::encode::<_, BITS_OF_PRECISION>(&table, message, checksum)?
+        })
+    }
+
+    /// Read the model the frame carries.
+    pub fn table<S: WireSymbol>(&self) -> Result<FrequencyTable<S>, FormatError> {
+        let model = self.model.as_ref().ok_or(FormatError::MissingModel)?;
+        Ok(FrequencyTable::read_from(&mut model.as_slice())?)
+    }
+
+    /// Decode the message in the frame, returning its symbols (not including
+    /// EOF), and verify its checksum if it has one.
+    ///
+    /// The frame must have been arithmetic coded with the given precision. A
+    /// frame with a checksum can't be decoded without the `crc32` feature.
+    pub fn decode<A, const BITS_OF_PRECISION: u32>(
+        &self,
+        alphabet: &A,
+    ) -> Result<Vec<A::S>, FormatError>
+    where
+        A: Alphabet,
+        A::S: WireSymbol,
+    {
+        if self.rice_parameter.is_some() {
+            return Err(FormatError::RiceCoded);
+        }
+        if self.precision != BITS_OF_PRECISION {
+            return Err(FormatError::PrecisionMismatch {
+                expected: BITS_OF_PRECISION,
+                found: self.precision,
+            });
+        }
+        let (symbols, _) =
+            alphabet.decode_to_vec::<_, BITS_OF_PRECISION>(SliceBits::new(&self.payload))?;
+        if let Some(expected) = self.checksum {
+            let found = content_crc32(&symbols)?;
+            if found != expected {
+                return Err(FormatError::ChecksumMismatch { expected, found });
+            }
+        }
+        Ok(symbols)
+    }
+
+    /// Decode the message in a frame which carries its model, returning its
+    /// symbols (not including EOF).
+    pub fn decode_with_model<S: WireSymbol, const BITS_OF_PRECISION: u32>(
+        &self,
+    ) -> Result<Vec<S>, FormatError> {
+        self.decode::<_, BITS_OF_PRECISION>(&self.table::<S>()?)
+    }
+}
+
+/// Add the content of a symbol of a message to its checksum, using the given
+/// buffer to hold it.
+fn add_to_checksum<S: WireSymbol>(hasher: &mut Hasher, content: &mut Vec<u8>, symbol: &S) {
+    content.clear();
+    symbol
+        .write_content(content)
+        .expect("Writing to a Vec can't fail");
+    hasher.update(content);
+}
+
+/// The CRC32 of the content of a decoded message.
+#[cfg(feature = "crc32")]
+fn content_crc32<S: WireSymbol>(symbols: &[S]) -> Result<u32, FormatError> {
+    let mut hasher = Hasher::new();
+    let mut content = Vec::new();
+    for symbol in symbols {
+        add_to_checksum(&mut hasher, &mut content, symbol);
+    }
+    Ok(hasher.finalize())
+}
+
+#[cfg(not(feature = "crc32"))]
+fn content_crc32<S: WireSymbol>(_symbols: &[S]) -> Result<u32, FormatError> {
+    Err(FormatError::ChecksumUnsupported)
+}
+
+/// Write a frame, with its header, to a writer.
+pub fn write_frame<W: io::Write>(writer: &mut W, frame: &Frame) -> io::Result<()> {
+    let precision = u8::try_from(frame.precision)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Precision out of range"))?;
+    let mut flags = frame.flags & !SECTION_FLAGS;
+    if frame.model_id.is_some() {
+        flags |= FLAG_MODEL_ID;
+    }
+    if frame.model.is_some() {
+        flags |= FLAG_MODEL;
+    }
+    if frame.rice_parameter.is_some() {
+        flags |= FLAG_RICE;
+    }
+    if frame.checksum.is_some() {
+        flags |= FLAG_CHECKSUM;
+    }
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&[VERSION, precision, flags])?;
+    if let Some(id) = frame.model_id {
+        write_varint(writer, id as u64)?;
+    }
+    if let Some(model) = &frame.model {
+        write_varint(writer, model.len() as u64)?;
+        writer.write_all(model)?;
+    }
+    if let Some(k) = frame.rice_parameter {
+        let k = u8::try_from(k).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Rice parameter out of range")
+        })?;
+        writer.write_all(&[k])?;
+    }
+    if let Some(checksum) = frame.checksum {
+        writer.write_all(&checksum.to_le_bytes())?;
+    }
+    write_varint(writer, frame.payload.len() as u64)?;
+    writer.write_all(&frame.payload)
+}
+
+/// Read a frame written by [`write_frame`], leaving the reader positioned
+/// after it.
+pub fn read_frame<R: io::Read>(reader: &mut R) -> Result<Frame, FormatError> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(FormatError::BadMagic);
+    }
+    let mut header = [0; 3];
+    reader.read_exact(&mut header)?;
+    let [version, precision, flags] = header;
+    if version != VERSION {
+        return Err(FormatError::UnsupportedVersion(version));
+    }
+    if flags & !KNOWN_FLAGS != 0 {
+        return Err(FormatError::UnknownFlags(flags & !KNOWN_FLAGS));
+    }
+
+    let model_id = match flags & FLAG_MODEL_ID {
+        0 => None,
+        _ => Some(
+            u32::try_from(read_varint(reader)?)
+                .map_err(|_| invalid_data("Model id out of range".to_string()))?,
+        ),
+    };
+    let model = match flags & FLAG_MODEL {
+        0 => None,
+        _ => Some(read_section(reader)?),
+    };
+    let rice_parameter = match flags & FLAG_RICE {
+        0 => None,
+        _ => {
+            let mut k = [0];
+            reader.read_exact(&mut k)?;
+            match k[0] as u32 {
+                k if k <= rice::MAX_PARAMETER => Some(k),
+                k => return Err(invalid_data(format!("Invalid Rice parameter {k}")).into()),
+            }
+        }
+    };
+    let checksum = match flags & FLAG_CHECKSUM {
+        0 => None,
+        _ => {
+            let mut checksum = [0; 4];
+            reader.read_exact(&mut checksum)?;
+            Some(u32::from_le_bytes(checksum))
+        }
+    };
+    let payload = read_section(reader)?;
+    Ok(Frame {
+        precision: precision as u32,
+        flags: flags & !SECTION_FLAGS,
+        model_id,
+        model,
+        rice_parameter,
+        checksum,
+        payload,
+    })
+}
+
+/// Read a length, then that many bytes.
+fn read_section<R: io::Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let length = read_usize(reader)?;
+    let mut section = Vec::new();
+    reader.take(length as u64).read_to_end(&mut section)?;
+    if section.len() != length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(section)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::byte::{ByteAlphabet, ByteSymbol};
+
+    /// The flag of the checksum frames carry by default.
+    fn checksum_flag() -> u8 {
+        if cfg!(feature = "crc32") {
+            FLAG_CHECKSUM
+        } else {
+            0
+        }
+    }
+
+    fn sample_frame() -> (ByteAlphabet, Frame) {
+        let text = b"frames carry their own precision";
+        let alphabet = ByteAlphabet::from_sample(text);
+        let message = ByteAlphabet::message(text.iter().copied());
+        let frame = Frame::encode::<_, 32>(&alphabet, message, Checksum::default()).unwrap();
+        (alphabet, frame)
+    }
+
+    #[test]
+    fn round_trip_frames() {
+        let (alphabet, frame) = sample_frame();
+        let mut bytes = Vec::new();
+        write_frame(&mut bytes, &frame).unwrap();
+        write_frame(&mut bytes, &frame).unwrap();
+        assert_eq!(bytes[..4], MAGIC);
+        assert_eq!(bytes[4..7], [VERSION, 32, checksum_flag()]);
+
+        let reader = &mut bytes.as_slice();
+        for _ in 0..2 {
+            let read = read_frame(reader).unwrap();
+            assert_eq!(read, frame);
+            let symbols = read.decode::<_, 32>(&alphabet).unwrap();
+            let expected: Vec<_> = ByteAlphabet::message(*b"frames carry their own precision")
+                .take(32)
+                .collect();
+            assert_eq!(symbols, expected);
+        }
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn errors_on_invalid_frames() {
+        let (alphabet, frame) = sample_frame();
+        let mut bytes = Vec::new();
+        write_frame(&mut bytes, &frame).unwrap();
+
+        let mut corrupt = bytes.clone();
+        corrupt[0] = b'X';
+        assert!(matches!(
+            read_frame(&mut corrupt.as_slice()),
+            Err(FormatError::BadMagic)
+        ));
+
+        let mut corrupt = bytes.clone();
+        corrupt[4] = 2;
+        assert!(matches!(
+            read_frame(&mut corrupt.as_slice()),
+            Err(FormatError::UnsupportedVersion(2))
+        ));
+
+        let mut corrupt = bytes.clone();
+        corrupt[6] = 0x80;
+        assert!(matches!(
+            read_frame(&mut corrupt.as_slice()),
+            Err(FormatError::UnknownFlags(0x80))
+        ));
+
+        assert!(matches!(
+            read_frame(&mut &bytes[..bytes.len() - 1]),
+            Err(FormatError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof
+        ));
+
+        assert!(matches!(
+            frame.decode::<_, 16>(&alphabet),
+            Err(FormatError::PrecisionMismatch {
+                expected: 16,
+                found: 32
+            })
+        ));
+    }
+
+    #[test]
+    fn frames_carry_their_model() {
+        let text = b"the model travels with the message";
+        let alphabet = ByteAlphabet::from_sample(text);
+        let message: Vec<_> = ByteAlphabet::message(text.iter().copied()).collect();
+        let frame =
+            Frame::encode_with_model::<_, 32>(&alphabet, message.clone(), Checksum::default())
+                .unwrap();
+        let mut bytes = Vec::new();
+        write_frame(&mut bytes, &frame).unwrap();
+        assert_eq!(bytes[6], FLAG_MODEL | checksum_flag());
+
+        // The reader needs nothing but the frame
+        let read = read_frame(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read, frame);
+        let symbols = read.decode_with_model::<ByteSymbol, 32>().unwrap();
+        assert_eq!(symbols, message[..message.len() - 1]);
+
+        // A frame without its model is smaller, but can't describe itself
+        let bare = Frame::encode::<_, 32>(&alphabet, message, Checksum::default()).unwrap();
+        let mut bare_bytes = Vec::new();
+        write_frame(&mut bare_bytes, &bare).unwrap();
+        assert!(bare_bytes.len() < bytes.len());
+        assert!(matches!(
+            bare.decode_with_model::<ByteSymbol, 32>(),
+            Err(FormatError::MissingModel)
+        ));
+    }
+
+    #[cfg(feature = "crc32")]
+    #[test]
+    fn checksum_covers_message_content() {
+        let (alphabet, frame) = sample_frame();
+        let text = b"frames carry their own precision";
+        assert_eq!(frame.checksum, Some(crc32fast::hash(text)));
+
+        // The checksum doesn't depend on the model
+        let uniform = ByteAlphabet::uniform();
+        let message = ByteAlphabet::message(text.iter().copied());
+        let other = Frame::encode::<_, 32>(&uniform, message, Checksum::Crc32).unwrap();
+        assert_ne!(other.payload, frame.payload);
+        assert_eq!(other.checksum, frame.checksum);
+
+        let message = ByteAlphabet::message(text.iter().copied());
+        let unchecked = Frame::encode::<_, 32>(&alphabet, message, Checksum::None).unwrap();
+        assert_eq!(unchecked.checksum, None);
+        assert_eq!(unchecked.payload, frame.payload);
+        let mut bytes = Vec::new();
+        write_frame(&mut bytes, &unchecked).unwrap();
+        assert_eq!(bytes[6], 0);
+        assert_eq!(
+            unchecked.decode::<_, 32>(&alphabet).unwrap(),
+            frame.decode::<_, 32>(&alphabet).unwrap()
+        );
+    }
+
+    #[cfg(feature = "crc32")]
+    #[test]
+    fn checksum_detects_corruption() {
+        let (alphabet, frame) = sample_frame();
+        let original = frame.decode::<_, 32>(&alphabet).unwrap();
+        let mut unnoticed = 0;
+        for bit in 0..frame.payload.len() * 8 {
+            let mut corrupt = frame.clone();
+            corrupt.payload[bit / 8] ^= 0x80 >> (bit % 8);
+            let checked = corrupt.decode::<_, 32>(&alphabet);
+
+            // Without the checksum, the corruption often goes unnoticed
+            corrupt.checksum = None;
+            match corrupt.decode::<_, 32>(&alphabet) {
+                // Flipping padding bits doesn't change the message
+                Ok(decoded) if decoded == original => assert!(checked.is_ok()),
+                Ok(_) => {
+                    unnoticed += 1;
+                    assert!(matches!(checked, Err(FormatError::ChecksumMismatch { .. })));
+                }
+                Err(_) => assert!(checked.is_err()),
+            }
+        }
+        assert!(unnoticed > 0);
+    }
+
+    #[test]
+    fn errors_on_symbols_not_in_alphabet() {
+        let alphabet = FrequencyTable::from_bytes(b"abc");
+        let message = ByteAlphabet::message(*b"abz");
+        assert!(matches!(
+            Frame::encode::<_, 32>(&alphabet, message, Checksum::default()),
+            Err(EncodeError::SymbolNotInAlphabet { position: 2 })
+        ));
+    }
+
+    #[cfg(not(feature = "crc32"))]
+    #[test]
+    fn checksums_need_crc32() {
+        let (alphabet, mut frame) = sample_frame();
+        assert_eq!(frame.checksum, None);
+        frame.checksum = Some(0);
+        assert!(matches!(
+            frame.decode::<_, 32>(&alphabet),
+            Err(FormatError::ChecksumUnsupported)
+        ));
+
+        let mut rice = encode_rice(&[1, 2, 3], Checksum::default());
+        assert_eq!(rice.checksum, None);
+        rice.checksum = Some(0);
+        assert!(matches!(
+            decode_rice(&rice),
+            Err(FormatError::ChecksumUnsupported)
+        ));
+    }
+}
@@ -0,0 +1,383 @@
+use super::{Checksum, FormatError, Frame, read_frame, write_frame};
+use crate::alphabet::Alphabet;
+use crate::wire::{WireSymbol, invalid_data, read_usize, write_varint};
+use std::io::{self, SeekFrom};
+use std::ops::Range;
+
+/// The bytes the index of a block stream starts with
+pub const INDEX_MAGIC: [u8; 4] = *b"ACX1";
+
+/// The position and size of one block of a block stream.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BlockEntry {
+    /// The offset of the block's frame from the start of the stream
+    pub offset: u64,
+    /// The length of the block's frame in bytes
+    pub frame_len: usize,
+    /// The position in the message of the block's first symbol
+    pub start: usize,
+    /// The number of symbols in the block (not including EOF)
+    pub len: usize,
+}
+
+/// The index at the end of a block stream, from which any block can be found
+/// without decoding those before it.
+///
+/// A block stream is a message split into blocks of a fixed number of
+/// symbols, each written as its own [`Frame`] (so each is coded
+/// independently, and can be decoded in parallel), followed by the index:
+///
+/// ```text
+/// magic      4 bytes   "ACX1"
+/// count      varint    the number of blocks
+/// blocks     count     for each block, the length of its frame in bytes and
+///                      its number of symbols, as varints
+/// position   8 bytes   the offset of the index, little endian
+/// ```
+///
+/// The fixed-size position at the end lets a reader find the index by
+/// seeking from the end of the stream.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct BlockIndex {
+    blocks: Vec<BlockEntry>,
+}
+
+impl BlockIndex {
+    /// The blocks of the stream, in order.
+    pub fn blocks(&self) -> &[BlockEntry] {
+        &self.blocks
+    }
+
+    /// The number of symbols in the whole message (not including EOF).
+    pub fn total_len(&self) -> usize {
+        self.blocks
+            .last()
+            .map_or(0, |block| block.start + block.len)
+    }
+
+    /// The blocks which hold the symbols in the given range of positions.
+    pub fn blocks_for(&self, range: Range<usize>) -> &[BlockEntry] {
+        let first = self
+            .blocks
+            .partition_point(|block| block.start + block.len <= range.start);
+        let last = self.blocks.partition_point(|block| block.start < range.end);
+        &self.blocks[first..last.max(first)]
+    }
+
+    /// Add a block after the last, failing if its position overflows (which
+    /// only a corrupt index can cause).
+    fn push(&mut self, frame_len: usize, len: usize) -> io::Result<()> {
+        let (offset, start) = match self.blocks.last() {
+            Some(block) => (
+                block.offset.checked_add(block.frame_len as u64),
+                block.start.checked_add(block.len),
+            ),
+            None => (Some(0), Some(0)),
+        };
+        let (Some(offset), Some(start)) = (offset, start) else {
+            return Err(invalid_data("Block positions overflow".to_string()));
+        };
+        self.blocks.push(BlockEntry {
+            offset,
+            frame_len,
+            start,
+            len,
+        });
+        Ok(())
+    }
+
+    /// The offset of the index, just after the last block, if it fits in a
+    /// u64.
+    fn end(&self) -> Option<u64> {
+        self.blocks.last().map_or(Some(0), |block| {
+            block.offset.checked_add(block.frame_len as u64)
+        })
+    }
+
+    fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&INDEX_MAGIC)?;
+        write_varint(writer, self.blocks.len() as u64)?;
+        for block in &self.blocks {
+            write_varint(writer, block.frame_len as u64)?;
+            write_varint(writer, block.len as u64)?;
+        }
+        let end = self
+            .end()
+            .ok_or_else(|| invalid_data("Block positions overflow".to_string()))?;
+        writer.write_all(&end.to_le_bytes())
+    }
+
+    /// Read the index from the end of a block stream, which starts at the
+    /// beginning of the reader.
+    pub fn read_from<R: io::Read + io::Seek>(reader: &mut R) -> Result<Self, FormatError> {
+        reader.seek(SeekFrom::End(-8))?;
+        let mut position = [0; 8];
+        reader.read_exact(&mut position)?;
+        let position = u64::from_le_bytes(position);
+        reader.seek(SeekFrom::Start(position))?;
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != INDEX_MAGIC {
+            return Err(FormatError::BadMagic);
+        }
+        let mut index = Self::default();
+        for _ in 0..read_usize(reader)? {
+            let frame_len = read_usize(reader)?;
+            index.push(frame_len, read_usize(reader)?)?;
+        }
+        if index.end() != Some(position) {
+            return Err(invalid_data(
+                "Block lengths don't match the position of the index".to_string(),
+            )
+            .into());
+        }
+        Ok(index)
+    }
+}
+
+/// Write a message (not including EOF) as a block stream of blocks of at
+/// most `block_len` symbols, each with the given checksum, returning its
+/// index.
+///
+/// `block_len` must be at least one.
+pub fn write_blocks<A, W, const BITS_OF_PRECISION: u32>(
+    writer: &mut W,
+    alphabet: &A,
+    symbols: &[A::S],
+    block_len: usize,
+    checksum: Checksum,
+) -> Result<BlockIndex, FormatError>
+where
+    A: Alphabet,
+    A::S: WireSymbol,
+    W: io::Write,
+{
+    write_blocks_with(writer, symbols, block_len, |block| {
+        let message = block.iter().cloned().chain([alphabet.eof()]);
+        Ok(Frame::encode::<_, BITS_OF_PRECISION>(
+            alphabet, message, checksum,
+        )?)
+    })
+}
+
+/// Write a message (not including EOF) as a block stream of blocks of at
+/// most `block_len` symbols, each encoded into a frame by `encode`, returning
+/// its index.
+///
+/// This lets each block be encoded differently, such as with a model from a
+/// [`ModelRegistry`](super::ModelRegistry) which its frame names.
+/// `block_len` must be at least one.
+pub fn write_blocks_with<S, W, F>(
+    writer: &mut W,
+    symbols: &[S],
+    block_len: usize,
+    mut encode: F,
+) -> Result<BlockIndex, FormatError>
+where
+    W: io::Write,
+    F: FnMut(&[S]) -> Result<Frame, FormatError>,
+{
+    if block_len == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Blocks must hold at least one symbol",
+        )
+        .into());
+    }
+    let mut index = BlockIndex::default();
+    for block in symbols.chunks(block_len) {
+        let frame = encode(block)?;
+        let mut bytes = Vec::new();
+        write_frame(&mut bytes, &frame)?;
+        writer.write_all(&bytes)?;
+        index.push(bytes.len(), block.len())?;
+    }
+    index.write_to(writer)?;
+    Ok(index)
+}
+
+/// Decode one block of a block stream, checking that it holds as many symbols
+/// as its entry in the index says.
+pub fn read_block<A, R, const BITS_OF_PRECISION: u32>(
+    reader: &mut R,
+    alphabet: &A,
+    block: &BlockEntry,
+) -> Result<Vec<A::S>, FormatError>
+where
+    A: Alphabet,
+    A::S: WireSymbol,
+    R: io::Read + io::Seek,
+{
+    read_block_with(reader, block, |frame| {
+        frame.decode::<_, BITS_OF_PRECISION>(alphabet)
+    })
+}
+
+/// Decode one block of a block stream with `decode`, which decodes the
+/// block's frame, checking that it holds as many symbols as its entry in the
+/// index says.
+pub fn read_block_with<S, R, F>(
+    reader: &mut R,
+    block: &BlockEntry,
+    mut decode: F,
+) -> Result<Vec<S>, FormatError>
+where
+    R: io::Read + io::Seek,
+    F: FnMut(&Frame) -> Result<Vec<S>, FormatError>,
+{
+    reader.seek(SeekFrom::Start(block.offset))?;
+    let decoded = decode(&read_frame(reader)?)?;
+    if decoded.len() != block.len {
+        return Err(FormatError::BlockLengthMismatch {
+            expected: block.len,
+            found: decoded.len(),
+        });
+    }
+    Ok(decoded)
+}
+
+/// Decode the symbols in the given range of positions of a block stream,
+/// decoding only the blocks which hold them.
+pub fn read_range<A, R, const BITS_OF_PRECISION: u32>(
+    reader: &mut R,
+    alphabet: &A,
+    index: &BlockIndex,
+    range: Range<usize>,
+) -> Result<Vec<A::S>, FormatError>
+where
+    A: Alphabet,
+    A::S: WireSymbol,
+    R: io::Read + io::Seek,
+{
+    read_range_with(reader, index, range, |frame| {
+        frame.decode::<_, BITS_OF_PRECISION>(alphabet)
+    })
+}
+
+/// Decode the symbols in the given range of positions of a block stream,
+/// decoding only the blocks which hold them, with `decode` (see
+/// [`read_block_with`]).
+pub fn read_range_with<S, R, F>(
+    reader: &mut R,
+    index: &BlockIndex,
+    range: Range<usize>,
+    mut decode: F,
+) -> Result<Vec<S>, FormatError>
+where
+    S: Clone,
+    R: io::Read + io::Seek,
+    F: FnMut(&Frame) -> Result<Vec<S>, FormatError>,
+{
+    let mut symbols = Vec::new();
+    for block in index.blocks_for(range.clone()) {
+        // The block holds exactly block.len symbols, and overlaps the range
+        let decoded = read_block_with(reader, block, &mut decode)?;
+        let start = range.start.saturating_sub(block.start);
+        let end = (range.end - block.start).min(block.len);
+        symbols.extend_from_slice(&decoded[start..end]);
+    }
+    Ok(symbols)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::byte::{ByteAlphabet, ByteSymbol};
+    use std::io::Cursor;
+
+    fn sample() -> (ByteAlphabet, Vec<ByteSymbol>) {
+        let text = b"peter piper picked a peck of pickled peppers ".repeat(20);
+        let alphabet = ByteAlphabet::from_sample(&text);
+        let symbols = text.iter().map(|&byte| ByteSymbol::Byte(byte)).collect();
+        (alphabet, symbols)
+    }
+
+    #[test]
+    fn read_any_range() {
+        let (alphabet, symbols) = sample();
+        let mut bytes = Vec::new();
+        let index =
+            write_blocks::<_, _, 32>(&mut bytes, &alphabet, &symbols, 100, Checksum::default())
+                .unwrap();
+        assert_eq!(index.blocks().len(), 9);
+        assert_eq!(index.total_len(), symbols.len());
+
+        let mut reader = Cursor::new(bytes);
+        assert_eq!(BlockIndex::read_from(&mut reader).unwrap(), index);
+        for range in [0..0, 0..1, 0..100, 99..101, 250..750, 850..900, 0..900] {
+            let decoded = read_range::<_, _, 32>(&mut reader, &alphabet, &index, range.clone());
+            assert_eq!(decoded.unwrap(), symbols[range.clone()], "{range:?}");
+        }
+        assert_eq!(index.blocks_for(250..750).len(), 6);
+
+        // Blocks can be decoded in any order
+        for block in index.blocks().iter().rev() {
+            let decoded = read_block::<_, _, 32>(&mut reader, &alphabet, block).unwrap();
+            assert_eq!(decoded, symbols[block.start..block.start + block.len]);
+        }
+    }
+
+    #[test]
+    fn empty_and_invalid_streams() {
+        let (alphabet, _) = sample();
+        let mut bytes = Vec::new();
+        let index =
+            write_blocks::<_, _, 32>(&mut bytes, &alphabet, &[], 100, Checksum::default()).unwrap();
+        assert_eq!(index.total_len(), 0);
+        assert_eq!(
+            BlockIndex::read_from(&mut Cursor::new(&bytes)).unwrap(),
+            index
+        );
+
+        let last = bytes.len() - 1;
+        bytes[last] = 1;
+        assert!(BlockIndex::read_from(&mut Cursor::new(&bytes)).is_err());
+        assert!(BlockIndex::read_from(&mut Cursor::new([0; 4])).is_err());
+
+        assert!(matches!(
+            write_blocks::<_, _, 32>(&mut Vec::new(), &alphabet, &[], 0, Checksum::default()),
+            Err(FormatError::Io(e)) if e.kind() == io::ErrorKind::InvalidInput
+        ));
+    }
+
+    #[test]
+    fn errors_on_corrupt_index() {
+        let (alphabet, symbols) = sample();
+        let mut bytes = Vec::new();
+        let index =
+            write_blocks::<_, _, 32>(&mut bytes, &alphabet, &symbols, 100, Checksum::default())
+                .unwrap();
+        bytes.truncate(index.end().unwrap() as usize);
+
+        // The first block claims twice the symbols it holds
+        let mut corrupt = BlockIndex::default();
+        for (i, block) in index.blocks().iter().enumerate() {
+            let len = if i == 0 { 2 * block.len } else { block.len };
+            corrupt.push(block.frame_len, len).unwrap();
+        }
+        corrupt.write_to(&mut bytes).unwrap();
+        let mut reader = Cursor::new(bytes);
+        let corrupt = BlockIndex::read_from(&mut reader).unwrap();
+        assert!(matches!(
+            read_range::<_, _, 32>(&mut reader, &alphabet, &corrupt, 150..160),
+            Err(FormatError::BlockLengthMismatch {
+                expected: 200,
+                found: 100
+            })
+        ));
+
+        // Lengths which overflow are rejected rather than wrapping
+        let mut bytes = INDEX_MAGIC.to_vec();
+        write_varint(&mut bytes, 2).unwrap();
+        for _ in 0..2 {
+            write_varint(&mut bytes, 1).unwrap();
+            write_varint(&mut bytes, u64::MAX).unwrap();
+        }
+        bytes.extend(0_u64.to_le_bytes());
+        assert!(matches!(
+            BlockIndex::read_from(&mut Cursor::new(bytes)),
+            Err(FormatError::Io(e)) if e.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+}
@@ -0,0 +1,198 @@
+use super::{BlockIndex, Checksum, FormatError, Frame, ModelRegistry};
+use super::{read_range_with, write_blocks_with};
+use crate::byte::{ByteAlphabet, ByteSymbol};
+use crate::frequency::FrequencyTable;
+use crate::models::{AdaptiveAlphabet, DEFAULT_MAX_TOTAL, OrderNModel};
+use crate::normalized::NormalizedAlphabet;
+use std::io::Cursor;
+
+/// The bits of precision every level codes with
+const BITS_OF_PRECISION: u32 = 32;
+
+/// The log2 of the total width a two-pass block's table is normalized to
+const STATIC_LOG2_TOTAL: u32 = 16;
+
+/// How hard [`compress_with_level`] works to compress its input, from
+/// `Level::Fast` to `Level::Best`.
+///
+/// Each level picks a model of the bytes, and the number of bytes in each
+/// block of the output (see [`BlockIndex`]). Larger blocks give adaptive
+/// models longer to learn, and higher orders capture more structure, but
+/// are slower and need more memory, for the tables of every context.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    /// Two passes over each block: count its bytes, then code it with their
+    /// frequencies, which its frame carries
+    Fast,
+    /// An adaptive order-0 model
+    #[default]
+    Normal,
+    /// An adaptive order-1 model
+    High,
+    /// An adaptive order-2 model, which keeps longer counts
+    Best,
+}
+
+impl Level {
+    /// Every level, from fastest to best.
+    pub const ALL: [Level; 4] = [Level::Fast, Level::Normal, Level::High, Level::Best];
+
+    /// The number of preceding bytes the model conditions on.
+    pub fn order(self) -> usize {
+        match self {
+            Level::Fast | Level::Normal => 0,
+            Level::High => 1,
+            Level::Best => 2,
+        }
+    }
+
+    /// The limit on the total count of each context of an adaptive model,
+    /// beyond which its counts are halved, so a larger limit remembers more
+    /// of the past.
+    pub fn max_total(self) -> usize {
+        match self {
+            Level::Best => DEFAULT_MAX_TOTAL << 4,
+            _ => DEFAULT_MAX_TOTAL,
+        }
+    }
+
+    /// The largest number of bytes in a block.
+    pub fn block_len(self) -> usize {
+        match self {
+            Level::Fast => 1 << 16,
+            Level::Normal => 1 << 18,
+            Level::High => 1 << 20,
+            Level::Best => 1 << 22,
+        }
+    }
+
+    /// Whether each block is coded with its own counted frequencies, rather
+    /// than with an adaptive model.
+    pub fn is_two_pass(self) -> bool {
+        self == Level::Fast
+    }
+}
+
+/// The adaptive models of the levels, by the levels' positions in
+/// [`Level::ALL`], which frames name them by.
+fn registry() -> ModelRegistry<ByteSymbol> {
+    let mut registry = ModelRegistry::new();
+    for (id, level) in Level::ALL.into_iter().enumerate() {
+        if level.is_two_pass() {
+            continue;
+        }
+        let symbols = || ByteAlphabet::message(0..=u8::MAX).collect::<Vec<_>>();
+        registry = match level.order() {
+            0 => registry.with_model(id as u32, move || {
+                AdaptiveAlphabet::new(symbols(), ByteSymbol::Eof).with_max_total(level.max_total())
+            }),
+            order => registry.with_model(id as u32, move || {
+                OrderNModel::new(symbols(), ByteSymbol::Eof, order)
+                    .with_max_total(level.max_total())
+            }),
+        };
+    }
+    registry
+}
+
+/// Compress bytes into a block stream, with the model and block size of the
+/// given level.
+///
+/// Every frame records how it was coded, so the output can be decompressed
+/// with [`decompress`] whatever its level. Frames carry checksums with the
+/// `crc32` feature.
+pub fn compress_with_level(data: &[u8], level: Level) -> Result<Vec<u8>, FormatError> {
+    let registry = registry();
+    let id = Level::ALL.iter().position(|&l| l == level).unwrap() as u32;
+    let mut output = Vec::new();
+    write_blocks_with(&mut output, data, level.block_len(), |block| {
+        if level.is_two_pass() {
+            let message = ByteAlphabet::message(block.iter().copied());
+            let table = FrequencyTable::from_bytes(block);
+            let alphabet = NormalizedAlphabet::new(table, STATIC_LOG2_TOTAL)
+                .expect("At most 257 symbols fit in a total of 2^16");
+            Ok(Frame::encode_with_model::<_, BITS_OF_PRECISION>(
+                &alphabet,
+                message,
+                Checksum::default(),
+            )?)
+        } else {
+            let symbols: Vec<_> = block.iter().map(|&byte| ByteSymbol::Byte(byte)).collect();
+            registry.encode::<BITS_OF_PRECISION>(id, &symbols, Checksum::default())
+        }
+    })?;
+    Ok(output)
+}
+
+/// Decompress a block stream written by [`compress_with_level`].
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, FormatError> {
+    let registry = registry();
+    let mut reader = Cursor::new(data);
+    let index = BlockIndex::read_from(&mut reader)?;
+    let symbols = read_range_with(&mut reader, &index, 0..index.total_len(), |frame| {
+        if frame.model.is_some() {
+            frame.decode_with_model::<ByteSymbol, BITS_OF_PRECISION>()
+        } else {
+            registry.decode::<BITS_OF_PRECISION>(frame)
+        }
+    })?;
+    Ok(symbols
+        .into_iter()
+        .filter_map(|symbol| match symbol {
+            ByteSymbol::Byte(byte) => Some(byte),
+            ByteSymbol::Eof => None,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample() -> Vec<u8> {
+        let text = b"How much wood would a woodchuck chuck if a woodchuck could chuck wood? ";
+        let mut data = text.repeat(200);
+        // Longer than a block at the fastest level
+        data.extend((0..70_000u64).map(|i| (i * i % 251) as u8));
+        data
+    }
+
+    fn round_trip(level: Level) {
+        let data = sample();
+        let compressed = compress_with_level(&data, level).unwrap();
+        assert_eq!(decompress(&compressed).unwrap(), data);
+        assert!(compressed.len() < data.len());
+        let empty = compress_with_level(&[], level).unwrap();
+        assert_eq!(decompress(&empty).unwrap(), []);
+    }
+
+    #[test]
+    fn round_trip_fast() {
+        round_trip(Level::Fast);
+    }
+
+    #[test]
+    fn round_trip_normal() {
+        round_trip(Level::Normal);
+    }
+
+    #[test]
+    fn round_trip_high() {
+        round_trip(Level::High);
+    }
+
+    #[test]
+    fn round_trip_best() {
+        round_trip(Level::Best);
+    }
+
+    #[test]
+    fn higher_orders_compress_text_better() {
+        let text = b"How much wood would a woodchuck chuck if a woodchuck could chuck wood? ";
+        let data = text.repeat(500);
+        let sizes = Level::ALL.map(|level| compress_with_level(&data, level).unwrap().len());
+        // The order-0 levels are close, as the text is short
+        let [fast, normal, high, best] = sizes;
+        assert!(high < fast.min(normal) && best < high, "{sizes:?}");
+    }
+}
@@ -0,0 +1,224 @@
+use super::{Checksum, FormatError, Frame, Hasher};
+use crate::bits::{Bit, SliceBits, bits_to_bytes};
+use crate::rice;
+use crate::wire::{read_usize, write_varint};
+
+/// Rice code a block of values into a frame, with the parameter which codes
+/// them in the fewest bits recorded in its header, and the given checksum of
+/// the values.
+///
+/// A Rice coded frame has no precision, which is recorded as 0.
+pub fn encode_rice(values: &[u64], checksum: Checksum) -> Frame {
+    let (k, _) = rice::best_parameter(values);
+    let mut bits: Vec<Bit> = Vec::new();
+    for &value in values {
+        rice::encode(value, k, &mut bits);
+    }
+
+    let mut payload = Vec::new();
+    write_varint(&mut payload, values.len() as u64).expect("Writing to a Vec can't fail");
+    payload.extend(bits_to_bytes(bits));
+    Frame {
+        precision: 0,
+        flags: 0,
+        model_id: None,
+        model: None,
+        rice_parameter: Some(k),
+        checksum: checksum.hasher().map(|mut hasher| {
+            add_values(&mut hasher, values);
+            hasher.finalize()
+        }),
+        payload,
+    }
+}
+
+/// Decode the values in a Rice coded frame, and verify their checksum if it
+/// has one.
+pub fn decode_rice(frame: &Frame) -> Result<Vec<u64>, FormatError> {
+    let k = frame.rice_parameter.ok_or(FormatError::NotRiceCoded)?;
+    let mut payload = frame.payload.as_slice();
+    let len = read_usize(&mut payload)?;
+    let mut source = SliceBits::new(payload);
+    let values = (0..len)
+        .map(|_| rice::decode(&mut source, k))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if let Some(expected) = frame.checksum {
+        let found = values_crc32(&values)?;
+        if found != expected {
+            return Err(FormatError::ChecksumMismatch { expected, found });
+        }
+    }
+    Ok(values)
+}
+
+/// Code a block of values into a frame either with the given arithmetic
+/// coding or Rice coded, whichever is smaller, recording which in the
+/// header.
+///
+/// Rice coding needs no model and is close to optimal for nearly geometric
+/// values, such as prediction residuals, where the arithmetic coder's
+/// per-symbol overhead isn't worth it. A block whose values the arithmetic
+/// coding can't code (failing with [`FormatError::Encode`]) is always Rice
+/// coded.
+pub fn encode_values<F>(
+    values: &[u64],
+    checksum: Checksum,
+    arithmetic: F,
+) -> Result<Frame, FormatError>
+where
+    F: FnOnce(&[u64]) -> Result<Frame, FormatError>,
+{
+    let rice = encode_rice(values, checksum);
+    match arithmetic(values) {
+        Ok(frame) if frame_len(&frame) < frame_len(&rice) => Ok(frame),
+        Ok(_) | Err(FormatError::Encode(_)) => Ok(rice),
+        Err(e) => Err(e),
+    }
+}
+
+/// Decode the values in a frame written by [`encode_values`], with the given
+/// arithmetic decoding unless the frame is Rice coded.
+pub fn decode_values<F>(frame: &Frame, arithmetic: F) -> Result<Vec<u64>, FormatError>
+where
+    F: FnOnce(&Frame) -> Result<Vec<u64>, FormatError>,
+{
+    match frame.rice_parameter {
+        Some(_) => decode_rice(frame),
+        None => arithmetic(frame),
+    }
+}
+
+/// The number of bytes [`super::write_frame`] writes for a frame.
+fn frame_len(frame: &Frame) -> usize {
+    let mut bytes = Vec::new();
+    super::write_frame(&mut bytes, frame).expect("Writing to a Vec can't fail");
+    bytes.len()
+}
+
+/// Add values to a checksum, as 8-byte little-endian integers.
+fn add_values(hasher: &mut Hasher, values: &[u64]) {
+    for value in values {
+        hasher.update(&value.to_le_bytes());
+    }
+}
+
+/// The CRC32 of a block of decoded values.
+#[cfg(feature = "crc32")]
+fn values_crc32(values: &[u64]) -> Result<u32, FormatError> {
+    let mut hasher = Hasher::new();
+    add_values(&mut hasher, values);
+    Ok(hasher.finalize())
+}
+
+#[cfg(not(feature = "crc32"))]
+fn values_crc32(_values: &[u64]) -> Result<u32, FormatError> {
+    Err(FormatError::ChecksumUnsupported)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::byte::{ByteAlphabet, ByteSymbol};
+    use crate::encoder::EncodeError;
+    use crate::format::{FLAG_RICE, read_frame, read_range_with, write_blocks_with, write_frame};
+    use std::io::Cursor;
+
+    /// Arithmetic code values which fit in a byte, with a model expecting
+    /// them to be mostly zero.
+    fn arithmetic(values: &[u64]) -> Result<Frame, FormatError> {
+        let alphabet = ByteAlphabet::from_sample(&[0; 2000]);
+        let bytes = values
+            .iter()
+            .map(|&value| u8::try_from(value))
+            .collect::<Result<Vec<_>, _>>();
+        let Ok(bytes) = bytes else {
+            let position = values.iter().position(|&value| value > 0xff).unwrap();
+            return Err(EncodeError::SymbolNotInAlphabet { position }.into());
+        };
+        let message = ByteAlphabet::message(bytes);
+        Ok(Frame::encode::<_, 32>(
+            &alphabet,
+            message,
+            Checksum::default(),
+        )?)
+    }
+
+    fn arithmetic_decode(frame: &Frame) -> Result<Vec<u64>, FormatError> {
+        let alphabet = ByteAlphabet::from_sample(&[0; 2000]);
+        let symbols = frame.decode::<_, 32>(&alphabet)?;
+        Ok(symbols
+            .into_iter()
+            .map(|symbol| match symbol {
+                ByteSymbol::Byte(byte) => byte as u64,
+                ByteSymbol::Eof => unreachable!("EOF isn't returned"),
+            })
+            .collect())
+    }
+
+    #[test]
+    fn round_trip_rice() {
+        let values = [0, 3, 1, 17, 4, 1 << 40, 2];
+        let frame = encode_rice(&values, Checksum::default());
+        assert_eq!(frame.rice_parameter, Some(rice::best_parameter(&values).0));
+
+        let mut bytes = Vec::new();
+        write_frame(&mut bytes, &frame).unwrap();
+        assert_eq!(bytes[6] & FLAG_RICE, FLAG_RICE);
+        let read = read_frame(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read, frame);
+        assert_eq!(decode_rice(&read).unwrap(), values);
+        assert!(
+            decode_rice(&encode_rice(&[], Checksum::None))
+                .unwrap()
+                .is_empty()
+        );
+
+        // A Rice coded frame isn't mistaken for an arithmetic coded one
+        let alphabet = ByteAlphabet::uniform();
+        assert!(matches!(
+            frame.decode::<_, 32>(&alphabet),
+            Err(FormatError::RiceCoded)
+        ));
+        assert!(matches!(
+            decode_rice(&arithmetic(&[0]).unwrap()),
+            Err(FormatError::NotRiceCoded)
+        ));
+
+        // The corrupt values still decode, so only the checksum catches them
+        #[cfg(feature = "crc32")]
+        {
+            let mut corrupt = frame.clone();
+            corrupt.payload[2] ^= 0x10;
+            assert!(decode_rice(&corrupt).is_err());
+        }
+    }
+
+    #[test]
+    fn blocks_choose_their_coding() {
+        // Mostly zeros, then spread out residuals, then values too large for
+        // a byte
+        let values: Vec<u64> = [
+            (0..64).map(|i| (i % 31 == 0) as u64).collect::<Vec<_>>(),
+            (0..64).map(|i| i * 37 % 50).collect(),
+            (0..64).map(|i| 1000 + i).collect(),
+        ]
+        .concat();
+
+        let mut bytes = Vec::new();
+        let mut rice_coded = Vec::new();
+        let index = write_blocks_with(&mut bytes, &values, 64, |block| {
+            let frame = encode_values(block, Checksum::default(), arithmetic)?;
+            rice_coded.push(frame.rice_parameter.is_some());
+            Ok(frame)
+        })
+        .unwrap();
+        assert_eq!(rice_coded, [false, true, true]);
+
+        let mut reader = Cursor::new(bytes);
+        let decoded = read_range_with(&mut reader, &index, 0..values.len(), |frame| {
+            decode_values(frame, arithmetic_decode)
+        });
+        assert_eq!(decoded.unwrap(), values);
+    }
+}
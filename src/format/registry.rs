@@ -0,0 +1,262 @@
+use super::{Checksum, FormatError, Frame};
+use crate::alphabet::{Alphabet, DynAlphabet};
+use crate::encoder::Encoder;
+use crate::wire::WireSymbol;
+use std::collections::BTreeMap;
+
+/// Constructs a fresh model for a frame.
+type Constructor<S> = Box<dyn Fn() -> Box<dyn DynAlphabet<S = S>> + Send + Sync>;
+
+/// The models an application supports, keyed by small integer ids which
+/// frames record (see [`FLAG_MODEL_ID`](super::FLAG_MODEL_ID)), so that a
+/// frame can be decoded with whichever of them encoded it.
+///
+/// Every model is built afresh for each frame, so adaptive models start over
+/// with each one, and a registry can be shared between threads.
+pub struct ModelRegistry<S: WireSymbol> {
+    models: BTreeMap<u32, Constructor<S>>,
+}
+
+impl<S: WireSymbol + 'static> ModelRegistry<S> {
+    pub fn new() -> Self {
+        Self {
+            models: BTreeMap::new(),
+        }
+    }
+
+    /// Register the model built by `constructor` under the given id,
+    /// replacing any model already registered under it.
+    pub fn with_model<A, F>(mut self, id: u32, constructor: F) -> Self
+    where
+        A: Alphabet<S = S> + 'static,
+        F: Fn() -> A + Send + Sync + 'static,
+    {
+        self.models.insert(
+            id,
+            Box::new(move || Box::new(constructor()) as Box<dyn DynAlphabet<S = S>>),
+        );
+        self
+    }
+
+    /// The ids of the registered models, in increasing order.
+    pub fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.models.keys().copied()
+    }
+
+    /// Build the model registered under the given id.
+    pub fn model(&self, id: u32) -> Result<Box<dyn DynAlphabet<S = S>>, FormatError> {
+        let constructor = self.models.get(&id).ok_or(FormatError::UnknownModel(id))?;
+        Ok(constructor())
+    }
+
+    /// Encode a message (not including EOF) with the model registered under
+    /// the given id, into a frame which names it.
+    pub fn encode<const BITS_OF_PRECISION: u32>(
+        &self,
+        id: u32,
+        symbols: &[S],
+        checksum: Checksum,
+    ) -> Result<Frame, FormatError> {
+        let model = self.model(id)?;
+        let message = symbols.iter().cloned().chain([model.eof()]);
+        Ok(Frame {
+            model_id: Some(id),
+            ..Frame::encode::<_, BITS_OF_PRECISION>(&model, message, checksum)?
+        })
+    }
+
+    /// Encode a message (not including EOF) with whichever registered model
+    /// codes it in the fewest bits, into a frame which names it.
+    ///
+    /// Each model is tried on the first `sample_len` symbols of the message,
+    /// measuring its output without producing it (see
+    /// [`Encoder::measure`]), and then only the best encodes the whole
+    /// message. If the best can't code a symbol beyond the sample, the next
+    /// best is used. Ties go to the model with the lowest id.
+    pub fn encode_best<const BITS_OF_PRECISION: u32>(
+        &self,
+        symbols: &[S],
+        sample_len: usize,
+        checksum: Checksum,
+    ) -> Result<Frame, FormatError> {
+        let sample = &symbols[..sample_len.min(symbols.len())];
+        let mut costs = Vec::new();
+        let mut error = FormatError::NoModels;
+        for id in self.ids() {
+            let model = self.model(id)?;
+            let message = sample.iter().cloned().chain([model.eof()]);
+            match model.measure::<_, BITS_OF_PRECISION>(message) {
+                Ok(cost) => costs.push((cost, id)),
+                Err(e) => error = e.into(),
+            }
+        }
+        costs.sort();
+
+        for (_, id) in costs {
+            match self.encode::<BITS_OF_PRECISION>(id, symbols, checksum) {
+                Ok(frame) => return Ok(frame),
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+
+    /// Decode the message in a frame with the model it names, returning its
+    /// symbols (not including EOF).
+    pub fn decode<const BITS_OF_PRECISION: u32>(
+        &self,
+        frame: &Frame,
+    ) -> Result<Vec<S>, FormatError> {
+        let id = frame.model_id.ok_or(FormatError::MissingModelId)?;
+        frame.decode::<_, BITS_OF_PRECISION>(&self.model(id)?)
+    }
+}
+
+impl<S: WireSymbol + 'static> Default for ModelRegistry<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::byte::{ByteAlphabet, ByteSymbol};
+    use crate::format::{BlockIndex, read_frame, read_range_with, write_blocks_with, write_frame};
+    use crate::frequency::FrequencyTable;
+    use crate::models::AdaptiveAlphabet;
+    use std::io::Cursor;
+
+    fn registry() -> ModelRegistry<ByteSymbol> {
+        ModelRegistry::new()
+            .with_model(0, ByteAlphabet::uniform)
+            .with_model(1, || {
+                AdaptiveAlphabet::new(
+                    ByteAlphabet::message(0..=u8::MAX).collect(),
+                    ByteSymbol::Eof,
+                )
+            })
+            .with_model(7, || ByteAlphabet::from_sample(b"aaaaaaab"))
+    }
+
+    #[test]
+    fn frames_name_their_model() {
+        let registry = registry();
+        assert!(registry.ids().eq([0, 1, 7]));
+        let symbols: Vec<_> = b"abababaaab".iter().map(|&b| ByteSymbol::Byte(b)).collect();
+
+        let mut bytes = Vec::new();
+        for id in registry.ids() {
+            let frame = registry
+                .encode::<32>(id, &symbols, Checksum::default())
+                .unwrap();
+            assert_eq!(frame.model_id, Some(id));
+            write_frame(&mut bytes, &frame).unwrap();
+        }
+
+        // The decoder needs only the registry, not the ids
+        let reader = &mut bytes.as_slice();
+        for _ in registry.ids() {
+            let frame = read_frame(reader).unwrap();
+            assert_eq!(registry.decode::<32>(&frame).unwrap(), symbols);
+        }
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn blocks_name_their_model() {
+        let registry = registry();
+        let symbols: Vec<_> = b"aaaaaaabaaaaaaab"
+            .repeat(10)
+            .into_iter()
+            .map(ByteSymbol::Byte)
+            .collect();
+        let mut bytes = Vec::new();
+        let mut ids = [7, 0, 1].into_iter().cycle();
+        let index = write_blocks_with(&mut bytes, &symbols, 50, |block| {
+            registry.encode::<32>(ids.next().unwrap(), block, Checksum::None)
+        })
+        .unwrap();
+
+        let mut reader = Cursor::new(bytes);
+        assert_eq!(BlockIndex::read_from(&mut reader).unwrap(), index);
+        let decoded = read_range_with(&mut reader, &index, 40..120, |frame| {
+            registry.decode::<32>(frame)
+        });
+        assert_eq!(decoded.unwrap(), symbols[40..120]);
+    }
+
+    #[test]
+    fn blocks_choose_their_model() {
+        // These only contain the bytes of their samples
+        let digits = || FrequencyTable::from_bytes(b"0123456789");
+        let registry = ModelRegistry::new()
+            .with_model(0, ByteAlphabet::uniform)
+            .with_model(1, || FrequencyTable::from_bytes(b"the quick brown fox"))
+            .with_model(2, digits);
+        let text = b"the fox ".repeat(8);
+        let numbers = b"31415926".repeat(8);
+        let late_digit = [&b"the fox ".repeat(7)[..], b"the 1st "].concat();
+        let symbols: Vec<_> = [text, numbers, late_digit]
+            .concat()
+            .into_iter()
+            .map(ByteSymbol::Byte)
+            .collect();
+
+        let mut bytes = Vec::new();
+        let mut chosen = Vec::new();
+        let index = write_blocks_with(&mut bytes, &symbols, 64, |block| {
+            let frame = registry.encode_best::<32>(block, 16, Checksum::default())?;
+            // No other model codes the block in fewer bytes
+            for id in registry.ids() {
+                if let Ok(other) = registry.encode::<32>(id, block, Checksum::default()) {
+                    assert!(frame.payload.len() <= other.payload.len());
+                }
+            }
+            chosen.push(frame.model_id.unwrap());
+            Ok(frame)
+        })
+        .unwrap();
+        // The last block has a digit beyond its sample, which only the uniform
+        // model can code
+        assert_eq!(chosen, [1, 2, 0]);
+
+        let mut reader = Cursor::new(bytes);
+        let decoded = read_range_with(&mut reader, &index, 0..symbols.len(), |frame| {
+            registry.decode::<32>(frame)
+        });
+        assert_eq!(decoded.unwrap(), symbols);
+
+        let empty = ModelRegistry::<ByteSymbol>::new();
+        assert!(matches!(
+            empty.encode_best::<32>(&symbols, 16, Checksum::None),
+            Err(FormatError::NoModels)
+        ));
+        let only_digits = ModelRegistry::new().with_model(2, digits);
+        assert!(matches!(
+            only_digits.encode_best::<32>(&symbols, 16, Checksum::None),
+            Err(FormatError::Encode(_))
+        ));
+    }
+
+    #[test]
+    fn errors_on_unknown_models() {
+        let registry = registry();
+        assert!(matches!(
+            registry.encode::<32>(2, &[], Checksum::None),
+            Err(FormatError::UnknownModel(2))
+        ));
+
+        let mut frame = registry.encode::<32>(0, &[], Checksum::None).unwrap();
+        frame.model_id = Some(3);
+        assert!(matches!(
+            registry.decode::<32>(&frame),
+            Err(FormatError::UnknownModel(3))
+        ));
+        frame.model_id = None;
+        assert!(matches!(
+            registry.decode::<32>(&frame),
+            Err(FormatError::MissingModelId)
+        ));
+    }
+}
@@ -0,0 +1,358 @@
+use crate::alphabet::{Alphabet, Symbol};
+use crate::byte::ByteSymbol;
+use crate::histogram::Histogram;
+use crate::lookup::SymbolLookup;
+use crate::wire::{self, WireSymbol};
+use std::hash::Hash;
+use std::io;
+
+/// A static alphabet trained on sample data: each symbol's interval width is
+/// the number of times it occurred in the sample.
+///
+/// Only symbols which occur in the sample are in the alphabet, plus EOF, which
+/// is always given one count in addition to any occurrences so that messages
+/// can be terminated. Symbols are kept in the order they were first seen. To
+/// cover symbols which are missing from the sample, use one of the
+/// constructors with add-k smoothing.
+///
+/// R is the size of the sample, so a table trained on a large corpus should be
+/// normalized with [`NormalizedAlphabet`](crate::normalized::NormalizedAlphabet)
+/// to keep R within the precision of the coder.
+///
+/// A table can be shared between threads, and its widths can be saved with
+/// [`FrequencyTable::widths`] and restored with [`FrequencyTable::from_widths`],
+/// or written in a compact binary form with [`FrequencyTable::write_to`].
+#[derive(Clone, Debug)]
+pub struct FrequencyTable<S: Symbol> {
+    symbols: SymbolLookup<S>,
+    eof: S,
+    /// bounds[i] is the lower bound of the i-th symbol, and bounds[n] is R
+    bounds: Vec<usize>,
+}
+
+impl<S: Symbol + Eq + Hash> FrequencyTable<S> {
+    /// Count the occurrences of each symbol in the sample.
+    pub fn from_symbols<I: IntoIterator<Item = S>>(sample: I, eof: S) -> Self {
+        Self::from_histogram(&sample.into_iter().collect(), eof)
+    }
+
+    /// Count the occurrences of each symbol in the sample, with add-k
+    /// (Laplace, for k = 1) smoothing: every symbol of the given alphabet is
+    /// included, even if it's missing from the sample, and every symbol is
+    /// given k counts in addition to its occurrences.
+    ///
+    /// The alphabet's symbols come first, in the order given, followed by any
+    /// other symbols in the sample and then EOF. EOF is always given at least
+    /// one additional count.
+    pub fn from_symbols_with_smoothing<I: IntoIterator<Item = S>>(
+        sample: I,
+        alphabet: &[S],
+        eof: S,
+        k: usize,
+    ) -> Self {
+        let mut histogram = Histogram::new();
+        for symbol in alphabet {
+            histogram.add_count(symbol.clone(), 0);
+        }
+        histogram.extend(sample);
+        Self::from_histogram_with_smoothing(&histogram, eof, k)
+    }
+
+    /// Use counts which have already been gathered.
+    pub fn from_histogram(histogram: &Histogram<S>, eof: S) -> Self {
+        Self::from_histogram_with_smoothing(histogram, eof, 0)
+    }
+
+    /// Use counts which have already been gathered, adding k to each (see
+    /// [`FrequencyTable::from_symbols_with_smoothing`]).
+    pub fn from_histogram_with_smoothing(histogram: &Histogram<S>, eof: S, k: usize) -> Self {
+        let mut histogram = histogram.clone();
+        histogram.add_count(eof.clone(), 0);
+
+        let widths = histogram
+            .iter()
+            .map(|(symbol, count)| {
+                let smoothing = if *symbol == eof { k.max(1) } else { k };
+                let width = usize::try_from(count).unwrap_or(usize::MAX);
+                (symbol.clone(), width.saturating_add(smoothing))
+            })
+            .collect();
+        Self::from_widths(widths, eof)
+    }
+}
+
+impl<S: Symbol> FrequencyTable<S> {
+    /// Use the given interval widths directly. The symbols must include EOF.
+    pub fn from_widths(widths: Vec<(S, usize)>, eof: S) -> Self {
+        let mut symbols = Vec::with_capacity(widths.len());
+        let mut bounds = Vec::with_capacity(widths.len() + 1);
+        let mut sum: usize = 0;
+        bounds.push(sum);
+        for (symbol, width) in widths {
+            symbols.push(symbol);
+            sum = sum.saturating_add(width);
+            bounds.push(sum);
+        }
+
+        Self {
+            symbols: SymbolLookup::new(symbols),
+            eof,
+            bounds,
+        }
+    }
+}
+
+impl FrequencyTable<ByteSymbol> {
+    /// Count the occurrences of each byte in the sample.
+    pub fn from_bytes(sample: &[u8]) -> Self {
+        Self::from_symbols(
+            sample.iter().map(|&byte| ByteSymbol::Byte(byte)),
+            ByteSymbol::Eof,
+        )
+    }
+
+    /// Count the occurrences of each byte in the sample, including every byte
+    /// value in the table with k counts added to each (see
+    /// [`FrequencyTable::from_symbols_with_smoothing`]).
+    pub fn from_bytes_with_smoothing(sample: &[u8], k: usize) -> Self {
+        let bytes: Vec<_> = (0..=u8::MAX).map(ByteSymbol::Byte).collect();
+        Self::from_symbols_with_smoothing(
+            sample.iter().map(|&byte| ByteSymbol::Byte(byte)),
+            &bytes,
+            ByteSymbol::Eof,
+            k,
+        )
+    }
+}
+
+impl<S: Symbol> FrequencyTable<S> {
+    /// The number of distinct symbols in the table, including EOF.
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.len() == 0
+    }
+
+    /// The symbols of the table and their interval widths, in order.
+    pub fn widths(&self) -> impl Iterator<Item = (&S, usize)> {
+        self.symbols
+            .symbols()
+            .iter()
+            .zip(self.bounds.windows(2))
+            .map(|(symbol, bounds)| (symbol, bounds[1] - bounds[0]))
+    }
+
+    /// Whether the symbol occurred in the sample (or is EOF), and so can be
+    /// encoded.
+    pub fn contains(&self, symbol: &S) -> bool {
+        self.symbols.symbols().contains(symbol)
+    }
+}
+
+impl<S: WireSymbol> FrequencyTable<S> {
+    /// Write the table in a compact binary form: the number of symbols and
+    /// the position of EOF, followed by each symbol and its width, with every
+    /// integer as a varint (see [`wire`]).
+    pub fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let eof = self.symbols.index_of(&self.eof);
+        wire::write_varint(writer, self.len() as u64)?;
+        wire::write_varint(writer, eof as u64)?;
+        for (symbol, width) in self.widths() {
+            symbol.write_to(writer)?;
+            wire::write_varint(writer, width as u64)?;
+        }
+        Ok(())
+    }
+
+    /// Read a table written by [`FrequencyTable::write_to`].
+    pub fn read_from<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let len = wire::read_usize(reader)?;
+        let eof = wire::read_usize(reader)?;
+        if eof >= len {
+            return Err(wire::invalid_data(format!(
+                "EOF position {eof} is out of range for {len} symbols"
+            )));
+        }
+
+        // The length isn't trusted enough to preallocate
+        let mut widths = Vec::new();
+        for _ in 0..len {
+            let symbol = S::read_from(reader)?;
+            widths.push((symbol, wire::read_usize(reader)?));
+        }
+        let eof = widths[eof].0.clone();
+        Ok(Self::from_widths(widths, eof))
+    }
+}
+
+impl<S: Symbol> Alphabet for FrequencyTable<S> {
+    type S = S;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.symbols.symbols().iter()
+    }
+
+    fn eof(&self) -> Self::S {
+        self.eof.clone()
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        let i = self.symbols.index_of(symbol);
+        self.bounds[i + 1] - self.bounds[i]
+    }
+
+    fn total_interval_width(&self) -> usize {
+        self.bounds[self.symbols.len()]
+    }
+
+    fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+        self.bounds[self.symbols.index_of(symbol)]
+    }
+
+    fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+        self.bounds[self.symbols.index_of(symbol) + 1]
+    }
+
+    fn quantile(&self, value: usize) -> &Self::S {
+        let index = self.bounds.partition_point(|&bound| bound <= value) - 1;
+        assert!(
+            index < self.symbols.len(),
+            "Value {value} is beyond the total interval width"
+        );
+        &self.symbols.symbols()[index]
+    }
+
+    fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        let index = self.symbols.position(symbol)?;
+        Some((self.bounds[index], self.bounds[index + 1]))
+    }
+
+    fn contains(&self, symbol: &Self::S) -> bool {
+        FrequencyTable::contains(self, symbol)
+    }
+
+    fn index(&self, symbol: &Self::S) -> usize {
+        self.symbols.index_of(symbol)
+    }
+
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        &self.symbols.symbols()[index]
+    }
+
+    fn is_static(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoder::{Decoder, DecoderEvent};
+    use crate::encoder::Encoder;
+    use crate::reference;
+
+    #[test]
+    fn counts_sample_and_reserves_eof() {
+        let table = FrequencyTable::from_bytes(b"abracadabra");
+        assert_eq!(table.len(), 6);
+        assert_eq!(table.total_interval_width(), 12);
+        assert_eq!(table.interval_width(&ByteSymbol::Byte(b'a')), 5);
+        assert_eq!(table.interval_width(&ByteSymbol::Eof), 1);
+        assert_eq!(table.interval_lower_bound(&ByteSymbol::Byte(b'b')), 5);
+        assert!(!table.contains(&ByteSymbol::Byte(b'z')));
+    }
+
+    #[test]
+    fn compact_tables_round_trip() {
+        let table = FrequencyTable::from_bytes(b"the quick brown fox jumps over the lazy dog");
+        let mut bytes = Vec::new();
+        table.write_to(&mut bytes).unwrap();
+        // Two bytes of header, two for each of the 27 distinct bytes, and
+        // three for EOF
+        assert_eq!(bytes.len(), 2 + 27 * 2 + 3);
+
+        let read = FrequencyTable::<ByteSymbol>::read_from(&mut bytes.as_slice()).unwrap();
+        assert!(read.widths().eq(table.widths()));
+        assert_eq!(read.eof(), ByteSymbol::Eof);
+
+        let words = FrequencyTable::from_widths(
+            vec![("eof".to_string(), 1), ("word".to_string(), 1000)],
+            "eof".to_string(),
+        );
+        let mut bytes = Vec::new();
+        words.write_to(&mut bytes).unwrap();
+        let read = FrequencyTable::<String>::read_from(&mut bytes.as_slice()).unwrap();
+        assert!(read.widths().eq(words.widths()));
+
+        // Truncated, and with EOF out of range
+        assert!(FrequencyTable::<String>::read_from(&mut &bytes[..bytes.len() - 1]).is_err());
+        assert!(FrequencyTable::<String>::read_from(&mut [1, 1].as_slice()).is_err());
+    }
+
+    #[test]
+    fn eof_in_sample_is_counted() {
+        use ByteSymbol::*;
+        let table = FrequencyTable::from_symbols([Byte(0), Eof, Byte(0), Eof], Eof);
+        assert_eq!(table.symbols().collect::<Vec<_>>(), vec![&Byte(0), &Eof]);
+        assert_eq!(table.interval_width(&Eof), 3);
+    }
+
+    #[test]
+    fn smoothing_covers_missing_symbols() {
+        use ByteSymbol::*;
+        let table = FrequencyTable::from_symbols_with_smoothing(
+            [Byte(1), Byte(1), Byte(2)],
+            &[Byte(0), Byte(1)],
+            Eof,
+            2,
+        );
+        assert_eq!(
+            table.symbols().collect::<Vec<_>>(),
+            vec![&Byte(0), &Byte(1), &Byte(2), &Eof]
+        );
+        assert_eq!(table.interval_width(&Byte(0)), 2);
+        assert_eq!(table.interval_width(&Byte(1)), 4);
+        assert_eq!(table.interval_width(&Byte(2)), 3);
+        assert_eq!(table.interval_width(&Eof), 2);
+
+        let table = FrequencyTable::from_bytes_with_smoothing(b"aab", 1);
+        assert_eq!(table.len(), 257);
+        assert_eq!(table.total_interval_width(), 3 + 257);
+        assert_eq!(table.interval_width(&Byte(b'z')), 1);
+        assert_eq!(table.validate(32), Ok(()));
+    }
+
+    #[test]
+    fn round_trip_words() {
+        let text = "the cat sat on the mat and the cat ran";
+        let words = || text.split(' ').map(String::from);
+        let eof = String::new();
+        let table = FrequencyTable::from_symbols(words(), eof.clone());
+
+        let input: Vec<String> = words().chain([eof]).collect();
+        let bits: Vec<_> = table
+            .encode::<_, 32>(input.clone())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let decoded: Vec<_> = table
+            .decode::<_, 32>(bits)
+            .filter_map(|event| match event.unwrap() {
+                DecoderEvent::DecodedSymbol(word) => Some(word),
+                DecoderEvent::MessageLength(_) => None,
+            })
+            .collect();
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn frequency_table_matches_reference() {
+        let table =
+            FrequencyTable::from_bytes(b"it was the best of times, it was the worst of times");
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&table, 50, 100, 9),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use crate::alphabet::Symbol;
+use crate::frequency::FrequencyTable;
+use crate::normalized::{NormalizeError, NormalizedAlphabet};
+use crate::rng::SplitMix64;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io;
+
+/// Frequency counts of the values in a stream, gathered in a single pass.
+///
+/// Values are kept in the order they were first seen. A histogram can be
+/// built from any iterator (via [`FromIterator`] or [`Extend`]), from the bytes
+/// of an [`io::Read`], or from a fixed-size uniform sample of a very large
+/// input with [`Histogram::sampled`]. Once gathered, the counts can be turned
+/// into an alphabet with [`Histogram::quantize`].
+#[derive(Clone, Debug)]
+pub struct Histogram<T> {
+    index: HashMap<T, usize>,
+    counts: Vec<(T, u64)>,
+    total: u64,
+}
+
+impl<T: Eq + Hash + Clone> Histogram<T> {
+    pub fn new() -> Self {
+        Self {
+            index: HashMap::new(),
+            counts: Vec::new(),
+            total: 0,
+        }
+    }
+
+    /// Count one occurrence of a value.
+    pub fn add(&mut self, value: T) {
+        self.add_count(value, 1);
+    }
+
+    /// Count the given number of occurrences of a value.
+    pub fn add_count(&mut self, value: T, count: u64) {
+        let counts = &mut self.counts;
+        let i = *self.index.entry(value.clone()).or_insert_with(|| {
+            counts.push((value, 0));
+            counts.len() - 1
+        });
+        self.counts[i].1 += count;
+        self.total += count;
+    }
+
+    /// The number of occurrences of a value.
+    pub fn count(&self, value: &T) -> u64 {
+        self.index.get(value).map_or(0, |&i| self.counts[i].1)
+    }
+
+    /// The total number of occurrences of all values.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The number of distinct values seen.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// An iterator over the distinct values and their counts, in the order
+    /// they were first seen.
+    pub fn iter(&self) -> impl Iterator<Item = (&T, u64)> {
+        self.counts.iter().map(|(value, count)| (value, *count))
+    }
+
+    /// Build a histogram from a uniform random sample of at most
+    /// `sample_size` values of the input, using reservoir sampling, so that
+    /// inputs too large to count exhaustively can be estimated in bounded
+    /// memory. The seed makes the sample reproducible.
+    ///
+    /// The resulting counts sum to the size of the sample, not of the input.
+    pub fn sampled<I: IntoIterator<Item = T>>(input: I, sample_size: usize, seed: u64) -> Self {
+        let mut rng = SplitMix64(seed);
+        let mut reservoir = Vec::with_capacity(sample_size);
+        for (seen, value) in input.into_iter().enumerate() {
+            if reservoir.len() < sample_size {
+                reservoir.push(value);
+            } else {
+                let j = rng.below(seen as u64 + 1) as usize;
+                if j < sample_size {
+                    reservoir[j] = value;
+                }
+            }
+        }
+        reservoir.into_iter().collect()
+    }
+
+    /// Convert the values of this histogram, merging the counts of values
+    /// which map to the same result.
+    pub fn map<U: Eq + Hash + Clone>(&self, mut f: impl FnMut(T) -> U) -> Histogram<U> {
+        let mut mapped = Histogram::new();
+        for (value, count) in &self.counts {
+            mapped.add_count(f(value.clone()), *count);
+        }
+        mapped
+    }
+}
+
+impl<T: Eq + Hash + Clone> Default for Histogram<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Eq + Hash + Clone> Extend<T> for Histogram<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.add(value);
+        }
+    }
+}
+
+impl<T: Eq + Hash + Clone> FromIterator<T> for Histogram<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut histogram = Self::new();
+        histogram.extend(iter);
+        histogram
+    }
+}
+
+impl Histogram<u8> {
+    /// Count the bytes read from the given reader until it is exhausted.
+    pub fn from_reader<R: io::Read>(mut reader: R) -> io::Result<Self> {
+        let mut counts = [0u64; 256];
+        let mut buffer = [0u8; 8192];
+        loop {
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            for &byte in &buffer[..read] {
+                counts[byte as usize] += 1;
+            }
+        }
+
+        let mut histogram = Self::new();
+        for (byte, count) in (0..=u8::MAX).zip(counts) {
+            if count > 0 {
+                histogram.add_count(byte, count);
+            }
+        }
+        Ok(histogram)
+    }
+}
+
+impl<S: Symbol + Eq + Hash> Histogram<S> {
+    /// Build an alphabet from the counts of this histogram (see
+    /// [`FrequencyTable::from_histogram`]), with interval widths normalized to
+    /// sum to 2^log2_total.
+    pub fn quantize(
+        &self,
+        eof: S,
+        log2_total: u32,
+    ) -> Result<NormalizedAlphabet<FrequencyTable<S>>, NormalizeError> {
+        NormalizedAlphabet::new(FrequencyTable::from_histogram(self, eof), log2_total)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::alphabet::Alphabet;
+    use crate::byte::ByteSymbol;
+    use crate::decoder::{Decoder, DecoderEvent};
+    use crate::encoder::Encoder;
+
+    #[test]
+    fn counts_in_order_of_appearance() {
+        let histogram: Histogram<char> = "abracadabra".chars().collect();
+        assert_eq!(histogram.total(), 11);
+        assert_eq!(
+            histogram.iter().collect::<Vec<_>>(),
+            vec![(&'a', 5), (&'b', 2), (&'r', 2), (&'c', 1), (&'d', 1)]
+        );
+        assert_eq!(histogram.count(&'z'), 0);
+    }
+
+    #[test]
+    fn counts_bytes_from_reader() {
+        let histogram = Histogram::from_reader(&b"hello"[..]).unwrap();
+        assert_eq!(histogram.total(), 5);
+        assert_eq!(histogram.count(&b'l'), 2);
+        assert_eq!(histogram.count(&b'h'), 1);
+        assert_eq!(histogram.len(), 4);
+    }
+
+    #[test]
+    fn sample_is_bounded() {
+        let histogram = Histogram::sampled((0..100_000).map(|i| i % 4), 1000, 7);
+        assert_eq!(histogram.total(), 1000);
+        assert_eq!(histogram.len(), 4);
+        // Each value makes up a quarter of the input, so should make up
+        // roughly a quarter of the sample
+        for (_, count) in histogram.iter() {
+            assert!((150..350).contains(&count), "count={count}");
+        }
+    }
+
+    #[test]
+    fn sample_of_short_input_is_exact() {
+        let histogram = Histogram::sampled("abca".chars(), 10, 0);
+        assert_eq!(histogram.count(&'a'), 2);
+        assert_eq!(histogram.total(), 4);
+    }
+
+    #[test]
+    fn round_trip_through_quantized_alphabet() {
+        let text = b"a man a plan a canal panama";
+        let alphabet = Histogram::from_reader(&text[..])
+            .unwrap()
+            .map(ByteSymbol::Byte)
+            .quantize(ByteSymbol::Eof, 12)
+            .unwrap();
+        assert_eq!(alphabet.total_interval_width(), 1 << 12);
+
+        let input: Vec<_> = text
+            .iter()
+            .map(|&byte| ByteSymbol::Byte(byte))
+            .chain([ByteSymbol::Eof])
+            .collect();
+        let bits: Vec<_> = alphabet
+            .encode::<_, 32>(input.clone())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let symbols: Vec<_> = alphabet
+            .decode::<_, 32>(bits)
+            .filter_map(|event| match event.unwrap() {
+                DecoderEvent::DecodedSymbol(symbol) => Some(symbol),
+                DecoderEvent::MessageLength(_) => None,
+            })
+            .collect();
+
+        assert_eq!(symbols, input);
+    }
+}
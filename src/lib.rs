@@ -1,6 +1,50 @@
 pub mod alphabet;
+#[cfg(feature = "quickcheck")]
+pub mod arbitrary;
+pub mod arcode;
+pub mod binarization;
+pub mod bits;
+pub mod byte;
+pub mod cached;
+pub mod composite;
 pub mod decoder;
+pub mod dynamic;
 pub mod encoder;
+#[cfg(feature = "constriction")]
+pub mod entropy_model;
+pub mod erased;
+mod fenwick;
+#[cfg(feature = "format")]
+pub mod format;
+pub mod frequency;
+pub mod histogram;
+mod lookup;
+pub mod map;
+pub mod models;
+pub mod normalized;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+#[cfg(feature = "presets")]
+pub mod presets;
+pub mod range;
+mod reciprocal;
+pub mod reference;
+mod rescale;
+pub mod resync;
+pub mod rice;
+mod rng;
+pub mod schedule;
+pub mod split;
+pub mod text;
+pub mod wire;
 
 #[cfg(test)]
 mod example;
+
+pub use bits::Bit;
+pub use decoder::Decoder;
+pub use encoder::Encoder;
+
+// Lets code derived within this crate refer to it by name
+#[cfg(feature = "derive")]
+extern crate self as arithmetic_coding;
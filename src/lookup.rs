@@ -0,0 +1,61 @@
+use crate::alphabet::Symbol;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The symbols of an alphabet in order, with lookup of a symbol's position.
+///
+/// Symbols can only be compared for equality, so finding one is a linear
+/// search. However, the decoder visits symbols in order, so the position after
+/// the previous lookup is checked first, making a scan over the alphabet cost
+/// O(1) per symbol.
+///
+/// The previous position is only a hint, so it's kept in a relaxed atomic,
+/// which lets alphabets using this lookup be shared between threads.
+#[derive(Debug)]
+pub(crate) struct SymbolLookup<S: Symbol> {
+    symbols: Vec<S>,
+    last_lookup: AtomicUsize,
+}
+
+impl<S: Symbol> Clone for SymbolLookup<S> {
+    fn clone(&self) -> Self {
+        Self::new(self.symbols.clone())
+    }
+}
+
+impl<S: Symbol> SymbolLookup<S> {
+    pub(crate) fn new(symbols: Vec<S>) -> Self {
+        Self {
+            symbols,
+            last_lookup: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn symbols(&self) -> &[S] {
+        &self.symbols
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Find the position of a symbol, if it's present.
+    pub(crate) fn position(&self, symbol: &S) -> Option<usize> {
+        let last = self.last_lookup.load(Ordering::Relaxed);
+        let index = [last, last + 1]
+            .into_iter()
+            .find(|&i| self.symbols.get(i) == Some(symbol))
+            .or_else(|| self.symbols.iter().position(|s| s == symbol))?;
+        self.last_lookup.store(index, Ordering::Relaxed);
+        Some(index)
+    }
+
+    /// Find the position of a symbol, panicking if it isn't present.
+    pub(crate) fn index_of(&self, symbol: &S) -> usize {
+        self.position(symbol)
+            .unwrap_or_else(|| panic!("Symbol {symbol:?} not in alphabet"))
+    }
+
+    pub(crate) fn contains(&self, symbol: &S) -> bool {
+        self.position(symbol).is_some()
+    }
+}
@@ -0,0 +1,178 @@
+use crate::alphabet::{Alphabet, Symbol};
+
+/// An alphabet wrapper which exposes another alphabet's distribution over a
+/// different symbol type, through a bijection between the two types.
+///
+/// The wrapped alphabet's symbols are converted once, on construction, and
+/// each symbol passed to the wrapper is converted back to look up its bounds,
+/// so the same table can serve several symbol representations. The
+/// [`Alphabet::begin_message`] and [`Alphabet::update`] hooks are forwarded,
+/// so adaptive alphabets can be wrapped too.
+pub struct MapAlphabet<A, B, F>
+where
+    A: Alphabet,
+    B: Symbol,
+    F: Fn(&B) -> A::S,
+{
+    alphabet: A,
+    symbols: Vec<B>,
+    eof: B,
+    from: F,
+}
+
+impl<A, B, F> MapAlphabet<A, B, F>
+where
+    A: Alphabet,
+    B: Symbol,
+    F: Fn(&B) -> A::S,
+{
+    /// Wrap an alphabet, with `to` converting its symbols to the new type and
+    /// `from` converting them back. The two must be inverses.
+    pub fn new(alphabet: A, to: impl Fn(&A::S) -> B, from: F) -> Self {
+        let symbols = alphabet.symbols().map(&to).collect();
+        let eof = to(&alphabet.eof());
+        Self {
+            alphabet,
+            symbols,
+            eof,
+            from,
+        }
+    }
+
+    /// The underlying alphabet.
+    pub fn inner(&self) -> &A {
+        &self.alphabet
+    }
+}
+
+impl<A, B, F> Alphabet for MapAlphabet<A, B, F>
+where
+    A: Alphabet,
+    B: Symbol,
+    F: Fn(&B) -> A::S,
+{
+    type S = B;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.symbols.iter()
+    }
+
+    fn eof(&self) -> Self::S {
+        self.eof.clone()
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        self.alphabet.interval_width(&(self.from)(symbol))
+    }
+
+    fn total_interval_width(&self) -> usize {
+        self.alphabet.total_interval_width()
+    }
+
+    fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+        self.alphabet.interval_lower_bound(&(self.from)(symbol))
+    }
+
+    fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+        self.alphabet.interval_upper_bound(&(self.from)(symbol))
+    }
+
+    fn quantile(&self, value: usize) -> &Self::S {
+        let index = self.alphabet.index(self.alphabet.quantile(value));
+        &self.symbols[index]
+    }
+
+    fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        self.alphabet.checked_cdf(&(self.from)(symbol))
+    }
+
+    fn index(&self, symbol: &Self::S) -> usize {
+        self.alphabet.index(&(self.from)(symbol))
+    }
+
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        &self.symbols[index]
+    }
+
+    fn begin_message(&self) {
+        self.alphabet.begin_message()
+    }
+
+    fn max_total_interval_width(&self) -> usize {
+        self.alphabet.max_total_interval_width()
+    }
+
+    fn update(&self, symbol: &Self::S) {
+        self.alphabet.update(&(self.from)(symbol))
+    }
+
+    fn is_static(&self) -> bool {
+        self.alphabet.is_static()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::encoder::Encoder;
+    use crate::example::{ExampleAlphabet, ExampleSymbol, ExampleSymbol::*};
+    use crate::models::AdaptiveAlphabet;
+    use crate::reference;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Letter(char);
+
+    impl Symbol for Letter {}
+
+    fn to_letter(symbol: &ExampleSymbol) -> Letter {
+        Letter(match symbol {
+            A => 'a',
+            B => 'b',
+            C => 'c',
+            Eof => '$',
+        })
+    }
+
+    fn from_letter(letter: &Letter) -> ExampleSymbol {
+        match letter.0 {
+            'a' => A,
+            'b' => B,
+            'c' => C,
+            _ => Eof,
+        }
+    }
+
+    #[test]
+    fn mapped_alphabet_encodes_identically() {
+        let mapped = MapAlphabet::new(ExampleAlphabet::new(), to_letter, from_letter);
+        assert_eq!(
+            mapped.symbols().map(|letter| letter.0).collect::<String>(),
+            "abc$"
+        );
+        assert_eq!(mapped.eof(), Letter('$'));
+
+        let letters: Vec<_> = "abacab$".chars().map(Letter).collect();
+        let symbols: Vec<_> = letters.iter().map(from_letter).collect();
+        assert!(
+            mapped
+                .encode::<_, 32>(letters)
+                .eq(ExampleAlphabet::new().encode::<_, 32>(symbols))
+        );
+    }
+
+    #[test]
+    fn mapped_alphabets_match_reference() {
+        let mapped = MapAlphabet::new(ExampleAlphabet::new(), to_letter, from_letter);
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&mapped, 50, 50, 15),
+            Ok(())
+        );
+
+        let adaptive = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof);
+        let mapped = MapAlphabet::new(adaptive, to_letter, from_letter);
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&mapped, 50, 200, 16),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,30 @@
+//! Models whose symbol distributions change as a message is coded.
+//!
+//! These are alphabets which learn from the symbols coded so far, via the
+//! [`Alphabet::update`](crate::alphabet::Alphabet::update) hook, so that data
+//! can be compressed without a pre-trained table. A [`Model`] is instead
+//! given the context of each symbol explicitly, and the encoder and decoder
+//! code with it directly (or through a [`ModelAlphabet`], which tracks the
+//! context).
+
+mod adaptive;
+mod conditional;
+mod escape;
+mod mixed;
+mod order_n;
+
+pub use adaptive::AdaptiveAlphabet;
+pub use conditional::{Model, ModelAlphabet};
+pub use escape::{EscapeModel, Escaped};
+pub use mixed::MixedModel;
+pub use order_n::OrderNModel;
+
+/// The default limit on the total count of an adaptive model, beyond which
+/// its counts are halved.
+///
+/// R must stay within a quarter of 2^precision, so models with this limit
+/// need a coder with at least 18 bits of precision; set a lower limit (with
+/// `with_max_total`) for less. [`Alphabet::validate`] checks the limit.
+///
+/// [`Alphabet::validate`]: crate::alphabet::Alphabet::validate
+pub const DEFAULT_MAX_TOTAL: usize = 1 << 16;
@@ -0,0 +1,303 @@
+use super::DEFAULT_MAX_TOTAL;
+use crate::alphabet::{Alphabet, Symbol};
+use crate::fenwick::Fenwick;
+use crate::frequency::FrequencyTable;
+use crate::lookup::SymbolLookup;
+use std::cell::RefCell;
+
+/// An adaptive order-0 model: every symbol starts with a count of one, and
+/// each time a symbol is coded its count is incremented, so the distribution
+/// converges on the frequencies in the message.
+///
+/// Counts are kept in a Fenwick tree, so looking up bounds and updating counts
+/// take O(log n) time. Counts are reset at the start of every message, so the
+/// encoder and decoder always begin from the same state.
+///
+/// When the total count exceeds a limit ([`DEFAULT_MAX_TOTAL`] unless set with
+/// [`AdaptiveAlphabet::with_max_total`]), every count is halved. This keeps R
+/// within the limit however long the message, and weights recent symbols more
+/// heavily, so the model follows changes in the distribution. The limit must
+/// be at most a quarter of 2^precision for the coder, which
+/// [`Alphabet::validate`] checks, so the default needs at least 18 bits of
+/// precision.
+pub struct AdaptiveAlphabet<S: Symbol> {
+    symbols: SymbolLookup<S>,
+    eof: S,
+    counts: RefCell<Fenwick>,
+    max_total: usize,
+}
+
+impl<S: Symbol> AdaptiveAlphabet<S> {
+    /// Construct an adaptive model over the given symbols, which must include
+    /// the EOF symbol.
+    pub fn new(symbols: Vec<S>, eof: S) -> Self {
+        assert!(symbols.contains(&eof), "Symbols must include EOF");
+        let counts = RefCell::new(Fenwick::from_counts(&vec![1; symbols.len()]));
+        Self {
+            symbols: SymbolLookup::new(symbols),
+            eof,
+            counts,
+            max_total: DEFAULT_MAX_TOTAL,
+        }
+    }
+
+    /// Halve the counts whenever their total exceeds the given limit, which
+    /// must be at least twice the number of symbols.
+    pub fn with_max_total(mut self, max_total: usize) -> Self {
+        assert!(
+            max_total >= 2 * self.symbols.len(),
+            "Limit {max_total} is too small for {} symbols",
+            self.symbols.len()
+        );
+        self.max_total = max_total;
+        self
+    }
+
+    /// Count the symbols of a corpus, as if they had been coded.
+    ///
+    /// The counts are reset when a message is coded with this alphabet, so
+    /// to reuse what was learned, [`AdaptiveAlphabet::freeze`] the model.
+    pub fn train<'a>(&self, corpus: impl IntoIterator<Item = &'a S>)
+    where
+        S: 'a,
+    {
+        for symbol in corpus {
+            self.update(symbol);
+        }
+    }
+
+    /// A static alphabet with the current counts as its interval widths.
+    ///
+    /// Unlike the model, the frozen table doesn't change as messages are
+    /// coded, and can be shared between threads.
+    pub fn freeze(&self) -> FrequencyTable<S> {
+        let counts = self.counts.borrow();
+        let widths = self
+            .symbols
+            .symbols()
+            .iter()
+            .enumerate()
+            .map(|(i, symbol)| (symbol.clone(), counts.get(i)))
+            .collect();
+        FrequencyTable::from_widths(widths, self.eof.clone())
+    }
+
+    /// The number of times the given symbol has been seen in the current
+    /// message, plus one.
+    pub fn count(&self, symbol: &S) -> usize {
+        self.counts.borrow().get(self.symbols.index_of(symbol))
+    }
+}
+
+impl<S: Symbol> Alphabet for AdaptiveAlphabet<S> {
+    type S = S;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.symbols.symbols().iter()
+    }
+
+    fn eof(&self) -> Self::S {
+        self.eof.clone()
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        self.count(symbol)
+    }
+
+    fn total_interval_width(&self) -> usize {
+        self.counts.borrow().total()
+    }
+
+    fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+        self.counts
+            .borrow()
+            .prefix_sum(self.symbols.index_of(symbol))
+    }
+
+    fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+        self.counts
+            .borrow()
+            .prefix_sum(self.symbols.index_of(symbol) + 1)
+    }
+
+    fn quantile(&self, value: usize) -> &Self::S {
+        let index = self.counts.borrow().search(value);
+        assert!(
+            index < self.symbols.len(),
+            "Value {value} is beyond the total interval width"
+        );
+        &self.symbols.symbols()[index]
+    }
+
+    fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        let index = self.symbols.position(symbol)?;
+        let counts = self.counts.borrow();
+        Some((counts.prefix_sum(index), counts.prefix_sum(index + 1)))
+    }
+
+    fn contains(&self, symbol: &Self::S) -> bool {
+        self.symbols.contains(symbol)
+    }
+
+    fn index(&self, symbol: &Self::S) -> usize {
+        self.symbols.index_of(symbol)
+    }
+
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        &self.symbols.symbols()[index]
+    }
+
+    fn begin_message(&self) {
+        *self.counts.borrow_mut() = Fenwick::from_counts(&vec![1; self.symbols.len()]);
+    }
+
+    fn max_total_interval_width(&self) -> usize {
+        self.max_total.max(self.total_interval_width())
+    }
+
+    fn update(&self, symbol: &Self::S) {
+        let index = self.symbols.index_of(symbol);
+        let mut counts = self.counts.borrow_mut();
+        counts.add(index, 1);
+        if counts.total() > self.max_total {
+            counts.halve();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::alphabet::AlphabetError;
+    use crate::decoder::Decoder;
+    use crate::encoder::{EncodeError, Encoder};
+    use crate::example::{ExampleAlphabet, ExampleSymbol::*};
+    use crate::reference;
+
+    fn adaptive() -> AdaptiveAlphabet<crate::example::ExampleSymbol> {
+        AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof)
+    }
+
+    #[test]
+    fn counts_adapt_to_symbols() {
+        let alphabet = adaptive();
+        assert_eq!(alphabet.total_interval_width(), 4);
+        alphabet.update(&C);
+        alphabet.update(&C);
+        alphabet.update(&A);
+        assert_eq!(alphabet.count(&C), 3);
+        assert_eq!(alphabet.interval_lower_bound(&C), 1 + 1 + 1);
+        assert_eq!(alphabet.interval_upper_bound(&C), 1 + 1 + 1 + 3);
+        assert_eq!(alphabet.total_interval_width(), 7);
+
+        alphabet.begin_message();
+        assert_eq!(alphabet.count(&C), 1);
+    }
+
+    #[test]
+    fn adaptive_alphabet_matches_reference() {
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&adaptive(), 100, 200, 4),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn counts_are_halved_at_limit() {
+        let alphabet = adaptive().with_max_total(10);
+        for _ in 0..6 {
+            alphabet.update(&A);
+        }
+        // 7 + 1 + 1 + 1 = 10
+        assert_eq!(alphabet.count(&A), 7);
+        alphabet.update(&B);
+        // 7 + 2 + 1 + 1 = 11, halved and rounded up
+        assert_eq!(alphabet.count(&A), 4);
+        assert_eq!(alphabet.count(&B), 1);
+        assert_eq!(alphabet.total_interval_width(), 7);
+
+        for _ in 0..1000 {
+            alphabet.update(&C);
+            assert!(alphabet.total_interval_width() <= 10);
+        }
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&adaptive().with_max_total(16), 50, 200, 13),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn limit_must_suit_precision() {
+        // The default limit is too large for 16 bits, even though the
+        // initial counts are small
+        let message: Vec<_> = [A, B, A, C]
+            .repeat(10_000)
+            .into_iter()
+            .chain([Eof])
+            .collect();
+        assert!(matches!(
+            adaptive().encode_to_vec::<_, 16>(message.clone()),
+            Err(EncodeError::InvalidAlphabet(AlphabetError::TotalTooLarge {
+                total: DEFAULT_MAX_TOTAL,
+                ..
+            }))
+        ));
+
+        let alphabet = adaptive().with_max_total(1 << 14);
+        let bits = alphabet.encode_to_vec::<_, 16>(message.clone()).unwrap();
+        let (decoded, _) = alphabet.decode_to_vec::<_, 16>(bits).unwrap();
+        assert_eq!(decoded, message[..message.len() - 1]);
+    }
+
+    #[test]
+    fn halving_follows_changing_input() {
+        // Half A then half B: a model which forgets old counts codes the B
+        // half more cheaply
+        let input: Vec<_> = std::iter::repeat_n(A, 2000)
+            .chain(std::iter::repeat_n(B, 2000))
+            .chain([Eof])
+            .collect();
+        let unlimited = adaptive().with_max_total(usize::MAX).message_cost(&input);
+        let limited = adaptive().with_max_total(64).message_cost(&input);
+        assert!(limited < unlimited / 2.0, "{limited} >= {unlimited} / 2");
+    }
+
+    #[test]
+    fn frozen_model_keeps_trained_counts() {
+        let model = adaptive();
+        model.train(&[C, C, C, A]);
+        let frozen = model.freeze();
+        assert_eq!(
+            frozen.widths().collect::<Vec<_>>(),
+            vec![(&A, 2), (&B, 1), (&C, 4), (&Eof, 1)]
+        );
+
+        // Coding with the model resets it, but not the frozen table
+        let input = vec![B, B, B, Eof];
+        let _ = model.encode::<_, 32>(input.clone()).count();
+        assert_eq!(model.count(&C), 1);
+        assert_eq!(frozen.interval_width(&C), 4);
+        assert_eq!(reference::cross_check::<_, 32>(&frozen, &input), Ok(()));
+
+        // The frozen table can be shared between threads
+        let frozen = std::sync::Arc::new(frozen);
+        let shared = frozen.clone();
+        let length = std::thread::spawn(move || shared.encode::<_, 32>(input).count())
+            .join()
+            .unwrap();
+        assert!(length > 0);
+    }
+
+    #[test]
+    fn adapts_to_skewed_input() {
+        // A message which is nearly all C, the least likely non-EOF symbol in
+        // the static example alphabet, is coded more compactly by the adaptive
+        // alphabet
+        let input: Vec<_> = std::iter::repeat_n(C, 200).chain([Eof]).collect();
+        let static_length = ExampleAlphabet::new()
+            .encode::<_, 32>(input.clone())
+            .count();
+        let adaptive_length = adaptive().encode::<_, 32>(input).count();
+        assert!(adaptive_length * 10 < static_length);
+    }
+}
@@ -0,0 +1,312 @@
+use crate::alphabet::{Alphabet, AlphabetRef, Symbol};
+use std::cell::RefCell;
+use std::sync::Arc;
+
+/// A source of symbol probabilities conditioned on the symbols preceding them.
+///
+/// Where an [`Alphabet`] has to keep track of a message's context itself, a
+/// model is given the context explicitly, so that a model such as a Markov
+/// chain can be a plain function of the context.
+///
+/// A model which is [`Clone`] can be coded with directly, as
+/// [`Encoder`](crate::encoder::Encoder) and
+/// [`Decoder`](crate::decoder::Decoder) are implemented for it as they are
+/// for alphabets. Each message is coded with a [`ModelAlphabet`] over a clone
+/// of the model, which keeps the context; a model which is expensive to clone
+/// can be wrapped in one to code with instead.
+pub trait Model {
+    type S: Symbol;
+
+    /// All symbols which can be coded, in a fixed order, including EOF.
+    fn symbols(&self) -> impl Iterator<Item = &Self::S>;
+
+    /// The symbol which terminates a message.
+    fn eof(&self) -> Self::S;
+
+    /// The number of preceding symbols the model conditions on.
+    fn order(&self) -> usize;
+
+    /// The weight of a symbol given its context: the (up to)
+    /// [`Model::order`] symbols preceding it, oldest first.
+    ///
+    /// P(symbol | context) is this weight divided by the sum of the weights of
+    /// all symbols in the same context.
+    fn interval_width(&self, symbol: &Self::S, context: &[Self::S]) -> usize;
+
+    /// Called after a symbol is coded in the given context, for models which
+    /// learn as they go. Does nothing by default.
+    fn update(&self, _symbol: &Self::S, _context: &[Self::S]) {}
+}
+
+/// An alphabet which codes symbols with the probabilities of a [`Model`],
+/// keeping track of the context of each symbol.
+///
+/// The context is reset at the start of every message, and is advanced as
+/// symbols are coded through [`Alphabet::update`], so the encoder and decoder
+/// see the same contexts. The bounds of every symbol in the current context
+/// are tabulated with one pass over the model's weights when they're first
+/// needed, after which [`Alphabet::cdf`] and [`Alphabet::quantile`] look
+/// them up, with a binary search for the quantile.
+pub struct ModelAlphabet<M: Model> {
+    model: M,
+    /// The model's symbols, so that each can be found by its position
+    symbols: Vec<M::S>,
+    context: RefCell<Vec<M::S>>,
+    /// The bounds in the current context, once tabulated: bounds[i] is the
+    /// lower bound of the i-th symbol, and bounds[n] is R
+    bounds: RefCell<Option<Vec<usize>>>,
+}
+
+impl<M: Model> ModelAlphabet<M> {
+    pub fn new(model: M) -> Self {
+        Self {
+            symbols: model.symbols().cloned().collect(),
+            model,
+            context: RefCell::new(Vec::new()),
+            bounds: RefCell::new(None),
+        }
+    }
+
+    /// An alphabet over a clone of the model, for a coder to own.
+    pub(crate) fn shared<'a>(model: &M) -> AlphabetRef<'a, Self>
+    where
+        M: Clone,
+    {
+        AlphabetRef::Shared(Arc::new(Self::new(model.clone())))
+    }
+
+    /// The underlying model.
+    pub fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// The context of the next symbol to be coded, oldest first.
+    pub fn context(&self) -> Vec<M::S> {
+        self.context.borrow().clone()
+    }
+
+    /// Look something up in the bounds of the current context, tabulating
+    /// them first if need be.
+    fn with_bounds<T>(&self, f: impl FnOnce(&[usize]) -> T) -> T {
+        let mut bounds = self.bounds.borrow_mut();
+        let bounds = bounds.get_or_insert_with(|| {
+            let context = self.context.borrow();
+            let mut sum = 0;
+            let mut bounds = Vec::with_capacity(self.symbols.len() + 1);
+            bounds.push(sum);
+            for symbol in &self.symbols {
+                sum += self.model.interval_width(symbol, &context);
+                bounds.push(sum);
+            }
+            bounds
+        });
+        f(bounds)
+    }
+}
+
+impl<M: Model> Alphabet for ModelAlphabet<M> {
+    type S = M::S;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.symbols.iter()
+    }
+
+    fn eof(&self) -> Self::S {
+        self.model.eof()
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        self.model.interval_width(symbol, &self.context.borrow())
+    }
+
+    fn total_interval_width(&self) -> usize {
+        self.with_bounds(|bounds| bounds[bounds.len() - 1])
+    }
+
+    fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+        self.cdf(symbol).0
+    }
+
+    fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+        self.cdf(symbol).1
+    }
+
+    fn cdf(&self, symbol: &Self::S) -> (usize, usize) {
+        let index = self.index(symbol);
+        self.with_bounds(|bounds| (bounds[index], bounds[index + 1]))
+    }
+
+    fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        let index = self.symbols.iter().position(|s| s == symbol)?;
+        Some(self.with_bounds(|bounds| (bounds[index], bounds[index + 1])))
+    }
+
+    fn quantile(&self, value: usize) -> &Self::S {
+        // The last symbol whose lower bound is at most the value, which skips
+        // any empty intervals at the same bound
+        let index = self.with_bounds(|bounds| {
+            let total = bounds[bounds.len() - 1];
+            assert!(
+                value < total,
+                "Value {value} is beyond the total interval width {total}"
+            );
+            bounds.partition_point(|&bound| bound <= value) - 1
+        });
+        &self.symbols[index]
+    }
+
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        &self.symbols[index]
+    }
+
+    fn begin_message(&self) {
+        self.context.borrow_mut().clear();
+        self.bounds.take();
+    }
+
+    fn update(&self, symbol: &Self::S) {
+        let context = &mut *self.context.borrow_mut();
+        self.model.update(symbol, context);
+        context.push(symbol.clone());
+        if context.len() > self.model.order() {
+            context.remove(0);
+        }
+        self.bounds.take();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoder::{Decoder, DecoderEvent};
+    use crate::encoder::{Coder, Encoder};
+    use crate::example::{ExampleSymbol, ExampleSymbol::*};
+    use crate::models::AdaptiveAlphabet;
+    use crate::reference;
+
+    /// A Markov chain in which each symbol is most likely followed by the next
+    /// one in the cycle A, B, C
+    #[derive(Clone)]
+    struct Cycle;
+
+    impl Model for Cycle {
+        type S = ExampleSymbol;
+
+        fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+            [A, B, C, Eof].iter()
+        }
+
+        fn eof(&self) -> Self::S {
+            Eof
+        }
+
+        fn order(&self) -> usize {
+            1
+        }
+
+        fn interval_width(&self, symbol: &Self::S, context: &[Self::S]) -> usize {
+            let expected = match context {
+                [A] => B,
+                [B] => C,
+                _ => A,
+            };
+            if *symbol == expected { 100 } else { 1 }
+        }
+    }
+
+    #[test]
+    fn widths_follow_context() {
+        let alphabet = ModelAlphabet::new(Cycle);
+        alphabet.begin_message();
+        assert_eq!(alphabet.interval_width(&A), 100);
+        alphabet.update(&A);
+        alphabet.update(&B);
+        assert_eq!(alphabet.context(), vec![B]);
+        assert_eq!(alphabet.interval_width(&C), 100);
+        assert_eq!(alphabet.total_interval_width(), 103);
+        assert_eq!(alphabet.interval_lower_bound(&C), 2);
+        alphabet.begin_message();
+        assert_eq!(alphabet.context(), vec![]);
+    }
+
+    #[test]
+    fn model_alphabet_matches_reference() {
+        let alphabet = ModelAlphabet::new(Cycle);
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&alphabet, 50, 100, 18),
+            Ok(())
+        );
+
+        let input: Vec<_> = [A, B, C]
+            .into_iter()
+            .cycle()
+            .take(300)
+            .chain([Eof])
+            .collect();
+        let context_free_length = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof)
+            .encode::<_, 32>(input.clone())
+            .count();
+        let length = alphabet.encode::<_, 32>(input).count();
+        assert!(length * 10 < context_free_length);
+    }
+
+    #[test]
+    fn bounds_follow_context() {
+        let alphabet = ModelAlphabet::new(Cycle);
+        alphabet.begin_message();
+        for context in [None, Some(A), Some(B), Some(C)] {
+            if let Some(symbol) = context {
+                alphabet.update(&symbol);
+            }
+            let mut lower = 0;
+            for symbol in [A, B, C, Eof] {
+                let width = alphabet.interval_width(&symbol);
+                assert_eq!(alphabet.cdf(&symbol), (lower, lower + width));
+                assert_eq!(alphabet.quantile(lower), &symbol);
+                assert_eq!(alphabet.quantile(lower + width - 1), &symbol);
+                lower += width;
+            }
+            assert_eq!(alphabet.total_interval_width(), lower);
+        }
+    }
+
+    #[test]
+    fn code_with_model() {
+        let symbols = [A, B, C, C, A, B];
+        let input: Vec<_> = symbols.into_iter().chain([Eof]).collect();
+        let bits = Cycle.encode_to_vec::<_, 32>(input.clone()).unwrap();
+        assert_eq!(
+            bits,
+            ModelAlphabet::new(Cycle)
+                .encode_to_vec::<_, 32>(input.clone())
+                .unwrap()
+        );
+        let (decoded, _) = Cycle.decode_to_vec::<_, 32>(bits).unwrap();
+        assert_eq!(decoded, symbols);
+
+        // Each message starts over with a fresh context
+        let messages = [&input[..], &input[..]].concat();
+        let bits: Vec<_> = Cycle
+            .encode_all::<_, 32>(messages.clone())
+            .map(Result::unwrap)
+            .collect();
+        let decoded: Vec<_> = Cycle
+            .decode_all::<_, 32>(bits)
+            .filter_map(|event| match event.unwrap() {
+                DecoderEvent::DecodedSymbol(symbol) => Some(symbol),
+                DecoderEvent::MessageLength(_) => None,
+            })
+            .collect();
+        assert_eq!(decoded, messages);
+
+        for coder in [
+            Coder::Arithmetic,
+            Coder::Range,
+            Coder::RangeWithCarry,
+            Coder::Arcode,
+        ] {
+            let bytes = Cycle.encode_with::<_, 32>(input.clone(), coder).unwrap();
+            assert_eq!(Cycle.decode_with::<32>(&bytes, coder).unwrap(), symbols);
+        }
+    }
+}
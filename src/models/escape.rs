@@ -0,0 +1,353 @@
+use super::DEFAULT_MAX_TOTAL;
+use crate::alphabet::{Alphabet, AlphabetError, Symbol};
+use crate::fenwick::Fenwick;
+use crate::lookup::SymbolLookup;
+use std::cell::RefCell;
+
+/// A symbol of an [`EscapeModel`]: either a symbol of the underlying alphabet,
+/// or the escape which precedes a symbol that hasn't been seen before.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Escaped<S> {
+    Symbol(S),
+    Escape,
+}
+
+impl<S: Symbol> Symbol for Escaped<S> {}
+
+/// An adaptive model which only gives probability to symbols it has seen,
+/// reserving an escape interval for the rest.
+///
+/// Every symbol starts out unseen, with a width of zero. The first occurrence
+/// of a symbol in a message is coded as an escape followed by the symbol,
+/// which is coded with a uniform distribution over the symbols still unseen.
+/// After that, the symbol is counted as in
+/// [`AdaptiveAlphabet`](super::AdaptiveAlphabet). The escape is counted once
+/// per new symbol, and dropped once every symbol has been seen.
+///
+/// Unlike a model which gives every symbol an initial count, this wastes no
+/// probability on symbols which never occur, which suits large alphabets of
+/// which a message uses only a few symbols. Use [`EscapeModel::escape`] to
+/// insert the escapes into a message, and [`EscapeModel::unescape`] to remove
+/// them after decoding.
+pub struct EscapeModel<S: Symbol> {
+    symbols: SymbolLookup<Escaped<S>>,
+    eof: S,
+    state: RefCell<EscapeState>,
+    max_total: usize,
+}
+
+struct EscapeState {
+    /// Counts of the escape (at position 0) and of each symbol seen
+    learned: Fenwick,
+    /// A width of one for each symbol not yet seen
+    fallback: Fenwick,
+    /// Whether the next symbol follows an escape
+    escaped: bool,
+}
+
+impl EscapeState {
+    fn new(symbols: usize) -> Self {
+        let mut fallback = vec![1; symbols + 1];
+        fallback[0] = 0;
+        let mut learned = vec![0; symbols + 1];
+        learned[0] = 1;
+        Self {
+            learned: Fenwick::from_counts(&learned),
+            fallback: Fenwick::from_counts(&fallback),
+            escaped: false,
+        }
+    }
+
+    fn table(&self) -> &Fenwick {
+        if self.escaped {
+            &self.fallback
+        } else {
+            &self.learned
+        }
+    }
+}
+
+impl<S: Symbol> EscapeModel<S> {
+    /// Construct a model over the given symbols, which must include the EOF
+    /// symbol.
+    pub fn new(symbols: Vec<S>, eof: S) -> Self {
+        assert!(symbols.contains(&eof), "Symbols must include EOF");
+        let state = RefCell::new(EscapeState::new(symbols.len()));
+        Self {
+            symbols: SymbolLookup::new(
+                [Escaped::Escape]
+                    .into_iter()
+                    .chain(symbols.into_iter().map(Escaped::Symbol))
+                    .collect(),
+            ),
+            eof,
+            state,
+            max_total: DEFAULT_MAX_TOTAL,
+        }
+    }
+
+    /// Halve the counts whenever their total exceeds the given limit, which
+    /// must be at least twice the number of symbols (plus one, for the
+    /// escape).
+    pub fn with_max_total(mut self, max_total: usize) -> Self {
+        assert!(
+            max_total >= 2 * self.symbols.len(),
+            "Limit {max_total} is too small for {} symbols",
+            self.symbols.len()
+        );
+        self.max_total = max_total;
+        self
+    }
+
+    /// The given message with an escape inserted before the first occurrence
+    /// of each symbol, ready to encode.
+    pub fn escape<I: IntoIterator<Item = S>>(&self, message: I) -> Vec<Escaped<S>> {
+        let mut seen = vec![false; self.symbols.len()];
+        let mut escaped = Vec::new();
+        for symbol in message {
+            let symbol = Escaped::Symbol(symbol);
+            let index = self.symbols.index_of(&symbol);
+            if !seen[index] {
+                seen[index] = true;
+                escaped.push(Escaped::Escape);
+            }
+            escaped.push(symbol);
+        }
+        escaped
+    }
+
+    /// The symbols of a decoded message, without its escapes.
+    pub fn unescape<I: IntoIterator<Item = Escaped<S>>>(&self, message: I) -> Vec<S> {
+        message
+            .into_iter()
+            .filter_map(|symbol| match symbol {
+                Escaped::Symbol(symbol) => Some(symbol),
+                Escaped::Escape => None,
+            })
+            .collect()
+    }
+
+    /// The number of times the given symbol has been seen in the current
+    /// message (or less, once the counts have been halved).
+    pub fn count(&self, symbol: &S) -> usize {
+        let index = self.symbols.index_of(&Escaped::Symbol(symbol.clone()));
+        self.state.borrow().learned.get(index)
+    }
+}
+
+/// A view of the symbols of an [`EscapeModel`] as a uniform alphabet, for
+/// validation.
+struct Fallback<'a, S: Symbol>(&'a EscapeModel<S>);
+
+impl<S: Symbol> Alphabet for Fallback<'_, S> {
+    type S = S;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.0
+            .symbols
+            .symbols()
+            .iter()
+            .filter_map(|symbol| match symbol {
+                Escaped::Symbol(symbol) => Some(symbol),
+                Escaped::Escape => None,
+            })
+    }
+
+    fn eof(&self) -> Self::S {
+        self.0.eof.clone()
+    }
+
+    fn interval_width(&self, _symbol: &Self::S) -> usize {
+        1
+    }
+
+    fn max_total_interval_width(&self) -> usize {
+        self.0.max_total.max(self.total_interval_width())
+    }
+}
+
+impl<S: Symbol> Alphabet for EscapeModel<S> {
+    type S = Escaped<S>;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.symbols.symbols().iter()
+    }
+
+    fn eof(&self) -> Self::S {
+        Escaped::Symbol(self.eof.clone())
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        let index = self.symbols.index_of(symbol);
+        self.state.borrow().table().get(index)
+    }
+
+    fn total_interval_width(&self) -> usize {
+        self.state.borrow().table().total()
+    }
+
+    fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+        let index = self.symbols.index_of(symbol);
+        self.state.borrow().table().prefix_sum(index)
+    }
+
+    fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+        let index = self.symbols.index_of(symbol);
+        self.state.borrow().table().prefix_sum(index + 1)
+    }
+
+    fn quantile(&self, value: usize) -> &Self::S {
+        let index = self.state.borrow().table().search(value);
+        assert!(
+            index < self.symbols.len(),
+            "Value {value} is beyond the total interval width"
+        );
+        &self.symbols.symbols()[index]
+    }
+
+    fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        let index = self.symbols.position(symbol)?;
+        let state = self.state.borrow();
+        let table = state.table();
+        Some((table.prefix_sum(index), table.prefix_sum(index + 1)))
+    }
+
+    fn contains(&self, symbol: &Self::S) -> bool {
+        self.symbols.contains(symbol)
+    }
+
+    fn index(&self, symbol: &Self::S) -> usize {
+        self.symbols.index_of(symbol)
+    }
+
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        &self.symbols.symbols()[index]
+    }
+
+    fn begin_message(&self) {
+        *self.state.borrow_mut() = EscapeState::new(self.symbols.len() - 1);
+    }
+
+    fn update(&self, symbol: &Self::S) {
+        let index = self.symbols.index_of(symbol);
+        let state = &mut *self.state.borrow_mut();
+        match symbol {
+            Escaped::Escape => {
+                state.escaped = true;
+                return;
+            }
+            Escaped::Symbol(_) if state.escaped => {
+                state.escaped = false;
+                state.fallback.set(index, 0);
+                state.learned.add(0, 1);
+                if state.fallback.total() == 0 {
+                    state.learned.set(0, 0);
+                }
+            }
+            Escaped::Symbol(_) => {}
+        }
+
+        state.learned.add(index, 1);
+        if state.learned.total() > self.max_total {
+            state.learned.halve();
+        }
+    }
+
+    /// Unseen symbols have no width until they are escaped to, so check that
+    /// every symbol could be coded after an escape instead.
+    fn validate(&self, bits_of_precision: u32) -> Result<(), AlphabetError> {
+        Fallback(self).validate(bits_of_precision)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoder::{Decoder, DecoderEvent};
+    use crate::encoder::Encoder;
+    use crate::models::AdaptiveAlphabet;
+    use crate::reference;
+
+    fn words() -> EscapeModel<String> {
+        EscapeModel::new((0..1000).map(|i| i.to_string()).collect(), "0".into())
+    }
+
+    fn message(numbers: &[usize]) -> Vec<String> {
+        numbers.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn escapes_precede_new_symbols() {
+        use Escaped::*;
+        let model = words();
+        let escaped = model.escape(message(&[7, 7, 9, 0]));
+        assert_eq!(
+            escaped,
+            vec![
+                Escape,
+                Symbol("7".into()),
+                Symbol("7".into()),
+                Escape,
+                Symbol("9".into()),
+                Escape,
+                Symbol("0".into())
+            ]
+        );
+        assert_eq!(model.unescape(escaped), message(&[7, 7, 9, 0]));
+    }
+
+    #[test]
+    fn escape_falls_back_to_unseen_symbols() {
+        use Escaped::*;
+        let model = EscapeModel::new(["a", "b", "c"].map(String::from).to_vec(), "c".into());
+        assert_eq!(model.total_interval_width(), 1);
+        assert_eq!(model.interval_width(&Symbol("a".into())), 0);
+
+        model.update(&Escape);
+        assert_eq!(model.total_interval_width(), 3);
+        model.update(&Symbol("a".into()));
+        assert_eq!(model.count(&"a".into()), 1);
+        assert_eq!(model.interval_width(&Escape), 2);
+
+        // Only b and c remain to be escaped to
+        model.update(&Escape);
+        assert_eq!(model.interval_width(&Symbol("a".into())), 0);
+        assert_eq!(model.total_interval_width(), 2);
+        model.update(&Symbol("b".into()));
+        model.update(&Escape);
+        model.update(&Symbol("c".into()));
+
+        // Every symbol has been seen, so the escape is dropped
+        assert_eq!(model.interval_width(&Escape), 0);
+        assert_eq!(model.total_interval_width(), 3);
+        assert_eq!(model.validate(32), Ok(()));
+    }
+
+    #[test]
+    fn round_trip_escaped_message() {
+        let model = words();
+        let numbers: Vec<_> = (0..300).map(|i| [17, 512, 999, 17, 4][i % 5]).collect();
+        let input = model.escape(message(&numbers).into_iter().chain(["0".into()]));
+        assert_eq!(reference::cross_check::<_, 32>(&model, &input), Ok(()));
+
+        let bits: Vec<_> = model
+            .encode::<_, 32>(input)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let decoded = model.unescape(model.decode::<_, 32>(bits.clone()).filter_map(|event| {
+            match event.unwrap() {
+                DecoderEvent::DecodedSymbol(symbol) => Some(symbol),
+                _ => None,
+            }
+        }));
+        assert_eq!(decoded[..numbers.len()], message(&numbers));
+
+        // Counting every one of the 1000 symbols from the start costs more
+        let adaptive =
+            AdaptiveAlphabet::new((0..1000).map(|i| i.to_string()).collect(), "0".into());
+        let adaptive_length = adaptive
+            .encode::<_, 32>(message(&numbers).into_iter().chain(["0".into()]))
+            .count();
+        assert!(bits.len() < adaptive_length, "{} bits", bits.len());
+    }
+}
@@ -0,0 +1,304 @@
+use crate::alphabet::Alphabet;
+use crate::lookup::SymbolLookup;
+use std::cell::RefCell;
+
+/// The total interval width of a mixed distribution.
+const RESOLUTION: usize = 1 << 16;
+
+/// The smallest weight a model can have, so that a model which predicted
+/// badly for a while can still regain its influence.
+const MIN_WEIGHT: f64 = 1e-4;
+
+/// A model which mixes the predictions of several models over the same
+/// symbols, such as models of different orders.
+///
+/// The probability of a symbol is the weighted average of the probabilities
+/// the models give it, quantized to a total interval width of at most 2^16.
+/// By default the weights start out equal and adapt as symbols are coded: each
+/// model's weight is multiplied by the probability it gave the symbol, so the
+/// models which predict the message best come to dominate the mix.
+///
+/// Every model is updated with every symbol, and the weights are reset at the
+/// start of every message. Mixing takes O(n) time per model for each symbol
+/// coded.
+pub struct MixedModel<A: Alphabet> {
+    models: Vec<A>,
+    symbols: SymbolLookup<A::S>,
+    initial_weights: Vec<f64>,
+    learning_rate: f64,
+    state: RefCell<MixState>,
+}
+
+struct MixState {
+    weights: Vec<f64>,
+    /// The bounds of the mixed distribution, computed when first needed after
+    /// each update
+    bounds: Option<Vec<usize>>,
+}
+
+impl<A: Alphabet> MixedModel<A> {
+    /// Mix the given models, which must have the same symbols in the same
+    /// order and the same EOF symbol.
+    pub fn new(models: Vec<A>) -> Self {
+        assert!(!models.is_empty(), "No models to mix");
+        let symbols: Vec<A::S> = models[0].symbols().cloned().collect();
+        let eof = models[0].eof();
+        for model in &models {
+            assert!(
+                model.symbols().eq(symbols.iter()) && model.eof() == eof,
+                "Models have different symbols"
+            );
+        }
+        assert!(
+            symbols.len() <= RESOLUTION / 2,
+            "Too many symbols to mix: {}",
+            symbols.len()
+        );
+
+        let weights = vec![1.0 / models.len() as f64; models.len()];
+        Self {
+            models,
+            symbols: SymbolLookup::new(symbols),
+            initial_weights: weights.clone(),
+            learning_rate: 1.0,
+            state: RefCell::new(MixState {
+                weights,
+                bounds: None,
+            }),
+        }
+    }
+
+    /// Start each message with the given weights, one per model, instead of
+    /// equal weights. The weights are normalized to sum to one.
+    pub fn with_weights(mut self, weights: Vec<f64>) -> Self {
+        assert_eq!(
+            weights.len(),
+            self.models.len(),
+            "Expected one weight per model"
+        );
+        assert!(
+            weights.iter().all(|&w| w.is_finite() && w >= 0.0) && weights.iter().sum::<f64>() > 0.0,
+            "Invalid weights {weights:?}"
+        );
+        let sum: f64 = weights.iter().sum();
+        self.initial_weights = weights.iter().map(|w| w / sum).collect();
+        self.state.get_mut().weights = self.initial_weights.clone();
+        self.state.get_mut().bounds = None;
+        self
+    }
+
+    /// Set how quickly the weights adapt: each model's weight is multiplied by
+    /// the probability it gave each symbol, raised to this power. A rate of 0
+    /// keeps the weights fixed, and the default is 1.
+    pub fn with_learning_rate(mut self, learning_rate: f64) -> Self {
+        assert!(
+            learning_rate.is_finite() && learning_rate >= 0.0,
+            "Invalid learning rate {learning_rate}"
+        );
+        self.learning_rate = learning_rate;
+        self
+    }
+
+    /// The models being mixed.
+    pub fn models(&self) -> &[A] {
+        &self.models
+    }
+
+    /// The current weight of each model, which sum to one.
+    pub fn weights(&self) -> Vec<f64> {
+        self.state.borrow().weights.clone()
+    }
+
+    /// Apply a function to the bounds of the mixed distribution, computing
+    /// them if necessary.
+    fn with_bounds<R>(&self, f: impl FnOnce(&[usize]) -> R) -> R {
+        let state = &mut *self.state.borrow_mut();
+        let weights = &state.weights;
+        let bounds = state.bounds.get_or_insert_with(|| {
+            let totals: Vec<f64> = self
+                .models
+                .iter()
+                .map(|model| model.total_interval_width() as f64)
+                .collect();
+            // Every symbol gets a width of at least one, so it can be coded
+            let spare = (RESOLUTION - self.symbols.len()) as f64;
+            let mut bounds = Vec::with_capacity(self.symbols.len() + 1);
+            let mut sum = 0;
+            bounds.push(sum);
+            for symbol in self.symbols.symbols() {
+                let probability: f64 = self
+                    .models
+                    .iter()
+                    .zip(weights)
+                    .zip(&totals)
+                    .map(|((model, weight), total)| {
+                        weight * model.interval_width(symbol) as f64 / total
+                    })
+                    .sum();
+                sum += 1 + (probability * spare) as usize;
+                bounds.push(sum);
+            }
+            bounds
+        });
+        f(bounds)
+    }
+}
+
+impl<A: Alphabet> Alphabet for MixedModel<A> {
+    type S = A::S;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.symbols.symbols().iter()
+    }
+
+    fn eof(&self) -> Self::S {
+        self.models[0].eof()
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        let index = self.symbols.index_of(symbol);
+        self.with_bounds(|bounds| bounds[index + 1] - bounds[index])
+    }
+
+    fn total_interval_width(&self) -> usize {
+        self.with_bounds(|bounds| bounds[bounds.len() - 1])
+    }
+
+    fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+        let index = self.symbols.index_of(symbol);
+        self.with_bounds(|bounds| bounds[index])
+    }
+
+    fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+        let index = self.symbols.index_of(symbol);
+        self.with_bounds(|bounds| bounds[index + 1])
+    }
+
+    fn quantile(&self, value: usize) -> &Self::S {
+        let index = self.with_bounds(|bounds| bounds.partition_point(|&bound| bound <= value) - 1);
+        assert!(
+            index < self.symbols.len(),
+            "Value {value} is beyond the total interval width"
+        );
+        &self.symbols.symbols()[index]
+    }
+
+    fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        let index = self.symbols.position(symbol)?;
+        Some(self.with_bounds(|bounds| (bounds[index], bounds[index + 1])))
+    }
+
+    fn contains(&self, symbol: &Self::S) -> bool {
+        self.symbols.contains(symbol)
+    }
+
+    fn index(&self, symbol: &Self::S) -> usize {
+        self.symbols.index_of(symbol)
+    }
+
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        &self.symbols.symbols()[index]
+    }
+
+    fn begin_message(&self) {
+        for model in &self.models {
+            model.begin_message();
+        }
+        let mut state = self.state.borrow_mut();
+        state.weights = self.initial_weights.clone();
+        state.bounds = None;
+    }
+
+    fn update(&self, symbol: &Self::S) {
+        let mut state = self.state.borrow_mut();
+        if self.learning_rate > 0.0 {
+            for (model, weight) in self.models.iter().zip(&mut state.weights) {
+                let probability =
+                    model.interval_width(symbol) as f64 / model.total_interval_width() as f64;
+                *weight *= probability.powf(self.learning_rate);
+            }
+            let sum: f64 = state.weights.iter().sum();
+            for weight in &mut state.weights {
+                *weight = if sum > 0.0 { *weight / sum } else { 0.0 }.max(MIN_WEIGHT);
+            }
+            let sum: f64 = state.weights.iter().sum();
+            for weight in &mut state.weights {
+                *weight /= sum;
+            }
+        }
+        state.bounds = None;
+
+        for model in &self.models {
+            model.update(symbol);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::encoder::Encoder;
+    use crate::example::{ExampleSymbol, ExampleSymbol::*};
+    use crate::frequency::FrequencyTable;
+    use crate::models::{AdaptiveAlphabet, OrderNModel};
+    use crate::reference;
+
+    fn orders_0_and_1() -> MixedModel<OrderNModel<ExampleSymbol>> {
+        MixedModel::new(vec![
+            OrderNModel::new(vec![A, B, C, Eof], Eof, 0),
+            OrderNModel::new(vec![A, B, C, Eof], Eof, 1),
+        ])
+    }
+
+    #[test]
+    fn fixed_weights_average_probabilities() {
+        let model = MixedModel::new(vec![
+            FrequencyTable::from_widths(vec![(A, 3), (B, 1), (Eof, 0)], Eof),
+            FrequencyTable::from_widths(vec![(A, 1), (B, 1), (Eof, 2)], Eof),
+        ])
+        .with_weights(vec![3.0, 1.0])
+        .with_learning_rate(0.0);
+        assert!(model.total_interval_width() <= RESOLUTION);
+        // 3/4 * 3/4 + 1/4 * 1/4 = 5/8 of the interval, and so on
+        assert_eq!(model.interval_width(&A), 1 + 40958);
+        assert_eq!(model.interval_width(&B), 1 + 16383);
+        assert_eq!(model.interval_width(&Eof), 1 + 8191);
+
+        model.update(&Eof);
+        assert_eq!(model.weights(), vec![0.75, 0.25]);
+    }
+
+    #[test]
+    fn weights_favor_better_model() {
+        let model = orders_0_and_1();
+        model.begin_message();
+        for symbol in [A, B, C].into_iter().cycle().take(30) {
+            model.update(&symbol);
+        }
+        let weights = model.weights();
+        assert!(weights[1] > 0.99, "{weights:?}");
+
+        model.begin_message();
+        assert_eq!(model.weights(), vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn mixing_matches_best_model() {
+        let input: Vec<_> = [A, B, C]
+            .into_iter()
+            .cycle()
+            .take(600)
+            .chain([Eof])
+            .collect();
+        let order_0_length = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof)
+            .encode::<_, 32>(input.clone())
+            .count();
+        let mixed_length = orders_0_and_1().encode::<_, 32>(input).count();
+        assert!(mixed_length * 10 < order_0_length);
+
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&orders_0_and_1(), 50, 200, 19),
+            Ok(())
+        );
+    }
+}
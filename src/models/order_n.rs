@@ -0,0 +1,255 @@
+use super::DEFAULT_MAX_TOTAL;
+use crate::alphabet::{Alphabet, Symbol};
+use crate::fenwick::Fenwick;
+use crate::lookup::SymbolLookup;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+
+/// An adaptive order-N context model: the distribution of each symbol depends
+/// on the N symbols preceding it.
+///
+/// Every context (sequence of up to N preceding symbols) has its own table of
+/// counts, which starts out uniform and is incremented for each symbol coded
+/// in that context, as in [`AdaptiveAlphabet`](super::AdaptiveAlphabet). The
+/// first symbols of a message use the shorter contexts available to them.
+///
+/// The context and all tables are reset at the start of every message, and
+/// the encoder and decoder update them identically through
+/// [`Alphabet::update`], so they stay in sync. The counts of a context are
+/// halved when their total exceeds a limit, as in `AdaptiveAlphabet`.
+pub struct OrderNModel<S: Symbol> {
+    symbols: SymbolLookup<S>,
+    eof: S,
+    order: usize,
+    uniform: Fenwick,
+    state: RefCell<ContextState>,
+    max_total: usize,
+}
+
+#[derive(Default)]
+struct ContextState {
+    /// Positions of the (up to) N most recent symbols, oldest first
+    context: VecDeque<usize>,
+    tables: Vec<Fenwick>,
+    table_for_context: HashMap<Vec<usize>, usize>,
+    /// The table for the current context, if it has been seen before
+    current: Option<usize>,
+}
+
+impl<S: Symbol> OrderNModel<S> {
+    /// Construct a model over the given symbols (which must include the EOF
+    /// symbol) conditioned on the previous `order` symbols.
+    pub fn new(symbols: Vec<S>, eof: S, order: usize) -> Self {
+        assert!(symbols.contains(&eof), "Symbols must include EOF");
+        Self {
+            uniform: Fenwick::from_counts(&vec![1; symbols.len()]),
+            symbols: SymbolLookup::new(symbols),
+            eof,
+            order,
+            state: RefCell::default(),
+            max_total: DEFAULT_MAX_TOTAL,
+        }
+    }
+
+    /// Halve the counts of a context whenever their total exceeds the given
+    /// limit, which must be at least twice the number of symbols.
+    pub fn with_max_total(mut self, max_total: usize) -> Self {
+        assert!(
+            max_total >= 2 * self.symbols.len(),
+            "Limit {max_total} is too small for {} symbols",
+            self.symbols.len()
+        );
+        self.max_total = max_total;
+        self
+    }
+
+    /// The number of symbols of context the model conditions on.
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// The number of distinct contexts seen so far in the current message.
+    pub fn contexts(&self) -> usize {
+        self.state.borrow().tables.len()
+    }
+
+    /// Apply a function to the table of counts for the current context.
+    fn with_table<R>(&self, f: impl FnOnce(&Fenwick) -> R) -> R {
+        let state = self.state.borrow();
+        match state.current {
+            Some(table) => f(&state.tables[table]),
+            None => f(&self.uniform),
+        }
+    }
+}
+
+impl<S: Symbol> Alphabet for OrderNModel<S> {
+    type S = S;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.symbols.symbols().iter()
+    }
+
+    fn eof(&self) -> Self::S {
+        self.eof.clone()
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        let index = self.symbols.index_of(symbol);
+        self.with_table(|table| table.get(index))
+    }
+
+    fn total_interval_width(&self) -> usize {
+        self.with_table(|table| table.total())
+    }
+
+    fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+        let index = self.symbols.index_of(symbol);
+        self.with_table(|table| table.prefix_sum(index))
+    }
+
+    fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+        let index = self.symbols.index_of(symbol);
+        self.with_table(|table| table.prefix_sum(index + 1))
+    }
+
+    fn quantile(&self, value: usize) -> &Self::S {
+        let index = self.with_table(|table| table.search(value));
+        assert!(
+            index < self.symbols.len(),
+            "Value {value} is beyond the total interval width"
+        );
+        &self.symbols.symbols()[index]
+    }
+
+    fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        let index = self.symbols.position(symbol)?;
+        Some(self.with_table(|table| (table.prefix_sum(index), table.prefix_sum(index + 1))))
+    }
+
+    fn contains(&self, symbol: &Self::S) -> bool {
+        self.symbols.contains(symbol)
+    }
+
+    fn index(&self, symbol: &Self::S) -> usize {
+        self.symbols.index_of(symbol)
+    }
+
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        &self.symbols.symbols()[index]
+    }
+
+    fn begin_message(&self) {
+        *self.state.borrow_mut() = ContextState::default();
+    }
+
+    fn max_total_interval_width(&self) -> usize {
+        self.max_total.max(self.total_interval_width())
+    }
+
+    fn update(&self, symbol: &Self::S) {
+        let index = self.symbols.index_of(symbol);
+        let state = &mut *self.state.borrow_mut();
+
+        // Count the symbol in the table for its context
+        let table = match state.current {
+            Some(table) => table,
+            None => {
+                state.tables.push(self.uniform.clone());
+                let table = state.tables.len() - 1;
+                let context = state.context.iter().copied().collect();
+                state.table_for_context.insert(context, table);
+                table
+            }
+        };
+        let counts = &mut state.tables[table];
+        counts.add(index, 1);
+        if counts.total() > self.max_total {
+            counts.halve();
+        }
+
+        // Move on to the context of the next symbol
+        state.context.push_back(index);
+        if state.context.len() > self.order {
+            state.context.pop_front();
+        }
+        let context: Vec<usize> = state.context.iter().copied().collect();
+        state.current = state.table_for_context.get(&context).copied();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::encoder::Encoder;
+    use crate::example::{ExampleSymbol, ExampleSymbol::*};
+    use crate::models::AdaptiveAlphabet;
+    use crate::reference;
+
+    fn model(order: usize) -> OrderNModel<ExampleSymbol> {
+        OrderNModel::new(vec![A, B, C, Eof], Eof, order)
+    }
+
+    #[test]
+    fn counts_depend_on_context() {
+        let model = model(1);
+        for symbol in [A, B, A, B, A, C] {
+            model.update(&symbol);
+        }
+        // Contexts seen: none, A, B
+        assert_eq!(model.contexts(), 3);
+
+        // After C, nothing has been seen yet
+        assert_eq!(model.interval_width(&A), 1);
+        model.update(&A);
+        assert_eq!(model.contexts(), 4);
+        // After A: B twice, C once
+        assert_eq!(model.interval_width(&B), 3);
+        assert_eq!(model.interval_width(&C), 2);
+        assert_eq!(model.total_interval_width(), 7);
+
+        model.begin_message();
+        assert_eq!(model.contexts(), 0);
+        assert_eq!(model.interval_width(&B), 1);
+    }
+
+    #[test]
+    fn order_n_model_matches_reference() {
+        for order in 0..=3 {
+            assert_eq!(
+                reference::cross_check_random::<_, 32>(&model(order), 50, 200, order as u64),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn context_counts_are_halved_at_limit() {
+        let model = model(1).with_max_total(8);
+        for _ in 0..100 {
+            model.update(&A);
+            assert!(model.total_interval_width() <= 8);
+        }
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&model, 50, 200, 14),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn context_improves_compression() {
+        // A repeating pattern is unpredictable symbol by symbol, but perfectly
+        // predictable from the previous symbol
+        let input: Vec<_> = [A, B, C]
+            .into_iter()
+            .cycle()
+            .take(600)
+            .chain([Eof])
+            .collect();
+        let order_0_length = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof)
+            .encode::<_, 32>(input.clone())
+            .count();
+        let order_1_length = model(1).encode::<_, 32>(input).count();
+        assert!(order_1_length * 10 < order_0_length);
+    }
+}
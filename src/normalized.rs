@@ -0,0 +1,338 @@
+use crate::alphabet::{Alphabet, Symbol};
+use crate::frequency::FrequencyTable;
+
+/// Errors that can occur while normalizing an alphabet
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum NormalizeError {
+    #[error(
+        "Alphabet has {symbols} symbols, which can't all have nonzero width in a total of 2^{log2_total}"
+    )]
+    TooManySymbols { symbols: usize, log2_total: u32 },
+    #[error("A total width of 2^{0} can't be represented")]
+    TotalTooLarge(u32),
+    #[error("Alphabet has a total interval width of zero")]
+    EmptyAlphabet,
+    #[error("Symbol {index} has probability {probability}, which isn't finite and non-negative")]
+    InvalidProbability { index: usize, probability: f64 },
+    #[error("EOF must have a nonzero probability")]
+    MissingEof,
+}
+
+/// An alphabet wrapper which rescales the interval widths of another alphabet
+/// so that they sum to exactly 2^k.
+///
+/// When R is a power of two, the encoder and decoder compute subintervals with
+/// a shift rather than a division. Every symbol with a nonzero width in the
+/// underlying alphabet keeps a nonzero width, and the remaining probability
+/// mass is distributed so as to stay as close as possible to the original
+/// distribution. The cost of the rescaling is reported by
+/// [`NormalizedAlphabet::quantization_error`].
+///
+/// Note that 2^k * 2^precision must be representable as a usize, so k must be
+/// at most `usize::BITS - precision`.
+pub struct NormalizedAlphabet<A: Alphabet> {
+    alphabet: A,
+    widths: Vec<usize>,
+    quantization_error: f64,
+}
+
+impl<A: Alphabet> NormalizedAlphabet<A> {
+    /// Normalize the given alphabet so its interval widths sum to 2^log2_total.
+    pub fn new(alphabet: A, log2_total: u32) -> Result<Self, NormalizeError> {
+        if log2_total >= usize::BITS {
+            return Err(NormalizeError::TotalTooLarge(log2_total));
+        }
+        let target = 1usize << log2_total;
+
+        let original: Vec<usize> = alphabet
+            .symbols()
+            .map(|symbol| alphabet.interval_width(symbol))
+            .collect();
+        let original_total: usize = original.iter().sum();
+        if original_total == 0 {
+            return Err(NormalizeError::EmptyAlphabet);
+        }
+
+        let nonzero_symbols = original.iter().filter(|&&width| width > 0).count();
+        if nonzero_symbols > target {
+            return Err(NormalizeError::TooManySymbols {
+                symbols: nonzero_symbols,
+                log2_total,
+            });
+        }
+
+        let widths = Self::rescale(&original, original_total, target);
+        let quantization_error = Self::kl_divergence(&original, original_total, &widths, target);
+
+        Ok(Self {
+            alphabet,
+            widths,
+            quantization_error,
+        })
+    }
+
+    /// The expected number of extra bits spent per symbol due to normalization,
+    /// assuming symbols are distributed according to the original alphabet.
+    ///
+    /// This is the Kullback-Leibler divergence of the normalized distribution
+    /// from the original one.
+    pub fn quantization_error(&self) -> f64 {
+        self.quantization_error
+    }
+
+    /// The underlying (unnormalized) alphabet.
+    pub fn inner(&self) -> &A {
+        &self.alphabet
+    }
+
+    /// Scale each width to its exact share of the target, rounded down (but
+    /// kept at least 1 if it was nonzero), then hand out or take back units one
+    /// at a time until the widths sum to the target.
+    fn rescale(original: &[usize], original_total: usize, target: usize) -> Vec<usize> {
+        // The exact scaled width of symbol i is original[i] * target / total.
+        // Compare against it using the numerators over a common denominator.
+        let exact = |i: usize| original[i] as u128 * target as u128;
+        let scaled = |width: usize| width as u128 * original_total as u128;
+
+        let mut widths: Vec<usize> = original
+            .iter()
+            .map(|&width| {
+                let share = (width as u128 * target as u128 / original_total as u128) as usize;
+                if width > 0 { share.max(1) } else { 0 }
+            })
+            .collect();
+        let mut sum: usize = widths.iter().sum();
+
+        // Give units to the symbols furthest below their exact share
+        while sum < target {
+            let i = (0..widths.len())
+                .filter(|&i| original[i] > 0)
+                .max_by_key(|&i| exact(i) as i128 - scaled(widths[i]) as i128)
+                .expect("Alphabet has a nonzero width");
+            widths[i] += 1;
+            sum += 1;
+        }
+
+        // Take units from the symbols furthest above their exact share, never
+        // reducing a width below 1
+        while sum > target {
+            let i = (0..widths.len())
+                .filter(|&i| widths[i] > 1)
+                .max_by_key(|&i| scaled(widths[i]) as i128 - exact(i) as i128)
+                .expect("Enough room for every symbol to have width 1");
+            widths[i] -= 1;
+            sum -= 1;
+        }
+
+        widths
+    }
+
+    /// Compute D(p || q) in bits.
+    fn kl_divergence(p: &[usize], p_total: usize, q: &[usize], q_total: usize) -> f64 {
+        p.iter()
+            .zip(q)
+            .filter(|&(&p_i, _)| p_i > 0)
+            .map(|(&p_i, &q_i)| {
+                let p_i = p_i as f64 / p_total as f64;
+                let q_i = q_i as f64 / q_total as f64;
+                p_i * (p_i / q_i).log2()
+            })
+            .sum()
+    }
+}
+
+impl<S: Symbol> NormalizedAlphabet<FrequencyTable<S>> {
+    /// Build an alphabet from symbol probabilities, quantized to integer
+    /// interval widths which sum to 2^log2_total.
+    ///
+    /// The probabilities needn't sum to exactly one, as they're divided by
+    /// their sum. Symbols with a probability of zero are left out of the
+    /// alphabet, every other symbol is given a nonzero width, and EOF must be
+    /// one of them. Probabilities are first resolved to
+    /// units of 2^-30, so log2_total should be at most 30.
+    pub fn from_probabilities(
+        probabilities: &[(S, f64)],
+        eof: S,
+        log2_total: u32,
+    ) -> Result<Self, NormalizeError> {
+        for (index, &(_, probability)) in probabilities.iter().enumerate() {
+            if !probability.is_finite() || probability < 0.0 {
+                return Err(NormalizeError::InvalidProbability { index, probability });
+            }
+        }
+        if !probabilities.iter().any(|(s, p)| *s == eof && *p > 0.0) {
+            return Err(NormalizeError::MissingEof);
+        }
+
+        let sum: f64 = probabilities.iter().map(|(_, p)| p).sum();
+        if !sum.is_finite() {
+            return Err(NormalizeError::TotalTooLarge(log2_total));
+        }
+        let resolution = (1u32 << 30) as f64;
+        let widths = probabilities
+            .iter()
+            .filter(|&&(_, p)| p > 0.0)
+            .map(|(symbol, p)| {
+                let width = (p / sum * resolution).round() as usize;
+                (symbol.clone(), width.max(1))
+            })
+            .collect();
+
+        Self::new(FrequencyTable::from_widths(widths, eof), log2_total)
+    }
+}
+
+impl<A: Alphabet> Alphabet for NormalizedAlphabet<A> {
+    type S = A::S;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.alphabet.symbols()
+    }
+
+    fn eof(&self) -> Self::S {
+        self.alphabet.eof()
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        self.widths[self.alphabet.index(symbol)]
+    }
+
+    fn contains(&self, symbol: &Self::S) -> bool {
+        self.alphabet.contains(symbol)
+    }
+
+    fn index(&self, symbol: &Self::S) -> usize {
+        self.alphabet.index(symbol)
+    }
+
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        self.alphabet.symbol_at(index)
+    }
+
+    fn is_static(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoder::{Decoder, DecoderEvent};
+    use crate::encoder::Encoder;
+    use crate::example::{ExampleAlphabet, ExampleSymbol::*};
+
+    fn widths<A: Alphabet>(alphabet: &A) -> Vec<usize> {
+        alphabet
+            .symbols()
+            .map(|symbol| alphabet.interval_width(symbol))
+            .collect()
+    }
+
+    #[test]
+    fn normalizes_to_power_of_two() {
+        let alphabet = NormalizedAlphabet::new(ExampleAlphabet::new(), 8).unwrap();
+        assert_eq!(alphabet.total_interval_width(), 256);
+        // 25/100, 50/100, 15/100, 10/100 of 256 = 64, 128, 38.4, 25.6
+        assert_eq!(widths(&alphabet), vec![64, 128, 38, 26]);
+        assert!(alphabet.quantization_error() > 0.0);
+        assert!(alphabet.quantization_error() < 0.001);
+    }
+
+    #[test]
+    fn keeps_small_widths_nonzero() {
+        // 25/100, 50/100, 15/100, 10/100 of 8 = 2, 4, 1.2, 0.8
+        let alphabet = NormalizedAlphabet::new(ExampleAlphabet::new(), 3).unwrap();
+        assert_eq!(widths(&alphabet), vec![2, 4, 1, 1]);
+    }
+
+    #[test]
+    fn exact_rescaling_has_no_error() {
+        let alphabet = NormalizedAlphabet::new(ExampleAlphabet::new(), 3).unwrap();
+        let doubled = NormalizedAlphabet::new(alphabet, 4).unwrap();
+        assert_eq!(widths(&doubled), vec![4, 8, 2, 2]);
+        assert_eq!(doubled.quantization_error(), 0.0);
+    }
+
+    #[test]
+    fn error_on_too_many_symbols() {
+        assert_eq!(
+            NormalizedAlphabet::new(ExampleAlphabet::new(), 1).err(),
+            Some(NormalizeError::TooManySymbols {
+                symbols: 4,
+                log2_total: 1
+            })
+        );
+    }
+
+    #[test]
+    fn error_on_total_too_large() {
+        assert_eq!(
+            NormalizedAlphabet::new(ExampleAlphabet::new(), usize::BITS).err(),
+            Some(NormalizeError::TotalTooLarge(usize::BITS))
+        );
+    }
+
+    #[test]
+    fn quantizes_probabilities() {
+        let alphabet = NormalizedAlphabet::from_probabilities(
+            &[(A, 0.25), (B, 0.5), (C, 0.15), (Eof, 0.1)],
+            Eof,
+            8,
+        )
+        .unwrap();
+        assert_eq!(widths(&alphabet), vec![64, 128, 38, 26]);
+
+        // Tiny probabilities still get a width, and probabilities are
+        // relative to their sum
+        let alphabet =
+            NormalizedAlphabet::from_probabilities(&[(A, 2.0), (B, 1e-12), (Eof, 2.0)], Eof, 4)
+                .unwrap();
+        assert_eq!(widths(&alphabet), vec![7, 1, 8]);
+    }
+
+    #[test]
+    fn error_on_unrepresentable_probabilities() {
+        assert!(matches!(
+            NormalizedAlphabet::from_probabilities(&[(A, 0.5), (B, f64::NAN)], A, 8),
+            Err(NormalizeError::InvalidProbability { index: 1, .. })
+        ));
+        assert_eq!(
+            NormalizedAlphabet::from_probabilities(&[(A, 0.5), (B, -0.5)], A, 8).err(),
+            Some(NormalizeError::InvalidProbability {
+                index: 1,
+                probability: -0.5
+            })
+        );
+        assert_eq!(
+            NormalizedAlphabet::from_probabilities(&[(A, 0.5), (Eof, 0.0)], Eof, 8).err(),
+            Some(NormalizeError::MissingEof)
+        );
+        assert_eq!(
+            NormalizedAlphabet::from_probabilities(&[(A, 0.5), (B, 0.5), (Eof, 0.5)], Eof, 1).err(),
+            Some(NormalizeError::TooManySymbols {
+                symbols: 3,
+                log2_total: 1
+            })
+        );
+    }
+
+    #[test]
+    fn round_trip_through_normalized_alphabet() {
+        let alphabet = NormalizedAlphabet::new(ExampleAlphabet::new(), 16).unwrap();
+        let input = vec![B, A, C, B, B, A, Eof];
+
+        let bits: Vec<_> = alphabet
+            .encode::<_, 32>(input.clone())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let symbols: Vec<_> = alphabet
+            .decode::<_, 32>(bits)
+            .filter_map(|event| match event.unwrap() {
+                DecoderEvent::DecodedSymbol(symbol) => Some(symbol),
+                DecoderEvent::MessageLength(_) => None,
+            })
+            .collect();
+
+        assert_eq!(symbols, input);
+    }
+}
@@ -0,0 +1,216 @@
+//! Encoding large inputs in parallel, by splitting them into blocks which are
+//! coded independently on a [`rayon`] thread pool.
+//!
+//! Each block is coded as a separate message (terminated by EOF) with a fresh
+//! model, so adaptive models start over at every block, and the blocks can
+//! also be decoded in parallel. The output is the blocks in order, each
+//! packed into bytes and preceded by its length in bytes as a varint (see
+//! [`crate::wire`]).
+//!
+//! Smaller blocks give more parallelism, but cost more: every block pays for
+//! its EOF and final bits, and an adaptive model has to learn the statistics
+//! of each block from scratch.
+//!
+//! The output doesn't depend on how the blocks are scheduled: block
+//! boundaries are fixed by `block_len` alone, every block is coded with its
+//! own model, and the blocks are written in order. So it's byte-for-byte the
+//! same on any number of threads, provided the model constructor returns an
+//! identical model every time it's called (rather than, say, one which
+//! shares counts with the others). [`encode_blocks_in`] codes on a given
+//! thread pool, to check this.
+
+use crate::alphabet::Alphabet;
+use crate::bits::SliceBits;
+use crate::decoder::{DecodeError, Decoder, DecoderEvent};
+use crate::encoder::{EncodeError, Encoder};
+use crate::wire::{read_usize, write_varint};
+use rayon::prelude::*;
+
+/// Errors that can occur while coding blocks
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum BlockError {
+    #[error("Block {0} extends past the end of the input")]
+    Truncated(usize),
+    #[error("Blocks must hold at least one symbol")]
+    ZeroBlockLength,
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+}
+
+/// Encode a stream of symbols (not including EOF) as blocks of at most
+/// `block_len` symbols, in parallel.
+///
+/// `model` constructs the alphabet for each block. For a static alphabet, it
+/// can simply clone a shared one. `block_len` must be at least one.
+pub fn encode_blocks<A, F, const BITS_OF_PRECISION: u32>(
+    model: F,
+    symbols: &[A::S],
+    block_len: usize,
+) -> Result<Vec<u8>, BlockError>
+where
+    A: Alphabet,
+    A::S: Send + Sync,
+    F: Fn() -> A + Sync,
+{
+    if block_len == 0 {
+        return Err(BlockError::ZeroBlockLength);
+    }
+    let blocks: Vec<Vec<u8>> = symbols
+        .par_chunks(block_len)
+        .map(|block| {
+            let alphabet = model();
+            let message = block.iter().cloned().chain([alphabet.eof()]);
+            alphabet
+                .encode::<_, BITS_OF_PRECISION>(message)
+                .bytes()
+                .collect::<Result<_, _>>()
+        })
+        .collect::<Result<_, _>>()?;
+
+    let mut output = Vec::with_capacity(blocks.iter().map(|block| block.len() + 4).sum());
+    for block in blocks {
+        write_varint(&mut output, block.len() as u64).expect("Writing to a Vec can't fail");
+        output.extend(block);
+    }
+    Ok(output)
+}
+
+/// Encode blocks as [`encode_blocks`] does, on the given thread pool rather
+/// than the global one.
+///
+/// The output is the same on every pool, whatever its number of threads.
+pub fn encode_blocks_in<A, F, const BITS_OF_PRECISION: u32>(
+    pool: &rayon::ThreadPool,
+    model: F,
+    symbols: &[A::S],
+    block_len: usize,
+) -> Result<Vec<u8>, BlockError>
+where
+    A: Alphabet,
+    A::S: Send + Sync,
+    F: Fn() -> A + Sync + Send,
+{
+    pool.install(|| encode_blocks::<A, F, BITS_OF_PRECISION>(model, symbols, block_len))
+}
+
+/// Decode the blocks produced by [`encode_blocks`] with the same model, in
+/// parallel, returning the symbols of every block in order (not including
+/// EOF).
+pub fn decode_blocks<A, F, const BITS_OF_PRECISION: u32>(
+    model: F,
+    mut input: &[u8],
+) -> Result<Vec<A::S>, BlockError>
+where
+    A: Alphabet,
+    A::S: Send,
+    F: Fn() -> A + Sync,
+{
+    let mut blocks = Vec::new();
+    while !input.is_empty() {
+        let index = blocks.len();
+        let len = read_usize(&mut input).map_err(|_| BlockError::Truncated(index))?;
+        if len > input.len() {
+            return Err(BlockError::Truncated(index));
+        }
+        let (block, rest) = input.split_at(len);
+        blocks.push(block);
+        input = rest;
+    }
+
+    let decoded: Vec<Vec<A::S>> = blocks
+        .into_par_iter()
+        .map(|block| {
+            let alphabet = model();
+            let mut symbols = Vec::new();
+            for event in alphabet.decode::<_, BITS_OF_PRECISION>(SliceBits::new(block)) {
+                if let DecoderEvent::DecodedSymbol(symbol) = event?
+                    && symbol != alphabet.eof()
+                {
+                    symbols.push(symbol);
+                }
+            }
+            Ok(symbols)
+        })
+        .collect::<Result<_, BlockError>>()?;
+    Ok(decoded.concat())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::byte::{ByteAlphabet, ByteSymbol};
+    use crate::example::{ExampleAlphabet, ExampleSymbol::*};
+    use crate::models::AdaptiveAlphabet;
+
+    #[test]
+    fn round_trip_blocks() {
+        let symbols: Vec<_> = [A, B, B, C, A].into_iter().cycle().take(1000).collect();
+        let adaptive = || AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof);
+        for block_len in [1, 7, 100, 1000, 5000] {
+            let bytes = encode_blocks::<_, _, 32>(adaptive, &symbols, block_len).unwrap();
+            assert_eq!(
+                decode_blocks::<_, _, 32>(adaptive, &bytes),
+                Ok(symbols.clone())
+            );
+        }
+
+        let bytes = encode_blocks::<_, _, 32>(ExampleAlphabet::new, &[], 10).unwrap();
+        assert!(bytes.is_empty());
+        assert_eq!(
+            decode_blocks::<_, _, 32>(ExampleAlphabet::new, &bytes),
+            Ok(vec![])
+        );
+        assert_eq!(
+            encode_blocks::<_, _, 32>(ExampleAlphabet::new, &symbols, 0),
+            Err(BlockError::ZeroBlockLength)
+        );
+    }
+
+    #[test]
+    fn blocks_cost_little_more_than_one_message() {
+        let text = b"she sells sea shells by the sea shore ".repeat(100);
+        let alphabet = ByteAlphabet::from_sample(&text);
+        let symbols: Vec<_> = text.iter().map(|&byte| ByteSymbol::Byte(byte)).collect();
+        let whole = alphabet
+            .encode_to_vec::<_, 32>(ByteAlphabet::message(text.iter().copied()))
+            .unwrap();
+        let bytes = encode_blocks::<_, _, 32>(|| alphabet.clone(), &symbols, 1000).unwrap();
+        assert!(
+            bytes.len() < whole.len() / 8 + 4 * 8,
+            "{} bytes",
+            bytes.len()
+        );
+        assert_eq!(
+            decode_blocks::<_, _, 32>(|| alphabet.clone(), &bytes),
+            Ok(symbols)
+        );
+    }
+
+    #[test]
+    fn output_is_independent_of_thread_count() {
+        let symbols: Vec<_> = (0..5000).map(|i| [A, B, C][i * i % 7 % 3]).collect();
+        let adaptive = || AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof);
+        let expected = encode_blocks::<_, _, 32>(adaptive, &symbols, 64).unwrap();
+        for threads in [1, 2, 3, 8] {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let bytes = encode_blocks_in::<_, _, 32>(&pool, adaptive, &symbols, 64).unwrap();
+            assert_eq!(bytes, expected, "{threads} threads");
+        }
+        assert_eq!(decode_blocks::<_, _, 32>(adaptive, &expected), Ok(symbols));
+    }
+
+    #[test]
+    fn error_on_truncated_blocks() {
+        let symbols = vec![B; 100];
+        let bytes = encode_blocks::<_, _, 32>(ExampleAlphabet::new, &symbols, 40).unwrap();
+        assert_eq!(
+            decode_blocks::<_, _, 32>(ExampleAlphabet::new, &bytes[..bytes.len() - 1]),
+            Err(BlockError::Truncated(2))
+        );
+    }
+}
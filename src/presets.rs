@@ -0,0 +1,270 @@
+//! Ready-made alphabets for common kinds of data, for trying out the coder and
+//! as standard models for benchmarks.
+
+use crate::alphabet::{Alphabet, Symbol};
+use crate::bits::Bit;
+use crate::byte::ByteAlphabet;
+use crate::decoder::{DecodeError, Decoder, DecoderEvent};
+use crate::encoder::{EncodeError, Encoder};
+
+/// Relative frequencies of the lowercase letters a to z in English text, per
+/// thousand letters.
+const LETTER_FREQUENCIES: [usize; 26] = [
+    82, 15, 28, 43, 127, 22, 20, 61, 70, 2, 8, 40, 24, 67, 75, 19, 1, 60, 63, 91, 28, 10, 24, 2,
+    20, 1,
+];
+
+/// Weights of punctuation and other characters, per ten thousand characters.
+const OTHER_FREQUENCIES: [(u8, usize); 13] = [
+    (b' ', 1700),
+    (b'\n', 50),
+    (b'.', 65),
+    (b',', 60),
+    (b'\'', 24),
+    (b'"', 20),
+    (b'-', 15),
+    (b'?', 6),
+    (b'!', 4),
+    (b';', 3),
+    (b':', 3),
+    (b'(', 2),
+    (b')', 2),
+];
+
+/// A byte alphabet weighted by the frequencies of characters in English prose:
+/// letters (with capitals much rarer than lowercase), spaces, punctuation, and
+/// digits.
+///
+/// Every other byte, and EOF, has the smallest possible weight, so any input
+/// can be encoded, but non-English text compresses poorly. R is about 10,000.
+pub fn english() -> ByteAlphabet {
+    let mut weights = [1; 256];
+    for (i, &frequency) in LETTER_FREQUENCIES.iter().enumerate() {
+        // About 97% of letters are lowercase
+        weights[(b'a' + i as u8) as usize] = frequency * 7;
+        weights[(b'A' + i as u8) as usize] = (frequency * 3 / 10).max(1);
+    }
+    for &(byte, frequency) in &OTHER_FREQUENCIES {
+        weights[byte as usize] = frequency;
+    }
+    for digit in b'0'..=b'9' {
+        weights[digit as usize] = 2;
+    }
+    ByteAlphabet::from_weights(weights, 1)
+}
+
+/// A base of a nucleotide sequence, or the end of the sequence.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Nucleotide {
+    A,
+    C,
+    G,
+    T,
+    /// Any base (the IUPAC code for an unknown or ambiguous base)
+    N,
+    Eof,
+}
+
+impl Symbol for Nucleotide {}
+
+impl Nucleotide {
+    /// Parse a base from its letter, in either case.
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte.to_ascii_uppercase() {
+            b'A' => Some(Nucleotide::A),
+            b'C' => Some(Nucleotide::C),
+            b'G' => Some(Nucleotide::G),
+            b'T' => Some(Nucleotide::T),
+            b'N' => Some(Nucleotide::N),
+            _ => None,
+        }
+    }
+
+    /// The uppercase letter of a base, or None for EOF.
+    pub fn to_byte(self) -> Option<u8> {
+        match self {
+            Nucleotide::A => Some(b'A'),
+            Nucleotide::C => Some(b'C'),
+            Nucleotide::G => Some(b'G'),
+            Nucleotide::T => Some(b'T'),
+            Nucleotide::N => Some(b'N'),
+            Nucleotide::Eof => None,
+        }
+    }
+}
+
+/// Errors that can occur while encoding a nucleotide sequence
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum SequenceError {
+    #[error("Byte {byte:#04x} at position {position} is not a base of the alphabet")]
+    InvalidBase { byte: u8, position: usize },
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+}
+
+/// An alphabet of the four nucleotides, each equally likely, optionally with N
+/// for unknown bases.
+///
+/// At two bits per base (plus a little for EOF), this is the baseline that
+/// any model of DNA has to beat.
+#[derive(Clone, Debug)]
+pub struct AcgtAlphabet {
+    symbols: Vec<Nucleotide>,
+}
+
+impl AcgtAlphabet {
+    /// The alphabet of A, C, G, and T.
+    pub fn new() -> Self {
+        use Nucleotide::*;
+        Self {
+            symbols: vec![A, C, G, T, Eof],
+        }
+    }
+
+    /// The alphabet of A, C, G, and T, plus N, which is given a much smaller
+    /// weight than the other bases.
+    pub fn with_ambiguity() -> Self {
+        use Nucleotide::*;
+        Self {
+            symbols: vec![A, C, G, T, N, Eof],
+        }
+    }
+
+    /// Encode a sequence given as text, such as the sequence lines of a FASTA
+    /// record. Bases may be in either case, and whitespace (including line
+    /// breaks) is skipped.
+    pub fn encode_sequence<const BITS_OF_PRECISION: u32>(
+        &self,
+        sequence: &[u8],
+    ) -> Result<Vec<Bit>, SequenceError> {
+        let mut bases = Vec::with_capacity(sequence.len() + 1);
+        for (position, &byte) in sequence.iter().enumerate() {
+            if byte.is_ascii_whitespace() {
+                continue;
+            }
+            match Nucleotide::from_byte(byte).filter(|base| self.symbols.contains(base)) {
+                Some(base) => bases.push(base),
+                None => return Err(SequenceError::InvalidBase { byte, position }),
+            }
+        }
+        bases.push(Nucleotide::Eof);
+
+        Ok(self.encode_to_vec::<_, BITS_OF_PRECISION>(bases)?)
+    }
+
+    /// Decode a sequence encoded with [`AcgtAlphabet::encode_sequence`] into
+    /// uppercase text, without line breaks.
+    pub fn decode_sequence<const BITS_OF_PRECISION: u32>(
+        &self,
+        bits: &[Bit],
+    ) -> Result<Vec<u8>, DecodeError> {
+        let mut sequence = Vec::new();
+        for event in self.decode::<_, BITS_OF_PRECISION>(bits.iter().copied()) {
+            if let DecoderEvent::DecodedSymbol(base) = event?
+                && let Some(byte) = base.to_byte()
+            {
+                sequence.push(byte);
+            }
+        }
+        Ok(sequence)
+    }
+}
+
+impl Default for AcgtAlphabet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Alphabet for AcgtAlphabet {
+    type S = Nucleotide;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.symbols.iter()
+    }
+
+    fn eof(&self) -> Self::S {
+        Nucleotide::Eof
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        match symbol {
+            Nucleotide::A | Nucleotide::C | Nucleotide::G | Nucleotide::T => 256,
+            Nucleotide::N if self.symbols.contains(&Nucleotide::N) => 16,
+            Nucleotide::N => panic!("Symbol {symbol:?} not in alphabet"),
+            Nucleotide::Eof => 1,
+        }
+    }
+
+    fn is_static(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::byte::ByteSymbol;
+    use crate::reference;
+
+    const TEXT: &[u8] = b"It was the best of times, it was the worst of times, it was the \
+        age of wisdom, it was the age of foolishness, it was the epoch of belief, it was \
+        the epoch of incredulity, it was the season of Light, it was the season of Darkness.";
+
+    #[test]
+    fn english_compresses_text() {
+        let alphabet = english();
+        assert!(alphabet.entropy() < 5.0);
+
+        let bits = alphabet
+            .encode::<_, 32>(ByteAlphabet::message(TEXT.iter().copied()))
+            .count();
+        assert!(bits < TEXT.len() * 5, "{bits} bits");
+    }
+
+    #[test]
+    fn english_favors_common_letters() {
+        let alphabet = english();
+        let weight = |byte| alphabet.interval_width(&ByteSymbol::Byte(byte));
+        assert!(weight(b'e') > weight(b' ') / 2);
+        assert!(weight(b'e') > weight(b'E'));
+        assert!(weight(b'z') > weight(0xff));
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&alphabet, 20, 100, 11),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn round_trip_sequence() {
+        let alphabet = AcgtAlphabet::new();
+        let sequence = b"GATTACA\ncatgcatg\nACGT";
+        let bits = alphabet.encode_sequence::<32>(sequence).unwrap();
+        // Two bits per base, plus about ten for EOF
+        assert!(bits.len() <= 19 * 2 + 12, "{} bits", bits.len());
+        assert_eq!(
+            alphabet.decode_sequence::<32>(&bits),
+            Ok(b"GATTACACATGCATGACGT".to_vec())
+        );
+    }
+
+    #[test]
+    fn ambiguous_bases() {
+        let sequence = b"ACGTNNACGT";
+        assert_eq!(
+            AcgtAlphabet::new().encode_sequence::<32>(sequence),
+            Err(SequenceError::InvalidBase {
+                byte: b'N',
+                position: 4
+            })
+        );
+
+        let alphabet = AcgtAlphabet::with_ambiguity();
+        let bits = alphabet.encode_sequence::<32>(sequence).unwrap();
+        assert_eq!(alphabet.decode_sequence::<32>(&bits), Ok(sequence.to_vec()));
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&alphabet, 50, 200, 12),
+            Ok(())
+        );
+    }
+}
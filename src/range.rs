@@ -0,0 +1,246 @@
+//! A carry-less range coder (after Dmitry Subbotin), as an alternative to the
+//! bitwise coder in [`crate::encoder`] and [`crate::decoder`].
+//!
+//! Rather than emitting a bit per rescaling step and deferring the bits of an
+//! interval straddling the midpoint (the s counter), the range coder keeps a
+//! 32-bit window of the interval and emits whole bytes once the top byte of
+//! its bounds agrees. When a narrow range straddles a byte boundary, the range
+//! is cut down so that the top byte settles, at a small cost in compression,
+//! and no carry can ever propagate into bytes already emitted.
+//!
+//! The output is a different format from the bitwise coder's, and is always a
+//! whole number of bytes, which makes it simple to write and fast to produce.
+//! The total interval width of the alphabet must not exceed 2^16 at any
+//! point.
+//!
+//! [`carry`] has an LZMA-style range coder, which propagates carries instead
+//! of cutting the range down.
+//!
+//! Either can be chosen in place of the bitwise coder, which remains the
+//! default, with a [`Coder`](crate::encoder::Coder) passed to
+//! [`Encoder::encode_with`](crate::encoder::Encoder::encode_with) and
+//! [`Decoder::decode_with`](crate::decoder::Decoder::decode_with).
+
+use crate::alphabet::{Alphabet, AlphabetError};
+use crate::encoder::EncodeError;
+
+pub mod carry;
+
+/// Bytes are emitted once the top byte of the range settles
+const TOP: u32 = 1 << 24;
+/// The smallest range, and so the largest total interval width
+const BOTTOM: u32 = 1 << 16;
+/// The precision at which [`Alphabet::validate`] allows a total of up to
+/// [`BOTTOM`]
+const VALIDATE_PRECISION: u32 = 18;
+
+/// Errors that can occur while range decoding
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum RangeDecodeError {
+    #[error("Input ended before EOF, after {0} symbols")]
+    Truncated(usize),
+    #[error(transparent)]
+    InvalidAlphabet(#[from] AlphabetError),
+}
+
+/// Check that R is small enough for the range to be divided by it.
+fn check_total(total: usize) -> Result<(), AlphabetError> {
+    if total > BOTTOM as usize {
+        return Err(AlphabetError::TotalTooLarge {
+            total,
+            bits_of_precision: 16,
+            max: BOTTOM as usize,
+        });
+    }
+    Ok(())
+}
+
+/// Range encode a message, which must be terminated by EOF.
+pub fn encode<A: Alphabet>(alphabet: &A, input: &[A::S]) -> Result<Vec<u8>, EncodeError> {
+    alphabet.begin_message();
+    alphabet.validate(VALIDATE_PRECISION)?;
+
+    let mut output = Vec::new();
+    let (mut low, mut range) = (0_u32, u32::MAX);
+    let mut input = input.iter().enumerate();
+    loop {
+        let (position, symbol) = input.next().ok_or(EncodeError::UnterminatedStream)?;
+        let (lower_bound, upper_bound) = alphabet
+            .checked_cdf(symbol)
+            .ok_or(EncodeError::SymbolNotInAlphabet { position })?;
+        let total = alphabet.total_interval_width();
+        check_total(total)?;
+        alphabet.update(symbol);
+
+        range /= total as u32;
+        low = low.wrapping_add(lower_bound as u32 * range);
+        range *= (upper_bound - lower_bound) as u32;
+        while let Some(next) = normalize(low, range) {
+            output.push((low >> 24) as u8);
+            (low, range) = (low << 8, next << 8);
+        }
+
+        if *symbol == alphabet.eof() {
+            break;
+        }
+    }
+
+    output.extend(low.to_be_bytes());
+    Ok(output)
+}
+
+/// Range decode a message, returning its symbols (including EOF).
+pub fn decode<A: Alphabet>(alphabet: &A, input: &[u8]) -> Result<Vec<A::S>, RangeDecodeError> {
+    alphabet.begin_message();
+    alphabet.validate(VALIDATE_PRECISION)?;
+
+    let mut bytes = input.iter().copied();
+    let mut read = || bytes.next();
+    let mut code = 0_u32;
+    for _ in 0..4 {
+        code = (code << 8) | read().unwrap_or(0) as u32;
+    }
+
+    let mut output = Vec::new();
+    let (mut low, mut range) = (0_u32, u32::MAX);
+    loop {
+        let total = alphabet.total_interval_width();
+        check_total(total)?;
+        range /= total as u32;
+        // Corrupt input can point past the last symbol
+        let value = ((code.wrapping_sub(low) / range) as usize).min(total - 1);
+        let symbol = alphabet.quantile(value).clone();
+        let (lower_bound, upper_bound) = alphabet.cdf(&symbol);
+        alphabet.update(&symbol);
+
+        low = low.wrapping_add(lower_bound as u32 * range);
+        range *= (upper_bound - lower_bound) as u32;
+        while let Some(next) = normalize(low, range) {
+            // The encoder emits every byte the decoder reads, and then four more
+            let byte = read().ok_or(RangeDecodeError::Truncated(output.len()))?;
+            code = (code << 8) | byte as u32;
+            (low, range) = (low << 8, next << 8);
+        }
+
+        let eof = symbol == alphabet.eof();
+        output.push(symbol);
+        if eof {
+            return Ok(output);
+        }
+    }
+}
+
+/// If the range needs to be scaled up by a byte, the range to scale up:
+/// either the range itself once its top byte has settled, or, if the range
+/// has become too small without settling, the part of it below the next byte
+/// boundary.
+fn normalize(low: u32, range: u32) -> Option<u32> {
+    if (low ^ low.wrapping_add(range)) < TOP {
+        Some(range)
+    } else if range < BOTTOM {
+        Some(low.wrapping_neg() & (BOTTOM - 1))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bits::bits_to_bytes;
+    use crate::byte::{ByteAlphabet, ByteSymbol};
+    use crate::decoder::{DecodeError, Decoder};
+    use crate::encoder::{Coder, Encoder};
+    use crate::example::{ExampleAlphabet, ExampleSymbol::*};
+    use crate::models::AdaptiveAlphabet;
+
+    #[test]
+    fn round_trip() {
+        let alphabet = ExampleAlphabet::new();
+        for message in [
+            vec![Eof],
+            vec![B, A, C, Eof],
+            [vec![C; 1000], vec![Eof]].concat(),
+        ] {
+            let bytes = encode(&alphabet, &message).unwrap();
+            assert_eq!(decode(&alphabet, &bytes), Ok(message));
+        }
+
+        let adaptive = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof);
+        let message: Vec<_> = [A, A, B, A, C]
+            .into_iter()
+            .cycle()
+            .take(500)
+            .chain([Eof])
+            .collect();
+        let bytes = encode(&adaptive, &message).unwrap();
+        assert_eq!(decode(&adaptive, &bytes), Ok(message));
+    }
+
+    #[test]
+    fn close_to_bitwise_coder() {
+        let text = b"she sells sea shells by the sea shore, and then some more text";
+        let alphabet = ByteAlphabet::from_sample(text);
+        let message: Vec<_> = ByteAlphabet::message(text.iter().copied()).collect();
+        let bytes = encode(&alphabet, &message).unwrap();
+        let bits = alphabet.encode_to_vec::<_, 32>(message.clone()).unwrap();
+        assert!(
+            bytes.len() <= bits.len().div_ceil(8) + 4,
+            "{} bytes",
+            bytes.len()
+        );
+        assert_eq!(decode(&alphabet, &bytes), Ok(message));
+    }
+
+    #[test]
+    fn selectable_coder() {
+        let text = b"she sells sea shells by the sea shore";
+        let alphabet = ByteAlphabet::from_sample(text);
+        let symbols: Vec<_> = text.iter().map(|&byte| ByteSymbol::Byte(byte)).collect();
+        let message: Vec<_> = ByteAlphabet::message(text.iter().copied()).collect();
+        for (coder, expected) in [
+            (
+                Coder::Arithmetic,
+                alphabet
+                    .encode_to_vec::<_, 32>(message.clone())
+                    .map(bits_to_bytes),
+            ),
+            (Coder::Range, encode(&alphabet, &message)),
+            (Coder::RangeWithCarry, carry::encode(&alphabet, &message)),
+        ] {
+            let bytes = alphabet.encode_with::<_, 32>(message.clone(), coder);
+            assert_eq!(bytes, expected, "{coder:?}");
+            let bytes = bytes.unwrap();
+            assert_eq!(
+                alphabet.decode_with::<32>(&bytes, coder),
+                Ok(symbols.clone())
+            );
+            assert!(matches!(
+                alphabet.decode_with::<32>(&bytes[..bytes.len() / 2], coder),
+                Err(DecodeError::UnexpectedEndOfInput(_))
+            ));
+        }
+        assert_eq!(Coder::default(), Coder::Arithmetic);
+    }
+
+    #[test]
+    fn errors() {
+        let alphabet = ExampleAlphabet::new();
+        let bytes = encode(&alphabet, &[B; 100]).unwrap_err();
+        assert_eq!(bytes, EncodeError::UnterminatedStream);
+
+        let bytes = encode(&alphabet, &[[B; 100].as_slice(), &[Eof]].concat()).unwrap();
+        assert_eq!(
+            decode(&alphabet, &bytes[..bytes.len() - 5]),
+            Err(RangeDecodeError::Truncated(62))
+        );
+
+        let large = ByteAlphabet::from_weights([256; 256], 1);
+        assert!(matches!(
+            encode(&large, &[ByteSymbol::Eof]),
+            Err(EncodeError::InvalidAlphabet(
+                AlphabetError::TotalTooLarge { .. }
+            ))
+        ));
+    }
+}
@@ -0,0 +1,222 @@
+//! A range coder which propagates carries, in the style of LZMA's range
+//! coder, as an alternative to the carry-less coder in [`crate::range`].
+//!
+//! The low end of the range is kept with one bit of headroom above 32 bits,
+//! and the range is scaled up by a byte whenever it falls below 2^24. Rather
+//! than cutting the range down when its top byte won't settle, the encoder
+//! holds back the last byte it shifted out (the cache), along with any run of
+//! 0xFF bytes following it, until it knows whether a carry will reach them.
+//! So the whole range is always used, which compresses slightly better than
+//! the carry-less coder, while bytes are still emitted one at a time.
+//!
+//! The output starts with a zero byte (the initial cache) and is always a
+//! whole number of bytes. As with the carry-less coder, the total interval
+//! width of the alphabet must not exceed 2^16 at any point.
+
+use super::{RangeDecodeError, VALIDATE_PRECISION, check_total};
+use crate::alphabet::Alphabet;
+use crate::encoder::EncodeError;
+
+/// The range is scaled up by a byte once it falls below this
+const TOP: u32 = 1 << 24;
+
+/// The state of the encoder which is yet to be emitted.
+struct CarryEncoder {
+    output: Vec<u8>,
+    /// The low end of the range, with a carry bit above the low 32 bits
+    low: u64,
+    range: u32,
+    /// The last byte shifted out of low, which a carry may still increment
+    cache: u8,
+    /// The number of bytes held back: the cache and the 0xFF bytes after it
+    cache_size: usize,
+}
+
+impl CarryEncoder {
+    fn new() -> Self {
+        Self {
+            output: Vec::new(),
+            low: 0,
+            range: u32::MAX,
+            cache: 0,
+            cache_size: 1,
+        }
+    }
+
+    fn encode(&mut self, lower_bound: u32, upper_bound: u32, total: u32) {
+        let r = self.range / total;
+        self.low += (lower_bound * r) as u64;
+        self.range = (upper_bound - lower_bound) * r;
+        while self.range < TOP {
+            self.range <<= 8;
+            self.shift_low();
+        }
+    }
+
+    /// Shift the top byte out of low, emitting the bytes held back once a
+    /// carry can no longer reach them.
+    fn shift_low(&mut self) {
+        if self.low < 0xFF00_0000 || self.low >= 1 << 32 {
+            let carry = (self.low >> 32) as u8;
+            self.output.push(self.cache.wrapping_add(carry));
+            for _ in 1..self.cache_size {
+                self.output.push(0xFF_u8.wrapping_add(carry));
+            }
+            self.cache_size = 0;
+            self.cache = (self.low >> 24) as u8;
+        }
+        self.cache_size += 1;
+        self.low = (self.low & 0x00FF_FFFF) << 8;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        for _ in 0..5 {
+            self.shift_low();
+        }
+        self.output
+    }
+}
+
+/// Range encode a message, which must be terminated by EOF.
+pub fn encode<A: Alphabet>(alphabet: &A, input: &[A::S]) -> Result<Vec<u8>, EncodeError> {
+    alphabet.begin_message();
+    alphabet.validate(VALIDATE_PRECISION)?;
+
+    let mut encoder = CarryEncoder::new();
+    let mut input = input.iter().enumerate();
+    loop {
+        let (position, symbol) = input.next().ok_or(EncodeError::UnterminatedStream)?;
+        let (lower_bound, upper_bound) = alphabet
+            .checked_cdf(symbol)
+            .ok_or(EncodeError::SymbolNotInAlphabet { position })?;
+        let total = alphabet.total_interval_width();
+        check_total(total)?;
+        alphabet.update(symbol);
+        encoder.encode(lower_bound as u32, upper_bound as u32, total as u32);
+
+        if *symbol == alphabet.eof() {
+            return Ok(encoder.finish());
+        }
+    }
+}
+
+/// Range decode a message, returning its symbols (including EOF).
+pub fn decode<A: Alphabet>(alphabet: &A, input: &[u8]) -> Result<Vec<A::S>, RangeDecodeError> {
+    alphabet.begin_message();
+    alphabet.validate(VALIDATE_PRECISION)?;
+
+    // The first byte is always the encoder's initial cache
+    let mut bytes = input.iter().copied().skip(1);
+    let mut code = 0_u32;
+    for _ in 0..4 {
+        code = (code << 8) | bytes.next().unwrap_or(0) as u32;
+    }
+
+    let mut output = Vec::new();
+    let mut range = u32::MAX;
+    loop {
+        let total = alphabet.total_interval_width();
+        check_total(total)?;
+        // The decoder tracks the code relative to the low end of the range
+        let r = range / total as u32;
+        // Corrupt input can point past the last symbol
+        let value = ((code / r) as usize).min(total - 1);
+        let symbol = alphabet.quantile(value).clone();
+        let (lower_bound, upper_bound) = alphabet.cdf(&symbol);
+        alphabet.update(&symbol);
+
+        code = code.wrapping_sub(lower_bound as u32 * r);
+        range = (upper_bound - lower_bound) as u32 * r;
+        while range < TOP {
+            // The encoder emits every byte the decoder reads
+            let byte = bytes
+                .next()
+                .ok_or(RangeDecodeError::Truncated(output.len()))?;
+            code = (code << 8) | byte as u32;
+            range <<= 8;
+        }
+
+        let eof = symbol == alphabet.eof();
+        output.push(symbol);
+        if eof {
+            return Ok(output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::byte::{ByteAlphabet, ByteSymbol};
+    use crate::example::{ExampleAlphabet, ExampleSymbol::*};
+    use crate::models::AdaptiveAlphabet;
+    use crate::rng::SplitMix64;
+
+    #[test]
+    fn round_trip() {
+        let alphabet = ExampleAlphabet::new();
+        for message in [
+            vec![Eof],
+            vec![B, A, C, Eof],
+            [vec![C; 1000], vec![Eof]].concat(),
+        ] {
+            let bytes = encode(&alphabet, &message).unwrap();
+            assert_eq!(decode(&alphabet, &bytes), Ok(message));
+        }
+
+        let adaptive = AdaptiveAlphabet::new(vec![A, B, C, Eof], Eof);
+        let message: Vec<_> = [A, A, B, A, C]
+            .into_iter()
+            .cycle()
+            .take(500)
+            .chain([Eof])
+            .collect();
+        let bytes = encode(&adaptive, &message).unwrap();
+        assert_eq!(decode(&adaptive, &bytes), Ok(message));
+    }
+
+    #[test]
+    fn carries_propagate() {
+        // Skewed weights make for many small steps up the range, so carries
+        // into bytes held back are common
+        let mut weights = [1; 256];
+        weights[0xFF] = 60000;
+        let alphabet = ByteAlphabet::from_weights(weights, 1);
+        let mut rng = SplitMix64(7);
+        for _ in 0..50 {
+            let message: Vec<_> = (0..500)
+                .map(|_| match rng.below(20) {
+                    0 => ByteSymbol::Byte(rng.below(256) as u8),
+                    _ => ByteSymbol::Byte(0xFF),
+                })
+                .chain([ByteSymbol::Eof])
+                .collect();
+            let bytes = encode(&alphabet, &message).unwrap();
+            assert_eq!(decode(&alphabet, &bytes), Ok(message));
+        }
+    }
+
+    #[test]
+    fn no_larger_than_carry_less_coder() {
+        let text = b"she sells sea shells by the sea shore, and then some more text";
+        let alphabet = ByteAlphabet::from_sample(text);
+        let message: Vec<_> = ByteAlphabet::message(text.repeat(20)).collect();
+        let bytes = encode(&alphabet, &message).unwrap();
+        let carry_less = crate::range::encode(&alphabet, &message).unwrap();
+        assert!(bytes.len() <= carry_less.len() + 1, "{} bytes", bytes.len());
+        assert_eq!(decode(&alphabet, &bytes), Ok(message));
+    }
+
+    #[test]
+    fn errors() {
+        let alphabet = ExampleAlphabet::new();
+        let error = encode(&alphabet, &[B; 100]).unwrap_err();
+        assert_eq!(error, EncodeError::UnterminatedStream);
+
+        let bytes = encode(&alphabet, &[[B; 100].as_slice(), &[Eof]].concat()).unwrap();
+        assert!(matches!(
+            decode(&alphabet, &bytes[..bytes.len() - 1]),
+            Err(RangeDecodeError::Truncated(_))
+        ));
+    }
+}
@@ -0,0 +1,113 @@
+/// A precomputed fixed-point reciprocal of a divisor, which allows repeated
+/// division by the same value to be performed with a multiplication, a shift,
+/// and a single correction step instead of a hardware division.
+///
+/// The encoder and decoder divide by R (the total interval width) for every
+/// symbol, so when the alphabet is static for the duration of a message this
+/// is computed once up front.
+///
+/// Given a divisor d, the multiplier is m = floor(2^64 / d). For any x < 2^64,
+/// the estimate q = floor(x * m / 2^64) satisfies floor(x / d) - 1 <= q <=
+/// floor(x / d), so the exact quotient is recovered by checking whether the
+/// remainder x - q * d is still at least d.
+///
+/// When d is a power of two, division is instead a plain shift.
+///
+/// For alphabets whose total width changes from symbol to symbol, the
+/// reciprocal is refreshed (and only recomputed when R actually changed)
+/// before each symbol.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Reciprocal {
+    divisor: usize,
+    multiplier: u128,
+    shift: Option<u32>,
+}
+
+impl Reciprocal {
+    /// Precompute the reciprocal of the given (nonzero) divisor.
+    pub(crate) fn new(divisor: usize) -> Self {
+        assert!(divisor > 0, "Cannot take the reciprocal of zero");
+        Self {
+            divisor,
+            multiplier: (1u128 << 64) / divisor as u128,
+            shift: divisor.is_power_of_two().then(|| divisor.trailing_zeros()),
+        }
+    }
+
+    /// Make this the reciprocal of the given divisor, if it isn't already.
+    pub(crate) fn refresh(&mut self, divisor: usize) {
+        if divisor != self.divisor {
+            *self = Self::new(divisor);
+        }
+    }
+
+    /// Compute floor(x / divisor) without dividing.
+    pub(crate) fn divide(&self, x: usize) -> usize {
+        if let Some(shift) = self.shift {
+            return x >> shift;
+        }
+
+        let mut quotient = ((x as u128 * self.multiplier) >> 64) as usize;
+        if x - quotient * self.divisor >= self.divisor {
+            quotient += 1;
+        }
+        quotient
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[test]
+    fn divide_by_one() {
+        let reciprocal = Reciprocal::new(1);
+        assert_eq!(reciprocal.divide(0), 0);
+        assert_eq!(reciprocal.divide(12345), 12345);
+        assert_eq!(reciprocal.divide(usize::MAX), usize::MAX);
+    }
+
+    #[test]
+    fn divide_extremes() {
+        for divisor in [
+            2,
+            3,
+            7,
+            64,
+            100,
+            1 << 31,
+            (1 << 32) + 1,
+            usize::MAX - 1,
+            usize::MAX,
+        ] {
+            let reciprocal = Reciprocal::new(divisor);
+            for x in [0, 1, divisor - 1, divisor, usize::MAX - 1, usize::MAX] {
+                assert_eq!(reciprocal.divide(x), x / divisor, "{x} / {divisor}");
+            }
+        }
+    }
+
+    #[test]
+    fn refresh_changes_divisor() {
+        let mut reciprocal = Reciprocal::new(7);
+        reciprocal.refresh(7);
+        assert_eq!(reciprocal, Reciprocal::new(7));
+        reciprocal.refresh(12);
+        assert_eq!(reciprocal.divide(100), 8);
+    }
+
+    #[test]
+    #[should_panic]
+    fn reciprocal_of_zero() {
+        Reciprocal::new(0);
+    }
+
+    /// Property test verifying that reciprocal division agrees with ordinary
+    /// integer division.
+    #[quickcheck]
+    fn divide_matches_division(x: usize, divisor: usize) -> bool {
+        let divisor = divisor.max(1);
+        Reciprocal::new(divisor).divide(x) == x / divisor
+    }
+}
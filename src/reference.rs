@@ -0,0 +1,293 @@
+//! A direct, unoptimized transcription of the finite-precision encoder and
+//! decoder from mathematicalmonk's lectures, for checking that the optimized
+//! implementations in [`crate::encoder`] and [`crate::decoder`] are
+//! bit-for-bit identical to the algorithm they're based on.
+//!
+//! The reference rescales one step at a time, divides by R directly, and
+//! computes products in 128 bits, so it shares none of the shortcuts taken by
+//! the real coders. Like them, it rounds subinterval bounds down.
+//!
+//! [`cross_check`] and [`cross_check_random`] compare the coders against the
+//! reference for any alphabet and precision.
+
+use crate::alphabet::{Alphabet, Symbol};
+use crate::bits::Bit::{self, One, Zero};
+use crate::decoder::{DecodeError, Decoder, DecoderEvent};
+use crate::encoder::{EncodeError, Encoder};
+use crate::rng::SplitMix64;
+use std::iter::repeat_n;
+
+/// Encode a message (which must be terminated by EOF) as in the lecture.
+pub fn encode<A: Alphabet>(
+    alphabet: &A,
+    input: &[A::S],
+    precision: u32,
+) -> Result<Vec<Bit>, EncodeError> {
+    let whole: u128 = 1 << precision;
+    let half = whole / 2;
+    let quarter = whole / 4;
+
+    let mut output = Vec::new();
+    let mut emit = |bit: Bit, s: usize| {
+        let opposite = if bit == One { Zero } else { One };
+        output.push(bit);
+        output.extend(repeat_n(opposite, s));
+    };
+
+    alphabet.begin_message();
+    let (mut a, mut b, mut s) = (0, whole, 0);
+    let mut input = input.iter().enumerate();
+    loop {
+        let (position, symbol) = input.next().ok_or(EncodeError::UnterminatedStream)?;
+        if !alphabet.contains(symbol) {
+            return Err(EncodeError::SymbolNotInAlphabet { position });
+        }
+        let r = alphabet.total_interval_width() as u128;
+        let c = alphabet.interval_lower_bound(symbol) as u128;
+        let d = alphabet.interval_upper_bound(symbol) as u128;
+        alphabet.update(symbol);
+
+        let w = b - a;
+        b = a + w * d / r;
+        a += w * c / r;
+
+        while b < half || a > half {
+            if b < half {
+                emit(Zero, s);
+                s = 0;
+                a *= 2;
+                b *= 2;
+            } else if a > half {
+                emit(One, s);
+                s = 0;
+                a = 2 * (a - half);
+                b = 2 * (b - half);
+            }
+        }
+
+        while a > quarter && b < 3 * quarter {
+            s += 1;
+            a = 2 * (a - quarter);
+            b = 2 * (b - quarter);
+        }
+
+        if *symbol == alphabet.eof() {
+            break;
+        }
+    }
+
+    s += 1;
+    if a <= quarter {
+        emit(Zero, s);
+    } else {
+        emit(One, s);
+    }
+
+    Ok(output)
+}
+
+/// Decode a single message (including its EOF symbol) as in the lecture. Bits
+/// beyond the end of the input are taken to be zero.
+pub fn decode<A: Alphabet>(alphabet: &A, input: &[Bit], precision: u32) -> Vec<A::S> {
+    let whole: u128 = 1 << precision;
+    let half = whole / 2;
+    let quarter = whole / 4;
+    let bit = |i: usize| (input.get(i) == Some(&One)) as u128;
+
+    alphabet.begin_message();
+    let (mut a, mut b, mut z) = (0, whole, 0);
+    let mut i = 0;
+    while i < precision as usize {
+        z = 2 * z + bit(i);
+        i += 1;
+    }
+
+    let mut output = Vec::new();
+    loop {
+        let r = alphabet.total_interval_width() as u128;
+        let mut decoded = None;
+        for symbol in alphabet.symbols() {
+            let c = alphabet.interval_lower_bound(symbol) as u128;
+            let d = alphabet.interval_upper_bound(symbol) as u128;
+            let w = b - a;
+            let b_0 = a + w * d / r;
+            let a_0 = a + w * c / r;
+            if a_0 <= z && z < b_0 {
+                decoded = Some(symbol.clone());
+                a = a_0;
+                b = b_0;
+                break;
+            }
+        }
+
+        let symbol = decoded.expect("z lies within some subinterval");
+        alphabet.update(&symbol);
+        let eof = symbol == alphabet.eof();
+        output.push(symbol);
+        if eof {
+            return output;
+        }
+
+        while b < half || a > half {
+            if b < half {
+                a *= 2;
+                b *= 2;
+                z *= 2;
+            } else if a > half {
+                a = 2 * (a - half);
+                b = 2 * (b - half);
+                z = 2 * (z - half);
+            }
+            z += bit(i);
+            i += 1;
+        }
+
+        while a > quarter && b < 3 * quarter {
+            a = 2 * (a - quarter);
+            b = 2 * (b - quarter);
+            z = 2 * (z - quarter);
+            z += bit(i);
+            i += 1;
+        }
+    }
+}
+
+/// Ways in which the encoder or decoder can disagree with the reference
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum CrossCheckError<S: Symbol> {
+    #[error("Encoding {message:?} produced {actual:?}, but the reference produced {expected:?}")]
+    EncodedBits {
+        message: Vec<S>,
+        expected: Vec<Bit>,
+        actual: Vec<Bit>,
+    },
+    #[error("Decoding the encoding of {message:?} produced {actual:?}")]
+    DecodedSymbols { message: Vec<S>, actual: Vec<S> },
+    #[error(
+        "Decoder reported a length of {actual:?} for {message:?}, but its encoding has {expected} bits"
+    )]
+    MessageLength {
+        message: Vec<S>,
+        expected: usize,
+        actual: Option<usize>,
+    },
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+}
+
+/// Check that the encoder produces exactly the reference encoding of the
+/// given message (which must be terminated by EOF), and that the decoder
+/// recovers the message and its length from it.
+pub fn cross_check<A, const BITS_OF_PRECISION: u32>(
+    alphabet: &A,
+    message: &[A::S],
+) -> Result<(), CrossCheckError<A::S>>
+where
+    A: Alphabet,
+{
+    let expected = encode(alphabet, message, BITS_OF_PRECISION)?;
+    let actual = alphabet
+        .encode::<_, BITS_OF_PRECISION>(message.iter().cloned())
+        .collect::<Result<Vec<_>, _>>()?;
+    if actual != expected {
+        return Err(CrossCheckError::EncodedBits {
+            message: message.to_vec(),
+            expected,
+            actual,
+        });
+    }
+
+    let mut decoded = Vec::new();
+    let mut length = None;
+    for event in alphabet.decode::<_, BITS_OF_PRECISION>(expected.iter().copied()) {
+        match event? {
+            DecoderEvent::DecodedSymbol(symbol) => decoded.push(symbol),
+            DecoderEvent::MessageLength(message_length) => length = Some(message_length),
+        }
+    }
+    if decoded != message {
+        return Err(CrossCheckError::DecodedSymbols {
+            message: message.to_vec(),
+            actual: decoded,
+        });
+    }
+    if length != Some(expected.len()) {
+        return Err(CrossCheckError::MessageLength {
+            message: message.to_vec(),
+            expected: expected.len(),
+            actual: length,
+        });
+    }
+
+    Ok(())
+}
+
+/// Cross-check the coders against the reference (see [`cross_check`]) on the
+/// given number of random messages of up to `max_length` symbols, drawn
+/// uniformly from the symbols of the alphabet with nonzero width. The seed
+/// makes the messages reproducible.
+pub fn cross_check_random<A, const BITS_OF_PRECISION: u32>(
+    alphabet: &A,
+    messages: usize,
+    max_length: usize,
+    seed: u64,
+) -> Result<(), CrossCheckError<A::S>>
+where
+    A: Alphabet,
+{
+    alphabet.begin_message();
+    let eof = alphabet.eof();
+    let symbols: Vec<A::S> = alphabet
+        .symbols()
+        .filter(|&symbol| *symbol != eof && alphabet.interval_width(symbol) > 0)
+        .cloned()
+        .collect();
+
+    let mut rng = SplitMix64(seed);
+    for _ in 0..messages {
+        let length = match symbols.len() {
+            0 => 0,
+            _ => rng.below(max_length as u64 + 1) as usize,
+        };
+        let message: Vec<_> = (0..length)
+            .map(|_| symbols[rng.below(symbols.len() as u64) as usize].clone())
+            .chain([eof.clone()])
+            .collect();
+        cross_check::<_, BITS_OF_PRECISION>(alphabet, &message)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::example::{ExampleAlphabet, ExampleSymbol::*};
+
+    #[test]
+    fn reference_encodes_example() {
+        let alphabet = ExampleAlphabet::new();
+        assert_eq!(
+            encode(&alphabet, &[B, A, C, Eof], 32),
+            Ok(vec![Zero, One, Zero, One, One, One, Zero, Zero, One, Zero])
+        );
+    }
+
+    #[test]
+    fn reference_decodes_example() {
+        let alphabet = ExampleAlphabet::new();
+        assert_eq!(
+            decode(&alphabet, &[One, Zero, Zero, One, One, One], 32),
+            vec![B, B, Eof]
+        );
+    }
+
+    #[test]
+    fn coders_match_reference() {
+        let alphabet = ExampleAlphabet::new();
+        assert_eq!(cross_check_random::<_, 32>(&alphabet, 200, 100, 1), Ok(()));
+        assert_eq!(cross_check_random::<_, 12>(&alphabet, 200, 100, 2), Ok(()));
+    }
+}
@@ -0,0 +1,259 @@
+//! Closed-form rescaling shared by the encoder and decoder.
+//!
+//! Rather than testing the rescaling conditions and doubling the interval one
+//! step at a time, these functions determine how many consecutive steps apply
+//! using leading/trailing bit counts, so that any number of steps can be
+//! performed at once with shifts. This matters for skewed alphabets, where a
+//! single improbable symbol can trigger dozens of rescales.
+
+/// The smallest precision the coders support, as middle rescaling needs the
+/// interval to have quarters.
+pub(crate) const MIN_BITS_OF_PRECISION: u32 = 2;
+
+/// Determine how many consecutive side rescaling steps (those performed while
+/// b < half or a > half) apply to the interval [a, b), with 0 <= a < b <= whole.
+///
+/// Each step doubles the interval within the half containing it, which drops
+/// the most significant bit of a and b - 1. Steps continue for as long as that
+/// bit is the same in both, except that they stop early once a or b lands
+/// exactly on the midpoint, as the conditions are strict.
+pub(crate) fn side_rescale_count(a: usize, b: usize, precision: u32) -> u32 {
+    let last = b - 1;
+    let unused_bits = usize::BITS - precision;
+
+    // Number of leading bits shared by a and b - 1
+    let common_prefix = match a ^ last {
+        0 => precision,
+        diff => diff.leading_zeros() - unused_bits,
+    };
+
+    // After i steps, a is (a << i) truncated to the precision, which is half
+    // when a's remaining bits are a one followed by zeros.
+    let a_reaches_half = match a {
+        0 => precision,
+        _ => precision - 1 - a.trailing_zeros(),
+    };
+
+    // After i steps, b - 1 is (b - 1) << i with ones shifted in, so b is half
+    // when the remaining bits of b - 1 are a zero followed by ones.
+    let b_reaches_half = precision
+        .checked_sub(1 + last.trailing_ones())
+        .unwrap_or(precision);
+
+    common_prefix.min(a_reaches_half).min(b_reaches_half)
+}
+
+/// Apply `count` side rescaling steps to a value in the interval [a, b) (or to
+/// the lower bound a), returning the rescaled value. New low-order bits are
+/// zero.
+pub(crate) fn side_rescale(x: usize, count: u32, precision: u32) -> usize {
+    let whole_mask = (1 << precision) - 1;
+    (x << count) & whole_mask
+}
+
+/// Apply `count` side rescaling steps to the upper bound b of an interval.
+pub(crate) fn side_rescale_upper(b: usize, count: u32, precision: u32) -> usize {
+    let shifted_in_ones = (1 << count) - 1;
+    side_rescale(b - 1, count, precision) + shifted_in_ones + 1
+}
+
+/// Determine how many consecutive middle rescaling steps (those performed
+/// while a > quarter and b < 3 * quarter) apply to the interval [a, b), which
+/// must straddle the midpoint (a <= half <= b).
+///
+/// Each step doubles the distance of a and b from the midpoint, so steps
+/// continue until the larger of those distances reaches a quarter. The
+/// precision must be at least [`MIN_BITS_OF_PRECISION`].
+pub(crate) fn middle_rescale_count(a: usize, b: usize, precision: u32) -> u32 {
+    let half = 1 << (precision - 1);
+    let max_distance = (half - a).max(b - half);
+    let log2_distance = usize::BITS - 1 - max_distance.leading_zeros();
+    (precision - 2).saturating_sub(log2_distance)
+}
+
+/// Apply `count` middle rescaling steps to a value in the interval [a, b),
+/// returning the rescaled value. New low-order bits are zero.
+pub(crate) fn middle_rescale(x: usize, count: u32, precision: u32) -> usize {
+    let half: usize = 1 << (precision - 1);
+    (x.wrapping_sub(half) << count).wrapping_add(half)
+}
+
+/// A run of consecutive rescaling steps of the same kind.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum RescaleRun {
+    /// Steps performed while the interval lies entirely within one half.
+    Side(u32),
+    /// Steps performed while the interval straddles the midpoint closely.
+    Middle(u32),
+}
+
+impl RescaleRun {
+    /// Determine the next run of rescaling steps that applies to the interval
+    /// [a, b), if any.
+    ///
+    /// Side rescaling takes priority, and once it no longer applies the
+    /// interval straddles the midpoint, so middle rescaling may follow. After
+    /// middle rescaling the interval still straddles the midpoint, so at most
+    /// two runs ever apply in sequence.
+    pub(crate) fn next(a: usize, b: usize, precision: u32) -> Option<Self> {
+        match side_rescale_count(a, b, precision) {
+            0 => match middle_rescale_count(a, b, precision) {
+                0 => None,
+                count => Some(RescaleRun::Middle(count)),
+            },
+            count => Some(RescaleRun::Side(count)),
+        }
+    }
+
+    /// The number of steps in this run. Each step doubles the interval, and
+    /// corresponds to exactly one bit of the encoded output.
+    pub(crate) fn steps(&self) -> u32 {
+        match *self {
+            RescaleRun::Side(count) | RescaleRun::Middle(count) => count,
+        }
+    }
+
+    /// Apply this run to a value in the interval [a, b) (or the lower bound a).
+    pub(crate) fn apply(&self, x: usize, precision: u32) -> usize {
+        match *self {
+            RescaleRun::Side(count) => side_rescale(x, count, precision),
+            RescaleRun::Middle(count) => middle_rescale(x, count, precision),
+        }
+    }
+
+    /// Apply this run to the upper bound b of an interval.
+    pub(crate) fn apply_upper(&self, b: usize, precision: u32) -> usize {
+        match *self {
+            RescaleRun::Side(count) => side_rescale_upper(b, count, precision),
+            RescaleRun::Middle(count) => middle_rescale(b, count, precision),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Perform side rescaling one step at a time, as in the lecture, returning
+    /// the number of steps and the resulting interval.
+    fn naive_side_rescaling(mut a: usize, mut b: usize, precision: u32) -> (u32, usize, usize) {
+        let half = 1 << (precision - 1);
+        let mut count = 0;
+        while b < half || a > half {
+            if b < half {
+                a *= 2;
+                b *= 2;
+            } else {
+                a = 2 * (a - half);
+                b = 2 * (b - half);
+            }
+            count += 1;
+        }
+        (count, a, b)
+    }
+
+    /// Perform middle rescaling one step at a time, as in the lecture.
+    fn naive_middle_rescaling(mut a: usize, mut b: usize, precision: u32) -> (u32, usize, usize) {
+        let quarter = 1 << (precision - 2);
+        let mut count = 0;
+        while a > quarter && b < 3 * quarter {
+            a = 2 * (a - quarter);
+            b = 2 * (b - quarter);
+            count += 1;
+        }
+        (count, a, b)
+    }
+
+    #[test]
+    fn side_rescaling_matches_naive() {
+        for precision in 2..=9 {
+            let whole = 1 << precision;
+            for a in 0..whole {
+                for b in a + 1..=whole {
+                    let count = side_rescale_count(a, b, precision);
+                    assert_eq!(
+                        (
+                            count,
+                            side_rescale(a, count, precision),
+                            side_rescale_upper(b, count, precision)
+                        ),
+                        naive_side_rescaling(a, b, precision),
+                        "a={a} b={b} precision={precision}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn middle_rescaling_matches_naive() {
+        for precision in 2..=9 {
+            let whole = 1 << precision;
+            let half = whole / 2;
+            for a in 0..=half {
+                for b in half.max(a + 1)..=whole {
+                    let count = middle_rescale_count(a, b, precision);
+                    assert_eq!(
+                        (
+                            count,
+                            middle_rescale(a, count, precision),
+                            middle_rescale(b, count, precision)
+                        ),
+                        naive_middle_rescaling(a, b, precision),
+                        "a={a} b={b} precision={precision}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn side_rescaling_at_full_precision() {
+        let precision = 32;
+        let half = 1 << 31;
+        let whole = 1 << 32;
+        for (a, b) in [(half + 1, half + 2), (half + 1, whole), (0, 1), (7, 8)] {
+            let count = side_rescale_count(a, b, precision);
+            assert_eq!(
+                (
+                    count,
+                    side_rescale(a, count, precision),
+                    side_rescale_upper(b, count, precision)
+                ),
+                naive_side_rescaling(a, b, precision),
+                "a={a} b={b}"
+            );
+        }
+    }
+
+    #[test]
+    fn rescaling_runs_match_naive() {
+        let precision = 8;
+        let whole = 1 << precision;
+        for a in 0..whole {
+            for b in a + 1..=whole {
+                let (side_count, naive_a, naive_b) = naive_side_rescaling(a, b, precision);
+                let (middle_count, naive_a, naive_b) =
+                    naive_middle_rescaling(naive_a, naive_b, precision);
+
+                let (mut rescaled_a, mut rescaled_b) = (a, b);
+                let mut runs = Vec::new();
+                while let Some(rescaling) = RescaleRun::next(rescaled_a, rescaled_b, precision) {
+                    rescaled_a = rescaling.apply(rescaled_a, precision);
+                    rescaled_b = rescaling.apply_upper(rescaled_b, precision);
+                    runs.push(rescaling);
+                }
+
+                let expected_runs: Vec<_> = [
+                    RescaleRun::Side(side_count),
+                    RescaleRun::Middle(middle_count),
+                ]
+                .into_iter()
+                .filter(|run| run.steps() > 0)
+                .collect();
+                assert_eq!(runs, expected_runs, "a={a} b={b}");
+                assert_eq!((rescaled_a, rescaled_b), (naive_a, naive_b), "a={a} b={b}");
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Error-resilient streams, which a decoder can pick up again after losing
+//! or corrupting part of its input.
+//!
+//! The message is split into segments of a fixed number of symbols, each
+//! coded as a separate message (terminated by EOF, with the model started
+//! afresh) and packed into bytes. Every segment is preceded by a restart
+//! marker, the byte `0xFF` followed by `0xD0` to `0xD7`, cycling through the
+//! eight markers in turn. Any `0xFF` byte within a segment is followed by a
+//! stuffed `0x00` byte, so markers can't occur inside a segment.
+//!
+//! When a segment fails to decode, the decoder scans forward to the next
+//! marker and carries on from there, and a gap in the cycle of markers shows
+//! that whole segments were lost (as long as fewer than eight were lost in a
+//! row). Note that arithmetic coding doesn't detect corruption by itself: a
+//! damaged segment often decodes to the wrong symbols rather than failing.
+//!
+//! Shorter segments lose less on an error, but every segment pays for its
+//! marker, EOF, and final bits, and an adaptive model has to learn the
+//! statistics of each segment from scratch.
+
+use crate::alphabet::Alphabet;
+use crate::bits::SliceBits;
+use crate::decoder::{DecodeError, Decoder};
+use crate::encoder::{EncodeError, Encoder};
+
+/// The first byte of every marker, and the byte which is escaped in segments
+const MARKER: u8 = 0xFF;
+/// The second byte of the first marker in the cycle
+const FIRST_RESTART: u8 = 0xD0;
+/// The number of distinct markers, which are used in turn
+const RESTARTS: u8 = 8;
+/// The byte stuffed after each [`MARKER`] byte in a segment
+const STUFFING: u8 = 0x00;
+
+/// Errors that can occur while decoding a segment
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum SegmentError {
+    #[error("The segment is missing from the input")]
+    Missing,
+    #[error("The segment contains an unescaped 0xFF byte")]
+    InvalidEscape,
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+}
+
+/// Encode a stream of symbols (not including EOF) as segments of at most
+/// `segment_len` symbols, each preceded by a restart marker.
+pub fn encode_segments<A: Alphabet, const BITS_OF_PRECISION: u32>(
+    alphabet: &A,
+    symbols: &[A::S],
+    segment_len: usize,
+) -> Result<Vec<u8>, EncodeError> {
+    assert!(segment_len > 0, "Segments must hold at least one symbol");
+    let mut output = Vec::new();
+    for (index, segment) in symbols.chunks(segment_len).enumerate() {
+        output.extend([MARKER, FIRST_RESTART + (index % RESTARTS as usize) as u8]);
+        let message = segment.iter().cloned().chain([alphabet.eof()]);
+        for byte in alphabet.encode::<_, BITS_OF_PRECISION>(message).bytes() {
+            let byte = byte?;
+            output.push(byte);
+            if byte == MARKER {
+                output.push(STUFFING);
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Decode the segments produced by [`encode_segments`], returning the symbols
+/// of each (not including EOF) in order, or the reason it couldn't be
+/// decoded.
+///
+/// Any input before the first marker is skipped.
+pub fn decode_segments<A: Alphabet, const BITS_OF_PRECISION: u32>(
+    alphabet: &A,
+    input: &[u8],
+) -> Vec<Result<Vec<A::S>, SegmentError>> {
+    let mut segments = Vec::new();
+    let mut expected = None;
+    for (restart, segment) in split_segments(input) {
+        if let Some(expected) = expected {
+            let missing = (restart + RESTARTS - expected) % RESTARTS;
+            segments.extend((0..missing).map(|_| Err(SegmentError::Missing)));
+        }
+        expected = Some((restart + 1) % RESTARTS);
+        segments.push(decode_segment::<_, BITS_OF_PRECISION>(alphabet, segment));
+    }
+    segments
+}
+
+/// Split the input at its markers, into the position of each marker in the
+/// cycle and the (still escaped) segment following it.
+fn split_segments(input: &[u8]) -> Vec<(u8, &[u8])> {
+    let is_marker = |i: usize| {
+        input[i] == MARKER
+            && input
+                .get(i + 1)
+                .is_some_and(|next| (FIRST_RESTART..FIRST_RESTART + RESTARTS).contains(next))
+    };
+    let markers: Vec<usize> = (0..input.len()).filter(|&i| is_marker(i)).collect();
+    markers
+        .iter()
+        .enumerate()
+        .map(|(n, &start)| {
+            let end = markers.get(n + 1).copied().unwrap_or(input.len());
+            (input[start + 1] - FIRST_RESTART, &input[start + 2..end])
+        })
+        .collect()
+}
+
+/// Remove the stuffing from a segment and decode it.
+fn decode_segment<A: Alphabet, const BITS_OF_PRECISION: u32>(
+    alphabet: &A,
+    segment: &[u8],
+) -> Result<Vec<A::S>, SegmentError> {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut escaped = segment.iter();
+    while let Some(&byte) = escaped.next() {
+        if byte == MARKER && escaped.next() != Some(&STUFFING) {
+            return Err(SegmentError::InvalidEscape);
+        }
+        bytes.push(byte);
+    }
+    let (symbols, _) = alphabet.decode_to_vec::<_, BITS_OF_PRECISION>(SliceBits::new(&bytes))?;
+    Ok(symbols)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::byte::{ByteAlphabet, ByteSymbol};
+    use crate::models::AdaptiveAlphabet;
+
+    fn sample() -> (ByteAlphabet, Vec<ByteSymbol>) {
+        let text = b"she sells sea shells by the sea shore ".repeat(10);
+        let alphabet = ByteAlphabet::from_sample(&text);
+        let symbols = text.iter().map(|&byte| ByteSymbol::Byte(byte)).collect();
+        (alphabet, symbols)
+    }
+
+    #[test]
+    fn round_trip_segments() {
+        let (alphabet, symbols) = sample();
+        for segment_len in [1, 7, 100, 1000] {
+            let bytes = encode_segments::<_, 32>(&alphabet, &symbols, segment_len).unwrap();
+            let segments: Result<Vec<_>, _> = decode_segments::<_, 32>(&alphabet, &bytes)
+                .into_iter()
+                .collect();
+            assert_eq!(
+                segments.map(|segments| segments.concat()),
+                Ok(symbols.clone())
+            );
+        }
+
+        // Each segment starts the model afresh
+        let adaptive =
+            AdaptiveAlphabet::new(alphabet.symbols().cloned().collect(), ByteSymbol::Eof);
+        let bytes = encode_segments::<_, 32>(&adaptive, &symbols, 50).unwrap();
+        let segments: Vec<_> = decode_segments::<_, 32>(&adaptive, &bytes);
+        assert_eq!(segments.len(), 8);
+        assert!(segments.iter().all(Result::is_ok));
+    }
+
+    #[test]
+    fn segments_escape_markers() {
+        // Repeating the most likely byte codes as mostly one bits
+        let mut weights = [1; 256];
+        weights[255] = 10000;
+        let alphabet = ByteAlphabet::from_weights(weights, 1);
+        let symbols = [ByteSymbol::Byte(255); 1000];
+        let bytes = encode_segments::<_, 32>(&alphabet, &symbols, 250).unwrap();
+        assert!(bytes.windows(2).any(|pair| pair == [MARKER, STUFFING]));
+        assert_eq!(split_segments(&bytes).len(), 4);
+        let segments: Result<Vec<_>, _> = decode_segments::<_, 32>(&alphabet, &bytes)
+            .into_iter()
+            .collect();
+        assert_eq!(
+            segments.map(|segments| segments.concat()),
+            Ok(symbols.to_vec())
+        );
+    }
+
+    #[test]
+    fn resynchronize_after_errors() {
+        let (alphabet, symbols) = sample();
+        let bytes = encode_segments::<_, 32>(&alphabet, &symbols, 50).unwrap();
+        let expected: Vec<_> = symbols.chunks(50).map(<[_]>::to_vec).collect();
+        let markers: Vec<_> = (0..bytes.len() - 1)
+            .filter(|&i| bytes[i] == MARKER && bytes[i + 1] != STUFFING)
+            .collect();
+
+        // Drop the second and third segments, and garble the start of the first
+        let mut damaged = vec![0x12, 0x34];
+        damaged.extend(&bytes[..markers[1]]);
+        damaged.extend(&bytes[markers[3]..]);
+        damaged[4] = MARKER;
+        damaged[5] = 0x01;
+        let segments = decode_segments::<_, 32>(&alphabet, &damaged);
+        assert_eq!(segments.len(), expected.len());
+        assert_eq!(segments[0], Err(SegmentError::InvalidEscape));
+        assert_eq!(segments[1], Err(SegmentError::Missing));
+        assert_eq!(segments[2], Err(SegmentError::Missing));
+        for (segment, expected) in segments.into_iter().zip(expected).skip(3) {
+            assert_eq!(segment, Ok(expected));
+        }
+    }
+}
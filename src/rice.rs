@@ -0,0 +1,331 @@
+//! Golomb–Rice coding of non-negative integers.
+//!
+//! With parameter k, a value v is written as the quotient v >> k in unary (that
+//! many ones, terminated by a zero) followed by the k low bits of v, most
+//! significant first. This is optimal for geometrically distributed values, and
+//! costs no more than a few shifts per value, so it is a cheap alternative to
+//! arithmetic coding for nearly-geometric data such as prediction residuals.
+//!
+//! So that an outlier can't cost an unbounded number of bits, a quotient of
+//! [`MAX_QUOTIENT`] or more is escaped: [`MAX_QUOTIENT`] ones, with no
+//! terminating zero, followed by all 64 bits of the value.
+//!
+//! A block of values can be written with [`encode_block`], which picks the
+//! best parameter for the block and records it in a header ahead of the values.
+//! A block stream of framed values records it in each frame's header instead,
+//! coding each block with Rice or arithmetic coding, whichever is smaller
+//! (see `format::encode_values`, with the `format` feature).
+
+use crate::bits::Bit::{One, Zero};
+use crate::bits::{BitSink, BitSource};
+
+/// Errors that can occur while decoding Rice-coded values
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum RiceError {
+    #[error("Input ended in the middle of a value")]
+    UnexpectedEndOfInput,
+    #[error("Decoded value does not fit in 64 bits")]
+    Overflow,
+}
+
+/// The largest parameter that can be used; larger ones would make the
+/// remainder wider than a value.
+pub const MAX_PARAMETER: u32 = 63;
+
+/// The smallest quotient which is escaped, and written as the raw value
+pub const MAX_QUOTIENT: u64 = 32;
+
+/// The number of bits of a block header, which holds the block's parameter
+const HEADER_BITS: u32 = 6;
+
+/// The number of bits needed to encode a value with parameter k.
+pub fn encoded_len(value: u64, k: u32) -> u64 {
+    match value >> k {
+        quotient if quotient < MAX_QUOTIENT => quotient + 1 + k as u64,
+        _ => MAX_QUOTIENT + u64::BITS as u64,
+    }
+}
+
+/// Choose the parameter which encodes the given values in the fewest bits,
+/// along with that number of bits.
+pub fn best_parameter(values: &[u64]) -> (u32, u64) {
+    (0..=MAX_PARAMETER)
+        .map(|k| (k, values.iter().map(|&v| encoded_len(v, k)).sum()))
+        .min_by_key(|&(_, bits)| bits)
+        .expect("There is at least one parameter")
+}
+
+/// Write a value to the sink using parameter k.
+pub fn encode<K: BitSink>(value: u64, k: u32, sink: &mut K) {
+    assert!(k <= MAX_PARAMETER, "Rice parameter {k} is too large");
+    let quotient = value >> k;
+    for _ in 0..quotient.min(MAX_QUOTIENT) {
+        sink.write_bit(One);
+    }
+    if quotient < MAX_QUOTIENT {
+        sink.write_bit(Zero);
+        write_raw(value, k, sink);
+    } else {
+        write_raw(value, u64::BITS, sink);
+    }
+}
+
+/// Write the low bits of a value, most significant first.
+fn write_raw<K: BitSink>(value: u64, bits: u32, sink: &mut K) {
+    for position in (0..bits).rev() {
+        sink.write_bit(((value >> position) & 1 == 1).into());
+    }
+}
+
+/// Read a value of the given number of bits, most significant first.
+fn read_raw<B: BitSource>(source: &mut B, bits: u32) -> Result<u64, RiceError> {
+    let mut value = 0;
+    for _ in 0..bits {
+        let bit = source.next_bit().ok_or(RiceError::UnexpectedEndOfInput)?;
+        value = (value << 1) | (bit == One) as u64;
+    }
+    Ok(value)
+}
+
+/// Read a value written with parameter k from the source.
+pub fn decode<B: BitSource>(source: &mut B, k: u32) -> Result<u64, RiceError> {
+    assert!(k <= MAX_PARAMETER, "Rice parameter {k} is too large");
+    let mut quotient: u64 = 0;
+    loop {
+        match source.next_bit() {
+            None => return Err(RiceError::UnexpectedEndOfInput),
+            Some(Zero) => break,
+            Some(One) => quotient += 1,
+        }
+        if quotient > u64::MAX >> k {
+            return Err(RiceError::Overflow);
+        }
+        if quotient == MAX_QUOTIENT {
+            return read_raw(source, u64::BITS);
+        }
+    }
+
+    let remainder = read_raw(source, k)?;
+    Ok((quotient << k) | remainder)
+}
+
+/// Write a block of values, with a header holding the parameter which
+/// encodes them in the fewest bits.
+pub fn encode_block<K: BitSink>(values: &[u64], sink: &mut K) {
+    let (k, _) = best_parameter(values);
+    write_raw(k as u64, HEADER_BITS, sink);
+    for &value in values {
+        encode(value, k, sink);
+    }
+}
+
+/// Read a block of `len` values written by [`encode_block`].
+pub fn decode_block<B: BitSource>(source: &mut B, len: usize) -> Result<Vec<u64>, RiceError> {
+    let k = read_raw(source, HEADER_BITS)? as u32;
+    (0..len).map(|_| decode(source, k)).collect()
+}
+
+/// An encoder for a sequence of values which adapts its parameter to the
+/// recent magnitude of the values, so no parameter needs to be chosen or
+/// transmitted up front.
+///
+/// The parameter is derived from a running mean of the values, which both
+/// sides update identically after every value; [`AdaptiveRiceDecoder`]
+/// mirrors it.
+#[derive(Clone, Debug)]
+pub struct AdaptiveRiceEncoder {
+    state: AdaptiveParameter,
+}
+
+impl AdaptiveRiceEncoder {
+    pub fn new() -> Self {
+        Self {
+            state: AdaptiveParameter::new(),
+        }
+    }
+
+    /// Write the next value to the sink.
+    pub fn encode<K: BitSink>(&mut self, value: u64, sink: &mut K) {
+        encode(value, self.state.parameter(), sink);
+        self.state.update(value);
+    }
+}
+
+impl Default for AdaptiveRiceEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The decoder for values written by an [`AdaptiveRiceEncoder`].
+#[derive(Clone, Debug)]
+pub struct AdaptiveRiceDecoder {
+    state: AdaptiveParameter,
+}
+
+impl AdaptiveRiceDecoder {
+    pub fn new() -> Self {
+        Self {
+            state: AdaptiveParameter::new(),
+        }
+    }
+
+    /// Read the next value from the source.
+    pub fn decode<B: BitSource>(&mut self, source: &mut B) -> Result<u64, RiceError> {
+        let value = decode(source, self.state.parameter())?;
+        self.state.update(value);
+        Ok(value)
+    }
+}
+
+impl Default for AdaptiveRiceDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A running sum of recent values, decayed so that it tracks about the last
+/// 2^WINDOW_LOG2 values, from which the parameter is estimated as
+/// floor(log2(mean)).
+#[derive(Clone, Debug)]
+struct AdaptiveParameter {
+    sum: u64,
+}
+
+impl AdaptiveParameter {
+    const WINDOW_LOG2: u32 = 4;
+
+    fn new() -> Self {
+        Self { sum: 0 }
+    }
+
+    fn parameter(&self) -> u32 {
+        let mean = self.sum >> Self::WINDOW_LOG2;
+        match mean {
+            0 => 0,
+            mean => (u64::BITS - 1 - mean.leading_zeros()).min(MAX_PARAMETER),
+        }
+    }
+
+    fn update(&mut self, value: u64) {
+        self.sum = (self.sum - (self.sum >> Self::WINDOW_LOG2)).saturating_add(value);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bits::Bit;
+
+    fn encode_all(values: &[u64], k: u32) -> Vec<Bit> {
+        let mut bits = Vec::new();
+        for &value in values {
+            encode(value, k, &mut bits);
+        }
+        bits
+    }
+
+    #[test]
+    fn encode_with_parameter() {
+        // 9 = 0b1001, k = 2: quotient 2 (110), remainder 01
+        assert_eq!(encode_all(&[9], 2), vec![One, One, Zero, Zero, One]);
+        assert_eq!(encode_all(&[0], 0), vec![Zero]);
+    }
+
+    #[test]
+    fn large_quotients_are_escaped() {
+        let value = 1000;
+        let bits = encode_all(&[value], 0);
+        assert_eq!(bits.len() as u64, MAX_QUOTIENT + 64);
+        assert_eq!(encoded_len(value, 0), MAX_QUOTIENT + 64);
+        assert!(bits[..MAX_QUOTIENT as usize].iter().all(|&bit| bit == One));
+
+        // The largest quotient which isn't escaped
+        let value = ((MAX_QUOTIENT - 1) << 3) | 5;
+        assert_eq!(encoded_len(value, 3), MAX_QUOTIENT + 3);
+        for value in [value, value + 3, u64::MAX] {
+            let bits = encode_all(&[value], 3);
+            assert_eq!(decode(&mut bits.into_iter(), 3), Ok(value));
+        }
+    }
+
+    #[test]
+    fn blocks_record_their_parameter() {
+        let small = [0, 1, 0, 2, 1];
+        let large = [100, 120, 90, 110, 5000];
+        let mut bits: Vec<Bit> = Vec::new();
+        encode_block(&small, &mut bits);
+        encode_block(&large, &mut bits);
+        let (small_k, small_len) = best_parameter(&small);
+        let (large_k, large_len) = best_parameter(&large);
+        assert!(small_k < large_k);
+        let header_len = 2 * HEADER_BITS as u64;
+        assert_eq!(bits.len() as u64, header_len + small_len + large_len);
+
+        let mut source = bits.into_iter();
+        assert_eq!(decode_block(&mut source, small.len()), Ok(small.to_vec()));
+        assert_eq!(decode_block(&mut source, large.len()), Ok(large.to_vec()));
+        assert_eq!(source.next(), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        let values = [0, 1, 5, 17, 1000, 3, u64::MAX >> 2];
+        for k in [0, 3, 10, 62] {
+            let values: Vec<_> = values.iter().map(|&v| v.min(1 << 20 << k)).collect();
+            let bits = encode_all(&values, k);
+            assert_eq!(
+                bits.len() as u64,
+                values.iter().map(|&v| encoded_len(v, k)).sum::<u64>()
+            );
+            let mut source = bits.into_iter();
+            for &value in &values {
+                assert_eq!(decode(&mut source, k), Ok(value));
+            }
+        }
+    }
+
+    #[test]
+    fn best_parameter_matches_magnitude() {
+        assert_eq!(best_parameter(&[0, 0, 1, 0]).0, 0);
+        let (k, bits) = best_parameter(&[100, 120, 90, 110]);
+        assert_eq!(k, 6);
+        assert_eq!(bits, encode_all(&[100, 120, 90, 110], k).len() as u64);
+    }
+
+    #[test]
+    fn error_on_truncated_input() {
+        let bits = encode_all(&[9], 2);
+        assert_eq!(
+            decode(&mut bits[..3].iter().copied(), 2),
+            Err(RiceError::UnexpectedEndOfInput)
+        );
+        assert_eq!(
+            decode(&mut [One, One].into_iter(), 2),
+            Err(RiceError::UnexpectedEndOfInput)
+        );
+    }
+
+    #[test]
+    fn error_on_overflow() {
+        let mut ones = std::iter::repeat(One);
+        assert_eq!(decode(&mut ones, 60), Err(RiceError::Overflow));
+    }
+
+    #[test]
+    fn adaptive_round_trip() {
+        let values: Vec<u64> = (0..200).map(|i| (i * 37 % 11) << (i / 40)).collect();
+        let mut encoder = AdaptiveRiceEncoder::new();
+        let mut bits: Vec<Bit> = Vec::new();
+        for &value in &values {
+            encoder.encode(value, &mut bits);
+        }
+
+        let mut decoder = AdaptiveRiceDecoder::new();
+        let mut source = bits.into_iter();
+        for &value in &values {
+            assert_eq!(decoder.decode(&mut source), Ok(value));
+        }
+        assert_eq!(source.next(), None);
+    }
+}
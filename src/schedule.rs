@@ -0,0 +1,203 @@
+use crate::alphabet::{Alphabet, Symbol};
+use std::cell::Cell;
+
+/// A model in which the distribution of each symbol depends on its position
+/// in the message.
+///
+/// This suits fixed-layout data such as protocol headers, where each field
+/// has a known position and its own distribution. The i-th symbol of a
+/// message is coded with the i-th alphabet of the schedule, and once the
+/// schedule runs out, its last alphabet is used for all remaining symbols.
+///
+/// Every alphabet in the schedule must share the same EOF symbol, and the EOF
+/// symbol must have a nonzero width wherever a message may end.
+///
+/// The current position is advanced through [`Alphabet::update`], so a single
+/// model shouldn't be used by two encoders or decoders at the same time.
+pub struct ScheduleModel<A: Alphabet> {
+    schedule: Vec<A>,
+    position: Cell<usize>,
+}
+
+impl<A: Alphabet> ScheduleModel<A> {
+    /// Construct a model which codes the i-th symbol with the i-th alphabet in
+    /// the schedule, which must not be empty.
+    pub fn new(schedule: Vec<A>) -> Self {
+        assert!(!schedule.is_empty(), "Schedule must contain an alphabet");
+        Self {
+            schedule,
+            position: Cell::new(0),
+        }
+    }
+
+    /// The position in the message of the next symbol to be coded.
+    pub fn position(&self) -> usize {
+        self.position.get()
+    }
+
+    /// The alphabet used for the next symbol.
+    pub fn current(&self) -> &A {
+        let last = self.schedule.len() - 1;
+        &self.schedule[self.position.get().min(last)]
+    }
+}
+
+impl<S: Symbol, A: Alphabet<S = S>> Alphabet for ScheduleModel<A> {
+    type S = S;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.current().symbols()
+    }
+
+    fn eof(&self) -> Self::S {
+        self.current().eof()
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        self.current().interval_width(symbol)
+    }
+
+    fn total_interval_width(&self) -> usize {
+        self.current().total_interval_width()
+    }
+
+    fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+        self.current().interval_lower_bound(symbol)
+    }
+
+    fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+        self.current().interval_upper_bound(symbol)
+    }
+
+    fn quantile(&self, value: usize) -> &Self::S {
+        self.current().quantile(value)
+    }
+
+    fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        self.current().checked_cdf(symbol)
+    }
+
+    fn contains(&self, symbol: &Self::S) -> bool {
+        self.current().contains(symbol)
+    }
+
+    fn index(&self, symbol: &Self::S) -> usize {
+        self.current().index(symbol)
+    }
+
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        self.current().symbol_at(index)
+    }
+
+    fn begin_message(&self) {
+        self.position.set(0);
+    }
+
+    fn update(&self, _symbol: &Self::S) {
+        self.position.set(self.position.get() + 1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoder::{Decoder, DecoderEvent};
+    use crate::encoder::Encoder;
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum Field {
+        Version(u8),
+        Flag(bool),
+        Eof,
+    }
+
+    impl Symbol for Field {}
+
+    /// An alphabet with explicit symbols and widths.
+    struct Table(Vec<(Field, usize)>);
+
+    impl Alphabet for Table {
+        type S = Field;
+
+        fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+            self.0.iter().map(|(symbol, _)| symbol)
+        }
+
+        fn eof(&self) -> Self::S {
+            Field::Eof
+        }
+
+        fn interval_width(&self, symbol: &Self::S) -> usize {
+            self.0
+                .iter()
+                .find(|(s, _)| s == symbol)
+                .map_or(0, |&(_, width)| width)
+        }
+    }
+
+    /// A header consisting of a version, which is almost always 1, followed by
+    /// any number of flags, which are usually set.
+    fn header_model() -> ScheduleModel<Table> {
+        ScheduleModel::new(vec![
+            Table(vec![
+                (Field::Version(1), 1000),
+                (Field::Version(2), 10),
+                (Field::Eof, 1),
+            ]),
+            Table(vec![
+                (Field::Flag(true), 30),
+                (Field::Flag(false), 10),
+                (Field::Eof, 1),
+            ]),
+        ])
+    }
+
+    fn decode(model: &ScheduleModel<Table>, bits: Vec<crate::bits::Bit>) -> Vec<Field> {
+        model
+            .decode::<_, 32>(bits)
+            .filter_map(|event| match event.unwrap() {
+                DecoderEvent::DecodedSymbol(symbol) => Some(symbol),
+                DecoderEvent::MessageLength(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn schedule_advances_per_symbol() {
+        let model = header_model();
+        assert_eq!(model.current().0.len(), 3);
+        model.update(&Field::Version(1));
+        model.update(&Field::Flag(true));
+        model.update(&Field::Flag(true));
+        assert_eq!(model.position(), 3);
+        assert_eq!(model.interval_width(&Field::Flag(true)), 30);
+        model.begin_message();
+        assert_eq!(model.interval_width(&Field::Version(1)), 1000);
+    }
+
+    #[test]
+    fn round_trip_through_schedule() {
+        let model = header_model();
+        let input = vec![
+            Field::Version(2),
+            Field::Flag(true),
+            Field::Flag(false),
+            Field::Flag(true),
+            Field::Eof,
+        ];
+
+        let bits: Vec<_> = model
+            .encode::<_, 32>(input.clone())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(decode(&model, bits), input);
+
+        // Each message starts again from the beginning of the schedule
+        let input = vec![Field::Version(1), Field::Eof];
+        let bits: Vec<_> = model
+            .encode::<_, 32>(input.clone())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(decode(&model, bits), input);
+    }
+}
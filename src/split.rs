@@ -0,0 +1,212 @@
+//! Splitting a stream of symbols into several sub-streams, each of which can be
+//! coded with its own model, and merging them back together.
+//!
+//! Symbols are assigned to sub-streams by a function of their index in the
+//! stream and the symbols preceding them. As the preceding symbols are known
+//! when merging, the same function reproduces the assignment on decode. This
+//! covers round-robin splitting (see [`round_robin`]) of interleaved data such
+//! as image planes, as well as assignments that depend on context.
+
+use crate::alphabet::Alphabet;
+use crate::bits::Bit;
+use crate::decoder::{DecodeError, Decoder, DecoderEvent};
+use crate::encoder::{EncodeError, Encoder};
+use std::collections::VecDeque;
+
+/// Errors that can occur while splitting or merging streams
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum SplitError {
+    #[error(
+        "Symbol at index {index} was assigned to stream {stream}, but there are only {streams}"
+    )]
+    NoSuchStream {
+        index: usize,
+        stream: usize,
+        streams: usize,
+    },
+    #[error("Stream {0} has symbols left over after merging")]
+    LeftoverSymbols(usize),
+    #[error("Expected {expected} encoded streams, got {actual}")]
+    StreamCount { expected: usize, actual: usize },
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+}
+
+/// An assignment which distributes symbols over the given number of streams
+/// in turn.
+pub fn round_robin<T>(streams: usize) -> impl FnMut(usize, &[T]) -> usize {
+    move |index, _| index % streams
+}
+
+/// Look up which stream a symbol is assigned to, checking that it exists.
+fn assigned_stream<T>(
+    assign: &mut impl FnMut(usize, &[T]) -> usize,
+    preceding: &[T],
+    streams: usize,
+) -> Result<usize, SplitError> {
+    let index = preceding.len();
+    match assign(index, preceding) {
+        stream if stream < streams => Ok(stream),
+        stream => Err(SplitError::NoSuchStream {
+            index,
+            stream,
+            streams,
+        }),
+    }
+}
+
+/// Split a stream of symbols into the given number of sub-streams, putting the
+/// symbol at each index into the stream chosen by `assign`.
+pub fn split<T: Clone>(
+    symbols: &[T],
+    streams: usize,
+    mut assign: impl FnMut(usize, &[T]) -> usize,
+) -> Result<Vec<Vec<T>>, SplitError> {
+    let mut split = vec![Vec::new(); streams];
+    for (index, symbol) in symbols.iter().enumerate() {
+        let stream = assigned_stream(&mut assign, &symbols[..index], streams)?;
+        split[stream].push(symbol.clone());
+    }
+    Ok(split)
+}
+
+/// Merge sub-streams produced by [`split`] with the same assignment.
+///
+/// Merging stops when the stream assigned to the next index is empty, at
+/// which point every other stream must be empty as well.
+pub fn merge<T: Clone>(
+    streams: Vec<Vec<T>>,
+    mut assign: impl FnMut(usize, &[T]) -> usize,
+) -> Result<Vec<T>, SplitError> {
+    let count = streams.len();
+    let mut streams: Vec<VecDeque<T>> = streams.into_iter().map(VecDeque::from).collect();
+    let mut merged = Vec::with_capacity(streams.iter().map(VecDeque::len).sum());
+
+    while streams.iter().any(|stream| !stream.is_empty()) {
+        let stream = assigned_stream(&mut assign, &merged, count)?;
+        match streams[stream].pop_front() {
+            Some(symbol) => merged.push(symbol),
+            None => break,
+        }
+    }
+
+    match streams.iter().position(|stream| !stream.is_empty()) {
+        Some(stream) => Err(SplitError::LeftoverSymbols(stream)),
+        None => Ok(merged),
+    }
+}
+
+/// Split a stream of symbols (not including EOF) with the given assignment,
+/// and encode each sub-stream as a separate message using its own alphabet.
+pub fn encode_split<A, const BITS_OF_PRECISION: u32>(
+    alphabets: &[A],
+    symbols: &[A::S],
+    assign: impl FnMut(usize, &[A::S]) -> usize,
+) -> Result<Vec<Vec<Bit>>, SplitError>
+where
+    A: Alphabet,
+{
+    split(symbols, alphabets.len(), assign)?
+        .into_iter()
+        .zip(alphabets)
+        .map(|(stream, alphabet)| {
+            let message = stream.into_iter().chain([alphabet.eof()]);
+            Ok(alphabet.encode_to_vec::<_, BITS_OF_PRECISION>(message)?)
+        })
+        .collect()
+}
+
+/// Decode streams produced by [`encode_split`] with the same alphabets and
+/// assignment, and merge them back into a single stream of symbols (not
+/// including EOF).
+pub fn decode_merged<A, const BITS_OF_PRECISION: u32>(
+    alphabets: &[A],
+    encoded: &[Vec<Bit>],
+    assign: impl FnMut(usize, &[A::S]) -> usize,
+) -> Result<Vec<A::S>, SplitError>
+where
+    A: Alphabet,
+{
+    if encoded.len() != alphabets.len() {
+        return Err(SplitError::StreamCount {
+            expected: alphabets.len(),
+            actual: encoded.len(),
+        });
+    }
+
+    let mut streams = Vec::with_capacity(alphabets.len());
+    for (alphabet, bits) in alphabets.iter().zip(encoded) {
+        let mut stream = Vec::new();
+        for event in alphabet.decode::<_, BITS_OF_PRECISION>(bits.iter().copied()) {
+            if let DecoderEvent::DecodedSymbol(symbol) = event?
+                && symbol != alphabet.eof()
+            {
+                stream.push(symbol);
+            }
+        }
+        streams.push(stream);
+    }
+
+    merge(streams, assign)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::example::{ExampleAlphabet, ExampleSymbol::*};
+
+    #[test]
+    fn split_and_merge_round_robin() {
+        let symbols: Vec<u8> = (0..10).collect();
+        let streams = split(&symbols, 3, round_robin(3)).unwrap();
+        assert_eq!(
+            streams,
+            vec![vec![0, 3, 6, 9], vec![1, 4, 7], vec![2, 5, 8]]
+        );
+        assert_eq!(merge(streams, round_robin(3)), Ok(symbols));
+    }
+
+    #[test]
+    fn split_and_merge_by_context() {
+        // Put each symbol in the stream for its predecessor's parity
+        let parity = |index: usize, preceding: &[u8]| match index {
+            0 => 0,
+            _ => (preceding[index - 1] % 2) as usize,
+        };
+        let symbols = vec![4, 7, 1, 2, 9, 9, 6];
+        let streams = split(&symbols, 2, parity).unwrap();
+        assert_eq!(streams, vec![vec![4, 7, 9], vec![1, 2, 9, 6]]);
+        assert_eq!(merge(streams, parity), Ok(symbols));
+    }
+
+    #[test]
+    fn error_on_invalid_assignment() {
+        assert_eq!(
+            split(&[1, 2], 2, |index, _: &[u8]| index * 2),
+            Err(SplitError::NoSuchStream {
+                index: 1,
+                stream: 2,
+                streams: 2
+            })
+        );
+        assert_eq!(
+            merge(vec![vec![1], vec![2, 3]], round_robin(2)),
+            Err(SplitError::LeftoverSymbols(1))
+        );
+    }
+
+    #[test]
+    fn round_trip_split_streams() {
+        let alphabets = [ExampleAlphabet::new(), ExampleAlphabet::new()];
+        let symbols = vec![A, B, B, C, A, A, B];
+
+        let encoded = encode_split::<_, 32>(&alphabets, &symbols, round_robin(2)).unwrap();
+        assert_eq!(encoded.len(), 2);
+        assert_eq!(
+            decode_merged::<_, 32>(&alphabets, &encoded, round_robin(2)),
+            Ok(symbols)
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use crate::alphabet::{Alphabet, Symbol};
+use crate::bits::Bit;
+use crate::decoder::{DecodeError, Decoder, DecoderEvent};
+use crate::encoder::{EncodeError, Encoder};
+use crate::frequency::FrequencyTable;
+
+/// A Unicode scalar value, or the end of a string.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CharSymbol {
+    Char(char),
+    Eof,
+}
+
+impl Symbol for CharSymbol {}
+
+impl From<char> for CharSymbol {
+    fn from(c: char) -> Self {
+        CharSymbol::Char(c)
+    }
+}
+
+/// Errors that can occur while encoding a string
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum TextError {
+    #[error("Character {c:?} at byte {position} is not in the alphabet")]
+    UnknownChar { c: char, position: usize },
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+}
+
+/// An alphabet of the characters of a sample text, weighted by the number of
+/// times each occurs, for compressing strings.
+///
+/// Only characters which occur in the sample can be encoded. Characters are
+/// kept in the order they were first seen, followed by EOF, which is given a
+/// single count.
+#[derive(Clone, Debug)]
+pub struct CharAlphabet {
+    table: FrequencyTable<CharSymbol>,
+}
+
+impl CharAlphabet {
+    /// Count the occurrences of each character in the sample.
+    pub fn from_text(sample: &str) -> Self {
+        Self {
+            table: FrequencyTable::from_symbols(
+                sample.chars().map(CharSymbol::Char),
+                CharSymbol::Eof,
+            ),
+        }
+    }
+
+    /// The table of character counts.
+    pub fn table(&self) -> &FrequencyTable<CharSymbol> {
+        &self.table
+    }
+
+    /// Encode a string, terminated by EOF.
+    pub fn encode_str<const BITS_OF_PRECISION: u32>(
+        &self,
+        text: &str,
+    ) -> Result<Vec<Bit>, TextError> {
+        if let Some((position, c)) = text
+            .char_indices()
+            .find(|&(_, c)| !self.table.contains(&CharSymbol::Char(c)))
+        {
+            return Err(TextError::UnknownChar { c, position });
+        }
+
+        let symbols = text.chars().map(CharSymbol::Char).chain([CharSymbol::Eof]);
+        Ok(self.encode_to_vec::<_, BITS_OF_PRECISION>(symbols)?)
+    }
+
+    /// Decode a string encoded with [`CharAlphabet::encode_str`].
+    pub fn decode_to_string<const BITS_OF_PRECISION: u32>(
+        &self,
+        bits: &[Bit],
+    ) -> Result<String, DecodeError> {
+        let mut text = String::new();
+        for event in self.decode::<_, BITS_OF_PRECISION>(bits.iter().copied()) {
+            if let DecoderEvent::DecodedSymbol(CharSymbol::Char(c)) = event? {
+                text.push(c);
+            }
+        }
+        Ok(text)
+    }
+}
+
+impl Alphabet for CharAlphabet {
+    type S = CharSymbol;
+
+    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
+        self.table.symbols()
+    }
+
+    fn eof(&self) -> Self::S {
+        CharSymbol::Eof
+    }
+
+    fn interval_width(&self, symbol: &Self::S) -> usize {
+        self.table.interval_width(symbol)
+    }
+
+    fn total_interval_width(&self) -> usize {
+        self.table.total_interval_width()
+    }
+
+    fn interval_lower_bound(&self, symbol: &Self::S) -> usize {
+        self.table.interval_lower_bound(symbol)
+    }
+
+    fn interval_upper_bound(&self, symbol: &Self::S) -> usize {
+        self.table.interval_upper_bound(symbol)
+    }
+
+    fn quantile(&self, value: usize) -> &Self::S {
+        self.table.quantile(value)
+    }
+
+    fn checked_cdf(&self, symbol: &Self::S) -> Option<(usize, usize)> {
+        self.table.checked_cdf(symbol)
+    }
+
+    fn contains(&self, symbol: &Self::S) -> bool {
+        self.table.contains(symbol)
+    }
+
+    fn index(&self, symbol: &Self::S) -> usize {
+        self.table.index(symbol)
+    }
+
+    fn symbol_at(&self, index: usize) -> &Self::S {
+        self.table.symbol_at(index)
+    }
+
+    fn is_static(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reference;
+
+    const TEXT: &str = "Ünïcödé text: 日本語のテキスト, and some emoji 🦀🦀🦀";
+
+    #[test]
+    fn counts_distinct_chars() {
+        let alphabet = CharAlphabet::from_text("naïve café");
+        // n a ï v e space c f é, plus EOF
+        assert_eq!(alphabet.table().len(), 10);
+        assert_eq!(alphabet.interval_width(&'a'.into()), 2);
+        assert_eq!(alphabet.symbol_at(2), &CharSymbol::Char('ï'));
+        assert_eq!(alphabet.total_interval_width(), 11);
+    }
+
+    #[test]
+    fn round_trip_strings() {
+        let alphabet = CharAlphabet::from_text(TEXT);
+        let bits = alphabet.encode_str::<32>(TEXT).unwrap();
+        assert!(bits.len() < TEXT.chars().count() * 6, "{} bits", bits.len());
+        assert_eq!(alphabet.decode_to_string::<32>(&bits), Ok(TEXT.to_string()));
+
+        let bits = alphabet.encode_str::<32>("").unwrap();
+        assert_eq!(alphabet.decode_to_string::<32>(&bits), Ok(String::new()));
+        assert_eq!(
+            reference::cross_check_random::<_, 32>(&alphabet, 50, 100, 20),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn unknown_chars_are_rejected() {
+        let alphabet = CharAlphabet::from_text("abc");
+        assert_eq!(
+            alphabet.encode_str::<32>("cab✓"),
+            Err(TextError::UnknownChar {
+                c: '✓',
+                position: 3
+            })
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! A compact binary encoding of models, for embedding them at the front of a
+//! compressed stream.
+//!
+//! Integers are written as LEB128 varints (seven bits per byte, least
+//! significant group first), so small widths and counts take a single byte.
+//! Symbols are written by their [`WireSymbol`] implementation.
+
+use crate::alphabet::Symbol;
+use crate::byte::ByteSymbol;
+use std::io::{self, Read};
+
+/// A symbol which can be written to and read back from a compact binary
+/// encoding.
+pub trait WireSymbol: Symbol + Sized {
+    fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()>;
+
+    fn read_from<R: io::Read>(reader: &mut R) -> io::Result<Self>;
+
+    /// Write the symbol as part of the content of a decoded message, which
+    /// the checksum of a frame is computed over (see the `format` module).
+    ///
+    /// This is the symbol's encoding by default.
+    fn write_content<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_to(writer)
+    }
+}
+
+/// A byte is written as its value, and EOF as 256, so each takes one or two
+/// bytes.
+impl WireSymbol for ByteSymbol {
+    fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            ByteSymbol::Byte(byte) => write_varint(writer, *byte as u64),
+            ByteSymbol::Eof => write_varint(writer, 256),
+        }
+    }
+
+    fn read_from<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        match read_varint(reader)? {
+            256 => Ok(ByteSymbol::Eof),
+            value => u8::try_from(value)
+                .map(ByteSymbol::Byte)
+                .map_err(|_| invalid_data(format!("Invalid byte symbol {value}"))),
+        }
+    }
+
+    /// A byte is its content, so the content of a message of bytes is those
+    /// bytes, and EOF has none.
+    fn write_content<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            ByteSymbol::Byte(byte) => writer.write_all(&[*byte]),
+            ByteSymbol::Eof => Ok(()),
+        }
+    }
+}
+
+/// A string is written as its length in bytes followed by its UTF-8 bytes.
+impl WireSymbol for String {
+    fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_varint(writer, self.len() as u64)?;
+        writer.write_all(self.as_bytes())
+    }
+
+    fn read_from<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let length = read_varint(reader)?;
+        let mut bytes = Vec::new();
+        reader.take(length).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != length {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        String::from_utf8(bytes).map_err(|e| invalid_data(e.to_string()))
+    }
+}
+
+/// Write an integer as a LEB128 varint.
+pub(crate) fn write_varint<W: io::Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity(10);
+    loop {
+        let group = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(group);
+            break;
+        }
+        bytes.push(group | 0x80);
+    }
+    writer.write_all(&bytes)
+}
+
+/// Read an integer written by [`write_varint`].
+pub(crate) fn read_varint<R: io::Read>(reader: &mut R) -> io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        let group = (byte[0] & 0x7f) as u64;
+        if shift == 63 && group > 1 {
+            break;
+        }
+        value |= group << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("Varint overflows 64 bits".to_string()))
+}
+
+/// Read a varint which must fit in a usize.
+pub(crate) fn read_usize<R: io::Read>(reader: &mut R) -> io::Result<usize> {
+    let value = read_varint(reader)?;
+    usize::try_from(value).map_err(|_| invalid_data(format!("{value} doesn't fit in a usize")))
+}
+
+pub(crate) fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn varints_round_trip() {
+        for value in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value).unwrap();
+            assert_eq!(read_varint(&mut bytes.as_slice()).unwrap(), value);
+        }
+
+        let mut bytes = Vec::new();
+        write_varint(&mut bytes, 300).unwrap();
+        assert_eq!(bytes, [0xac, 0x02]);
+        assert_eq!(
+            read_varint(&mut [0xff; 10].as_slice()).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            read_varint(&mut [0x80].as_slice()).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn symbols_round_trip() {
+        let mut bytes = Vec::new();
+        ByteSymbol::Byte(7).write_to(&mut bytes).unwrap();
+        ByteSymbol::Eof.write_to(&mut bytes).unwrap();
+        "héllo".to_string().write_to(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 1 + 2 + 7);
+
+        let reader = &mut bytes.as_slice();
+        assert_eq!(ByteSymbol::read_from(reader).unwrap(), ByteSymbol::Byte(7));
+        assert_eq!(ByteSymbol::read_from(reader).unwrap(), ByteSymbol::Eof);
+        assert_eq!(String::read_from(reader).unwrap(), "héllo");
+    }
+}
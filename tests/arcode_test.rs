@@ -0,0 +1,105 @@
+//! Cross-checks the streams of `arithmetic_coding::arcode` against the arcode
+//! crate itself, coding bytes with arcode's adaptive model.
+
+use arcode::bitbit::{BitReader, BitWriter, MSB};
+use arcode::{ArithmeticDecoder, ArithmeticEncoder, EOFKind, Model};
+use arithmetic_coding::byte::{ByteAlphabet, ByteSymbol};
+use arithmetic_coding::models::AdaptiveAlphabet;
+use std::io::Cursor;
+
+const BITS_OF_PRECISION: u32 = 48;
+
+fn model() -> Model {
+    Model::builder()
+        .num_symbols(256)
+        .eof(EOFKind::EndAddOne)
+        .build()
+}
+
+/// The alphabet which codes bytes as arcode's model does
+fn alphabet() -> AdaptiveAlphabet<ByteSymbol> {
+    AdaptiveAlphabet::new(
+        ByteAlphabet::message(0..=u8::MAX).collect(),
+        ByteSymbol::Eof,
+    )
+    .with_max_total(1 << 30)
+}
+
+fn arcode_encode(data: &[u8]) -> Vec<u8> {
+    let mut model = model();
+    let mut writer = BitWriter::new(Cursor::new(vec![]));
+    let mut encoder = ArithmeticEncoder::new(BITS_OF_PRECISION as u64);
+    for &byte in data {
+        encoder.encode(byte as u32, &model, &mut writer).unwrap();
+        model.update_symbol(byte as u32);
+    }
+    encoder.encode(model.eof(), &model, &mut writer).unwrap();
+    encoder.finish_encode(&mut writer).unwrap();
+    writer.pad_to_byte().unwrap();
+    writer.get_ref().get_ref().clone()
+}
+
+fn arcode_decode(bytes: &[u8]) -> Vec<u8> {
+    let mut model = model();
+    let mut reader = BitReader::<_, MSB>::new(bytes);
+    let mut decoder = ArithmeticDecoder::new(BITS_OF_PRECISION as u64);
+    let mut data = Vec::new();
+    while !decoder.finished() {
+        let symbol = decoder.decode(&model, &mut reader).unwrap();
+        model.update_symbol(symbol);
+        data.push(symbol as u8);
+    }
+    // The last symbol is EOF
+    data.pop();
+    data
+}
+
+/// Inputs which exercise pending bits, long runs and every byte value
+fn inputs() -> Vec<Vec<u8>> {
+    let mut state = 0x2545_f491_u32;
+    let noise = (0..5000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    vec![
+        vec![],
+        b"a".to_vec(),
+        b"she sells sea shells by the sea shore".repeat(50),
+        vec![0; 10_000],
+        (0..=u8::MAX).cycle().take(3000).collect(),
+        noise,
+    ]
+}
+
+#[test]
+fn decode_arcode_streams() {
+    let alphabet = alphabet();
+    for data in inputs() {
+        let bytes = arcode_encode(&data);
+        let message: Vec<_> = ByteAlphabet::message(data).collect();
+        assert_eq!(
+            arithmetic_coding::arcode::decode::<_, BITS_OF_PRECISION>(&alphabet, &bytes),
+            Ok(message.clone())
+        );
+        // Byte for byte the same stream
+        assert_eq!(
+            arithmetic_coding::arcode::encode::<_, BITS_OF_PRECISION>(&alphabet, &message),
+            Ok(bytes)
+        );
+    }
+}
+
+#[test]
+fn arcode_decodes_our_streams() {
+    let alphabet = alphabet();
+    for data in inputs() {
+        let message: Vec<_> = ByteAlphabet::message(data.iter().copied()).collect();
+        let bytes =
+            arithmetic_coding::arcode::encode::<_, BITS_OF_PRECISION>(&alphabet, &message).unwrap();
+        assert_eq!(arcode_decode(&bytes), data);
+    }
+}
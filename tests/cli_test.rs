@@ -0,0 +1,47 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn arith(args: &[&Path]) -> bool {
+    Command::new(env!("CARGO_BIN_EXE_arith"))
+        .args(args)
+        .output()
+        .expect("Failed to run arith")
+        .status
+        .success()
+}
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("arith-cli-{}-{name}", std::process::id()))
+}
+
+#[test]
+fn compress_and_decompress_files() {
+    // Larger than a quarter of 2^16, and skewed, so that models reach the
+    // limits of the lowest precision
+    let text = "a".repeat(40_000) + &"she sells sea shells by the sea shore\n".repeat(500);
+    let (input, compressed, output) = (scratch("in"), scratch("arith"), scratch("out"));
+    fs::write(&input, &text).unwrap();
+
+    for model in ["byte", "static", "adaptive"] {
+        for precision in ["16", "24", "32"] {
+            let options = ["compress", "--model", model, "--precision", precision];
+            let mut args: Vec<&Path> = options.iter().map(Path::new).collect();
+            args.extend([input.as_path(), compressed.as_path()]);
+            assert!(arith(&args), "{model} at {precision} bits");
+            if model != "byte" {
+                assert!(fs::metadata(&compressed).unwrap().len() < text.len() as u64 / 2);
+            }
+
+            let args = [Path::new("decompress"), &compressed, &output];
+            assert!(arith(&args), "{model} at {precision} bits");
+            assert_eq!(fs::read_to_string(&output).unwrap(), text);
+        }
+    }
+
+    // Garbage isn't a compressed file
+    assert!(!arith(&[Path::new("decompress"), &input, &output]));
+    for path in [input, compressed, output] {
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,159 +1,15 @@
-use arithmetic_coding::alphabet::{Alphabet, Symbol};
+use arithmetic_coding::Bit;
+use arithmetic_coding::alphabet::Alphabet;
+use arithmetic_coding::arbitrary::{BitStream, NumAlphabet, NumMessage, NumSymbol};
 use arithmetic_coding::decoder::{Decoder, DecoderEvent};
-use arithmetic_coding::encoder::{EncodeError, Encoder};
-use biterator::Bit;
-use quickcheck::{Arbitrary, Gen};
+use arithmetic_coding::encoder::{EncodeError, Encoder, EncoderSink};
+use arithmetic_coding::models::AdaptiveAlphabet;
+use arithmetic_coding::reference;
 use quickcheck_macros::quickcheck;
-use rand::Rng;
 use test_log::test;
 
 // TODO(tcastleman) Tests where size of encoding > precision
 
-/// A symbol type that wraps an integer, so we can easily generate
-/// arbitrary-sized alphabets composed of these symbols. EOF is represented
-/// by the value zero.
-#[derive(Debug, PartialEq, Copy, Clone)]
-struct NumSymbol(usize);
-
-impl Symbol for NumSymbol {}
-
-impl NumSymbol {
-    fn eof() -> Self {
-        NumSymbol(0)
-    }
-}
-
-#[derive(Debug, Clone)]
-struct NumAlphabet {
-    symbols: Vec<NumSymbol>,
-    interval_widths: Vec<usize>,
-}
-
-impl NumAlphabet {
-    fn new(interval_widths: Vec<usize>) -> Self {
-        assert!(
-            !interval_widths.is_empty(),
-            "Alphabet must have at least one symbol"
-        );
-        assert!(
-            interval_widths.iter().all(|&width| width > 0),
-            "Interval widths must be >0"
-        );
-        Self {
-            symbols: (0..interval_widths.len()).map(NumSymbol).collect(),
-            interval_widths,
-        }
-    }
-
-    /// Generate a stream of random symbols of the indicated length, terminated
-    /// by the EOF symbol.
-    fn random_symbol_stream(&self, length: usize) -> Vec<NumSymbol> {
-        let max = self.symbols.len();
-
-        (0..length)
-            .map(|_| NumSymbol(rand::rng().random_range(1..max)))
-            .chain(std::iter::once(NumSymbol::eof()))
-            .collect()
-    }
-}
-
-impl Alphabet for NumAlphabet {
-    type S = NumSymbol;
-
-    fn symbols(&self) -> impl Iterator<Item = &Self::S> {
-        self.symbols.iter()
-    }
-
-    fn eof(&self) -> Self::S {
-        NumSymbol::eof()
-    }
-
-    fn interval_width(&self, symbol: &Self::S) -> usize {
-        for (s, &interval) in self.symbols.iter().zip(self.interval_widths.iter()) {
-            if symbol == s {
-                return interval;
-            }
-        }
-
-        panic!("Symbol {symbol:?} not in alphabet {self:?}")
-    }
-}
-
-struct ShrinkingNumAlphabet {
-    alphabet: NumAlphabet,
-}
-
-impl Iterator for ShrinkingNumAlphabet {
-    type Item = NumAlphabet;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        // Stop removing symbols when only EOF and one other symbol is left,
-        // because this is the smallest alphabet that can support arbitrary
-        // length input streams.
-        if self.alphabet.symbols.len() <= 2 {
-            return None;
-        }
-
-        self.alphabet.symbols.pop();
-        self.alphabet.interval_widths.pop();
-
-        Some(self.alphabet.clone())
-    }
-}
-
-const MAX_BITS_OF_PRECISION: u32 = 32;
-const QUARTER: usize = 2_usize.pow(MAX_BITS_OF_PRECISION) / 4;
-
-impl Arbitrary for NumAlphabet {
-    fn arbitrary(g: &mut Gen) -> Self {
-        let mut interval_widths = Vec::arbitrary(g);
-
-        // Ensure that at least two interval widths are present, to guarantee
-        // that there is room for 1) an EOF symbol, and 2) a non-EOF symbol.
-        // This is the smallest possible alphabet for which we can generate
-        // arbitrary-length random input sequences. An alphabet consisting
-        // only of EOF isn't capable of this because EOF immediately terminates
-        // the input.
-        interval_widths.push(usize::arbitrary(g));
-        interval_widths.push(usize::arbitrary(g));
-
-        // Due to the calculations done in the encoder/decoder to determine
-        // subintervals, there are constraints on how large R (the sum of all
-        // interval widths) can be.
-        //
-        // Specifically, we must be able to represent 2^precision * R as a usize.
-        // i.e. 2^precision * R <= usize::MAX
-        //                    R <= usize::MAX / 2^precision
-        let max_total_width = usize::MAX / 2usize.pow(MAX_BITS_OF_PRECISION);
-
-        // To ensure intervals don't sum to an R greater than the max, set a
-        // conservative upper limit by dividing the max R.
-        let max_width = max_total_width / interval_widths.len();
-
-        // To ensure intervals aren't so small relative to R that they shrink to
-        // 0 when a and b are close enough together, set a lower limit. This is
-        // determined assuming the minimum difference between a and b is
-        // QUARTER = 2^precision / 4.
-        //
-        // In order to avoid shrinking to zero, each width must satisfy:
-        // (QUARTER * width) / R >= 1
-        let min_width = max_total_width / QUARTER;
-
-        // Scale all widths to be in [min_width, max_width)
-        for width in &mut interval_widths {
-            *width = min_width + (*width % (max_width - min_width));
-        }
-
-        NumAlphabet::new(interval_widths)
-    }
-
-    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
-        Box::new(ShrinkingNumAlphabet {
-            alphabet: self.clone(),
-        })
-    }
-}
-
 const BITS_OF_PRECISION: u32 = 32;
 
 fn encode(alphabet: &NumAlphabet, input: Vec<NumSymbol>) -> Vec<Bit> {
@@ -173,7 +29,7 @@ fn decode(alphabet: &NumAlphabet, bits: Vec<Bit>) -> Decoded {
     let mut length = None;
 
     for event in decoder_events {
-        match event {
+        match event.expect("Decoding failed") {
             DecoderEvent::DecodedSymbol(symbol) => symbols.push(symbol),
             DecoderEvent::MessageLength(message_length) => length = Some(message_length),
         };
@@ -185,8 +41,9 @@ fn decode(alphabet: &NumAlphabet, bits: Vec<Bit>) -> Decoded {
 /// Property test verifying that decoding an encoded stream of symbols results
 /// in the same stream of symbols.
 #[quickcheck]
-fn encoder_and_decoder_cancel(alphabet: NumAlphabet, input_length: u8) -> bool {
-    let input = alphabet.random_symbol_stream(input_length as usize);
+fn encoder_and_decoder_cancel(message: NumMessage) -> bool {
+    let NumMessage { alphabet, symbols } = message;
+    let input = symbols;
     let expected_output = input.clone();
 
     let bits = encode(&alphabet, input);
@@ -198,12 +55,9 @@ fn encoder_and_decoder_cancel(alphabet: NumAlphabet, input_length: u8) -> bool {
 /// Property test verifying that the decoder correctly calculates the number of
 /// bits from the input that comprise the decoded message.
 #[quickcheck]
-fn decoder_calculates_length(
-    alphabet: NumAlphabet,
-    input_length: u8,
-    extra_bits: Vec<bool>,
-) -> bool {
-    let input = alphabet.random_symbol_stream(input_length as usize);
+fn decoder_calculates_length(message: NumMessage, extra_bits: BitStream) -> bool {
+    let NumMessage { alphabet, symbols } = message;
+    let input = symbols;
 
     let mut bits = encode(&alphabet, input);
     let encoding_length = bits.len();
@@ -211,13 +65,80 @@ fn decoder_calculates_length(
     // Append random extra bits after the encoded input, to validate that the
     // decoder correctly extracts the portion of the bitstream relevant to the
     // encoded input.
-    bits.extend(extra_bits.into_iter().map(Bit::from));
+    bits.extend(extra_bits.0);
 
     let decoded = decode(&alphabet, bits);
 
     decoded.length == Some(encoding_length)
 }
 
+/// Property test verifying that the decoder never reads beyond the end of the
+/// message from its input, even when more bits follow.
+#[quickcheck]
+fn decoder_reads_only_message(message: NumMessage, extra_bits: BitStream) -> bool {
+    let NumMessage { alphabet, symbols } = message;
+
+    let mut bits = encode(&alphabet, symbols);
+    let encoding_length = bits.len();
+    bits.extend(extra_bits.0);
+    let total_length = bits.len();
+
+    let mut input = bits.into_iter();
+    let mut decoder = alphabet.decode::<_, BITS_OF_PRECISION>(input.by_ref());
+    decoder.by_ref().for_each(drop);
+    let bits_read = decoder.bits_read();
+
+    bits_read <= encoding_length && input.len() == total_length - bits_read
+}
+
+/// Property test verifying that the encoder and decoder agree bit-for-bit with
+/// the reference transcription of the lecture's algorithm.
+#[quickcheck]
+fn coders_match_reference(message: NumMessage) -> bool {
+    let NumMessage { alphabet, symbols } = message;
+    reference::cross_check::<_, BITS_OF_PRECISION>(&alphabet, &symbols).is_ok()
+}
+
+/// The bounds of every symbol in an alphabet, as a snapshot of its state.
+fn bounds<A: Alphabet<S = NumSymbol>>(alphabet: &A) -> Vec<(usize, usize)> {
+    alphabet
+        .symbols()
+        .map(|symbol| alphabet.cdf(symbol))
+        .collect()
+}
+
+/// Property test verifying that an adaptive model used to decode a message
+/// goes through exactly the same states as the one used to encode it, so the
+/// decoder never codes a symbol with a different distribution.
+#[quickcheck]
+fn adaptive_models_stay_in_sync(message: NumMessage) -> bool {
+    let NumMessage { alphabet, symbols } = message;
+    let model = || AdaptiveAlphabet::new(alphabet.symbols().cloned().collect(), NumSymbol::eof());
+
+    let encoding_model = model();
+    let mut sink = EncoderSink::<_, BITS_OF_PRECISION>::new(&encoding_model).unwrap();
+    let mut bits = Vec::new();
+    let mut encoder_states = vec![bounds(&encoding_model)];
+    for &symbol in &symbols[..symbols.len() - 1] {
+        bits.extend(sink.push(symbol).unwrap());
+        encoder_states.push(bounds(&encoding_model));
+    }
+    bits.extend(sink.finish());
+    encoder_states.push(bounds(&encoding_model));
+
+    let decoding_model = model();
+    let mut decoder_states = vec![bounds(&decoding_model)];
+    let mut decoded = Vec::new();
+    for event in decoding_model.decode::<_, BITS_OF_PRECISION>(bits) {
+        if let DecoderEvent::DecodedSymbol(symbol) = event.expect("Decoding failed") {
+            decoded.push(symbol);
+            decoder_states.push(bounds(&decoding_model));
+        }
+    }
+
+    decoded == symbols && decoder_states == encoder_states
+}
+
 /// A bug in which z was not initialized with N bits from the input (where N
 /// is the precision) caused this test to fail.
 #[test]
@@ -234,6 +155,9 @@ fn z_initialization_regression_test() {
     assert_eq!(decoded.symbols, expected_output);
 }
 
+/// A bug in which the decoder measured the message length as the smallest
+/// prefix of z identifying the final interval (rather than the number of bits
+/// the encoder actually emits) caused this test to fail.
 #[test]
 fn decoder_miscalculates_length() {
     let alphabet = NumAlphabet::new(vec![72845840, 37260030]);
@@ -246,3 +170,28 @@ fn decoder_miscalculates_length() {
 
     assert_eq!(decoded.length, Some(encoding_length));
 }
+
+/// The decoder counts the rescaling steps the encoder performed, including
+/// those still pending after EOF, rather than measuring the shortest prefix
+/// of z identifying the final interval, which can fall short of the bits the
+/// encoder emits. So the length is exact for every message, whatever follows
+/// it.
+#[test]
+fn decoder_counts_every_emitted_bit() {
+    let alphabet = NumAlphabet::new(vec![1, 2, 1]);
+    for length in 0..=6 {
+        for pattern in 0..1 << length {
+            let input: Vec<_> = (0..length)
+                .map(|i| NumSymbol(1 + (pattern >> i & 1)))
+                .chain(std::iter::once(NumSymbol::eof()))
+                .collect();
+            let bits = encode(&alphabet, input.clone());
+            for extra in [Bit::Zero, Bit::One] {
+                let mut padded = bits.clone();
+                padded.extend(std::iter::repeat_n(extra, 40));
+                let decoded = decode(&alphabet, padded);
+                assert_eq!(decoded.length, Some(bits.len()), "{input:?}");
+            }
+        }
+    }
+}